@@ -0,0 +1,69 @@
+# bash completion for cargo-native-install, generated by cargo-native-install --completions=bash
+_cargo_native_install() {
+    local cur="${COMP_WORDS[COMP_CWORD]}" opt=
+    # Readline splits --option=value at the =
+    if [[ $cur == = ]]; then
+        opt="${COMP_WORDS[COMP_CWORD-1]}"
+        cur=
+    elif [[ $COMP_CWORD -gt 1 && ${COMP_WORDS[COMP_CWORD-1]} == = ]]; then
+        opt="${COMP_WORDS[COMP_CWORD-2]}"
+    fi
+    case "$opt" in
+        --prefix) COMPREPLY=($(compgen -d -- "$cur")) ;;
+        --exec-prefix) COMPREPLY=($(compgen -d -- "$cur")) ;;
+        --root) COMPREPLY=($(compgen -d -- "$cur")) ;;
+        --dist) COMPREPLY=($(compgen -f -- "$cur")) ;;
+        --vendor-dist) COMPREPLY=($(compgen -f -- "$cur")) ;;
+        --from-archive) COMPREPLY=($(compgen -f -- "$cur")) ;;
+        --sysroot) COMPREPLY=($(compgen -d -- "$cur")) ;;
+        --stow-dir) COMPREPLY=($(compgen -d -- "$cur")) ;;
+        --completions) COMPREPLY=($(compgen -W 'bash zsh fish' -- "$cur")) ;;
+        --bindir) COMPREPLY=($(compgen -d -- "$cur")) ;;
+        --libdir) COMPREPLY=($(compgen -d -- "$cur")) ;;
+        --sbindir) COMPREPLY=($(compgen -d -- "$cur")) ;;
+        --libexecdir) COMPREPLY=($(compgen -d -- "$cur")) ;;
+        --includedir) COMPREPLY=($(compgen -d -- "$cur")) ;;
+        --datarootdir) COMPREPLY=($(compgen -d -- "$cur")) ;;
+        --datadir) COMPREPLY=($(compgen -d -- "$cur")) ;;
+        --mandir) COMPREPLY=($(compgen -d -- "$cur")) ;;
+        --infodir) COMPREPLY=($(compgen -d -- "$cur")) ;;
+        --docdir) COMPREPLY=($(compgen -d -- "$cur")) ;;
+        --localedir) COMPREPLY=($(compgen -d -- "$cur")) ;;
+        --sysconfdir) COMPREPLY=($(compgen -d -- "$cur")) ;;
+        --localstatedir) COMPREPLY=($(compgen -d -- "$cur")) ;;
+        --sharedstatedir) COMPREPLY=($(compgen -d -- "$cur")) ;;
+        --strip) COMPREPLY=($(compgen -c -- "$cur")) ;;
+        --strip-mode) COMPREPLY=($(compgen -W 'all debug unneeded' -- "$cur")) ;;
+        --debugdir) COMPREPLY=($(compgen -d -- "$cur")) ;;
+        --install) COMPREPLY=($(compgen -c -- "$cur")) ;;
+        --install-flavor) COMPREPLY=($(compgen -W 'gnu bsd busybox' -- "$cur")) ;;
+        --pandoc) COMPREPLY=($(compgen -c -- "$cur")) ;;
+        --asciidoctor) COMPREPLY=($(compgen -c -- "$cur")) ;;
+        --patchelf) COMPREPLY=($(compgen -c -- "$cur")) ;;
+        --owner) COMPREPLY=($(compgen -u -- "$cur")) ;;
+        --group) COMPREPLY=($(compgen -g -- "$cur")) ;;
+        --log-file) COMPREPLY=($(compgen -f -- "$cur")) ;;
+        --check-packages) COMPREPLY=($(compgen -W 'refuse warn off' -- "$cur")) ;;
+        --check-build-paths) COMPREPLY=($(compgen -W 'deny warn off' -- "$cur")) ;;
+        --elevate-with) COMPREPLY=($(compgen -c -- "$cur")) ;;
+        --env-prefix) COMPREPLY=($(compgen -d -- "$cur")) ;;
+        --emit-config-h) COMPREPLY=($(compgen -f -- "$cur")) ;;
+        --emit-paths-rs) COMPREPLY=($(compgen -f -- "$cur")) ;;
+        --emit-env) COMPREPLY=($(compgen -f -- "$cur")) ;;
+        --emit-dotenv) COMPREPLY=($(compgen -f -- "$cur")) ;;
+        --print-dirs) COMPREPLY=($(compgen -W 'human shell json' -- "$cur")) ;;
+        --emit-ninja) COMPREPLY=($(compgen -f -- "$cur")) ;;
+        --emit-script) COMPREPLY=($(compgen -W 'sh ps1' -- "$cur")) ;;
+        --shared) COMPREPLY=($(compgen -W 'lib bin' -- "$cur")) ;;
+        --manifest-dir) COMPREPLY=($(compgen -d -- "$cur")) ;;
+        --manifest-path) COMPREPLY=($(compgen -f -- "$cur")) ;;
+        --out-dir) COMPREPLY=($(compgen -d -- "$cur")) ;;
+        --config) COMPREPLY=($(compgen -f -- "$cur")) ;;
+        "")
+            COMPREPLY=($(compgen -W '--help --version --dry-run --diff --user-prefix --prefix= --exec-prefix= --root= --dist= --vendor-dist= --from-archive= --from-release= --tag= --multiarch --multiarch= --sysroot= --stow --stow-dir= --unstow --slot --activate --develop --watch --emit-schema --completions= --man-page --dest= --bindir= --libdir= --sbindir= --libexecdir= --includedir= --datarootdir= --datadir= --mandir= --infodir= --docdir= --localedir= --sysconfdir= --localstatedir= --sharedstatedir= --no-strip --without-strip --strip= --strip-mode= --separate-debug --debugdir= --strip-arg= --install --install= --install-flavor= --pandoc= --asciidoctor= --patchelf= --internal-install --mode= --umask= --lock-timeout= --retries= --owner= --group= --copy= --copy-buffer-size= --sync --no-sync --preserve-timestamps --clamp-timestamps --clamp-timestamps= --no-create --log-file= --report= --verbose -v --quiet -q --color= --annotations= --force --adopt --keep-going -k --run-checks --check-packages --check-packages= --check-build-paths --check-build-paths= --no-privileged --privileged --locales= --all-locales --allow-outside-prefix --elevate --no-elevate --elevate-with= --target= --no-libexec --no-sbin --arch-target --arch-target= --env-prefix= --env-name= --build --build-only --no-space-check --require-built --devel --third-party-licenses --bundle-libs --fail-on-missing-deps --sign-artifacts= --emit-config-h= --emit-paths-rs= --install-emitted --emit-env= --emit-dotenv= --print-dirs --print-dirs= --emit-ninja= --emit-script= --keep-privileges --container --no-container --shared= --manifest-dir= --manifest-path= --out-dir= --release --debug --config= switch-version relocate diff-receipts report-orphans check test-install verify config build' -- "$cur"))
+            [[ ${#COMPREPLY[@]} -eq 1 && ${COMPREPLY[0]} == *= ]] && compopt -o nospace
+            ;;
+        *) COMPREPLY=() ;;
+    esac
+}
+complete -o filenames -F _cargo_native_install cargo-native-install
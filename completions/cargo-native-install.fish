@@ -0,0 +1,132 @@
+# fish completion for cargo-native-install, generated by cargo-native-install --completions=fish
+complete -c cargo-native-install -l help -d 'Prints this message, and exits'
+complete -c cargo-native-install -l version -d 'Prints version information, and exits'
+complete -c cargo-native-install -l dry-run -d 'Show the results of each install operation, but do not perform any operations'
+complete -c cargo-native-install -l diff -d 'With --dry-run, show a unified diff of each text file that would be changed'
+complete -c cargo-native-install -l user-prefix -d 'Default prefix to ~/.local, instead of a system-wide dir'
+complete -c cargo-native-install -l prefix -x -a '(__fish_complete_directories)' -d 'Sets the prefix for installation operations'
+complete -c cargo-native-install -l exec-prefix -x -a '(__fish_complete_directories)' -d 'Sets the prefix for architecture-dependent files'
+complete -c cargo-native-install -l root -x -a '(__fish_complete_directories)' -d 'Install into <dir>, like cargo install --root, and record the programs installed to <dir>/bin in the metadata of cargo install, so that cargo install --list shows them and cargo uninstall removes them'
+complete -c cargo-native-install -l dist -x -a '(__fish_complete_path)' -d 'Install into a staging directory, then pack it into <archive> (a zip file if it ends in .zip, and otherwise a tarball compressed according to its name), which can be installed with --from-archive'
+complete -c cargo-native-install -l vendor-dist -x -a '(__fish_complete_path)' -d 'Pack the source of the package, with its dependencies vendored by cargo vendor and a .cargo/config.toml which uses them, into <archive> (as with --dist), instead of installing, so that it can be built and installed without network access'
+complete -c cargo-native-install -l from-archive -x -a '(__fish_complete_path)' -d 'Install <archive>, made by --dist, to the paths it was made for, instead of the package in the manifest directory'
+complete -c cargo-native-install -l from-release -x -d 'Download a release archive, made by --dist, from a repository on GitHub or GitLab (with --tag), or from the URL of the archive, verify it against its published SHA-256 checksum (and signature, if any), and install it as with --from-archive'
+complete -c cargo-native-install -l tag -x -d 'The tag of the release to install with --from-release'
+complete -c cargo-native-install -l multiarch -d 'Install libraries into libdir/<triple>, and helpers into libexecdir/<triple>, so that one prefix can hold the libraries of several architectures'
+complete -c cargo-native-install -l sysroot -x -a '(__fish_complete_directories)' -d 'Install headers, libraries, and pkg-config files beneath <dir>, a cross-compilation sysroot, and skip every other target'
+complete -c cargo-native-install -l stow -d 'Install into <prefix>/stow/<package>-<version>, then link each installed file into the prefix, replacing the links of any other version of the package'
+complete -c cargo-native-install -l stow-dir -x -a '(__fish_complete_directories)' -d 'Implies --stow, and uses <dir> instead of <prefix>/stow'
+complete -c cargo-native-install -l unstow -d 'Remove the links created by --stow for the package, leaving the stowed files in place, instead of installing'
+complete -c cargo-native-install -l slot -d 'Install into <prefix>/opt/<package>/<version>, so that several versions can be installed side by side'
+complete -c cargo-native-install -l activate -d 'With --slot, link the programs of the installed version into bindir and sbindir, replacing the links to any other version'
+complete -c cargo-native-install -l develop -d 'Link each artifact into its destination, instead of copying it, so that rebuilding the package updates the installation'
+complete -c cargo-native-install -l watch -d 'Install, then watch the artifacts of each target, and install again whenever they change (such as when the package is rebuilt), until interrupted'
+complete -c cargo-native-install -l emit-schema -d 'Print a JSON Schema for [package.metadata.install-targets] to standard output, for editors which validate Cargo.toml, and exit'
+complete -c cargo-native-install -l completions -x -a 'bash zsh fish' -d 'Print a script which completes the options of this program in bash, zsh, or fish to standard output, and exit'
+complete -c cargo-native-install -l man-page -d 'Print the manual page of this program, in roff, to standard output, and exit'
+complete -c cargo-native-install -l dest -x -d 'Install onto a remote machine, by running commands through ssh'
+complete -c cargo-native-install -l bindir -x -a '(__fish_complete_directories)' -d 'Use dir as the directory to install binary programs'
+complete -c cargo-native-install -l libdir -x -a '(__fish_complete_directories)' -d 'Use dir as the directory to install libraries'
+complete -c cargo-native-install -l sbindir -x -a '(__fish_complete_directories)' -d 'Use dir as the directory to install system administrator programs'
+complete -c cargo-native-install -l libexecdir -x -a '(__fish_complete_directories)' -d 'Use dir as the directory to install programs that aren'\''t for direct use from the shell'
+complete -c cargo-native-install -l includedir -x -a '(__fish_complete_directories)' -d 'Use dir as the directory to install header files'
+complete -c cargo-native-install -l datarootdir -x -a '(__fish_complete_directories)' -d 'Use dir as the prefix for platform independent data, documentation, and manuals'
+complete -c cargo-native-install -l datadir -x -a '(__fish_complete_directories)' -d 'Use dir as the directory to install platform independent data'
+complete -c cargo-native-install -l mandir -x -a '(__fish_complete_directories)' -d 'Use dir as the directory for installing manual pages'
+complete -c cargo-native-install -l infodir -x -a '(__fish_complete_directories)' -d 'Use dir as the directory for installing info pages'
+complete -c cargo-native-install -l docdir -x -a '(__fish_complete_directories)' -d 'Use dir as the directory for installing project documentation'
+complete -c cargo-native-install -l localedir -x -a '(__fish_complete_directories)' -d 'Use dir as the directory for installing locale specific information'
+complete -c cargo-native-install -l sysconfdir -x -a '(__fish_complete_directories)' -d 'Use dir as the directory for system configuration files'
+complete -c cargo-native-install -l localstatedir -x -a '(__fish_complete_directories)' -d 'Use dir as the directory for local system state'
+complete -c cargo-native-install -l sharedstatedir -x -a '(__fish_complete_directories)' -d 'Use dir as the directory for shared system state'
+complete -c cargo-native-install -l no-strip -d 'Do not strip programs, even if strip is found'
+complete -c cargo-native-install -l without-strip -d 'Same as --no-strip'
+complete -c cargo-native-install -l strip -x -a '(__fish_complete_command)' -d 'Use <prg> to strip, instead of the default (strip, or if it is not found, llvm-strip or rust-objcopy from the Rust toolchain)'
+complete -c cargo-native-install -l strip-mode -x -a 'all debug unneeded' -d 'Remove every symbol, only debug information, or only unneeded symbols, from targets which do not set strip-mode (default: unneeded for shared libraries, all otherwise)'
+complete -c cargo-native-install -l separate-debug -d 'Before stripping a binary, keep its debug information in <debugdir>/.build-id/xx/yyyy.debug, named after its build ID, where debuggers and debuginfod find it'
+complete -c cargo-native-install -l debugdir -x -a '(__fish_complete_directories)' -d 'The directory --separate-debug keeps debug information in'
+complete -c cargo-native-install -l strip-arg -x -d 'Pass <arg> to strip, for every target'
+complete -c cargo-native-install -l install -a '(__fish_complete_command)' -d 'Use <prg> (default: install) to install each file, instead of copying files natively'
+complete -c cargo-native-install -l install-flavor -x -a 'gnu bsd busybox' -d 'The implementation of the install program, which decides the options it is given (default: detected from the output of <prg> --version)'
+complete -c cargo-native-install -l pandoc -x -a '(__fish_complete_command)' -d 'Use <prg> to render man targets written in Markdown, instead of the default (pandoc)'
+complete -c cargo-native-install -l asciidoctor -x -a '(__fish_complete_command)' -d 'Use <prg> to render man targets written in AsciiDoc, instead of the default (asciidoctor)'
+complete -c cargo-native-install -l patchelf -x -a '(__fish_complete_command)' -d 'Use <prg> to change the rpath of targets which set rpath, instead of the default (patchelf)'
+complete -c cargo-native-install -l internal-install -d 'Do not invoke any programs to install'
+complete -c cargo-native-install -l mode -x -d 'Force installed files to use <mode> in the form of a chmod mode (X is the executable bit if the file is a binary target, or a directory)'
+complete -c cargo-native-install -l umask -x -d 'Use the octal file mode creation mask <mask> while installing, instead of the umask of the invoking shell'
+complete -c cargo-native-install -l lock-timeout -x -d 'Wait at most <seconds> for another installation into the prefix to finish (default: 300)'
+complete -c cargo-native-install -l retries -x -d 'Retry a copy, chmod, or link which fails with a transient error, such as a busy file or an unavailable network filesystem, up to <count> times (default: 3)'
+complete -c cargo-native-install -l owner -x -a '(__fish_complete_users)' -d 'Make installed files and directories owned by <user>, given as a name or uid'
+complete -c cargo-native-install -l group -x -a '(__fish_complete_groups)' -d 'Make installed files and directories owned by <group>, given as a name or gid'
+complete -c cargo-native-install -l copy -x -d 'How the internal installer copies files'
+complete -c cargo-native-install -l copy-buffer-size -x -d 'The buffer size for --copy=buffered, in bytes, optionally followed by K or M'
+complete -c cargo-native-install -l sync -d 'Flush each installed file, and the directory containing it, to disk before continuing'
+complete -c cargo-native-install -l no-sync -d 'Do not flush installed files to disk (default)'
+complete -c cargo-native-install -l preserve-timestamps -d 'Give installed files the modification time of their source, rather than the current time'
+complete -c cargo-native-install -l clamp-timestamps -d 'Give installed files the modification time of their source, but no later than <time> (in seconds since the unix epoch)'
+complete -c cargo-native-install -l no-create -d 'Do not create installed directories'
+complete -c cargo-native-install -l log-file -x -a '(__fish_complete_path)' -d 'Append a timestamped log of every action, command invoked, and error to <path>, regardless of --verbose or --quiet'
+complete -c cargo-native-install -l report -x -d 'Write whether each target was installed, skipped, or failed to <path>, as JUnit XML or TAP, for CI systems to display'
+complete -c cargo-native-install -l verbose -d 'Print additional messages for each action'
+complete -c cargo-native-install -s v -d 'Print additional messages for each action'
+complete -c cargo-native-install -l quiet -d 'Only print errors and failed operations'
+complete -c cargo-native-install -s q -d 'Only print errors and failed operations'
+complete -c cargo-native-install -l color -x -d 'Whether to color status markers'
+complete -c cargo-native-install -l annotations -x -d 'Also write warnings and errors as annotations, which CI shows on the pull request'
+complete -c cargo-native-install -l force -d 'Install all files, even if this would replace files that are newer, or the target is unchanged since it was last installed'
+complete -c cargo-native-install -l adopt -d 'Replace existing files which were not installed by this package, such as files placed by the administrator or another tool, and record them in the install receipt'
+complete -c cargo-native-install -l keep-going -d 'Continue installing other targets after a target fails to install'
+complete -c cargo-native-install -s k -d 'Continue installing other targets after a target fails to install'
+complete -c cargo-native-install -l run-checks -d 'Once every file is installed, run the check targets of the package, which validate the installation in place, like make installcheck'
+complete -c cargo-native-install -l check-packages -a 'refuse warn off' -d 'Before overwriting a file under a system directory (such as /usr or /etc), ask the system package manager (dpkg, rpm, or pacman) whether a package owns it'
+complete -c cargo-native-install -l check-build-paths -a 'deny warn off' -d 'After installing, report installed programs and libraries which contain paths in the source directory, the cargo home directory, or the home directory of the build machine, and the --remap-path-prefix flags that remove them'
+complete -c cargo-native-install -l no-privileged -d 'Do not install privileged binaries (those installed to sbin)'
+complete -c cargo-native-install -l privileged -d 'Install privilged binaries to sbindir, even if a user-specific prefix is used'
+complete -c cargo-native-install -l locales -x -d 'Only install the targets whose lang is one of the given locales, or a variant of one (so pt includes pt_BR), and the targets without a lang'
+complete -c cargo-native-install -l all-locales -d 'Install the targets of every locale'
+complete -c cargo-native-install -l allow-outside-prefix -d 'Allow targets to be installed to paths outside of every installation directory, such as through absolute paths or '
+complete -c cargo-native-install -l elevate -d 'If the current user cannot write to an installation directory, re-execute the installation using sudo, doas, or pkexec without asking first'
+complete -c cargo-native-install -l no-elevate -d 'Never re-execute the installation with elevated privileges'
+complete -c cargo-native-install -l elevate-with -x -a '(__fish_complete_command)' -d 'Use <prg> to elevate privileges, instead of the first of sudo, doas, or pkexec that is found'
+complete -c cargo-native-install -l target -x -d 'Install only this target'
+complete -c cargo-native-install -l no-libexec -d 'Install libexec targets to bin instead'
+complete -c cargo-native-install -l no-sbin -d 'Install privileged binaries to bin instead of sbin (note that this does not enable privileged binaries)'
+complete -c cargo-native-install -l arch-target -d 'Install bin, lib, include, libexec, sbin targets to an an architecture specific prefix'
+complete -c cargo-native-install -l env-prefix -x -a '(__fish_complete_directories)' -d 'With build, export each installation directory as <prefix> followed by its name, such as <prefix>bindir'
+complete -c cargo-native-install -l env-name -x -d 'With build, export the installation directory <dir> (such as bindir) as <var>, instead of its name'
+complete -c cargo-native-install -l build -d 'Deprecated: use build --install'
+complete -c cargo-native-install -l build-only -d 'Deprecated: use build'
+complete -c cargo-native-install -l no-space-check -d 'Install even if the files do not fit in the space available on the filesystems they are installed to'
+complete -c cargo-native-install -l require-built -d 'Check that the artifact of every target to be installed exists before installing anything, and fail, listing each missing artifact and the cargo command that builds it, if any do not'
+complete -c cargo-native-install -l devel -d 'Also install the library of the package for system Rust consumers: its rlib (and rmeta, if cargo produced one) to libdir/rustlib/<triple>/lib, and its source, as cargo package includes it, to datadir/cargo/registry/<package>-<version>, which can be used as a directory source for offline builds'
+complete -c cargo-native-install -l third-party-licenses -d 'Also install <docdir>/<package>/THIRD_PARTY_LICENSES, which lists each dependency the package is built from, with its license and license texts, for the attribution that statically linked dependencies require'
+complete -c cargo-native-install -l bundle-libs -d 'Copy the shared libraries needed by installed programs and shared libraries, which are not in the directories of the dynamic linker, to libdir, and set the rpath of the binaries which need them, so the installation does not need the libraries of the build machine'
+complete -c cargo-native-install -l fail-on-missing-deps -d 'Fail if an installed program or shared library needs a shared library which is not installed on this system, instead of warning'
+complete -c cargo-native-install -l sign-artifacts -x -d 'After installing, sign each installed program and shared library with the gpg key <keyid>, creating a detached signature <file>.sig beside it, which is recorded in the install receipt and checked by verify'
+complete -c cargo-native-install -l emit-config-h -x -a '(__fish_complete_path)' -d 'Write a C header to <file>, defining each resolved installation directory (such as BINDIR) as a string literal, before building'
+complete -c cargo-native-install -l emit-paths-rs -x -a '(__fish_complete_path)' -d 'Write a Rust module to <file>, defining each resolved installation directory (such as BINDIR) as a &str constant, before building'
+complete -c cargo-native-install -l install-emitted -d 'Also install the files written by --emit-config-h and --emit-paths-rs to <includedir>/<package>'
+complete -c cargo-native-install -l emit-env -x -a '(__fish_complete_path)' -d 'Write a shell script to <file>, assigning each resolved installation directory to a variable named as its environment variable (such as bindir)'
+complete -c cargo-native-install -l emit-dotenv -x -a '(__fish_complete_path)' -d 'Write the variables of --emit-env to <file> in dotenv format, which can also be included by make'
+complete -c cargo-native-install -l print-dirs -a 'human shell json' -d 'Print each resolved installation directory, after the configuration files, environment variables, and options are applied, and exit'
+complete -c cargo-native-install -l emit-ninja -x -a '(__fish_complete_path)' -d 'Write a ninja file to <file>, with an edge for each install step and a phony edge, install-<package>, which depends on all of them, instead of installing'
+complete -c cargo-native-install -l emit-script -x -a 'sh ps1' -d 'Print a standalone shell (sh) or PowerShell (ps1) script to standard output, which installs the package with plain commands such as install, mkdir, and ln -s, instead of installing'
+complete -c cargo-native-install -l keep-privileges -d 'When running as root through sudo, doas, or pkexec, build as root instead of as the invoking user'
+complete -c cargo-native-install -l container -d 'Skip the steps which fail in an unprivileged container: elevating, unless --elevate is given, and running install-docs, with a single warning'
+complete -c cargo-native-install -l no-container -d 'Do not skip any steps in a container'
+complete -c cargo-native-install -l shared -x -a 'lib bin' -d 'Treat cdylib targets as library targets by default and install to libdir'
+complete -c cargo-native-install -l manifest-dir -x -a '(__fish_complete_directories)' -d 'Install the package in <dir>, instead of the current directory'
+complete -c cargo-native-install -l manifest-path -x -a '(__fish_complete_path)' -d 'Install the package whose Cargo.toml is at <path>, like cargo'\''s --manifest-path'
+complete -c cargo-native-install -l out-dir -x -a '(__fish_complete_directories)' -d 'Consider cargo targets to be stored in <dir> instead of <manifest-dir>/target'
+complete -c cargo-native-install -l release -d 'Consider cargo targets to have been built in release mode (default)'
+complete -c cargo-native-install -l debug -d 'Consider cargo targets to have been built in debug mode'
+complete -c cargo-native-install -l config -x -a '(__fish_complete_path)' -d 'Parse project configuration from the specified file if it exists, rather than config.toml in the manifest directory'
+complete -c cargo-native-install -n __fish_use_subcommand -f -a switch-version -d 'Link the programs of <version>, which must already be installed with --slot, into bindir and sbindir, instead of building or installing'
+complete -c cargo-native-install -n __fish_use_subcommand -f -a relocate -d 'Move the installed package, recorded in its install receipt, to <prefix>, instead of building or installing, changing links, rpaths, and pkg-config files that refer to the old prefix'
+complete -c cargo-native-install -n __fish_use_subcommand -f -a diff-receipts -d 'Show the files which are added, removed, or changed between the install receipts <old> and <new>, instead of installing'
+complete -c cargo-native-install -n __fish_use_subcommand -f -a report-orphans -d 'List the files installed by earlier versions of the package which no target installs now, such as targets that were removed, and old versions of versioned targets, instead of installing'
+complete -c cargo-native-install -n __fish_use_subcommand -f -a check -d 'Validate the install targets of the package and of each workspace member, reporting unknown keys (with suggestions), targets without a type, missing required fields, conflicting options, and target files that do not exist, instead of installing'
+complete -c cargo-native-install -n __fish_use_subcommand -f -a test-install -d 'Install the package into a temporary prefix, run each installed program with --version (or the smoke-test commands of its target), then remove the prefix, instead of installing'
+complete -c cargo-native-install -n __fish_use_subcommand -f -a verify -d 'Check the signatures of the installed files, made by --sign-artifacts, with gpg, instead of installing'
+complete -c cargo-native-install -n __fish_use_subcommand -f -a config -d 'Set <key> to <value> in the configuration of the user, $XDG_CONFIG_HOME/cargo-native-install/config.toml, instead of installing'
+complete -c cargo-native-install -n __fish_use_subcommand -f -a build -d 'Build the package with cargo build, exporting each installation directory to cargo as an environment variable (see BUILD ENVIRONMENT in the manual), instead of installing'
@@ -0,0 +1,109 @@
+use std::{collections::BTreeMap, ffi::OsString, path::Path, process::Command};
+
+use install_dirs::dirs::InstallDirs;
+
+use crate::{output, privs, Error, Options, Verbosity};
+
+///
+/// The version of the variables [`environment`] exports to cargo, which is exported as `CARGO_NATIVE_INSTALL_ENV`.
+/// It is only increased when a variable is removed, or its meaning changes, so build scripts can check for the version they were written for
+pub const ENV_VERSION: u32 = 1;
+
+///
+/// Whether `name` is one of the installation directories which are exported to cargo: those of [`crate::emit::DIRS`], and `runstatedir`
+pub fn is_exported(name: &str) -> bool {
+    name == "runstatedir" || crate::emit::DIRS.contains(&name)
+}
+
+///
+/// The variable which the directory `name` is exported as: the name given to it by `--env-name`, or otherwise its name after `--env-prefix`
+fn var_name(name: &str, opts: &Options) -> String {
+    match opts.env_names.iter().find(|(dir, _)| dir == name) {
+        Some((_, var)) => var.clone(),
+        None => format!("{}{}", opts.env_prefix.as_deref().unwrap_or(""), name),
+    }
+}
+
+///
+/// The variables exported to cargo while building: each installation directory in `dirs`, as the absolute path the package finds it at when it runs,
+/// `CARGO_NATIVE_INSTALL_ENV`, and, with `--sysroot`, `DESTDIR`, the directory the files are staged beneath, which the directories do not include
+pub fn environment(dirs: &InstallDirs, opts: &Options) -> BTreeMap<String, OsString> {
+    let mut env = dirs
+        .as_env()
+        .into_iter()
+        .map(|(name, dir)| (name, dir.as_os_str()))
+        .chain(std::iter::once(("includedir", dirs.includedir.as_os_str())))
+        // install-dirs does not resolve runstatedir, which is beneath localstatedir, as in the GNU Coding Standards
+        .map(|(name, dir)| match name {
+            "runstatedir" if Path::new(dir).is_relative() => (
+                var_name(name, opts),
+                dirs.localstatedir.join(dir).into_os_string(),
+            ),
+            name => (var_name(name, opts), dir.to_owned()),
+        })
+        .collect::<BTreeMap<_, _>>();
+    env.insert(
+        "CARGO_NATIVE_INSTALL_ENV".to_owned(),
+        ENV_VERSION.to_string().into(),
+    );
+    if let Some(sysroot) = &opts.sysroot {
+        env.insert("DESTDIR".to_owned(), sysroot.clone().into_os_string());
+    }
+    env
+}
+
+///
+/// Builds the package in `manifest_dir` with `cargo build`, with the [`environment`] of `dirs`,
+/// as the invoking user if this program was run with sudo, so that the target directory is not made unwritable to them
+pub fn build(manifest_dir: &Path, dirs: &InstallDirs, opts: &Options) -> Result<(), Error> {
+    let mut cargo = Command::new("cargo");
+    cargo.arg("build");
+    cargo.current_dir(manifest_dir);
+    if let Some(dir) = &opts.out_dir {
+        cargo.arg("--target-dir");
+        cargo.arg(dir);
+    }
+    match opts.verbosity {
+        Verbosity::Quiet => {
+            cargo.arg("--quiet");
+        }
+        Verbosity::Normal => {}
+        Verbosity::Verbose => {
+            cargo.arg("--verbose");
+        }
+        Verbosity::VeryVerbose => {
+            cargo.arg("-vv");
+        }
+    }
+
+    if !opts.debug {
+        cargo.arg("--release");
+    }
+
+    let env = environment(dirs, opts);
+    for (var, value) in &env {
+        output::verbose(
+            opts,
+            format_args!("Exporting {}={}", var, value.to_string_lossy()),
+        );
+    }
+    cargo.envs(env);
+    if !opts.keep_privileges {
+        if let Some(user) = privs::invoking_user() {
+            output::verbose(
+                opts,
+                format_args!("Building as {} instead of root", user.name()),
+            );
+            privs::drop_privileges(&mut cargo, &user);
+        }
+    }
+    output::command(opts, &cargo);
+
+    let status = cargo
+        .status()
+        .map_err(|e| Error::Spawn("cargo".into(), e))?;
+    if !status.success() {
+        return Err(Error::Tool("cargo".into(), status));
+    }
+    Ok(())
+}
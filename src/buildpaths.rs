@@ -0,0 +1,201 @@
+use std::{
+    cmp::Reverse,
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use install_dirs::dirs::InstallDirs;
+
+use crate::{
+    deps,
+    manifest::{Target, TargetType},
+    output,
+    output::Status,
+    sysroot, Options,
+};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum BuildPathCheck {
+    #[default]
+    Off,
+    Warn,
+    Deny,
+}
+
+impl BuildPathCheck {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "off" => Some(BuildPathCheck::Off),
+            "warn" => Some(BuildPathCheck::Warn),
+            "deny" => Some(BuildPathCheck::Deny),
+            _ => None,
+        }
+    }
+}
+
+///
+/// A directory of the build machine which should not appear in installed binaries
+struct BuildPath {
+    dir: PathBuf,
+    description: &'static str,
+    /// The prefix that `--remap-path-prefix` should replace it with
+    remap: &'static str,
+}
+
+///
+/// The directories of the build machine, from the most specific (longest) to the least, so that a path is reported as the first one it is within
+fn build_paths(manifest_dir: &Path, opts: &Options) -> Vec<BuildPath> {
+    let mut paths = vec![BuildPath {
+        dir: std::path::absolute(manifest_dir).unwrap_or_else(|_| manifest_dir.to_path_buf()),
+        description: "the source directory",
+        remap: ".",
+    }];
+    if let Some(dir) = &opts.out_dir {
+        paths.push(BuildPath {
+            dir: std::path::absolute(dir).unwrap_or_else(|_| dir.clone()),
+            description: "the target directory",
+            remap: "target",
+        });
+    }
+    if let Ok(dir) = home::cargo_home() {
+        paths.push(BuildPath {
+            dir,
+            description: "the cargo home directory",
+            remap: "/cargo",
+        });
+    }
+    if let Some(dir) = home::home_dir() {
+        paths.push(BuildPath {
+            dir,
+            description: "the home directory",
+            remap: "~",
+        });
+    }
+    // A home directory of / would match every path
+    paths.retain(|path| path.dir.parent().is_some());
+    paths.sort_by_key(|path| Reverse(path.dir.as_os_str().len()));
+    paths
+}
+
+///
+/// The printable string around `pos` in `buf`, such as the whole path that a build path is the start of
+fn string_at(buf: &[u8], pos: usize) -> String {
+    let printable = |b: &u8| b.is_ascii_graphic() || *b == b' ';
+    let end = buf[pos..]
+        .iter()
+        .position(|b| !printable(b))
+        .map_or(buf.len(), |len| pos + len);
+    String::from_utf8_lossy(&buf[pos..end.min(pos + 200)]).into_owned()
+}
+
+///
+/// Reports the directories of the build machine, such as the source directory and the home directory, which appear in the installed programs and libraries in `targets`,
+/// such as in panic messages and debug information, and how to rebuild without them.
+/// With `--dry-run`, the artifacts are examined instead, before they are stripped.
+/// Returns the number of files which contain build paths
+pub fn audit(
+    dirs: &InstallDirs,
+    targets: &HashMap<String, Target>,
+    manifest_dir: &Path,
+    opts: &Options,
+) -> usize {
+    // The installed files are on the remote machine, which cannot be examined
+    if opts.filesystem.is_some() {
+        return 0;
+    }
+    let paths = build_paths(manifest_dir, opts);
+    let mut leaking = 0;
+    let mut found = vec![false; paths.len()];
+    for (_, target, dest) in deps::installed(dirs, targets, opts) {
+        if !matches!(
+            target.type_,
+            Some(
+                TargetType::Bin
+                    | TargetType::SBin
+                    | TargetType::Libexec
+                    | TargetType::Shared
+                    | TargetType::Library
+            )
+        ) {
+            continue;
+        }
+        let dest = sysroot::in_sysroot(&dest, opts);
+        let file = match &target.target_file {
+            Some(src) if opts.dry_run => src.clone(),
+            _ => dest.clone(),
+        };
+        let buf = match fs::read(&file) {
+            Ok(buf) => buf,
+            Err(_) => continue,
+        };
+
+        // The number of occurrences of each build path, and the first of them
+        let mut counts = vec![(0, None); paths.len()];
+        for (i, path) in paths.iter().enumerate() {
+            let needle = path.dir.to_string_lossy();
+            let needle = needle.as_bytes();
+            let mut pos = 0;
+            while let Some(off) = buf[pos..]
+                .windows(needle.len())
+                .position(|window| window == needle)
+            {
+                let at = pos + off;
+                pos = at + needle.len();
+                let more_specific = paths[..i]
+                    .iter()
+                    .any(|other| buf[at..].starts_with(other.dir.to_string_lossy().as_bytes()));
+                if !more_specific {
+                    counts[i].0 += 1;
+                    counts[i].1.get_or_insert(at);
+                }
+            }
+        }
+        if counts.iter().all(|(count, _)| *count == 0) {
+            continue;
+        }
+        leaking += 1;
+        for (i, (count, first)) in counts.into_iter().enumerate() {
+            let first = match first {
+                Some(first) => first,
+                None => continue,
+            };
+            found[i] = true;
+            let msg = format_args!(
+                "{} contains {} path{} in {} ({}), such as {}",
+                dest.display(),
+                count,
+                if count == 1 { "" } else { "s" },
+                paths[i].description,
+                paths[i].dir.display(),
+                string_at(&buf, first)
+            );
+            if opts.build_path_check == BuildPathCheck::Deny {
+                output::status(opts, Status::Failed, msg);
+            } else {
+                output::status(opts, Status::Warning, format_args!("Warning: {}", msg));
+            }
+        }
+    }
+
+    if leaking != 0 {
+        // rustc applies the last prefix which matches, so the most specific prefixes go last
+        let flags = paths
+            .iter()
+            .zip(&found)
+            .rev()
+            .filter(|(_, found)| **found)
+            .map(|(path, _)| format!("--remap-path-prefix={}={}", path.dir.display(), path.remap))
+            .collect::<Vec<_>>()
+            .join(" ");
+        output::status(
+            opts,
+            Status::Warning,
+            format_args!(
+                "Warning: to remove these paths, rebuild with RUSTFLAGS=\"{}\", and strip debug information",
+                flags
+            ),
+        );
+    }
+    leaking
+}
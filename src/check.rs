@@ -0,0 +1,437 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::Path,
+};
+
+use cargo_toml::Manifest;
+use serde_json::{json, Map, Value};
+
+use crate::{
+    get_package_targets,
+    manifest::{InstallMethod, NativeInstallMetadata, Target, TargetType},
+    manpage, output,
+    output::Status,
+    Error, Options,
+};
+
+///
+/// A problem with a target. Errors prevent the target from installing, while warnings are options that have no effect
+enum Problem {
+    Error(String),
+    Warning(String),
+}
+
+///
+/// Finds the problems with `target`, as `raw` was written in the manifest of the package in `package_dir`, and after defaults are filled in
+fn check_target(raw: Option<&Target>, target: &Target, package_dir: &Path) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    for (field, suggestion) in raw.iter().flat_map(|raw| raw.unknown_fields()) {
+        problems.push(Problem::Error(match suggestion {
+            Some(suggestion) => format!(
+                "has unknown field `{}`, did you mean `{}`?",
+                field, suggestion
+            ),
+            None => format!("has unknown field `{}`", field),
+        }));
+    }
+    let type_ = match target.type_ {
+        Some(type_) => type_,
+        None => {
+            problems.push(Problem::Error("has no `type`".into()));
+            return problems;
+        }
+    };
+
+    if let Some(kind) = match type_ {
+        TargetType::Run => Some("run"),
+        TargetType::Check => Some("check"),
+        _ => None,
+    } {
+        if target.target_file.is_none() {
+            problems.push(Problem::Error(format!(
+                "is a `{}` target, but has no `target-file` to run",
+                kind
+            )));
+        }
+        if let Some(raw) = raw {
+            let ignored = [
+                ("installed-path", raw.installed_path.is_some()),
+                ("installed-aliases", raw.installed_aliases.is_some()),
+                ("install-method", raw.install_method.is_some()),
+                ("versioned", raw.versioned),
+                ("directory", raw.directory),
+                ("post-install", raw.post_install.is_some()),
+                ("pre-uninstall", raw.pre_uninstall.is_some()),
+                ("smoke-test", raw.smoke_test.is_some()),
+                ("template-engine", raw.template_engine.is_some()),
+            ];
+            for (field, _) in ignored.iter().filter(|(_, set)| *set) {
+                problems.push(Problem::Warning(format!(
+                    "`{}` has no effect on `{}` targets",
+                    field, kind
+                )));
+            }
+            // The first list a code is in decides what it means, so a code in two of them is most likely a mistake
+            let lists = [
+                ("success-codes", &raw.success_codes),
+                ("skip-codes", &raw.skip_codes),
+                ("soft-fail-codes", &raw.soft_fail_codes),
+            ];
+            for (i, (first, codes)) in lists.iter().enumerate() {
+                for (second, others) in &lists[i + 1..] {
+                    let others = others.as_deref().unwrap_or_default();
+                    if let Some(code) = codes.iter().flatten().find(|code| others.contains(code)) {
+                        problems.push(Problem::Error(format!(
+                            "has exit code {} in both `{}` and `{}`",
+                            code, first, second
+                        )));
+                    }
+                }
+            }
+        }
+    } else {
+        if target.installed_path.is_none() {
+            problems.push(Problem::Error("has no `installed-path`".into()));
+        }
+        if !target.directory && target.target_file.is_none() {
+            problems.push(Problem::Error(
+                "has no `target-file`, and is not a `directory`".into(),
+            ));
+        }
+    }
+
+    if let Some(raw) = raw {
+        if raw.directory && raw.strip == Some(true) {
+            problems.push(Problem::Error(
+                "sets both `directory` and `strip`, but directories cannot be stripped".into(),
+            ));
+        }
+        if target.strip != Some(true) {
+            for (field, set) in [
+                ("strip-mode", raw.strip_mode.is_some()),
+                ("strip-args", raw.strip_args.is_some()),
+                ("strip-keep-symbols", raw.strip_keep_symbols.is_some()),
+            ] {
+                if set {
+                    problems.push(Problem::Warning(format!(
+                        "`{}` has no effect on targets which are not stripped",
+                        field
+                    )));
+                }
+            }
+        }
+        if !target.directory {
+            for (field, set) in [
+                ("dir-mode", raw.dir_mode.is_some()),
+                ("file-mode", raw.file_mode.is_some()),
+                ("modes", raw.modes.is_some()),
+                ("strip-components", raw.strip_components.is_some()),
+            ] {
+                if set {
+                    problems.push(Problem::Warning(format!(
+                        "`{}` has no effect on targets which are not a `directory`",
+                        field
+                    )));
+                }
+            }
+        }
+        if raw
+            .install_method
+            .is_some_and(|method| method != InstallMethod::Copy)
+        {
+            // A linked artifact is shared with the build directory, so it is never changed once installed
+            for (field, set) in [
+                ("strip", raw.strip == Some(true)),
+                ("rpath", raw.rpath.is_some()),
+                ("mode", raw.mode.is_some()),
+                ("dir-mode", raw.dir_mode.is_some()),
+                ("file-mode", raw.file_mode.is_some()),
+                ("modes", raw.modes.is_some()),
+            ] {
+                if set {
+                    problems.push(Problem::Warning(format!(
+                        "`{}` has no effect on targets which are installed as links",
+                        field
+                    )));
+                }
+            }
+            if raw.target_file.is_none() {
+                problems.push(Problem::Warning(
+                    "sets `install-method`, but has no `target-file` to link to".into(),
+                ));
+            }
+            if raw.template_engine.is_some() {
+                problems.push(Problem::Warning(
+                    "sets `install-method`, but a rendered template is always copied".into(),
+                ));
+            }
+        }
+        if raw.rpath.is_some()
+            && !matches!(
+                type_,
+                TargetType::Bin | TargetType::SBin | TargetType::Libexec | TargetType::Shared
+            )
+        {
+            problems.push(Problem::Warning(
+                "sets `rpath`, but only programs and shared libraries have an rpath".into(),
+            ));
+        }
+        if raw.template_engine.is_some() && raw.directory {
+            problems.push(Problem::Warning(
+                "`template-engine` has no effect on targets which are a `directory`".into(),
+            ));
+        }
+        if let Some(lang) = raw.lang.as_ref().filter(|lang| !manpage::is_locale(lang)) {
+            problems.push(Problem::Error(format!(
+                "has `lang` {}, which is not named like a locale, such as de or pt_BR",
+                lang
+            )));
+        }
+        if !matches!(type_, TargetType::Run | TargetType::Check) {
+            for (field, set) in [
+                ("success-codes", raw.success_codes.is_some()),
+                ("skip-codes", raw.skip_codes.is_some()),
+                ("soft-fail-codes", raw.soft_fail_codes.is_some()),
+            ] {
+                if set {
+                    problems.push(Problem::Warning(format!(
+                        "`{}` has no effect on targets other than `run` and `check` targets",
+                        field
+                    )));
+                }
+            }
+        }
+        if type_ != TargetType::HtmlDoc {
+            for (field, set) in [("index", raw.index.is_some()), ("doc-base", raw.doc_base)] {
+                if set {
+                    problems.push(Problem::Warning(format!(
+                        "`{}` has no effect on targets other than `html-doc` targets",
+                        field
+                    )));
+                }
+            }
+        }
+    }
+
+    if let Some(file) = &target.target_file {
+        if !package_dir.join(file).exists() {
+            // Artifacts built by cargo are usually missing until the package is built
+            problems.push(match raw.and_then(|raw| raw.target_file.as_ref()) {
+                Some(_) if target.optional => Problem::Warning(format!(
+                    "`target-file` {} does not exist, so the optional target is skipped",
+                    file.display()
+                )),
+                Some(_) => {
+                    Problem::Error(format!("`target-file` {} does not exist", file.display()))
+                }
+                None => Problem::Warning(format!("{} has not been built", file.display())),
+            });
+        }
+    }
+    problems
+}
+
+///
+/// Checks the install targets of the package in `package_dir`, reporting each problem, and returns the number of errors
+fn check_package(package_dir: &Path, manifest_dir: &Path, opts: &Options) -> Result<usize, Error> {
+    let mut manifest =
+        Manifest::<NativeInstallMetadata>::from_path_with_metadata(package_dir.join("Cargo.toml"))?;
+    let package = match &manifest.package {
+        Some(package) => package.name.clone(),
+        None => return Ok(0),
+    };
+    let raw = manifest
+        .package
+        .as_ref()
+        .and_then(|package| package.metadata.as_ref())
+        .map(|metadata| metadata.install_targets.clone())
+        .unwrap_or_default();
+    let mut targets = HashMap::new();
+    get_package_targets(&mut manifest, &mut targets, opts, manifest_dir)?;
+
+    let manifest_path = package_dir.join("Cargo.toml");
+    let mut errors = 0;
+    for (name, target) in targets.iter().collect::<BTreeMap<_, _>>() {
+        if target.exclude {
+            continue;
+        }
+        for problem in check_target(raw.get(name), target, package_dir) {
+            match problem {
+                Problem::Error(msg) => {
+                    errors += 1;
+                    output::status_in(
+                        opts,
+                        Status::Failed,
+                        &manifest_path,
+                        format_args!("{}: target {} {}", package, name, msg),
+                    );
+                }
+                Problem::Warning(msg) => output::status_in(
+                    opts,
+                    Status::Warning,
+                    &manifest_path,
+                    format_args!("Warning: {}: target {} {}", package, name, msg),
+                ),
+            }
+        }
+    }
+    Ok(errors)
+}
+
+///
+/// Validates the install targets of the package in `manifest_dir`, and of each workspace member, without building or installing anything
+pub fn check(manifest_dir: &Path, opts: &Options) -> Result<(), Error> {
+    let manifest = Manifest::<NativeInstallMetadata>::from_path_with_metadata(
+        manifest_dir.join("Cargo.toml"),
+    )?;
+    let mut package_dirs = vec![manifest_dir.to_path_buf()];
+    if let Some(workspace) = &manifest.workspace {
+        package_dirs.extend(workspace.members.iter().map(|member| {
+            member
+                .split(&['/', '\\'] as &[_])
+                .fold(manifest_dir.to_path_buf(), |dir, component| {
+                    dir.join(component)
+                })
+        }));
+    }
+
+    let mut errors = 0;
+    for package_dir in &package_dirs {
+        errors += check_package(package_dir, manifest_dir, opts)?;
+    }
+    if errors != 0 {
+        return Err(Error::InvalidTargets(errors));
+    }
+    output::status(
+        opts,
+        Status::Installed,
+        format_args!("No problems found in the install targets"),
+    );
+    Ok(())
+}
+
+///
+/// A JSON Schema for `[package.metadata.install-targets]`, for editors which validate `Cargo.toml`
+pub fn schema() -> Value {
+    let string = |description: &str| json!({ "type": "string", "description": description });
+    let flag = |description: &str| json!({ "type": "boolean", "description": description });
+    let codes = |description: &str, default: &str| {
+        json!({
+            "type": "array",
+            "items": { "type": "integer" },
+            "description": format!("{}. Defaults to {}", description, default)
+        })
+    };
+    // The properties are written in parts, as one `json!` literal with all of them needs more than the default recursion limit
+    let mut properties = Map::new();
+    for part in [
+        json!({
+            "type": {
+                "description": "The kind of target, which determines the directory it is installed to",
+                "enum": [
+                    "bin", "sbin", "library", "shared", "libexec", "include", "sysconfig",
+                    "data", "doc", "html-doc", "man", "info", "run", "check"
+                ]
+            },
+            "privileged": flag("Do not install with --user-prefix or --no-privileged"),
+            "directory": flag("Create the target as a directory, and copy the source files to it"),
+            "install-dir": string("The directory to install into, instead of the directory for the type"),
+            "mode": string("The mode to install as, in a form acceptable to chmod(1)"),
+            "dir-mode": string("The mode of the directories of a directory target, instead of mode"),
+            "file-mode": string("The mode of the files of a directory target, instead of mode"),
+            "modes": {
+                "type": "object",
+                "additionalProperties": { "type": "string" },
+                "description": "For directory targets, the mode of the files which match each wildcard pattern, such as \"*.sh\" = \"0755\", instead of file-mode. The longest matching pattern applies"
+            },
+            "strip-components": {
+                "type": "integer",
+                "minimum": 0,
+                "description": "The number of leading components to remove from the paths in a directory target, as with tar --strip-components"
+            },
+            "installed-path": string("The path to the installed file, relative to install-dir"),
+            "target-file": string("The file in the source directory, relative to Cargo.toml"),
+            "prefix": string("The prefix of the file name of a library built by cargo"),
+            "installed-aliases": {
+                "type": "array",
+                "items": { "type": "string" },
+                "description": "Symbolic links to the installed target"
+            }
+        }),
+        json!({
+            "install-method": {
+                "description": "How the target is installed: by copying target-file, or as a symbolic or hard link to it",
+                "enum": ["copy", "symlink", "hardlink"]
+            },
+            "exclude": flag("Disable this target"),
+            "optional": flag("Skip this target, instead of failing, if target-file does not exist"),
+            "only-if": string("A shell command which must succeed for the target to be installed"),
+            "strip": flag("Strip the installed binary"),
+            "strip-mode": {
+                "description": "What strip removes: every symbol (-s), only debug information (-S), or only unneeded symbols (--strip-unneeded)",
+                "enum": ["all", "debug", "unneeded"]
+            },
+            "strip-args": {
+                "type": "array",
+                "items": { "type": "string" },
+                "description": "Extra arguments to strip, after the strip mode"
+            },
+            "strip-keep-symbols": {
+                "type": "array",
+                "items": { "type": "string" },
+                "description": "Symbols, or wildcard patterns, to keep when stripping"
+            },
+            "rpath": {
+                "type": "string",
+                "pattern": "^(set:.*|remove)$",
+                "description": "Change the run-time library search path of the installed binary: set:<path> (such as set:$ORIGIN/../lib), or remove"
+            }
+        }),
+        json!({
+            "post-install": string("A command run with the shell once the target is installed, such as ldconfig. Directories written as <libdir>, @libdir@, or ${libdir} are replaced"),
+            "pre-uninstall": {
+                "type": "array",
+                "items": { "type": "string" },
+                "description": "Commands recorded in the install receipt, and run with the shell before the target is uninstalled, such as stopping a service"
+            },
+            "success-codes": codes("The exit codes of a run or check target which succeed", "[0, 20]"),
+            "skip-codes": codes("The exit codes of a run or check target which skip it, which is reported", "[10]"),
+            "soft-fail-codes": codes("The exit codes of a run or check target which fail it, which is reported, but do not fail installation", "[2]"),
+            "smoke-test": {
+                "type": "array",
+                "items": { "type": "string" },
+                "description": "Commands run with the shell by test-install, which must succeed, instead of running bin and sbin targets with --version"
+            },
+            "index": string("For html-doc targets, the page index.html links to"),
+            "doc-base": flag("For html-doc targets, register the documentation with doc-base"),
+            "versioned": flag("Install the target as <name>-<version>, with an alias of <name>"),
+            "lang": string("The locale of the target, such as de, so that --locales can leave it out. Man targets are installed to <mandir>/<lang>/man<N>"),
+            "template-engine": {
+                "description": "Render target-file as a template with this engine before installing it, with the installation directories, the package, and the platform as variables",
+                "enum": ["minijinja"]
+            }
+        }),
+    ] {
+        if let Value::Object(part) = part {
+            properties.extend(part);
+        }
+    }
+    json!({
+        "$schema": "http://json-schema.org/draft-07/schema#",
+        "title": "install-targets",
+        "description": "The targets installed by cargo-native-install, from [package.metadata.install-targets]",
+        "type": "object",
+        "additionalProperties": { "$ref": "#/definitions/target" },
+        "definitions": {
+            "target": {
+                "type": "object",
+                "additionalProperties": false,
+                "properties": properties,
+                "allOf": [{
+                    "if": { "properties": { "type": { "enum": ["run", "check"] } }, "required": ["type"] },
+                    "then": { "required": ["target-file"] }
+                }]
+            }
+        }
+    })
+}
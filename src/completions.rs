@@ -0,0 +1,289 @@
+use std::fmt::Write;
+
+use crate::help::OPTIONS;
+
+///
+/// A shell which `--completions` writes a completion script for
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+impl Shell {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "bash" => Some(Self::Bash),
+            "zsh" => Some(Self::Zsh),
+            "fish" => Some(Self::Fish),
+            _ => None,
+        }
+    }
+}
+
+///
+/// What is completed as the value of an option
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Value {
+    None,
+    Any,
+    Directory,
+    File,
+    Command,
+    User,
+    Group,
+    Choices(Vec<String>),
+}
+
+impl Value {
+    ///
+    /// The completion of the value named by `spec` in `--help`, such as `<dir>` or `all|debug|unneeded`
+    fn parse(spec: &str) -> Self {
+        match spec.trim_start_matches('<').trim_end_matches('>') {
+            "dir" | "prefix" => Self::Directory,
+            "file" | "path" | "archive" => Self::File,
+            "prg" => Self::Command,
+            "user" => Self::User,
+            "group" => Self::Group,
+            _ if spec.starts_with('<') || spec.contains(['<', '/', ':']) => Self::Any,
+            spec => Self::Choices(spec.split('|').map(ToOwned::to_owned).collect()),
+        }
+    }
+}
+
+struct Opt {
+    name: &'static str,
+    value: Value,
+    optional: bool,
+    description: String,
+}
+
+///
+/// The first sentence of `description`, which is all that fits beside a completion
+fn summary(description: &str) -> String {
+    let end = description.find(". ").unwrap_or(description.len());
+    description[..end].trim_end_matches('.').to_owned()
+}
+
+///
+/// The options and commands listed by `--help`. Options which are listed once for each value, such as `--shared=lib`, are merged
+fn options() -> (Vec<Opt>, Vec<(&'static str, String)>) {
+    let mut opts = Vec::<Opt>::new();
+    let mut commands = Vec::<(&str, String)>::new();
+    for (names, description) in OPTIONS {
+        if !names.starts_with('-') {
+            let command = names.split(' ').next().unwrap_or(names);
+            if !commands.iter().any(|(name, _)| *name == command) {
+                commands.push((command, summary(description)));
+            }
+            continue;
+        }
+        for name in names.split(", ") {
+            let (name, value, optional) = match name.find("[=") {
+                Some(pos) => (
+                    &name[..pos],
+                    Value::parse(name[pos + 2..].trim_end_matches(']')),
+                    true,
+                ),
+                None => match name.split_once('=') {
+                    Some((name, spec)) => (name, Value::parse(spec), false),
+                    None => (name, Value::None, false),
+                },
+            };
+            match opts.iter_mut().find(|opt| opt.name == name) {
+                Some(Opt {
+                    value: Value::Choices(choices),
+                    ..
+                }) => {
+                    if let Value::Choices(more) = value {
+                        choices.extend(more);
+                    }
+                }
+                Some(_) => {}
+                None => opts.push(Opt {
+                    name,
+                    value,
+                    optional,
+                    description: summary(description),
+                }),
+            }
+        }
+    }
+    (opts, commands)
+}
+
+///
+/// Quotes `s` for a shell, in single quotes
+fn quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "'\\''"))
+}
+
+fn bash() -> String {
+    let (opts, commands) = options();
+    let mut words = Vec::new();
+    let mut cases = String::new();
+    for opt in &opts {
+        match opt.value {
+            Value::None => words.push(opt.name.to_owned()),
+            _ if opt.optional => {
+                words.push(opt.name.to_owned());
+                words.push(format!("{}=", opt.name));
+            }
+            _ => words.push(format!("{}=", opt.name)),
+        }
+        let action = match &opt.value {
+            Value::None | Value::Any => continue,
+            Value::Directory => "compgen -d".to_owned(),
+            Value::File => "compgen -f".to_owned(),
+            Value::Command => "compgen -c".to_owned(),
+            Value::User => "compgen -u".to_owned(),
+            Value::Group => "compgen -g".to_owned(),
+            Value::Choices(choices) => format!("compgen -W {}", quote(&choices.join(" "))),
+        };
+        let _ = writeln!(
+            cases,
+            "        {}) COMPREPLY=($({} -- \"$cur\")) ;;",
+            opt.name, action
+        );
+    }
+    words.extend(commands.iter().map(|(name, _)| (*name).to_owned()));
+
+    format!(
+        r#"# bash completion for cargo-native-install, generated by cargo-native-install --completions=bash
+_cargo_native_install() {{
+    local cur="${{COMP_WORDS[COMP_CWORD]}}" opt=
+    # Readline splits --option=value at the =
+    if [[ $cur == = ]]; then
+        opt="${{COMP_WORDS[COMP_CWORD-1]}}"
+        cur=
+    elif [[ $COMP_CWORD -gt 1 && ${{COMP_WORDS[COMP_CWORD-1]}} == = ]]; then
+        opt="${{COMP_WORDS[COMP_CWORD-2]}}"
+    fi
+    case "$opt" in
+{}        "")
+            COMPREPLY=($(compgen -W {} -- "$cur"))
+            [[ ${{#COMPREPLY[@]}} -eq 1 && ${{COMPREPLY[0]}} == *= ]] && compopt -o nospace
+            ;;
+        *) COMPREPLY=() ;;
+    esac
+}}
+complete -o filenames -F _cargo_native_install cargo-native-install
+"#,
+        cases,
+        quote(&words.join(" "))
+    )
+}
+
+fn zsh() -> String {
+    let escape = |s: &str| {
+        s.replace('\\', "\\\\")
+            .replace('[', "\\[")
+            .replace(']', "\\]")
+            .replace(':', "\\:")
+    };
+    let describe = |s: &str| s.replace('[', "\\[").replace(']', "\\]");
+    let (opts, commands) = options();
+    let mut specs = String::new();
+    for opt in &opts {
+        let action = match &opt.value {
+            Value::None => String::new(),
+            Value::Any => " ".to_owned(),
+            Value::Directory => "_files -/".to_owned(),
+            Value::File => "_files".to_owned(),
+            Value::Command => "_command_names -e".to_owned(),
+            Value::User => "_users".to_owned(),
+            Value::Group => "_groups".to_owned(),
+            Value::Choices(choices) => format!("({})", choices.join(" ")),
+        };
+        let spec = if opt.value == Value::None {
+            format!("{}[{}]", opt.name, describe(&opt.description))
+        } else {
+            format!(
+                "{}=-[{}]:{}{}:{}",
+                opt.name,
+                describe(&opt.description),
+                if opt.optional { ":" } else { "" },
+                opt.name.trim_start_matches('-'),
+                action
+            )
+        };
+        let _ = writeln!(specs, "    {} \\", quote(&spec));
+    }
+    let commands = commands
+        .iter()
+        .map(|(name, description)| {
+            format!(
+                "{}\\:\"{}\"",
+                name,
+                escape(description).replace('"', "\\\"")
+            )
+        })
+        .collect::<Vec<_>>();
+
+    format!(
+        "#compdef cargo-native-install\n\
+         # zsh completion for cargo-native-install, generated by cargo-native-install --completions=zsh\n\
+         _arguments -s \\\n\
+         {}    {}\n",
+        specs,
+        quote(&format!("*:command:(({}))", commands.join(" ")))
+    )
+}
+
+fn fish() -> String {
+    let (opts, commands) = options();
+    let mut out = String::from(
+        "# fish completion for cargo-native-install, generated by cargo-native-install --completions=fish\n",
+    );
+    for opt in &opts {
+        let _ = write!(out, "complete -c cargo-native-install");
+        match opt.name.strip_prefix("--") {
+            Some(long) => {
+                let _ = write!(out, " -l {}", long);
+            }
+            None => {
+                let _ = write!(out, " -s {}", opt.name.trim_start_matches('-'));
+            }
+        }
+        let arguments = match &opt.value {
+            Value::None => None,
+            Value::Any => Some(None),
+            Value::Directory => Some(Some("(__fish_complete_directories)".to_owned())),
+            Value::File => Some(Some("(__fish_complete_path)".to_owned())),
+            Value::Command => Some(Some("(__fish_complete_command)".to_owned())),
+            Value::User => Some(Some("(__fish_complete_users)".to_owned())),
+            Value::Group => Some(Some("(__fish_complete_groups)".to_owned())),
+            Value::Choices(choices) => Some(Some(choices.join(" "))),
+        };
+        // Options whose value is optional must be given it after =, which fish completes without -r
+        if let Some(arguments) = arguments {
+            if !opt.optional {
+                out.push_str(" -x");
+            }
+            if let Some(arguments) = arguments {
+                let _ = write!(out, " -a {}", quote(&arguments));
+            }
+        }
+        let _ = writeln!(out, " -d {}", quote(&opt.description));
+    }
+    for (name, description) in commands {
+        let _ = writeln!(
+            out,
+            "complete -c cargo-native-install -n __fish_use_subcommand -f -a {} -d {}",
+            name,
+            quote(&description)
+        );
+    }
+    out
+}
+
+///
+/// The script which completes the options and commands of this program in `shell`, generated from those listed by `--help`
+pub fn script(shell: Shell) -> String {
+    match shell {
+        Shell::Bash => bash(),
+        Shell::Zsh => zsh(),
+        Shell::Fish => fish(),
+    }
+}
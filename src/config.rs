@@ -0,0 +1,275 @@
+use std::{
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+use install_dirs::dirs::InstallDirs;
+use serde::Deserialize;
+
+use crate::{
+    emit::DIRS,
+    manifest::{self, Config, Defaults, SignTool},
+    output, Error, Options,
+};
+
+///
+/// The configuration file of the user, `$XDG_CONFIG_HOME/cargo-native-install/config.toml`, which `config set` writes
+pub fn user_path() -> Option<PathBuf> {
+    std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| home::home_dir().map(|home| home.join(".config")))
+        .map(|dir| dir.join("cargo-native-install").join("config.toml"))
+}
+
+///
+/// The configuration files that are read, if they exist, from lowest to highest precedence:
+/// the system configuration, the configuration of the user, and the configuration of the project (or the file given by `--config`)
+pub fn search_path(opts: &Options) -> Vec<PathBuf> {
+    let mut paths = vec![PathBuf::from("/etc/cargo-native-install/config.toml")];
+    paths.extend(opts.user_config.clone().or_else(user_path));
+    paths.push(match &opts.config {
+        Some(file) => file.clone(),
+        None => opts
+            .manifest_dir
+            .as_deref()
+            .unwrap_or_else(|| Path::new("."))
+            .join("config.toml"),
+    });
+    paths
+}
+
+///
+/// Merges `layer` into `base`. Tables are merged key by key, and any other value in `layer` replaces the value in `base`
+fn merge(base: &mut toml::Value, layer: toml::Value) {
+    match (base, layer) {
+        (toml::Value::Table(base), toml::Value::Table(layer)) => {
+            for (key, value) in layer {
+                match base.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, layer) => *base = layer,
+    }
+}
+
+///
+/// Reads `path`, if it exists. Each file is checked on its own, so that errors are reported against the file that caused them
+fn read(path: &Path) -> Result<Option<toml::Value>, Error> {
+    let str = match fs::read_to_string(path) {
+        Ok(str) => str,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(Error::Config(path.to_path_buf(), e.into())),
+    };
+    let layer = match toml::from_str::<toml::Value>(&str) {
+        Ok(v) => v,
+        Err(e) => return Err(Error::Config(path.to_path_buf(), e.into())),
+    };
+    if let Err(e) = manifest::Config::deserialize(layer.clone()) {
+        return Err(Error::Config(path.to_path_buf(), e.into()));
+    }
+    Ok(Some(layer))
+}
+
+///
+/// Each configuration file in the [`search_path`] that exists, with its contents
+fn layers(opts: &Options) -> impl Iterator<Item = Result<(PathBuf, toml::Value), Error>> {
+    search_path(opts)
+        .into_iter()
+        .filter_map(|path| read(&path).transpose().map(|layer| Ok((path, layer?))))
+}
+
+///
+/// Reads the default installation directories, and the other settings, from each configuration file in the [`search_path`] that exists.
+/// Settings in a file take precedence over those in the files before it
+pub fn load(opts: &Options) -> Result<Config, Error> {
+    let mut merged = None;
+    for layer in layers(opts) {
+        let (path, layer) = layer?;
+        output::verbose(
+            opts,
+            format_args!("Reading configuration from {}", path.display()),
+        );
+        match &mut merged {
+            Some((merged, last)) => {
+                merge(merged, layer);
+                *last = path;
+            }
+            None => merged = Some((layer, path)),
+        }
+    }
+
+    match merged {
+        Some((merged, last)) => {
+            let config =
+                Config::deserialize(merged).map_err(|e| Error::Config(last.clone(), e.into()))?;
+            if let Some(SignTool {
+                thumbprint: Some(_),
+                certificate: Some(_),
+                ..
+            }) = &config.signtool
+            {
+                return Err(Error::Config(
+                    last,
+                    "[signtool] sets both `thumbprint` and `certificate`".into(),
+                ));
+            }
+            Ok(config)
+        }
+        None => Ok(Config {
+            dirs: InstallDirs::defaults(),
+            signtool: None,
+            notarize: None,
+            defaults: Defaults::default(),
+        }),
+    }
+}
+
+///
+/// Reads the `[defaults]` table of each configuration file, which `parse` applies to the options which are not given on the command line.
+/// Unlike [`load`], nothing is logged, as the options which control logging are not final yet
+pub fn defaults(opts: &Options) -> Result<Defaults, Error> {
+    let mut defaults = Defaults::default();
+    for layer in layers(opts) {
+        let (path, layer) = layer?;
+        let layer = manifest::Config::deserialize(layer)
+            .map_err(|e| Error::Config(path, e.into()))?
+            .defaults;
+        defaults.strip = layer.strip.or(defaults.strip);
+        defaults.mode = layer.mode.or(defaults.mode);
+    }
+    Ok(defaults)
+}
+
+///
+/// A `config` command, which shows or changes the configuration of the user
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ConfigCommand {
+    /// Set a key in the configuration of the user
+    Set(String, String),
+    /// Print the value of a key, from the configuration files which apply
+    Get(String),
+    /// Print every key set by the configuration files which apply
+    List,
+}
+
+/// The keys of the configuration which are not installation directories, as `table.key`
+const KEYS: &[&str] = &[
+    "defaults.strip",
+    "defaults.mode",
+    "signtool.program",
+    "signtool.thumbprint",
+    "signtool.certificate",
+    "signtool.password-env",
+    "signtool.timestamp-url",
+    "signtool.digest",
+    "signtool.description",
+    "notarize.keychain-profile",
+];
+
+///
+/// The table and key named by `key`, either as `table.key`, or as the name of an installation directory (in `[dirs]`), `strip`, or `mode` (in `[defaults]`)
+fn key_path(key: &str) -> Result<(&str, &str), Error> {
+    let path = match key.split_once('.') {
+        Some(path) => path,
+        None if DIRS.contains(&key) || key == "runstatedir" => ("dirs", key),
+        None => ("defaults", key),
+    };
+    let known = match path {
+        ("dirs", key) => DIRS.contains(&key) || key == "runstatedir",
+        (table, key) => KEYS
+            .iter()
+            .any(|known| known.split_once('.') == Some((table, key))),
+    };
+    if !known {
+        return Err(Error::InvalidArgument {
+            option: "config",
+            value: key.to_owned(),
+            expected: "an installation directory (such as prefix), strip, mode, or a key of [signtool] or [notarize] as table.key",
+        });
+    }
+    Ok(path)
+}
+
+///
+/// Each key set in `value`, a merged configuration, as `table.key`, with its value
+fn entries(value: &toml::Value) -> Vec<(String, &toml::Value)> {
+    let mut entries = Vec::new();
+    for (table, keys) in value.as_table().into_iter().flatten() {
+        for (key, value) in keys.as_table().into_iter().flatten() {
+            entries.push((format!("{}.{}", table, key), value));
+        }
+    }
+    entries
+}
+
+///
+/// The configuration files in the [`search_path`] that exist, merged
+fn merged(opts: &Options) -> Result<toml::Value, Error> {
+    let mut merged = toml::Value::Table(Default::default());
+    for layer in layers(opts) {
+        merge(&mut merged, layer?.1);
+    }
+    Ok(merged)
+}
+
+///
+/// Sets `key` to `value` in the configuration of the user, which is rewritten (so comments in it are not kept), after checking that it is still valid
+fn set(key: &str, value: &str, opts: &Options) -> Result<(), Error> {
+    let (table, key) = key_path(key)?;
+    let path = user_path().ok_or_else(|| {
+        Error::Config(
+            PathBuf::from("~/.config/cargo-native-install/config.toml"),
+            "the home directory is not known".into(),
+        )
+    })?;
+    let mut config = read(&path)?.unwrap_or_else(|| toml::Value::Table(Default::default()));
+    let value = match (table, key, value) {
+        ("defaults", "strip", "true") => toml::Value::Boolean(true),
+        ("defaults", "strip", "false") => toml::Value::Boolean(false),
+        _ => toml::Value::String(value.to_owned()),
+    };
+    let mut layer = toml::value::Table::new();
+    layer.insert(key.to_owned(), value);
+    let mut tables = toml::value::Table::new();
+    tables.insert(table.to_owned(), toml::Value::Table(layer));
+    merge(&mut config, toml::Value::Table(tables));
+    if let Err(e) = manifest::Config::deserialize(config.clone()) {
+        return Err(Error::Config(path, e.into()));
+    }
+
+    output::verbose(opts, format_args!("Writing {}", path.display()));
+    let contents = toml::to_string(&config).map_err(|e| Error::Config(path.clone(), e.into()))?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| Error::Config(path.clone(), e.into()))?;
+    }
+    fs::write(&path, contents).map_err(|e| Error::Config(path.clone(), e.into()))
+}
+
+///
+/// Runs `cmd`. `get` and `list` show the configuration which applies, from every file in the [`search_path`]
+pub fn command(cmd: &ConfigCommand, opts: &Options) -> Result<(), Error> {
+    match cmd {
+        ConfigCommand::Set(key, value) => set(key, value, opts),
+        ConfigCommand::Get(key) => {
+            let (table, key) = key_path(key)?;
+            match merged(opts)?.get(table).and_then(|table| table.get(key)) {
+                Some(toml::Value::String(s)) => println!("{}", s),
+                Some(value) => println!("{}", value),
+                None => {}
+            }
+            Ok(())
+        }
+        ConfigCommand::List => {
+            for (key, value) in entries(&merged(opts)?) {
+                println!("{} = {}", key, value);
+            }
+            Ok(())
+        }
+    }
+}
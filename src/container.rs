@@ -0,0 +1,34 @@
+use std::{collections::HashMap, path::Path};
+
+use crate::{
+    manifest::{Target, TargetType},
+    output,
+    output::Status,
+    Options,
+};
+
+///
+/// Whether this process appears to run in a container: Docker creates `/.dockerenv`, Podman creates `/run/.containerenv`,
+/// and systemd-nspawn, LXC, and Podman set `container` in the environment of the first process
+pub fn detect() -> bool {
+    Path::new("/.dockerenv").exists()
+        || Path::new("/run/.containerenv").exists()
+        || std::env::var_os("container").is_some()
+}
+
+///
+/// Reports that the documentation of `targets` is not registered in a container, in a single warning, rather than one for each target
+pub fn warn(targets: &HashMap<String, Target>, opts: &Options) {
+    let registered = targets
+        .values()
+        .any(|target| target.doc_base && target.type_ == Some(TargetType::HtmlDoc));
+    if opts.container && registered {
+        output::status(
+            opts,
+            Status::Warning,
+            format_args!(
+                "Warning: Installing in a container, so documentation is not registered with install-docs (use --no-container if the container is privileged)"
+            ),
+        );
+    }
+}
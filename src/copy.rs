@@ -0,0 +1,399 @@
+use std::{
+    fs,
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{filesystem::Filesystem, output, output::Status, Options};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum CopyMethod {
+    /// Reflinks where supported, preserving sparse files, otherwise the fastest copy the platform provides
+    #[default]
+    Auto,
+    /// Reads and writes through a buffer of `--copy-buffer-size` bytes
+    Buffered,
+    /// Copies with `sendfile`, on platforms that support it
+    Sendfile,
+}
+
+impl CopyMethod {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "auto" => Some(CopyMethod::Auto),
+            "buffered" => Some(CopyMethod::Buffered),
+            "sendfile" => Some(CopyMethod::Sendfile),
+            _ => None,
+        }
+    }
+}
+
+/// The buffer size used by `--copy=buffered`, unless `--copy-buffer-size` is given
+pub const DEFAULT_BUFFER_SIZE: usize = 64 * 1024;
+
+///
+/// Copies the contents and permissions of the file `src` to `dest`, returning the number of bytes copied.
+///
+/// On filesystems which support it (such as btrfs and XFS), `dest` is created as a reflink, sharing its data with `src`
+/// until either is modified, which makes the copy near-instant regardless of size.
+/// Sparse files are copied region by region, so that holes in `src` remain holes in `dest`, rather than being filled with zeroes.
+/// Otherwise, this falls back to [`fs::copy`], which already uses in-kernel copies where they are available
+/// (`copy_file_range` on Linux, and `clonefile` on macOS).
+///
+/// `--copy=buffered` and `--copy=sendfile` bypass all of the above, and always copy every byte of `src` in the given way.
+///
+/// Like install(1), the file at `dest` is replaced rather than overwritten in place: `src` is copied beside it, then renamed over it,
+/// so that a program which is running, or a library which is loaded, keeps its old file.
+/// On Windows, a program or library which is running cannot be replaced either, but it can be renamed, so it is moved aside, and the copy is renamed to `dest`.
+/// The old file is removed once nothing uses it, which is usually when the system restarts
+pub fn copy_file(src: &Path, dest: &Path, opts: &Options) -> io::Result<u64> {
+    let mut tmp = dest.as_os_str().to_owned();
+    tmp.push(format!(".cargo-native-install-{}.tmp", std::process::id()));
+    let tmp = PathBuf::from(tmp);
+    // A file left behind by an installation which was interrupted
+    let _ = fs::remove_file(&tmp);
+    let len = match copy_contents(src, &tmp, opts) {
+        Ok(len) => len,
+        Err(e) => {
+            let _ = fs::remove_file(&tmp);
+            return Err(e);
+        }
+    };
+    match fs::rename(&tmp, dest) {
+        Ok(()) => Ok(len),
+        Err(e) if is_in_use(&e) => {
+            let aside = move_aside(dest)?;
+            fs::rename(&tmp, dest)?;
+            if fs::remove_file(&aside).is_err() {
+                let removal = match delete_on_restart(&aside) {
+                    Ok(()) => "will be removed when the system restarts",
+                    Err(_) => "must be removed once it is no longer running",
+                };
+                output::status(
+                    opts,
+                    Status::Warning,
+                    format_args!(
+                        "Warning: {} is in use, so it was moved to {}, which {}",
+                        dest.display(),
+                        aside.display(),
+                        removal
+                    ),
+                );
+            }
+            Ok(len)
+        }
+        Err(e) => {
+            let _ = fs::remove_file(&tmp);
+            Err(e)
+        }
+    }
+}
+
+fn copy_contents(src: &Path, dest: &Path, opts: &Options) -> io::Result<u64> {
+    match opts.copy_method {
+        CopyMethod::Auto => {}
+        CopyMethod::Buffered => return copy_buffered(src, dest, opts.copy_buffer_size),
+        CopyMethod::Sendfile => {
+            if let Some(len) = copy_sendfile(src, dest)? {
+                return Ok(len);
+            }
+            return fs::copy(src, dest);
+        }
+    }
+    if let Some(len) = reflink(src, dest)? {
+        return Ok(len);
+    }
+    if let Some(len) = copy_sparse(src, dest)? {
+        return Ok(len);
+    }
+    fs::copy(src, dest)
+}
+
+/// `_IOW(0x94, 9, int)`, from `linux/fs.h`. Not all versions of the libc crate define this
+#[cfg(any(target_os = "linux", target_os = "android"))]
+const FICLONE: libc::c_ulong = 0x40049409;
+
+///
+/// Attempts to create `dest` as a reflink of `src`. Returns `Ok(None)` if the filesystem does not support reflinks
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn reflink(src: &Path, dest: &Path) -> io::Result<Option<u64>> {
+    use std::os::unix::io::AsRawFd;
+
+    let src_file = fs::File::open(src)?;
+    let md = src_file.metadata()?;
+    if !md.is_file() {
+        return Ok(None);
+    }
+    let dest_file = fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(dest)?;
+    // SAFETY: both file descriptors are open for the duration of the call
+    if unsafe { libc::ioctl(dest_file.as_raw_fd(), FICLONE as _, src_file.as_raw_fd()) } != 0 {
+        let err = io::Error::last_os_error();
+        return match err.raw_os_error() {
+            // The filesystem does not support reflinks, or src and dest are on different filesystems
+            Some(libc::EOPNOTSUPP)
+            | Some(libc::ENOTTY)
+            | Some(libc::EXDEV)
+            | Some(libc::EINVAL)
+            | Some(libc::ENOSYS) => Ok(None),
+            _ => Err(err),
+        };
+    }
+    dest_file.set_permissions(md.permissions())?;
+    Ok(Some(md.len()))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn reflink(_src: &Path, _dest: &Path) -> io::Result<Option<u64>> {
+    Ok(None)
+}
+
+///
+/// If `src` is a sparse file, copies only the regions of `src` which contain data, leaving holes in `dest`.
+/// Returns `Ok(None)` if `src` is not sparse, or the filesystem cannot report where its holes are
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn copy_sparse(src: &Path, dest: &Path) -> io::Result<Option<u64>> {
+    use std::{
+        io::{Read, Seek, SeekFrom},
+        os::unix::{fs::MetadataExt, io::AsRawFd},
+    };
+
+    let mut src_file = fs::File::open(src)?;
+    let md = src_file.metadata()?;
+    // st_blocks is always in units of 512 bytes
+    if !md.is_file() || md.blocks() * 512 >= md.len() {
+        return Ok(None);
+    }
+    let fd = src_file.as_raw_fd();
+    let seek = |offset: u64, whence: libc::c_int| -> io::Result<Option<u64>> {
+        // SAFETY: fd is open for the duration of the call
+        match unsafe { libc::lseek(fd, offset as libc::off_t, whence) } {
+            -1 => match io::Error::last_os_error() {
+                // There is no more data after offset
+                e if e.raw_os_error() == Some(libc::ENXIO) => Ok(None),
+                e => Err(e),
+            },
+            pos => Ok(Some(pos as u64)),
+        }
+    };
+    match seek(0, libc::SEEK_DATA) {
+        Err(e) if e.raw_os_error() == Some(libc::EINVAL) => return Ok(None),
+        Err(e) => return Err(e),
+        Ok(_) => {}
+    }
+
+    let mut dest_file = fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(dest)?;
+    let mut offset = 0;
+    while let Some(data) = seek(offset, libc::SEEK_DATA)? {
+        let hole = seek(data, libc::SEEK_HOLE)?.unwrap_or(md.len());
+        src_file.seek(SeekFrom::Start(data))?;
+        dest_file.seek(SeekFrom::Start(data))?;
+        io::copy(&mut (&mut src_file).take(hole - data), &mut dest_file)?;
+        offset = hole;
+    }
+    // Extends dest over any hole at the end of src
+    dest_file.set_len(md.len())?;
+    dest_file.set_permissions(md.permissions())?;
+    Ok(Some(md.len()))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn copy_sparse(_src: &Path, _dest: &Path) -> io::Result<Option<u64>> {
+    Ok(None)
+}
+
+fn copy_buffered(src: &Path, dest: &Path, buffer_size: usize) -> io::Result<u64> {
+    let mut src_file = fs::File::open(src)?;
+    let md = src_file.metadata()?;
+    let mut dest_file = fs::File::create(dest)?;
+    let mut buf = vec![0u8; buffer_size.max(1)];
+    let mut total = 0;
+    loop {
+        let n = match src_file.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        dest_file.write_all(&buf[..n])?;
+        total += n as u64;
+    }
+    dest_file.set_permissions(md.permissions())?;
+    Ok(total)
+}
+
+///
+/// Copies `src` to `dest` with `sendfile`. Returns `Ok(None)` if `sendfile` is not supported for these files
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn copy_sendfile(src: &Path, dest: &Path) -> io::Result<Option<u64>> {
+    use std::os::unix::io::AsRawFd;
+
+    let src_file = fs::File::open(src)?;
+    let md = src_file.metadata()?;
+    let dest_file = fs::File::create(dest)?;
+    let mut total = 0;
+    loop {
+        // sendfile transfers at most 0x7ffff000 bytes at a time
+        let count = (md.len() - total).min(0x7fff_f000) as usize;
+        // SAFETY: both file descriptors are open for the duration of the call.
+        // A null offset uses and updates the file offset of the source
+        let n = unsafe {
+            libc::sendfile(
+                dest_file.as_raw_fd(),
+                src_file.as_raw_fd(),
+                std::ptr::null_mut(),
+                count,
+            )
+        };
+        match n {
+            -1 => match io::Error::last_os_error() {
+                e if e.kind() == io::ErrorKind::Interrupted => continue,
+                e if total == 0
+                    && matches!(e.raw_os_error(), Some(libc::EINVAL) | Some(libc::ENOSYS)) =>
+                {
+                    return Ok(None)
+                }
+                e => return Err(e),
+            },
+            0 => break,
+            n => total += n as u64,
+        }
+    }
+    dest_file.set_permissions(md.permissions())?;
+    Ok(Some(total))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn copy_sendfile(_src: &Path, _dest: &Path) -> io::Result<Option<u64>> {
+    Ok(None)
+}
+
+///
+/// With `--sync`, flushes the installed file or directory at `path`, and the directory containing it, to disk,
+/// so that the installation survives a crash or power loss
+pub fn sync(path: &Path, opts: &Options) -> io::Result<()> {
+    if !opts.sync {
+        return Ok(());
+    }
+    opts.fs().sync(path)
+}
+
+///
+/// Whether `e` is the error for replacing a file which is open in a running process, such as a program which is running, or a library it loaded
+fn is_in_use(e: &io::Error) -> bool {
+    // ERROR_ACCESS_DENIED, which renaming over a running program gives, ERROR_SHARING_VIOLATION, ERROR_LOCK_VIOLATION, and ERROR_USER_MAPPED_FILE
+    cfg!(windows) && matches!(e.raw_os_error(), Some(5 | 32 | 33 | 1224))
+}
+
+///
+/// Renames `path` to the first of `<path>.old`, `<path>.old.1`, and so on, which does not exist, so that a new file can be created in its place
+fn move_aside(path: &Path) -> io::Result<PathBuf> {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".old");
+    let mut aside = PathBuf::from(&name);
+    let mut n = 0;
+    while fs::symlink_metadata(&aside).is_ok() {
+        n += 1;
+        let mut numbered = name.clone();
+        numbered.push(format!(".{}", n));
+        aside = PathBuf::from(numbered);
+    }
+    fs::rename(path, &aside)?;
+    Ok(aside)
+}
+
+///
+/// Asks Windows to delete `path` when the system next restarts, which needs administrator privileges
+#[cfg(windows)]
+fn delete_on_restart(path: &Path) -> io::Result<()> {
+    use std::os::windows::ffi::OsStrExt;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn MoveFileExW(existing: *const u16, new: *const u16, flags: u32) -> i32;
+    }
+    const MOVEFILE_DELAY_UNTIL_REBOOT: u32 = 0x4;
+
+    let path = path
+        .as_os_str()
+        .encode_wide()
+        .chain(Some(0))
+        .collect::<Vec<_>>();
+    // SAFETY: path is a valid, NUL-terminated wide string, and a null new name deletes the file
+    match unsafe { MoveFileExW(path.as_ptr(), std::ptr::null(), MOVEFILE_DELAY_UNTIL_REBOOT) } {
+        0 => Err(io::Error::last_os_error()),
+        _ => Ok(()),
+    }
+}
+
+// Files in use can be replaced directly elsewhere
+#[cfg(not(windows))]
+fn delete_on_restart(_path: &Path) -> io::Result<()> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+///
+/// Flushes the file `path`, and the directory containing it, to disk
+pub fn sync_path(path: &Path) -> io::Result<()> {
+    fs::File::open(path)?.sync_all()?;
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => sync_dir(parent),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(unix)]
+fn sync_dir(path: &Path) -> io::Result<()> {
+    fs::File::open(path)?.sync_all()
+}
+
+// Directories cannot be opened as files on Windows
+#[cfg(not(unix))]
+fn sync_dir(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tempdir::TempDir;
+
+    #[test]
+    fn replaces_open_file() {
+        let dir = TempDir::new("test").unwrap();
+        let src = dir.create_file("new".as_ref(), b"new").unwrap();
+        let dest = dir.create_file("installed".as_ref(), b"old").unwrap();
+        let mut open = fs::File::open(&dest).unwrap();
+
+        copy_file(&src, &dest, &Options::default()).unwrap();
+        assert_eq!(fs::read(&dest).unwrap(), b"new");
+        // The file which was open is replaced, not overwritten
+        let mut contents = Vec::new();
+        open.read_to_end(&mut contents).unwrap();
+        assert_eq!(contents, b"old");
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 2);
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn replaces_running_program() {
+        let dir = TempDir::new("test").unwrap();
+        let dest = dir.path().join("sleep");
+        fs::copy(which::which("sleep").unwrap(), &dest).unwrap();
+        let mut running = std::process::Command::new(&dest).arg("10").spawn().unwrap();
+        let src = dir.create_file("new".as_ref(), b"new").unwrap();
+
+        let result = copy_file(&src, &dest, &Options::default());
+        let _ = running.kill();
+        let _ = running.wait();
+        result.unwrap();
+        assert_eq!(fs::read(&dest).unwrap(), b"new");
+    }
+}
@@ -0,0 +1,193 @@
+use std::{
+    ffi::OsString,
+    fs,
+    io::{self, ErrorKind},
+    path::Path,
+    process::Command,
+};
+
+use serde_json::json;
+
+use crate::{output, Error, Options, DEFAULT_TARGET};
+
+///
+/// The package ID cargo uses for a package installed from the source in `manifest_dir`, such as `foo 0.1.0 (path+file:///src/foo)`
+pub fn package_id(package: &str, version: &str, manifest_dir: &Path) -> String {
+    let dir = fs::canonicalize(manifest_dir).unwrap_or_else(|_| manifest_dir.to_path_buf());
+    format!(
+        "{} {} (path+file://{})",
+        package,
+        version,
+        dir.to_string_lossy().replace('\\', "/")
+    )
+}
+
+///
+/// Whether `id` is a package ID for `package`
+fn is_package(id: &str, package: &str) -> bool {
+    id.split(' ').next() == Some(package)
+}
+
+fn invalid(e: impl std::error::Error + Send + Sync + 'static) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, e)
+}
+
+///
+/// Reads the file at `path`, or `None` if it does not exist
+fn read(path: &Path) -> io::Result<Option<String>> {
+    match fs::read_to_string(path) {
+        Ok(s) => Ok(Some(s)),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+///
+/// The bins of the package `id` which remain once `bins` are installed for `package`, as `cargo install` takes bins from the package
+/// that previously owned them, and replaces other versions of the same package. Returns `None` if the package should be removed
+fn remaining<'a>(
+    id: &str,
+    installed: impl Iterator<Item = &'a str>,
+    package: &str,
+    bins: &[String],
+) -> Option<Vec<String>> {
+    if is_package(id, package) {
+        return None;
+    }
+    let installed = installed
+        .filter(|bin| !bins.iter().any(|b| b == bin))
+        .map(ToOwned::to_owned)
+        .collect::<Vec<_>>();
+    Some(installed).filter(|installed| !installed.is_empty())
+}
+
+fn update_v1(root: &Path, id: &str, package: &str, bins: &[String]) -> io::Result<()> {
+    let path = root.join(".crates.toml");
+    let mut doc = match read(&path)? {
+        Some(s) => toml::from_str::<toml::value::Table>(&s).map_err(invalid)?,
+        None => toml::value::Table::new(),
+    };
+    let old = match doc.remove("v1") {
+        Some(toml::Value::Table(v1)) => v1,
+        Some(_) => return Err(io::Error::new(ErrorKind::InvalidData, "v1 is not a table")),
+        None => toml::value::Table::new(),
+    };
+    let mut v1 = toml::value::Table::new();
+    for (other, installed) in old {
+        let left = installed.as_array().map(|list| {
+            remaining(
+                &other,
+                list.iter().filter_map(toml::Value::as_str),
+                package,
+                bins,
+            )
+        });
+        match left {
+            Some(Some(left)) => {
+                v1.insert(other, toml::Value::from(left));
+            }
+            Some(None) => {}
+            None if is_package(&other, package) => {}
+            None => {
+                v1.insert(other, installed);
+            }
+        }
+    }
+    v1.insert(id.to_owned(), toml::Value::from(bins.to_vec()));
+    doc.insert("v1".to_owned(), toml::Value::Table(v1));
+    fs::write(&path, toml::to_string(&doc).map_err(invalid)?)
+}
+
+///
+/// The output of `rustc -vV`, which cargo records to tell whether a package was built by a different compiler
+fn rustc_version() -> String {
+    let rustc = std::env::var_os("RUSTC").unwrap_or_else(|| OsString::from("rustc"));
+    Command::new(rustc)
+        .arg("-vV")
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).into_owned())
+        .unwrap_or_default()
+}
+
+fn update_v2(
+    root: &Path,
+    id: &str,
+    package: &str,
+    bins: &[String],
+    opts: &Options,
+) -> io::Result<()> {
+    let path = root.join(".crates2.json");
+    let mut doc = match read(&path)? {
+        Some(s) => serde_json::from_str::<serde_json::Value>(&s).map_err(invalid)?,
+        None => json!({ "installs": {} }),
+    };
+    let installs = match doc.get_mut("installs").and_then(|i| i.as_object_mut()) {
+        Some(installs) => installs,
+        None => {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                "installs is not an object",
+            ))
+        }
+    };
+    for (other, mut entry) in std::mem::take(installs) {
+        let left = entry
+            .get("bins")
+            .and_then(serde_json::Value::as_array)
+            .map(|list| {
+                remaining(
+                    &other,
+                    list.iter().filter_map(serde_json::Value::as_str),
+                    package,
+                    bins,
+                )
+            });
+        match left {
+            Some(Some(left)) => {
+                entry["bins"] = json!(left);
+                installs.insert(other, entry);
+            }
+            Some(None) => {}
+            None if is_package(&other, package) => {}
+            None => {
+                installs.insert(other, entry);
+            }
+        }
+    }
+    installs.insert(
+        id.to_owned(),
+        json!({
+            "version_req": null,
+            "bins": bins,
+            "features": [],
+            "all_features": false,
+            "no_default_features": false,
+            "profile": if opts.debug { "dev" } else { "release" },
+            "target": DEFAULT_TARGET,
+            "rustc": rustc_version(),
+        }),
+    );
+    let mut buf = serde_json::to_vec(&doc).map_err(invalid)?;
+    buf.push(b'\n');
+    fs::write(&path, buf)
+}
+
+///
+/// Records `bins`, installed to `<root>/bin`, in the metadata `cargo install` keeps in `root`,
+/// so that they are listed by `cargo install --list` and can be removed by `cargo uninstall`
+pub fn register(
+    root: &Path,
+    id: &str,
+    package: &str,
+    bins: &[String],
+    opts: &Options,
+) -> Result<(), Error> {
+    output::verbose(
+        opts,
+        format_args!("Registering {} with cargo in {}", id, root.display()),
+    );
+    update_v1(root, id, package, bins).map_err(|e| Error::Emit(root.join(".crates.toml"), e))?;
+    update_v2(root, id, package, bins, opts).map_err(|e| Error::Emit(root.join(".crates2.json"), e))
+}
@@ -0,0 +1,369 @@
+use std::{
+    collections::{HashMap, HashSet},
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use install_dirs::dirs::InstallDirs;
+
+use crate::{
+    copy, elf,
+    manifest::{Rpath, Target, TargetType},
+    output,
+    output::Status,
+    receipt::Receipt,
+    rpath, stow, sysroot, target_destination, Error, Options,
+};
+
+///
+/// The directories the dynamic linker searches by default, after those in `/etc/ld.so.conf`
+const DEFAULT_DIRS: [&str; 4] = ["/lib64", "/usr/lib64", "/lib", "/usr/lib"];
+
+///
+/// Adds the directories listed in the ld.so.conf file at `path`, and in the files it includes, to `dirs`
+fn read_ld_so_conf(path: &Path, dirs: &mut Vec<PathBuf>, depth: u32) {
+    // Guards against files which include themselves
+    if depth > 8 {
+        return;
+    }
+    let conf = match fs::read_to_string(path) {
+        Ok(conf) => conf,
+        Err(_) => return,
+    };
+    let base = path.parent().unwrap_or_else(|| Path::new("/"));
+    for line in conf.lines() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if let Some(patterns) = line.strip_prefix("include") {
+            for pattern in patterns.split_whitespace() {
+                for file in expand(&base.join(pattern)) {
+                    read_ld_so_conf(&file, dirs, depth + 1);
+                }
+            }
+        } else if !line.starts_with("hwcap") {
+            dirs.extend(
+                line.split(|c: char| c == ':' || c == ',' || c.is_whitespace())
+                    .filter(|dir| !dir.is_empty())
+                    .map(PathBuf::from),
+            );
+        }
+    }
+}
+
+///
+/// Expands a path whose file name contains a single `*`, such as `/etc/ld.so.conf.d/*.conf`, to the files it matches, in order
+fn expand(pattern: &Path) -> Vec<PathBuf> {
+    let name = pattern
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let (before, after) = match name.split_once('*') {
+        Some(parts) => parts,
+        None => return vec![pattern.to_path_buf()],
+    };
+    let dir = pattern.parent().unwrap_or_else(|| Path::new("/"));
+    let mut files = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(Result::ok)
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .filter(|name| {
+            name.len() >= before.len() + after.len()
+                && name.starts_with(before)
+                && name.ends_with(after)
+        })
+        .map(|name| dir.join(name))
+        .collect::<Vec<_>>();
+    files.sort();
+    files
+}
+
+///
+/// The directories the dynamic linker of this system searches for libraries which are not found on the search path of the binary
+fn system_dirs() -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    read_ld_so_conf(Path::new("/etc/ld.so.conf"), &mut dirs, 0);
+    dirs.extend(DEFAULT_DIRS.iter().map(PathBuf::from));
+    dirs
+}
+
+///
+/// Finds the library `name`, needed by a binary in the directory `origin`, in the directories of `search_path` (the rpath of the binary), then in `dirs`
+fn locate<'a>(
+    name: &str,
+    origin: &Path,
+    search_path: &[String],
+    dirs: impl IntoIterator<Item = &'a PathBuf>,
+) -> Option<PathBuf> {
+    if name.contains('/') {
+        return Some(PathBuf::from(name)).filter(|path| path.exists());
+    }
+    let origin = origin.to_string_lossy();
+    search_path
+        .iter()
+        .map(|dir| {
+            PathBuf::from(
+                dir.replace("${ORIGIN}", &origin)
+                    .replace("$ORIGIN", &origin),
+            )
+        })
+        .chain(dirs.into_iter().cloned())
+        .map(|dir| dir.join(name))
+        .find(|path| path.exists())
+}
+
+///
+/// The targets in `targets` which are installed as files, by name, with their destinations, in the order of their destinations
+pub fn installed<'a>(
+    dirs: &InstallDirs,
+    targets: &'a HashMap<String, Target>,
+    opts: &Options,
+) -> Vec<(&'a str, &'a Target, PathBuf)> {
+    let mut installed = targets
+        .iter()
+        .filter(|(name, _)| {
+            opts.install_target
+                .as_ref()
+                .is_none_or(|target| target == *name)
+        })
+        .filter(|(_, target)| target.is_selected(opts) && !target.directory)
+        .filter_map(|(name, target)| {
+            Some((
+                name.as_str(),
+                target,
+                target_destination(dirs, target, opts)?,
+            ))
+        })
+        .collect::<Vec<_>>();
+    installed.sort_by(|(_, _, a), (_, _, b)| a.cmp(b));
+    installed
+}
+
+fn is_binary(target: &Target) -> bool {
+    matches!(
+        target.type_,
+        Some(TargetType::Bin | TargetType::SBin | TargetType::Libexec | TargetType::Shared)
+    )
+}
+
+///
+/// The names of the libraries installed by the package, under their own names, or the names of their aliases (such as a soname)
+fn provided(installed: &[(&str, &Target, PathBuf)]) -> HashSet<String> {
+    installed
+        .iter()
+        .filter(|(_, target, _)| {
+            matches!(target.type_, Some(TargetType::Shared | TargetType::Library))
+        })
+        .flat_map(|(_, target, dest)| {
+            std::iter::once(dest.as_path()).chain(
+                target
+                    .installed_aliases
+                    .iter()
+                    .flatten()
+                    .map(PathBuf::as_path),
+            )
+        })
+        .filter_map(|path| Some(path.file_name()?.to_string_lossy().into_owned()))
+        .collect()
+}
+
+///
+/// Reports the shared libraries needed by the installed programs and shared libraries in `targets` which cannot be found on this system,
+/// in the search path of the binary, the directories of the dynamic linker, or the libdir being installed to.
+/// With `--dry-run`, the artifacts are examined instead. Only ELF binaries are examined.
+/// Returns the number of libraries which were not found
+pub fn audit(dirs: &InstallDirs, targets: &HashMap<String, Target>, opts: &Options) -> usize {
+    // The libraries would be looked for on the remote machine, which cannot be examined
+    if opts.filesystem.is_some() {
+        return 0;
+    }
+    let installed = installed(dirs, targets, opts);
+    // Libraries installed by the package satisfy the binaries that need them, even in a dry run
+    let provided = provided(&installed);
+    let system_dirs = system_dirs();
+    let libdir = sysroot::in_sysroot(&dirs.libdir, opts);
+
+    let mut missing = 0;
+    for (_, target, dest) in installed.iter().filter(|(_, target, _)| is_binary(target)) {
+        let dest = sysroot::in_sysroot(dest, opts);
+        let file = match &target.target_file {
+            Some(src) if opts.dry_run => src.clone(),
+            _ => dest.clone(),
+        };
+        let deps = match elf::dependencies(&file) {
+            Ok(Some(deps)) => deps,
+            Ok(None) | Err(_) => continue,
+        };
+        for name in &deps.needed {
+            let origin = dest.parent().unwrap_or_else(|| Path::new(""));
+            if provided.contains(name)
+                || locate(
+                    name,
+                    origin,
+                    &deps.search_path,
+                    system_dirs.iter().chain(std::iter::once(&libdir)),
+                )
+                .is_some()
+            {
+                continue;
+            }
+            missing += 1;
+            if opts.fail_on_missing_deps {
+                output::status(
+                    opts,
+                    Status::Failed,
+                    format_args!(
+                        "{} needs {}, which is not installed on this system",
+                        dest.display(),
+                        name
+                    ),
+                );
+            } else {
+                output::status(
+                    opts,
+                    Status::Warning,
+                    format_args!(
+                        "Warning: {} needs {}, which is not installed on this system",
+                        dest.display(),
+                        name
+                    ),
+                );
+            }
+        }
+    }
+    missing
+}
+
+///
+/// Sets the rpath of the binary at `file` to `$ORIGIN/<libdir>`, the path of `libdir` relative to `dir`, the directory it is installed in
+/// (which differs from the directory of `file` with `--sysroot`), unless it already is
+fn set_rpath(file: &Path, dir: &Path, libdir: &Path, opts: &Options) -> Result<(), Error> {
+    let dir = stow::relative(dir, libdir);
+    let rpath = match dir.as_os_str().is_empty() {
+        true => "$ORIGIN".to_owned(),
+        false => format!("$ORIGIN/{}", dir.display()),
+    };
+    match elf::dependencies(file)? {
+        Some(deps) if deps.search_path == [rpath.as_str()] => Ok(()),
+        _ => rpath::apply(&Rpath::Set(rpath), file, opts),
+    }
+}
+
+///
+/// With `--bundle-libs`, copies the shared libraries needed by the programs and shared libraries in `targets` which are not in the directories of the dynamic linker to libdir,
+/// along with the libraries they need in turn, and sets the rpath of each binary which needs them, so that the installation does not depend on the libraries of the machine it was built on.
+/// The libraries are found as the dynamic linker would find them for the artifacts, including in `LD_LIBRARY_PATH`.
+/// Each copied library is recorded in the receipt as a file of the first target which needs it.
+/// Returns the number of libraries which could not be bundled
+pub fn bundle(
+    dirs: &InstallDirs,
+    targets: &HashMap<String, Target>,
+    receipt: &mut Receipt,
+    opts: &Options,
+) -> usize {
+    let installed = installed(dirs, targets, opts);
+    let provided = provided(&installed);
+    let system_dirs = system_dirs();
+    let search_dirs = env::var_os("LD_LIBRARY_PATH")
+        .map(|path| env::split_paths(&path).collect::<Vec<_>>())
+        .unwrap_or_default()
+        .into_iter()
+        .chain(system_dirs.iter().cloned())
+        .collect::<Vec<_>>();
+    let libdir = sysroot::in_sysroot(&dirs.libdir, opts);
+
+    let mut bundled = HashSet::new();
+    let mut failed = 0;
+    for (name, target, dest) in installed.iter().filter(|(_, target, _)| is_binary(target)) {
+        let src = match &target.target_file {
+            Some(src) => src,
+            None => continue,
+        };
+        // Each binary to examine, with the path of its installed copy, if it is a bundled library
+        let mut pending = vec![(src.clone(), None)];
+        while let Some((file, copy)) = pending.pop() {
+            let deps = match elf::dependencies(&file) {
+                Ok(Some(deps)) => deps,
+                Ok(None) | Err(_) => continue,
+            };
+            let origin = file.parent().unwrap_or_else(|| Path::new(""));
+            let mut needs_bundled = false;
+            for lib in &deps.needed {
+                if provided.contains(lib) {
+                    continue;
+                }
+                // Libraries which are not found are reported by the audit
+                let path = match locate(lib, origin, &deps.search_path, &search_dirs) {
+                    Some(path) => path,
+                    None => continue,
+                };
+                if path
+                    .parent()
+                    .is_some_and(|dir| system_dirs.iter().any(|system| system == dir))
+                {
+                    continue;
+                }
+                needs_bundled = true;
+                if !bundled.insert(lib.clone()) {
+                    continue;
+                }
+
+                let lib_dest = libdir.join(lib);
+                output::status(
+                    opts,
+                    Status::Installed,
+                    format_args!("Bundling {} to {}", path.display(), lib_dest.display()),
+                );
+                if opts.dry_run {
+                    continue;
+                }
+                let result = fs::create_dir_all(&libdir)
+                    .and_then(|()| copy::copy_file(&path, &lib_dest, opts))
+                    .and_then(|_| fs::metadata(&path))
+                    .and_then(|md| fs::set_permissions(&lib_dest, md.permissions()));
+                if let Err(e) = result {
+                    output::status(
+                        opts,
+                        Status::Failed,
+                        format_args!("Failed to bundle {}, {}", lib, e),
+                    );
+                    failed += 1;
+                    continue;
+                }
+                if let Some(entry) = receipt.targets.get_mut(*name) {
+                    if !entry.files.contains(&lib_dest) {
+                        entry.files.push(lib_dest.clone());
+                    }
+                }
+                pending.push((path, Some(lib_dest)));
+            }
+
+            if !needs_bundled || opts.dry_run {
+                continue;
+            }
+            // Bundled libraries find the libraries they need beside them, and binaries find them in libdir
+            let result = match &copy {
+                Some(copy) => set_rpath(copy, &dirs.libdir, &dirs.libdir, opts),
+                None if target.rpath.is_none() => set_rpath(
+                    &sysroot::in_sysroot(dest, opts),
+                    dest.parent().unwrap_or_else(|| Path::new("")),
+                    &dirs.libdir,
+                    opts,
+                ),
+                None => Ok(()),
+            };
+            if let Err(e) = result {
+                output::status(
+                    opts,
+                    Status::Failed,
+                    format_args!(
+                        "Failed to set the rpath of {}, {}",
+                        copy.as_deref().unwrap_or(dest).display(),
+                        e
+                    ),
+                );
+                failed += 1;
+            }
+        }
+    }
+    failed
+}
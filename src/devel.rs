@@ -0,0 +1,186 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use cargo_toml::Manifest;
+use serde_json::json;
+
+use crate::{
+    manifest::{Generated, NativeInstallMetadata, Target, TargetType},
+    output, privs, release, Error, Options, Verbosity, DEFAULT_TARGET,
+};
+
+///
+/// The rmeta file which cargo produced along with `rlib`, the library it copied out of the `deps` directory of the profile.
+/// The copy has the size and modification time of the rlib in `deps`, whose name has a hash that the rmeta shares
+fn rmeta(rlib: &Path, lib: &str) -> Option<PathBuf> {
+    let md = fs::metadata(rlib).ok()?;
+    let prefix = format!("lib{}-", lib);
+    fs::read_dir(rlib.parent()?.join("deps"))
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "rlib"))
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with(&prefix))
+        })
+        .find(|path| {
+            fs::metadata(path)
+                .is_ok_and(|dep| dep.len() == md.len() && dep.modified().ok() == md.modified().ok())
+        })
+        .map(|path| path.with_extension("rmeta"))
+}
+
+///
+/// With `--devel`, adds the targets which install the library of the package in `manifest` for system Rust consumers, unless targets with their names exist:
+/// `<lib>-rlib` and `<lib>-rmeta`, which install its rlib and rmeta to `<libdir>/rustlib/<triple>/lib`,
+/// and `<package>-source`, which installs its source, as `cargo package` includes it, to `<datadir>/cargo/registry/<package>-<version>`
+pub fn add_targets(
+    manifest: &Manifest<NativeInstallMetadata>,
+    targets: &mut HashMap<String, Target>,
+    opts: &Options,
+    manifest_dir: &Path,
+) {
+    let (package, product) = match (&manifest.package, &manifest.lib) {
+        (Some(package), Some(product)) if opts.devel => (package, product),
+        _ => return,
+    };
+    let mut profile = opts
+        .out_dir
+        .clone()
+        .unwrap_or_else(|| manifest_dir.join("target"));
+
+    let lib = product
+        .name
+        .clone()
+        .unwrap_or_else(|| package.name.replace("-", "_"));
+    let is_rlib = product.crate_type.is_empty()
+        || product
+            .crate_type
+            .iter()
+            .any(|crate_type| crate_type == "lib" || crate_type == "rlib");
+    if is_rlib {
+        profile.push(if opts.debug { "debug" } else { "release" });
+        let rlib = profile.join(format!("lib{}.rlib", lib));
+        let rmeta = rmeta(&rlib, &lib).unwrap_or_else(|| rlib.with_extension("rmeta"));
+        let dir = Path::new("rustlib").join(DEFAULT_TARGET).join("lib");
+        for (suffix, src, optional) in [("rlib", rlib, false), ("rmeta", rmeta, true)] {
+            targets
+                .entry(format!("{}-{}", lib, suffix))
+                .or_insert_with(|| Target {
+                    type_: Some(TargetType::Library),
+                    mode: Some("u=rw,g=r,o=r".to_string()),
+                    strip: Some(false),
+                    installed_path: Some(dir.join(format!("lib{}.{}", lib, suffix))),
+                    target_file: Some(src),
+                    // Only check builds produce a separate rmeta
+                    optional,
+                    ..Default::default()
+                });
+        }
+        profile.pop();
+    }
+
+    let source = format!("{}-{}", package.name, package.version);
+    targets
+        .entry(format!("{}-source", package.name))
+        .or_insert_with(|| Target {
+            type_: Some(TargetType::Data),
+            directory: true,
+            dir_mode: Some("u=rwx,g=rx,o=rx".to_string()),
+            file_mode: Some("u=rw,g=r,o=r".to_string()),
+            installed_path: Some(Path::new("cargo").join("registry").join(&source)),
+            target_file: Some(profile.join("package").join(&source)),
+            generated: Some(Generated::CrateSource(package.name.clone())),
+            ..Default::default()
+        });
+}
+
+///
+/// Runs `cmd`, as the invoking user if this program was run with sudo, so that the target directory is not made unwritable to them
+fn run_unprivileged(mut cmd: Command, opts: &Options) -> Result<(), Error> {
+    if !opts.keep_privileges {
+        if let Some(user) = privs::invoking_user() {
+            privs::drop_privileges(&mut cmd, &user);
+        }
+    }
+    output::command(opts, &cmd);
+    let status = cmd
+        .status()
+        .map_err(|e| Error::Spawn(cmd.get_program().into(), e))?;
+    if !status.success() {
+        return Err(Error::Tool(cmd.get_program().into(), status));
+    }
+    Ok(())
+}
+
+///
+/// Produces the source installed by each `<package>-source` target added by [`add_targets`]: packages it with `cargo package`,
+/// and unpacks the `.crate` beside it, with a `.cargo-checksum.json`, so that the installed directory can be used as a directory source
+/// (a `[source]` replacement, as Linux distributions use for offline builds)
+pub fn prepare(
+    targets: &HashMap<String, Target>,
+    manifest_dir: &Path,
+    opts: &Options,
+) -> Result<(), Error> {
+    for target in targets.values().filter(|target| !target.exclude) {
+        let (package, dir) = match (&target.generated, &target.target_file) {
+            (Some(Generated::CrateSource(package)), Some(dir)) => (package, dir),
+            _ => continue,
+        };
+        let parent = dir.parent().unwrap_or_else(|| Path::new(""));
+        let mut crate_file = dir.as_os_str().to_owned();
+        crate_file.push(".crate");
+        let crate_file = PathBuf::from(crate_file);
+
+        output::verbose(opts, format_args!("Packaging the source of {}", package));
+        let mut cargo = Command::new("cargo");
+        cargo.current_dir(manifest_dir).args([
+            "package",
+            "--no-verify",
+            "--allow-dirty",
+            "--package",
+            package,
+        ]);
+        if let Some(dir) = &opts.out_dir {
+            cargo.arg("--target-dir").arg(dir);
+        }
+        if opts.verbosity == Verbosity::Quiet {
+            cargo.arg("--quiet");
+        }
+        run_unprivileged(cargo, opts)?;
+
+        match fs::remove_dir_all(dir) {
+            Err(e) if e.kind() != ErrorKind::NotFound => return Err(Error::Io(e)),
+            _ => {}
+        }
+        let mut tar = Command::new("tar");
+        tar.args(["-x", "-z", "-f"])
+            .arg(&crate_file)
+            .arg("-C")
+            .arg(parent);
+        run_unprivileged(tar, opts)?;
+
+        // The files are not listed, so that the installed source may be patched
+        let checksum = json!({
+            "files": {},
+            "package": release::sha256(&crate_file)?,
+        });
+        let path = dir.join(".cargo-checksum.json");
+        fs::write(&path, checksum.to_string())?;
+        // The unpacked files keep the times recorded in the `.crate`, so the checksum does too, to keep the target unchanged until the source is
+        if let Ok(time) = fs::metadata(dir.join("Cargo.toml")).and_then(|md| md.modified()) {
+            fs::File::options()
+                .write(true)
+                .open(&path)?
+                .set_modified(time)?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,69 @@
+use std::{
+    path::Path,
+    process::{Command, Stdio},
+};
+
+///
+/// The implementation of `install(1)` which `--install` refers to, which decides the options it understands
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum InstallFlavor {
+    /// GNU coreutils, which understands every option that is used
+    #[default]
+    Gnu,
+    /// The BSDs and macOS, which cannot create the parent directories of a file (`-D`), or be told that the destination is a file (`-T`)
+    Bsd,
+    /// BusyBox, as on Alpine, which cannot be told that the destination is a file (`-T`), and has no verbose output (`-v`)
+    Busybox,
+}
+
+impl InstallFlavor {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "gnu" => Some(InstallFlavor::Gnu),
+            "bsd" => Some(InstallFlavor::Bsd),
+            "busybox" => Some(InstallFlavor::Busybox),
+            _ => None,
+        }
+    }
+
+    ///
+    /// Finds the flavor of the install program `prg` from what `prg --version` prints.
+    /// GNU install prints its version, BusyBox prints its usage, which names BusyBox, and the BSD install rejects the option
+    pub fn detect(prg: &Path) -> Self {
+        let output = match Command::new(prg)
+            .arg("--version")
+            .stdin(Stdio::null())
+            .output()
+        {
+            Ok(output) => output,
+            Err(_) => return InstallFlavor::Gnu,
+        };
+        let text = [output.stdout, output.stderr].concat();
+        let text = String::from_utf8_lossy(&text);
+        if text.contains("GNU") {
+            InstallFlavor::Gnu
+        } else if text.contains("BusyBox") {
+            InstallFlavor::Busybox
+        } else {
+            InstallFlavor::Bsd
+        }
+    }
+
+    ///
+    /// Whether the install program creates the parent directories of the destination with `-D`
+    pub fn creates_parents(self) -> bool {
+        self != InstallFlavor::Bsd
+    }
+
+    ///
+    /// Whether the install program is told that the destination is a file, not a directory to install into, with `-T`
+    pub fn has_no_target_directory(self) -> bool {
+        self == InstallFlavor::Gnu
+    }
+
+    ///
+    /// Whether the install program lists the files it installs with `-v`
+    pub fn has_verbose(self) -> bool {
+        self != InstallFlavor::Busybox
+    }
+}
@@ -0,0 +1,164 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    io::{self, ErrorKind},
+    path::{Path, PathBuf},
+};
+
+use install_dirs::dirs::InstallDirs;
+
+use crate::{
+    convert_to_path, gpg, manifest::Target, missing_optional, normalize_path, output,
+    output::Status, receipt, receipt::Receipt, receipt::TargetReceipt, sysroot, target_destination,
+    target_mode, Error, Options,
+};
+
+///
+/// The receipts compared by `diff-receipts`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DiffReceipts {
+    /// Two receipt files, the old one and then the new one
+    Files(PathBuf, PathBuf),
+    /// The receipt of the installed version of the package, and the receipt that installing the package would write
+    Installed,
+}
+
+fn load(path: &Path) -> Result<Receipt, Error> {
+    // A missing receipt is an error here, unlike when installing
+    let buf = std::fs::read(path).map_err(|e| Error::Receipt(path.to_path_buf(), e))?;
+    serde_json::from_slice(&buf).map_err(|e| {
+        Error::Receipt(
+            path.to_path_buf(),
+            io::Error::new(ErrorKind::InvalidData, e),
+        )
+    })
+}
+
+///
+/// The receipt that installing `targets` to `dirs` would write, computed without installing anything
+pub fn planned(dirs: &InstallDirs, targets: &HashMap<String, Target>, opts: &Options) -> Receipt {
+    let mut receipt = Receipt::default();
+    for (name, target) in targets {
+        if opts.install_target.as_ref().is_some_and(|t| t != name)
+            || !target.is_selected(opts)
+            || missing_optional(target).is_some()
+        {
+            continue;
+        }
+        let installed_file = match target_destination(dirs, target, opts) {
+            Some(dest) => dest,
+            None => continue,
+        };
+        if opts.sysroot.is_some() && !sysroot::is_needed(target, &installed_file) {
+            continue;
+        }
+        let target_file = sysroot::in_sysroot(&installed_file, opts);
+        let mut files = Vec::new();
+        receipt::installed_files(
+            target.target_file.as_deref(),
+            &target_file,
+            target.strip_components.unwrap_or(0),
+            &mut files,
+        );
+        for alias in target.installed_aliases.iter().flatten() {
+            let alias_path = normalize_path(&convert_to_path(
+                alias,
+                dirs,
+                installed_file.parent().unwrap_or_else(|| Path::new("")),
+            ));
+            files.push(sysroot::in_sysroot(&alias_path, opts));
+        }
+        if opts.sign_artifacts.is_some() && gpg::is_signed(target) {
+            files.push(gpg::signature_path(&target_file));
+        }
+        receipt.record(
+            name,
+            TargetReceipt {
+                destination: target_file,
+                fingerprint: receipt::fingerprint(target, &target_mode(target, opts), dirs, opts),
+                files,
+                ..Default::default()
+            },
+        );
+    }
+    receipt
+}
+
+///
+/// Each file in `receipt`, with the fingerprint of the target that installed it
+fn files(receipt: &Receipt) -> BTreeMap<&Path, Option<&str>> {
+    receipt
+        .targets
+        .values()
+        .flat_map(|entry| {
+            entry
+                .files
+                .iter()
+                .map(move |file| (file.as_path(), entry.fingerprint.as_deref()))
+        })
+        .collect()
+}
+
+///
+/// Prints the files which are added, removed, or changed between the installation recorded by `old`, and that recorded by `new`.
+/// A file is changed if the artifact of the target that installs it differs
+fn print(old: &Receipt, new: &Receipt, opts: &Options) {
+    let describe = |receipt: &Receipt| match &receipt.version {
+        Some(version) => format!("{} {}", receipt.package, version),
+        None => receipt.package.clone(),
+    };
+    output::status(
+        opts,
+        Status::Installed,
+        format_args!("Comparing {} with {}", describe(old), describe(new)),
+    );
+    let old = files(old);
+    let new = files(new);
+    let (mut added, mut removed, mut changed) = (0, 0, 0);
+    for (file, fingerprint) in &new {
+        match old.get(file) {
+            None => {
+                added += 1;
+                println!("added:   {}", file.display());
+            }
+            Some(old) if old != fingerprint => {
+                changed += 1;
+                println!("changed: {}", file.display());
+            }
+            Some(_) => {}
+        }
+    }
+    for file in old.keys().filter(|file| !new.contains_key(*file)) {
+        removed += 1;
+        println!("removed: {}", file.display());
+    }
+    output::status(
+        opts,
+        Status::Installed,
+        format_args!("{} added, {} removed, {} changed", added, removed, changed),
+    );
+}
+
+///
+/// Compares the receipts at `old` and `new`
+pub fn files_diff(old: &Path, new: &Path, opts: &Options) -> Result<(), Error> {
+    print(&load(old)?, &load(new)?, opts);
+    Ok(())
+}
+
+///
+/// Compares the receipt of the installed version of `package` with the receipt that installing `targets` to `dirs` would write
+pub fn installed_diff(
+    dirs: &InstallDirs,
+    package: &str,
+    version: Option<String>,
+    targets: &HashMap<String, Target>,
+    opts: &Options,
+) -> Result<(), Error> {
+    let path = sysroot::in_sysroot(&Receipt::path(dirs, package), opts);
+    let old = Receipt::load(&path, package).map_err(|e| Error::Receipt(path.clone(), e))?;
+    let mut new = planned(dirs, targets, opts);
+    new.package = package.to_owned();
+    new.version = version;
+    print(&old, &new, opts);
+    Ok(())
+}
@@ -0,0 +1,263 @@
+use std::{
+    fs, io,
+    iter::once,
+    path::{Component, Path, PathBuf},
+    process::Command,
+    sync::OnceLock,
+};
+
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{
+    elevate, fail, filesystem::Filesystem, is_within_install_dirs, output, output::Status,
+    receipt::Receipt, resolve_dirs, save_receipt, sysroot, tempdir, tempdir::TempDir, Error,
+    Options,
+};
+
+/// The file in an archive which describes the installation it contains
+const PLAN: &str = ".cargo-native-install/plan.json";
+
+///
+/// The installation contained in an archive made by `--dist`
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub struct Plan {
+    /// Where the receipt is written when the archive is installed
+    pub receipt_path: PathBuf,
+    /// The targets in the archive, and the files installed for each of them. Every path is absolute
+    pub receipt: Receipt,
+    /// The directory the archive was staged in. Aliases in the archive refer to paths beneath it
+    pub stage: PathBuf,
+}
+
+static STAGE: OnceLock<PathBuf> = OnceLock::new();
+
+///
+/// The directory the package is installed into with `--dist`, before it is packed. Its name is random, and it is created by [`create_stage`]
+pub fn stage_dir() -> PathBuf {
+    STAGE.get_or_init(|| tempdir::unique_path("stage")).clone()
+}
+
+///
+/// Creates the directory returned by [`stage_dir`], which only this user can access
+pub fn create_stage() -> io::Result<()> {
+    tempdir::create_private_dir(&stage_dir())
+}
+
+fn is_zip(archive: &Path) -> bool {
+    archive
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("zip"))
+}
+
+///
+/// The path that `path`, beneath `stage`, is installed to
+fn unstage(path: &Path, stage: &Path) -> PathBuf {
+    match path.strip_prefix(stage) {
+        Ok(rel) => Path::new("/").join(rel),
+        Err(_) => path.to_path_buf(),
+    }
+}
+
+fn run_tool(mut cmd: Command, opts: &Options) -> Result<(), Error> {
+    output::command(opts, &cmd);
+    let prg = PathBuf::from(cmd.get_program());
+    match cmd.status() {
+        Ok(status) if status.success() => Ok(()),
+        Ok(status) => Err(Error::Tool(prg, status)),
+        Err(e) => Err(Error::Spawn(prg, e)),
+    }
+}
+
+///
+/// Packs the installation staged in `stage` into `archive`, with a plan made from `receipt`, which was written to `receipt_path`.
+/// The archive is a zip file if its name ends in `.zip`, and otherwise a tarball, compressed according to its name
+pub fn pack(
+    stage: &Path,
+    archive: &Path,
+    receipt: &Receipt,
+    receipt_path: &Path,
+    opts: &Options,
+) -> Result<(), Error> {
+    let mut receipt = receipt.clone();
+    for entry in receipt.targets.values_mut() {
+        entry.destination = unstage(&entry.destination, stage);
+        for file in &mut entry.files {
+            *file = unstage(file, stage);
+        }
+        if let Some(signature) = &mut entry.signature {
+            *signature = unstage(signature, stage);
+        }
+    }
+    let plan = Plan {
+        receipt_path: unstage(receipt_path, stage),
+        receipt,
+        stage: stage.to_path_buf(),
+    };
+    let plan_path = stage.join(PLAN);
+    let buf = serde_json::to_vec_pretty(&plan).map_err(std::io::Error::other);
+    buf.and_then(|buf| {
+        fs::create_dir_all(plan_path.parent().unwrap())?;
+        fs::write(&plan_path, buf)
+    })
+    .map_err(|e| Error::Emit(plan_path, e))?;
+
+    pack_dir(stage, archive, opts)
+}
+
+///
+/// Packs the contents of `dir` into `archive`, a zip file if its name ends in `.zip`, and otherwise a tarball, compressed according to its name
+pub fn pack_dir(dir: &Path, archive: &Path, opts: &Options) -> Result<(), Error> {
+    output::status(
+        opts,
+        Status::Installed,
+        format_args!("Packing {}", archive.display()),
+    );
+    // The archiver runs in the staging directory
+    let archive = std::env::current_dir()?.join(archive);
+    // zip adds to an existing archive, rather than replacing it
+    let _ = fs::remove_file(&archive);
+    let cmd = if is_zip(&archive) {
+        let mut cmd = Command::new("zip");
+        cmd.args(["-q", "-r", "-y"]).arg(&archive).arg(".");
+        cmd.current_dir(dir);
+        cmd
+    } else {
+        let mut cmd = Command::new("tar");
+        cmd.args(["-c", "-a", "-f"]).arg(&archive);
+        cmd.arg("-C").arg(dir).arg(".");
+        cmd
+    };
+    run_tool(cmd, opts)
+}
+
+///
+/// Checks that every path `plan` installs to is absolute without `..` components, and, unless `--allow-outside-prefix` is given,
+/// is inside the installation directories, as the archive need not have been made on this system
+fn check_plan(plan: &Plan, archive: &Path, opts: &Options) -> Result<(), Error> {
+    let dirs = resolve_dirs(opts)?;
+    let paths = plan
+        .receipt
+        .targets
+        .values()
+        .flat_map(|entry| {
+            once(&entry.destination)
+                .chain(&entry.files)
+                .chain(&entry.signature)
+        })
+        .chain(once(&plan.receipt_path));
+    for path in paths {
+        if !path.has_root() || path.components().any(|c| c == Component::ParentDir) {
+            return Err(Error::InvalidArchive(
+                archive.to_path_buf(),
+                format!("{} is not an absolute path", path.display()),
+            ));
+        }
+        if !opts.allow_outside_prefix && !is_within_install_dirs(path, &dirs) {
+            return Err(fail(opts, format_args!(
+                "Failed, {} is outside of the installation directories (use --allow-outside-prefix to install it anyway)",
+                path.display()
+            ))
+            .into());
+        }
+    }
+    Ok(())
+}
+
+///
+/// Installs the archive `archive`, made by `--dist`, without the source of the package
+pub fn install(archive: &Path, opts: &Options) -> Result<(), Error> {
+    let dir = TempDir::new("unpack")?;
+    install_unpacked(archive, dir.path(), opts)
+}
+
+fn install_unpacked(archive: &Path, dir: &Path, opts: &Options) -> Result<(), Error> {
+    let cmd = if is_zip(archive) {
+        let mut cmd = Command::new("unzip");
+        cmd.arg("-q").arg(archive).arg("-d").arg(dir);
+        cmd
+    } else {
+        let mut cmd = Command::new("tar");
+        cmd.arg("-x").arg("-f").arg(archive).arg("-C").arg(dir);
+        cmd
+    };
+    run_tool(cmd, opts)?;
+    let plan: Plan = fs::read(dir.join(PLAN))
+        .map_err(|e| e.to_string())
+        .and_then(|buf| serde_json::from_slice(&buf).map_err(|e| e.to_string()))
+        .map_err(|e| Error::InvalidArchive(archive.to_path_buf(), e))?;
+    check_plan(&plan, archive, opts)?;
+
+    if !opts.dry_run && opts.filesystem.is_none() {
+        let dests = plan
+            .receipt
+            .targets
+            .values()
+            .map(|entry| sysroot::in_sysroot(&entry.destination, opts))
+            .collect::<Vec<_>>();
+        elevate::elevate_if_needed(
+            opts,
+            &resolve_dirs(opts)?,
+            &std::env::current_dir()?,
+            elevate::find_unwritable(dests.iter().map(PathBuf::as_path)),
+        )?;
+    }
+
+    for (name, entry) in &plan.receipt.targets {
+        output::status(
+            opts,
+            Status::Installed,
+            format_args!(
+                "Installing {} to {}",
+                name,
+                sysroot::in_sysroot(&entry.destination, opts).display()
+            ),
+        );
+        if opts.dry_run {
+            continue;
+        }
+        for file in &entry.files {
+            let src = dir.join(sysroot::without_root(file));
+            let dest = sysroot::in_sysroot(file, opts);
+            let md = fs::symlink_metadata(&src).map_err(|e| {
+                Error::InvalidArchive(archive.to_path_buf(), format!("{}: {}", file.display(), e))
+            })?;
+            output::verbose(opts, format_args!("Installing {}", dest.display()));
+            let result = if md.file_type().is_symlink() {
+                fs::read_link(&src).and_then(|link| {
+                    let link = match link.strip_prefix(&plan.stage) {
+                        Ok(_) => sysroot::in_sysroot(&unstage(&link, &plan.stage), opts),
+                        Err(_) => link,
+                    };
+                    opts.fs().symlink(&link, &dest, src.is_dir())
+                })
+            } else if md.is_dir() {
+                opts.fs().create_dir_all(&dest)
+            } else {
+                match dest.parent() {
+                    Some(parent) if !opts.no_create_dirs => opts.fs().create_dir_all(parent),
+                    _ => Ok(()),
+                }
+                .and_then(|()| opts.fs().copy_file(&src, &dest, opts).map(drop))
+            }
+            .and_then(|()| match (opts.owner, opts.group) {
+                (None, None) => Ok(()),
+                (owner, group) if !md.file_type().is_symlink() => {
+                    opts.fs().chown(&dest, owner, group)
+                }
+                _ => Ok(()),
+            });
+            if let Err(e) = result {
+                return Err(fail(
+                    opts,
+                    format_args!("Failed to install {}, {}", dest.display(), e),
+                )
+                .into());
+            }
+        }
+    }
+
+    let receipt_path = sysroot::in_sysroot(&plan.receipt_path, opts);
+    save_receipt(opts, &plan.receipt, &receipt_path);
+    Ok(())
+}
@@ -0,0 +1,165 @@
+use std::{
+    ffi::OsString,
+    io::{BufRead, IsTerminal, Write},
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use install_dirs::dirs::InstallDirs;
+
+use crate::{config, output, Error, Options};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum Elevate {
+    /// Ask before re-executing, if stdin is a terminal
+    #[default]
+    Ask,
+    /// Re-execute without asking
+    Always,
+    /// Never re-execute
+    Never,
+}
+
+/// Programs used to re-execute the installation with elevated privileges, in order of preference
+const ELEVATION_PROGRAMS: [&str; 3] = ["sudo", "doas", "pkexec"];
+
+///
+/// Returns the first path in `dests` which the current user cannot create or replace.
+/// For paths that do not exist yet, the nearest existing ancestor directory is checked instead
+#[cfg(unix)]
+pub fn find_unwritable<'a, I: IntoIterator<Item = &'a Path>>(dests: I) -> Option<&'a Path> {
+    use std::{ffi::CString, os::unix::ffi::OsStrExt};
+
+    // SAFETY: geteuid has no preconditions and cannot fail
+    if unsafe { libc::geteuid() } == 0 {
+        return None;
+    }
+    dests.into_iter().find(|dest| {
+        let existing = match dest.ancestors().find(|p| p.exists()) {
+            Some(p) => p,
+            None => return false,
+        };
+        match CString::new(existing.as_os_str().as_bytes()) {
+            // SAFETY: path is a valid, NUL-terminated string
+            Ok(path) => unsafe { libc::access(path.as_ptr(), libc::W_OK) != 0 },
+            Err(_) => false,
+        }
+    })
+}
+
+#[cfg(not(unix))]
+pub fn find_unwritable<'a, I: IntoIterator<Item = &'a Path>>(_dests: I) -> Option<&'a Path> {
+    None
+}
+
+fn find_program(opts: &Options) -> Option<PathBuf> {
+    match &opts.elevate_with {
+        Some(prg) => which::which(prg).ok(),
+        None => ELEVATION_PROGRAMS
+            .iter()
+            .find_map(|prg| which::which(prg).ok()),
+    }
+}
+
+fn confirm(prg: &Path, unwritable: &Path) -> bool {
+    if !std::io::stdin().is_terminal() {
+        return false;
+    }
+    eprint!(
+        "Cannot write to {}. Retry the installation using {}? [y/N] ",
+        unwritable.display(),
+        prg.display()
+    );
+    let _ = std::io::stderr().flush();
+    let mut line = String::new();
+    if std::io::stdin().lock().read_line(&mut line).is_err() {
+        return false;
+    }
+    matches!(line.trim(), "y" | "Y" | "yes" | "Yes")
+}
+
+///
+/// If the installation would write somewhere the current user cannot, offers to re-execute this program
+/// with elevated privileges (through sudo, doas, or pkexec) to perform the installation.
+/// The elevated process is passed the resolved installation directories, and skips building.
+/// If the installation is re-executed, returns [`Error::Elevated`] with the status of the elevated process once it exits,
+/// which the caller returns rather than continuing
+pub fn elevate_if_needed(
+    opts: &Options,
+    dirs: &InstallDirs,
+    manifest_dir: &Path,
+    unwritable: Option<&Path>,
+) -> Result<(), Error> {
+    let unwritable = match unwritable {
+        // An image is built as the user it installs as, with no one to give a password, so it is only elevated with --elevate
+        Some(path)
+            if opts.elevate == Elevate::Always
+                || opts.elevate == Elevate::Ask && !opts.container =>
+        {
+            path
+        }
+        _ => return Ok(()),
+    };
+    let prg = match find_program(opts) {
+        Some(prg) => prg,
+        None => return Ok(()),
+    };
+    if opts.elevate == Elevate::Ask && !confirm(&prg, unwritable) {
+        return Ok(());
+    }
+    let exe = match std::env::current_exe() {
+        Ok(exe) => exe,
+        Err(_) => return Ok(()),
+    };
+
+    let mut cmd = Command::new(&prg);
+    cmd.arg(exe);
+    // The package is already built, so `build` is not passed on. The options of CARGO_NATIVE_INSTALL_FLAGS are passed explicitly,
+    // as sudo does not keep the environment, and are removed from it so that they are not read twice
+    cmd.args(opts.args.iter().filter(|arg| {
+        *arg != "--build" && *arg != "--elevate" && !arg.as_encoded_bytes().starts_with(b"--emit-")
+    }));
+    cmd.env_remove("CARGO_NATIVE_INSTALL_FLAGS");
+    cmd.arg("--elevated");
+    cmd.arg(flag("--manifest-dir=", manifest_dir));
+    for (name, dir) in dirs.as_env() {
+        match name {
+            "exec_prefix" | "runstatedir" => {}
+            // The elevated process appends the triple of `--multiarch` itself
+            "libdir" | "libexecdir" if opts.multiarch.is_some() => {
+                cmd.arg(flag(&format!("--{}=", name), dir.parent().unwrap_or(dir)));
+            }
+            name => {
+                cmd.arg(flag(&format!("--{}=", name), dir));
+            }
+        }
+    }
+    // The elevated process would read the configuration of another user, so it is given the file of this one,
+    // which sudo hides by resetting HOME and XDG_CONFIG_HOME. The defaults this one resolved are passed on as well
+    if let Some(file) = opts.user_config.clone().or_else(config::user_path) {
+        cmd.arg(flag("--user-config=", &file));
+    }
+    match &opts.strip {
+        Some(strip) => cmd.arg(flag("--strip=", strip)),
+        None => cmd.arg("--no-strip"),
+    };
+    if let Some(mode) = &opts.mode {
+        cmd.arg(format!("--mode={}", mode));
+    }
+    output::status(
+        opts,
+        output::Status::Installed,
+        format_args!("Retrying the installation using {}", prg.display()),
+    );
+    output::command(opts, &cmd);
+    match cmd.status() {
+        Ok(status) => Err(Error::Elevated(status.code().unwrap_or(1))),
+        Err(e) => Err(Error::Spawn(prg, e)),
+    }
+}
+
+fn flag(name: &str, value: &Path) -> OsString {
+    let mut arg = OsString::from(name);
+    arg.push(value);
+    arg
+}
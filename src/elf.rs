@@ -0,0 +1,423 @@
+use std::{
+    convert::TryInto,
+    fs::{File, OpenOptions},
+    io::{self, BufRead, BufReader, ErrorKind, Read, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+const SHT_NOTE: u32 = 7;
+const NT_GNU_BUILD_ID: u32 = 3;
+const PT_LOAD: u64 = 1;
+const PT_DYNAMIC: u64 = 2;
+const DT_NULL: u64 = 0;
+const DT_NEEDED: u64 = 1;
+const DT_STRTAB: u64 = 5;
+const DT_RPATH: u64 = 15;
+const DT_RUNPATH: u64 = 29;
+
+///
+/// Reads integers in the byte order of an ELF file
+#[derive(Copy, Clone)]
+struct Endian {
+    little: bool,
+}
+
+impl Endian {
+    fn u16(self, buf: &[u8], off: usize) -> u64 {
+        let bytes = buf[off..off + 2].try_into().unwrap_or_default();
+        u64::from(if self.little {
+            u16::from_le_bytes(bytes)
+        } else {
+            u16::from_be_bytes(bytes)
+        })
+    }
+
+    fn u32(self, buf: &[u8], off: usize) -> u64 {
+        let bytes = buf[off..off + 4].try_into().unwrap_or_default();
+        u64::from(if self.little {
+            u32::from_le_bytes(bytes)
+        } else {
+            u32::from_be_bytes(bytes)
+        })
+    }
+
+    fn u64(self, buf: &[u8], off: usize) -> u64 {
+        let bytes = buf[off..off + 8].try_into().unwrap_or_default();
+        if self.little {
+            u64::from_le_bytes(bytes)
+        } else {
+            u64::from_be_bytes(bytes)
+        }
+    }
+
+    ///
+    /// Reads an address-sized integer
+    fn word(self, wide: bool, buf: &[u8], off: usize) -> u64 {
+        if wide {
+            self.u64(buf, off)
+        } else {
+            self.u32(buf, off)
+        }
+    }
+}
+
+///
+/// The identification of an ELF file: whether it is 64-bit, and its byte order
+struct Header {
+    wide: bool,
+    endian: Endian,
+    bytes: [u8; 64],
+}
+
+fn read_header(file: &mut File) -> io::Result<Option<Header>> {
+    let mut bytes = [0; 64];
+    match file.read_exact(&mut bytes) {
+        Ok(()) => {}
+        Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    }
+    if bytes[..4] != *b"\x7fELF" {
+        return Ok(None);
+    }
+    let wide = match bytes[4] {
+        1 => false,
+        2 => true,
+        _ => return Ok(None),
+    };
+    let endian = match bytes[5] {
+        1 => Endian { little: true },
+        2 => Endian { little: false },
+        _ => return Ok(None),
+    };
+    Ok(Some(Header {
+        wide,
+        endian,
+        bytes,
+    }))
+}
+
+fn read_at(file: &mut File, offset: u64, len: u64) -> io::Result<Vec<u8>> {
+    file.seek(SeekFrom::Start(offset))?;
+    let mut buf = Vec::new();
+    file.take(len).read_to_end(&mut buf)?;
+    if (buf.len() as u64) < len {
+        return Err(io::Error::new(
+            ErrorKind::UnexpectedEof,
+            "ELF file is truncated",
+        ));
+    }
+    Ok(buf)
+}
+
+///
+/// Finds the GNU build ID note in a note section
+fn find_build_id(notes: &[u8], endian: Endian) -> Option<Vec<u8>> {
+    let align = |n: usize| (n + 3) & !3;
+    let mut off = 0;
+    while off + 12 <= notes.len() {
+        let namesz = endian.u32(notes, off) as usize;
+        let descsz = endian.u32(notes, off + 4) as usize;
+        let type_ = endian.u32(notes, off + 8) as u32;
+        let name = off + 12;
+        let desc = name + align(namesz);
+        let next = desc + align(descsz);
+        if next > notes.len() {
+            return None;
+        }
+        if type_ == NT_GNU_BUILD_ID && &notes[name..name + namesz] == b"GNU\0" {
+            return Some(notes[desc..desc + descsz].to_vec());
+        }
+        off = next;
+    }
+    None
+}
+
+///
+/// Reads the GNU build ID of the ELF file at `path`, which identifies the build that produced it.
+/// Returns `None` if the file is not an ELF file, or was linked without a build ID
+pub fn build_id(path: &Path) -> io::Result<Option<Vec<u8>>> {
+    let mut file = File::open(path)?;
+    let Header {
+        wide,
+        endian,
+        bytes: header,
+    } = match read_header(&mut file)? {
+        Some(header) => header,
+        None => return Ok(None),
+    };
+
+    let (shoff, shentsize, shnum) = if wide {
+        (
+            endian.u64(&header, 0x28),
+            endian.u16(&header, 0x3a),
+            endian.u16(&header, 0x3c),
+        )
+    } else {
+        (
+            endian.u32(&header, 0x20),
+            endian.u16(&header, 0x2e),
+            endian.u16(&header, 0x30),
+        )
+    };
+    if shoff == 0 || shentsize < if wide { 0x40 } else { 0x28 } {
+        return Ok(None);
+    }
+    let sections = read_at(&mut file, shoff, shentsize * shnum)?;
+    for section in sections.chunks_exact(shentsize as usize) {
+        if endian.u32(section, 4) as u32 != SHT_NOTE {
+            continue;
+        }
+        let (offset, size) = if wide {
+            (endian.u64(section, 0x18), endian.u64(section, 0x20))
+        } else {
+            (endian.u32(section, 0x10), endian.u32(section, 0x14))
+        };
+        let notes = read_at(&mut file, offset, size)?;
+        if let Some(id) = find_build_id(&notes, endian) {
+            return Ok(Some(id));
+        }
+    }
+    Ok(None)
+}
+
+///
+/// The dynamic section of an ELF file, which lists the shared libraries it needs
+struct Dynamic {
+    wide: bool,
+    endian: Endian,
+    /// The offset of the section in the file
+    offset: u64,
+    bytes: Vec<u8>,
+    /// The number of entries before the first `DT_NULL` entry
+    len: usize,
+    /// The offset of the string table in the file
+    strtab: Option<u64>,
+}
+
+impl Dynamic {
+    ///
+    /// Reads the dynamic section of the ELF file with `header`. Returns `None` if it has none, because it is statically linked
+    fn read(file: &mut File, header: &Header) -> io::Result<Option<Self>> {
+        let Header {
+            wide,
+            endian,
+            bytes: ref header,
+        } = *header;
+        let (phoff, phentsize, phnum) = if wide {
+            (
+                endian.u64(header, 0x20),
+                endian.u16(header, 0x36),
+                endian.u16(header, 0x38),
+            )
+        } else {
+            (
+                endian.u32(header, 0x1c),
+                endian.u16(header, 0x2a),
+                endian.u16(header, 0x2c),
+            )
+        };
+        if phentsize < if wide { 0x38 } else { 0x20 } {
+            return Ok(None);
+        }
+        let phdrs = read_at(file, phoff, phentsize * phnum)?;
+        // (file offset, virtual address, size in the file) of each segment
+        let segment = |phdr: &[u8]| {
+            if wide {
+                (
+                    endian.u64(phdr, 0x08),
+                    endian.u64(phdr, 0x10),
+                    endian.u64(phdr, 0x20),
+                )
+            } else {
+                (
+                    endian.u32(phdr, 0x04),
+                    endian.u32(phdr, 0x08),
+                    endian.u32(phdr, 0x10),
+                )
+            }
+        };
+        let phdrs = phdrs.chunks_exact(phentsize as usize);
+        let (offset, _, size) = match phdrs.clone().find(|phdr| endian.u32(phdr, 0) == PT_DYNAMIC) {
+            Some(phdr) => segment(phdr),
+            None => return Ok(None),
+        };
+
+        let mut dynamic = Dynamic {
+            wide,
+            endian,
+            offset,
+            bytes: read_at(file, offset, size)?,
+            len: 0,
+            strtab: None,
+        };
+        let entries = dynamic.bytes.len() / dynamic.entsize();
+        dynamic.len = (0..entries)
+            .find(|&i| dynamic.tag(i) == DT_NULL)
+            .unwrap_or(entries);
+        // The string table is given by its address, which is found in the segment loaded there
+        dynamic.strtab = (0..dynamic.len)
+            .find(|&i| dynamic.tag(i) == DT_STRTAB)
+            .map(|i| dynamic.val(i))
+            .and_then(|strtab| {
+                phdrs
+                    .filter(|phdr| endian.u32(phdr, 0) == PT_LOAD)
+                    .map(segment)
+                    .find(|(_, vaddr, size)| (*vaddr..vaddr + size).contains(&strtab))
+                    .map(|(offset, vaddr, _)| strtab - vaddr + offset)
+            });
+        Ok(Some(dynamic))
+    }
+
+    fn entsize(&self) -> usize {
+        if self.wide {
+            16
+        } else {
+            8
+        }
+    }
+
+    fn tag(&self, i: usize) -> u64 {
+        self.endian.word(self.wide, &self.bytes, i * self.entsize())
+    }
+
+    fn val(&self, i: usize) -> u64 {
+        let word = self.entsize() / 2;
+        self.endian
+            .word(self.wide, &self.bytes, i * self.entsize() + word)
+    }
+
+    ///
+    /// The entries with one of `tags`, before the end of the section
+    fn find<'a>(&'a self, tags: &'a [u64]) -> impl Iterator<Item = usize> + 'a {
+        (0..self.len).filter(move |&i| tags.contains(&self.tag(i)))
+    }
+
+    ///
+    /// Reads the string the value of entry `i` refers to
+    fn string(&self, file: &mut File, i: usize) -> io::Result<String> {
+        let strtab = self.strtab.ok_or_else(|| {
+            io::Error::new(ErrorKind::InvalidData, "ELF file has no string table")
+        })?;
+        file.seek(SeekFrom::Start(strtab + self.val(i)))?;
+        let mut buf = Vec::new();
+        BufReader::new(file).take(4096).read_until(0, &mut buf)?;
+        if buf.last() == Some(&0) {
+            buf.pop();
+        }
+        Ok(String::from_utf8_lossy(&buf).into_owned())
+    }
+}
+
+///
+/// The shared libraries an ELF binary needs, and the directories it asks for them to be searched for in
+pub struct Dependencies {
+    /// The names of the libraries, from each `DT_NEEDED` entry
+    pub needed: Vec<String>,
+    /// The directories of `DT_RUNPATH`, or if the binary has none, of `DT_RPATH`, as written (so `$ORIGIN` is not substituted)
+    pub search_path: Vec<String>,
+}
+
+///
+/// Reads the shared libraries the ELF file at `path` needs.
+/// Returns `None` if the file is not an ELF file, or is statically linked
+pub fn dependencies(path: &Path) -> io::Result<Option<Dependencies>> {
+    let mut file = File::open(path)?;
+    let dynamic = match read_header(&mut file)? {
+        Some(header) => match Dynamic::read(&mut file, &header)? {
+            Some(dynamic) => dynamic,
+            None => return Ok(None),
+        },
+        None => return Ok(None),
+    };
+    let needed = dynamic
+        .find(&[DT_NEEDED])
+        .map(|i| dynamic.string(&mut file, i))
+        .collect::<io::Result<Vec<_>>>()?;
+    let rpath = match dynamic.find(&[DT_RUNPATH]).next() {
+        Some(i) => Some(i),
+        None => dynamic.find(&[DT_RPATH]).next(),
+    };
+    let search_path = match rpath {
+        Some(i) => dynamic
+            .string(&mut file, i)?
+            .split(':')
+            .filter(|dir| !dir.is_empty())
+            .map(ToOwned::to_owned)
+            .collect(),
+        None => Vec::new(),
+    };
+    Ok(Some(Dependencies {
+        needed,
+        search_path,
+    }))
+}
+
+///
+/// A change to the run-time library search path of a binary
+pub enum RpathEdit<'a> {
+    Set(&'a str),
+    Remove,
+}
+
+///
+/// Changes the `DT_RUNPATH` or `DT_RPATH` of the ELF file at `path`, without changing its layout.
+/// Entries are removed by moving the entries after them in the dynamic section up, and a new path is written over the old one.
+/// Returns `false`, without changing the file, if the new path is longer than the old one, or the file has no rpath to replace,
+/// which requires the file to be laid out again (as `patchelf` does)
+pub fn edit_rpath(path: &Path, edit: RpathEdit) -> io::Result<bool> {
+    let mut file = OpenOptions::new().read(true).write(true).open(path)?;
+    let header = read_header(&mut file)?.ok_or_else(|| {
+        io::Error::new(
+            ErrorKind::InvalidData,
+            format!("{} is not an ELF file", path.display()),
+        )
+    })?;
+    let dynamic = match Dynamic::read(&mut file, &header)? {
+        Some(dynamic) => dynamic,
+        // Statically linked binaries have no rpath
+        None => return Ok(matches!(edit, RpathEdit::Remove)),
+    };
+    let rpaths = dynamic.find(&[DT_RPATH, DT_RUNPATH]).collect::<Vec<_>>();
+
+    match edit {
+        RpathEdit::Remove => {
+            if rpaths.is_empty() {
+                return Ok(true);
+            }
+            let entsize = dynamic.entsize();
+            let kept = dynamic
+                .bytes
+                .chunks_exact(entsize)
+                .enumerate()
+                .filter(|(i, _)| !rpaths.contains(i))
+                .flat_map(|(_, entry)| entry.to_vec())
+                .collect::<Vec<_>>();
+            // The space left at the end is filled with DT_NULL entries
+            let mut edited = vec![0; dynamic.bytes.len()];
+            edited[..kept.len()].copy_from_slice(&kept);
+            file.seek(SeekFrom::Start(dynamic.offset))?;
+            file.write_all(&edited)?;
+            Ok(true)
+        }
+        RpathEdit::Set(new) => {
+            let strtab = match dynamic.strtab {
+                Some(strtab) if !rpaths.is_empty() => strtab,
+                _ => return Ok(false),
+            };
+            for &i in &rpaths {
+                // The new path fits if the old one does not end within its length
+                let old = read_at(&mut file, strtab + dynamic.val(i), new.len() as u64)?;
+                if old.contains(&0) {
+                    return Ok(false);
+                }
+            }
+            for &i in &rpaths {
+                file.seek(SeekFrom::Start(strtab + dynamic.val(i)))?;
+                file.write_all(new.as_bytes())?;
+                file.write_all(&[0])?;
+            }
+            // A binary with both entries only uses DT_RUNPATH, so there is no need to change their tags
+            Ok(true)
+        }
+    }
+}
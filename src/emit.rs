@@ -0,0 +1,256 @@
+use std::{
+    collections::HashMap,
+    fmt::Write as _,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use cargo_toml::Manifest;
+use install_dirs::dirs::InstallDirs;
+
+use crate::{
+    manifest::{NativeInstallMetadata, Target, TargetType},
+    output, privs, script, Error, Options,
+};
+
+///
+/// The format `--print-dirs` prints the installation directories in
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum DirsFormat {
+    #[default]
+    Human,
+    Shell,
+    Json,
+}
+
+impl DirsFormat {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "human" => Some(DirsFormat::Human),
+            "shell" => Some(DirsFormat::Shell),
+            "json" => Some(DirsFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+/// The directories which are written, in order
+pub const DIRS: [&str; 16] = [
+    "prefix",
+    "exec_prefix",
+    "bindir",
+    "sbindir",
+    "libdir",
+    "libexecdir",
+    "includedir",
+    "datarootdir",
+    "datadir",
+    "mandir",
+    "infodir",
+    "docdir",
+    "localedir",
+    "sysconfdir",
+    "localstatedir",
+    "sharedstatedir",
+];
+
+///
+/// The name of each directory, with its path. Trailing separators, left by empty directories, are removed
+pub fn resolved(dirs: &InstallDirs) -> Vec<(&'static str, PathBuf)> {
+    let mut env = dirs.as_env().into_iter().collect::<HashMap<_, _>>();
+    // `as_env` of install-dirs leaves out includedir
+    env.insert("includedir", &dirs.includedir);
+    DIRS.iter()
+        .filter_map(|name| env.get(name).map(|dir| (*name, dir.components().collect())))
+        .collect()
+}
+
+///
+/// A C header defining each installation directory as a string literal macro, such as `BINDIR`
+pub fn config_h(dirs: &InstallDirs) -> String {
+    let mut out = String::new();
+    out.push_str("/* Generated by cargo-native-install. Do not edit */\n");
+    out.push_str("#ifndef CARGO_NATIVE_INSTALL_CONFIG_H\n");
+    out.push_str("#define CARGO_NATIVE_INSTALL_CONFIG_H\n\n");
+    for (name, dir) in resolved(dirs) {
+        out.push_str("#define ");
+        out.push_str(&name.to_ascii_uppercase());
+        out.push_str(" \"");
+        for b in dir.to_string_lossy().bytes() {
+            match b {
+                b'"' | b'\\' => {
+                    out.push('\\');
+                    out.push(b as char);
+                }
+                0x20..=0x7e => out.push(b as char),
+                // Octal escapes are at most 3 digits, so they cannot absorb the following character
+                b => write!(out, "\\{:03o}", b).unwrap(),
+            }
+        }
+        out.push_str("\"\n");
+    }
+    out.push_str("\n#endif\n");
+    out
+}
+
+///
+/// A Rust module defining each installation directory as a `&str` constant, such as `BINDIR`
+pub fn paths_rs(dirs: &InstallDirs) -> String {
+    let mut out = String::new();
+    out.push_str("// Generated by cargo-native-install. Do not edit\n\n");
+    for (name, dir) in resolved(dirs) {
+        writeln!(
+            out,
+            "pub const {}: &str = {:?};",
+            name.to_ascii_uppercase(),
+            dir.to_string_lossy()
+        )
+        .unwrap();
+    }
+    out
+}
+
+///
+/// Shell variable assignments for each installation directory, named as the environment variables which set them (such as `bindir`)
+pub fn shell_env(dirs: &InstallDirs) -> String {
+    let mut out = String::new();
+    for (name, dir) in resolved(dirs) {
+        writeln!(out, "{}={}", name, script::quote_sh(dir.as_os_str())).unwrap();
+    }
+    out
+}
+
+///
+/// A dotenv file, with the same variables as [`shell_env`]. Values are only quoted if they need to be,
+/// so that the file can also be included by make
+pub fn dotenv(dirs: &InstallDirs) -> String {
+    let mut out = String::new();
+    for (name, dir) in resolved(dirs) {
+        let dir = dir.to_string_lossy();
+        if dir
+            .chars()
+            .any(|c| c.is_whitespace() || matches!(c, '"' | '\'' | '\\' | '$' | '#' | '`'))
+        {
+            out.push_str(name);
+            out.push_str("=\"");
+            for c in dir.chars() {
+                if matches!(c, '"' | '\\' | '$' | '`') {
+                    out.push('\\');
+                }
+                out.push(c);
+            }
+            out.push_str("\"\n");
+        } else {
+            writeln!(out, "{}={}", name, dir).unwrap();
+        }
+    }
+    out
+}
+
+///
+/// The installation directories in `format`, for `--print-dirs`: aligned columns for a person to read,
+/// `export` commands to be evaluated by a shell, or a JSON object
+pub fn print_dirs(dirs: &InstallDirs, format: DirsFormat) -> String {
+    let dirs = resolved(dirs);
+    let mut out = String::new();
+    match format {
+        DirsFormat::Human => {
+            let width = dirs.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+            for (name, dir) in dirs {
+                writeln!(out, "{:width$}  {}", name, dir.display(), width = width).unwrap();
+            }
+        }
+        DirsFormat::Shell => {
+            for (name, dir) in dirs {
+                writeln!(out, "export {}={}", name, script::quote_sh(dir.as_os_str())).unwrap();
+            }
+        }
+        DirsFormat::Json => {
+            let object = dirs
+                .into_iter()
+                .map(|(name, dir)| (name.to_owned(), dir.to_string_lossy().into()))
+                .collect::<serde_json::Map<_, _>>();
+            writeln!(out, "{:#}", serde_json::Value::Object(object)).unwrap();
+        }
+    }
+    out
+}
+
+pub fn write(opts: &Options, path: &Path, contents: String) -> Result<(), Error> {
+    output::verbose(opts, format_args!("Writing {}", path.display()));
+    let err = |e| Error::Emit(path.to_path_buf(), e);
+    // Under sudo, the file and the directories created for it belong to the user who ran it, as they would had it been built by them
+    let created = path
+        .ancestors()
+        .skip(1)
+        .take_while(|dir| !dir.as_os_str().is_empty() && !dir.exists())
+        .last()
+        .unwrap_or(path)
+        .to_path_buf();
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent).map_err(err)?;
+    }
+    // Leave an unchanged file alone, so that cargo does not rebuild everything that includes it
+    if fs::read(path).is_ok_and(|old| old == contents.as_bytes()) {
+        return Ok(());
+    }
+    fs::write(path, contents).map_err(err)?;
+    match privs::invoking_user().filter(|_| !opts.keep_privileges) {
+        Some(user) => privs::give_to(&created, &user).map_err(err),
+        None => Ok(()),
+    }
+}
+
+///
+/// With `--install-emitted`, adds the targets `<package>-config-h` and `<package>-paths-rs`, which install the files written by `--emit-config-h` and `--emit-paths-rs`
+/// to `<includedir>/<package>`, keeping their names, unless targets with those names exist
+pub fn add_targets(
+    manifest: &Manifest<NativeInstallMetadata>,
+    targets: &mut HashMap<String, Target>,
+    opts: &Options,
+) {
+    let package = match &manifest.package {
+        Some(package) if opts.install_emitted => package,
+        _ => return,
+    };
+    for (suffix, path) in [
+        ("config-h", &opts.emit_config_h),
+        ("paths-rs", &opts.emit_paths_rs),
+    ] {
+        let path = match path
+            .as_deref()
+            .and_then(|path| std::path::absolute(path).ok())
+        {
+            Some(path) => path,
+            None => continue,
+        };
+        let installed = Path::new(&package.name).join(path.file_name().unwrap_or_default());
+        targets
+            .entry(format!("{}-{}", package.name, suffix))
+            .or_insert_with(|| Target {
+                type_: Some(TargetType::Include),
+                mode: Some("u=rw,g=r,o=r".to_string()),
+                installed_path: Some(installed),
+                target_file: Some(path),
+                ..Default::default()
+            });
+    }
+}
+
+///
+/// Writes the files requested by `--emit-config-h`, `--emit-paths-rs`, `--emit-env`, and `--emit-dotenv`
+pub fn emit(dirs: &InstallDirs, opts: &Options) -> Result<(), Error> {
+    if let Some(path) = &opts.emit_config_h {
+        write(opts, path, config_h(dirs))?;
+    }
+    if let Some(path) = &opts.emit_paths_rs {
+        write(opts, path, paths_rs(dirs))?;
+    }
+    if let Some(path) = &opts.emit_env {
+        write(opts, path, shell_env(dirs))?;
+    }
+    if let Some(path) = &opts.emit_dotenv {
+        write(opts, path, dotenv(dirs))?;
+    }
+    Ok(())
+}
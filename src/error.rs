@@ -0,0 +1,458 @@
+use std::{
+    fmt::Display,
+    io::{self, ErrorKind},
+    path::PathBuf,
+    process::ExitStatus,
+};
+
+use install_dirs::dirs::CanonicalizationError;
+
+use crate::{progress::human_bytes, InstallError};
+
+///
+/// An error which stops the installation
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// An option on the command line was not recognized
+    UnknownOption(String),
+    /// An option was given a value it does not accept
+    InvalidArgument {
+        option: &'static str,
+        value: String,
+        expected: &'static str,
+    },
+    /// An option requires a value, but none was given
+    MissingArgument {
+        option: &'static str,
+        expected: &'static str,
+    },
+    /// The file given by `--log-file` could not be opened
+    LogFile(PathBuf, io::Error),
+    /// The config file could not be read or parsed
+    Config(PathBuf, Box<dyn std::error::Error + Send + Sync>),
+    /// The cargo manifest could not be read or parsed
+    Manifest(cargo_toml::Error),
+    /// The installation directories could not be resolved
+    Prefix(CanonicalizationError),
+    /// These installation directories begin with placeholders of each other, so they cannot be resolved
+    DirectoryCycle(Vec<&'static str>),
+    /// A mode, from a target or `--mode`, is not a valid chmod mode
+    InvalidMode(String),
+    /// The file a target installs does not exist, usually because the project has not been built.
+    /// If the file is built by cargo, this is the command that builds it
+    MissingArtifact(PathBuf, Option<String>),
+    /// With `--require-built`, the artifacts of this many targets do not exist, so nothing was installed. Each has already been reported
+    MissingArtifacts(usize),
+    /// The files installed to the filesystem of this directory need more space than is available on it, so nothing was installed
+    NoSpace {
+        dir: PathBuf,
+        needed: u64,
+        available: u64,
+    },
+    /// With `--fail-on-missing-deps`, this many shared libraries needed by the installed binaries were not found. Each has already been reported
+    MissingDependencies(usize),
+    /// With `--check-build-paths=deny`, this many installed files contain paths of the build machine. Each has already been reported
+    BuildPaths(usize),
+    /// `test-install` found this many smoke tests which failed. Each has already been reported
+    SmokeTests(usize),
+    /// `verify` found this many signatures which are not valid. Each has already been reported
+    InvalidSignatures(usize),
+    /// The notary service did not accept the archive made by `--dist`
+    Notarization(PathBuf, String),
+    /// An external program could not be started
+    Spawn(PathBuf, io::Error),
+    /// An external program exited unsuccessfully
+    Tool(PathBuf, ExitStatus),
+    /// The rpath of a binary cannot be changed without rewriting its layout, and patchelf was not found to do so
+    NoPatchelf(PathBuf),
+    /// A file requested by one of the `--emit-*` options could not be written
+    Emit(PathBuf, io::Error),
+    /// A path cannot be written exactly in the file generated by one of the `--emit-*` options, such as a path which is not UTF-8 in a ninja file
+    Unrepresentable {
+        path: PathBuf,
+        format: &'static str,
+        reason: &'static str,
+    },
+    /// A target with `template-engine` is selected for one of the `--emit-*` options, which cannot render it
+    EmitTemplate(String),
+    /// Two options were given which cannot be used together
+    Conflict(&'static str, &'static str),
+    /// With `--stow` or `--activate`, a link in the prefix could not be created or removed
+    Stow(PathBuf, io::Error),
+    /// With `--stow`, a file in the prefix is in the way of a link, and does not belong to the package
+    StowConflict(PathBuf),
+    /// `switch-version` names a version of the package which is not installed in a slot
+    NoSuchVersion(String, String),
+    /// `relocate` could not move or copy an installed file, or a file it would create already exists
+    Relocate(PathBuf, io::Error),
+    /// The archive given to `--from-archive` was not made by `--dist`, or is missing files
+    InvalidArchive(PathBuf, String),
+    /// The release given by `--from-release` could not be found, downloaded, or verified
+    Release(String),
+    /// An install receipt given to `diff-receipts`, or the receipt of the installed package, could not be read
+    Receipt(PathBuf, io::Error),
+    /// A target in the manifest has a key which is not one of its fields, with the field it is most likely a misspelling of
+    UnknownField {
+        target: String,
+        field: String,
+        suggestion: Option<&'static str>,
+    },
+    /// The library target has a `crate-type` which cannot be installed, such as `proc-macro`
+    UnsupportedCrateType {
+        target: String,
+        crate_type: String,
+    },
+    /// A target which is installed to a path has no `installed-path`, and its type gives it none
+    MissingInstalledPath(String),
+    /// A workspace member defines a target with the same name as a target of the root package or of an earlier member
+    DuplicateTarget {
+        target: String,
+        member: String,
+    },
+    /// A thread loading the manifests of workspace members panicked, with its message
+    LoadMembers(String),
+    /// `check` found this many problems in the install targets, which have already been reported
+    InvalidTargets(usize),
+    /// `--target` names a target which does not exist
+    NoSuchTarget(String),
+    /// The lock file of the prefix could not be locked, usually because another installation into it did not finish in time
+    Lock(PathBuf, String),
+    /// The installation was re-executed with elevated privileges, and the elevated process exited with this status.
+    /// It has already reported any failure
+    Elevated(i32),
+    /// A target failed to install, and the installation stopped. The failure has already been reported
+    Target(InstallError),
+    /// With `--keep-going`, the given number of targets failed to install. Each failure has already been reported
+    TargetsFailed(usize),
+    Io(io::Error),
+}
+
+///
+/// The status the program exits with, which tells scripts what class of failure occurred. The values are stable
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ExitCode {
+    /// Everything was installed
+    Success = 0,
+    /// Any failure which is not described by another code
+    Failure = 1,
+    /// The command line was invalid
+    Usage = 2,
+    /// The manifest, its install targets, or the configuration is invalid
+    Manifest = 3,
+    /// The artifact of a target does not exist, usually because the project has not been built
+    MissingArtifact = 4,
+    /// A destination could not be written, usually because the program is not running as root
+    Permission = 5,
+    /// With `--keep-going`, some targets failed to install
+    Partial = 6,
+    /// An external program, such as `install` or `cargo`, could not be found
+    ToolNotFound = 7,
+}
+
+impl ExitCode {
+    pub fn code(self) -> i32 {
+        self as i32
+    }
+
+    ///
+    /// The class with the status `code`, if it is one this program exits with
+    pub fn from_code(code: i32) -> Option<Self> {
+        [
+            ExitCode::Success,
+            ExitCode::Failure,
+            ExitCode::Usage,
+            ExitCode::Manifest,
+            ExitCode::MissingArtifact,
+            ExitCode::Permission,
+            ExitCode::Partial,
+            ExitCode::ToolNotFound,
+        ]
+        .iter()
+        .copied()
+        .find(|exit_code| exit_code.code() == code)
+    }
+
+    ///
+    /// The class of a failure caused by `e`
+    pub fn for_io(e: &io::Error) -> Self {
+        match e.kind() {
+            ErrorKind::PermissionDenied => ExitCode::Permission,
+            _ => ExitCode::Failure,
+        }
+    }
+}
+
+impl Error {
+    ///
+    /// Whether the error was reported to the user when it occurred, and should not be reported again
+    pub fn is_reported(&self) -> bool {
+        matches!(self, Error::Target(_) | Error::Elevated(_))
+    }
+
+    ///
+    /// The status the program exits with because of this error
+    pub fn exit_code(&self) -> ExitCode {
+        match self {
+            Error::UnknownOption(_)
+            | Error::InvalidArgument { .. }
+            | Error::MissingArgument { .. }
+            | Error::Conflict(_, _)
+            | Error::EmitTemplate(_)
+            | Error::NoSuchTarget(_) => ExitCode::Usage,
+            Error::Config(_, _)
+            | Error::Manifest(_)
+            | Error::Prefix(_)
+            | Error::DirectoryCycle(_)
+            | Error::InvalidMode(_)
+            | Error::UnknownField { .. }
+            | Error::UnsupportedCrateType { .. }
+            | Error::MissingInstalledPath(_)
+            | Error::DuplicateTarget { .. }
+            | Error::InvalidTargets(_) => ExitCode::Manifest,
+            Error::MissingArtifact(_, _) | Error::MissingArtifacts(_) => ExitCode::MissingArtifact,
+            Error::Spawn(_, e) if e.kind() == ErrorKind::NotFound => ExitCode::ToolNotFound,
+            Error::NoPatchelf(_) => ExitCode::ToolNotFound,
+            Error::LogFile(_, e)
+            | Error::Emit(_, e)
+            | Error::Stow(_, e)
+            | Error::Relocate(_, e)
+            | Error::Receipt(_, e)
+            | Error::Io(e) => ExitCode::for_io(e),
+            Error::Target(e) => e.exit_code(),
+            Error::Elevated(code) => ExitCode::from_code(*code).unwrap_or(ExitCode::Failure),
+            Error::TargetsFailed(_) => ExitCode::Partial,
+            _ => ExitCode::Failure,
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::UnknownOption(opt) => write!(f, "Unrecognized option {}", opt),
+            Error::InvalidArgument {
+                option,
+                value,
+                expected,
+            } => write!(
+                f,
+                "Invalid argument to {}: {} (expected {})",
+                option, value, expected
+            ),
+            Error::MissingArgument { option, expected } => {
+                write!(f, "{} requires {}", option, expected)
+            }
+            Error::LogFile(path, e) => {
+                write!(f, "Cannot open log file {}: {}", path.display(), e)
+            }
+            Error::Config(path, e) => {
+                write!(f, "Failed to read config file {}: {}", path.display(), e)
+            }
+            Error::Manifest(e) => write!(f, "Failed to parse cargo manifest {}", e),
+            Error::Prefix(e) => write!(f, "Failed to resolve installation prefix: {}", e),
+            Error::DirectoryCycle(dirs) => write!(
+                f,
+                "Failed to resolve installation directories, {} refer to each other",
+                dirs.join(", ")
+            ),
+            Error::InvalidMode(mode) => write!(f, "Invalid mode {}", mode),
+            Error::MissingArtifact(path, Some(build)) => write!(
+                f,
+                "{} does not exist, build it with `{}` first",
+                path.display(),
+                build
+            ),
+            Error::MissingArtifact(path, None) => write!(
+                f,
+                "{} does not exist (has the project been built?)",
+                path.display()
+            ),
+            Error::MissingArtifacts(count) => write!(
+                f,
+                "{} artifact{} not exist, nothing was installed",
+                count,
+                if *count == 1 { " does" } else { "s do" }
+            ),
+            Error::Spawn(prg, e) => write!(f, "Failed to run {}, {}", prg.display(), e),
+            Error::Tool(prg, status) => match status.code() {
+                Some(code) => write!(f, "{} exited with code {}", prg.display(), code),
+                None => write!(f, "{} was terminated by a signal", prg.display()),
+            },
+            Error::NoSpace {
+                dir,
+                needed,
+                available,
+            } => write!(
+                f,
+                "Not enough space on the filesystem of {}: {} is needed, but only {} is available (use --no-space-check to install anyway)",
+                dir.display(),
+                human_bytes(*needed),
+                human_bytes(*available)
+            ),
+            Error::MissingDependencies(n) => write!(
+                f,
+                "{} shared librar{} needed by the installed binaries {} not installed",
+                n,
+                if *n == 1 { "y" } else { "ies" },
+                if *n == 1 { "is" } else { "are" }
+            ),
+            Error::SmokeTests(n) => write!(
+                f,
+                "{} smoke test{} failed",
+                n,
+                if *n == 1 { "" } else { "s" }
+            ),
+            Error::BuildPaths(n) => write!(
+                f,
+                "{} installed file{} paths of the build machine",
+                n,
+                if *n == 1 { " contains" } else { "s contain" }
+            ),
+            Error::InvalidSignatures(n) => write!(
+                f,
+                "{} signature{} not valid",
+                n,
+                if *n == 1 { " is" } else { "s are" }
+            ),
+            Error::Notarization(archive, msg) => {
+                write!(f, "Failed to notarize {}: {}", archive.display(), msg)
+            }
+            Error::NoPatchelf(path) => write!(
+                f,
+                "patchelf is needed to change the rpath of {}, as the new rpath is longer than its current rpath (if it has one)",
+                path.display()
+            ),
+            Error::Emit(path, e) => write!(f, "Failed to write {}: {}", path.display(), e),
+            Error::EmitTemplate(target) => write!(
+                f,
+                "Cannot emit install target {}, as its `template-engine` is only rendered when installing directly",
+                target
+            ),
+            Error::Unrepresentable {
+                path,
+                format,
+                reason,
+            } => write!(
+                f,
+                "Cannot write {} in {}, {}",
+                path.display(),
+                format,
+                reason
+            ),
+            Error::Conflict(a, b) => write!(f, "{} cannot be used with {}", a, b),
+            Error::Stow(path, e) => write!(f, "Failed to link {}: {}", path.display(), e),
+            Error::StowConflict(path) => write!(
+                f,
+                "Cannot link {}, it already exists and does not belong to the package",
+                path.display()
+            ),
+            Error::NoSuchVersion(package, version) => write!(
+                f,
+                "Cannot switch to {} {}, that version is not installed",
+                package, version
+            ),
+            Error::Relocate(path, e) => {
+                write!(f, "Failed to relocate {}: {}", path.display(), e)
+            }
+            Error::InvalidArchive(path, e) => write!(
+                f,
+                "{} is not an archive made by --dist ({})",
+                path.display(),
+                e
+            ),
+            Error::Release(e) => write!(f, "Cannot install release: {}", e),
+            Error::Lock(path, e) => write!(f, "Cannot lock {}, {}", path.display(), e),
+            Error::UnknownField {
+                target,
+                field,
+                suggestion,
+            } => {
+                write!(f, "Unknown field `{}` in install target {}", field, target)?;
+                match suggestion {
+                    Some(suggestion) => write!(f, ", did you mean `{}`?", suggestion),
+                    None => Ok(()),
+                }
+            }
+            Error::UnsupportedCrateType { target, crate_type } => write!(
+                f,
+                "Cannot install library {}, its crate type {} is not one which can be installed",
+                target, crate_type
+            ),
+            Error::MissingInstalledPath(target) => write!(
+                f,
+                "Install target {} has no `installed-path`, and its type does not give it one",
+                target
+            ),
+            Error::DuplicateTarget { target, member } => write!(
+                f,
+                "Workspace member {} defines install target {}, which another package of the workspace already defines",
+                member, target
+            ),
+            Error::LoadMembers(message) => {
+                write!(f, "Failed to load workspace members, {}", message)
+            }
+            Error::InvalidTargets(count) => write!(
+                f,
+                "{} problem{} found in the install targets",
+                count,
+                if *count == 1 { "" } else { "s" }
+            ),
+            Error::Receipt(path, e) => write!(
+                f,
+                "Failed to read install receipt {}: {}",
+                path.display(),
+                e
+            ),
+            Error::NoSuchTarget(name) => {
+                write!(f, "Cannot install target {}, no such target exists", name)
+            }
+            Error::Elevated(code) => write!(f, "The elevated installation exited with status {}", code),
+            Error::Target(e) => e.fmt(f),
+            Error::TargetsFailed(n) => write!(
+                f,
+                "Installation failed, {} target(s) could not be installed",
+                n
+            ),
+            Error::Io(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::LogFile(_, e)
+            | Error::Spawn(_, e)
+            | Error::Emit(_, e)
+            | Error::Stow(_, e)
+            | Error::Relocate(_, e)
+            | Error::Receipt(_, e)
+            | Error::Io(e) => Some(e),
+            Error::Prefix(e) => Some(e),
+            Error::Config(_, e) => Some(&**e),
+            Error::Manifest(e) => Some(e),
+            Error::Target(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<cargo_toml::Error> for Error {
+    fn from(e: cargo_toml::Error) -> Self {
+        Error::Manifest(e)
+    }
+}
+
+impl From<InstallError> for Error {
+    fn from(e: InstallError) -> Self {
+        Error::Target(e)
+    }
+}
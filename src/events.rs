@@ -0,0 +1,35 @@
+use std::path::Path;
+
+use crate::{report, InstallError, Options};
+
+///
+/// Installation lifecycle events, delivered to [`Options::events`] as they happen.
+/// Events are delivered in order, from the thread that performs the installation
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Event<'a> {
+    /// Installation of the target `name` has begun
+    TargetStarted { name: &'a str },
+    /// A file was copied to `path`. Files belong to the most recently started target
+    FileCopied { path: &'a Path, bytes: u64 },
+    /// The target `name` was not installed, for the given reason
+    TargetSkipped { name: &'a str, reason: &'a str },
+    /// The target `name` could not be installed, or one of its binaries could not be stripped
+    TargetFailed {
+        name: &'a str,
+        error: &'a InstallError,
+    },
+    /// The target `name` was installed, or skipped
+    TargetFinished { name: &'a str },
+}
+
+///
+/// A callback which receives installation events
+pub type EventHandler = Box<dyn Fn(&Event) + Send + Sync>;
+
+pub fn emit(opts: &Options, event: Event) {
+    report::record(opts, &event);
+    if let Some(handler) = &opts.events {
+        handler(&event);
+    }
+}
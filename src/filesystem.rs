@@ -0,0 +1,729 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::{self, ErrorKind},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, PoisonError},
+    thread,
+    time::{Duration, SystemTime},
+};
+
+use crate::{copy, normalize_path, output, output::Status, privs, Options};
+
+pub use crate::ssh::SshFilesystem;
+
+///
+/// The operations the internal installer performs on the destination filesystem.
+///
+/// Sources are always read from the local filesystem. Only the files being installed go through this trait,
+/// so that the installation can be performed in memory (for example, in tests), or written to another backend.
+/// Set [`Options::filesystem`] to use an implementation other than [`NativeFilesystem`]
+pub trait Filesystem: Send + Sync {
+    ///
+    /// Copies the contents and permissions of the local file `src` to `dest`, returning the number of bytes copied
+    fn copy_file(&self, src: &Path, dest: &Path, opts: &Options) -> io::Result<u64>;
+
+    ///
+    /// Creates the directory `path`, and any missing parents
+    fn create_dir_all(&self, path: &Path) -> io::Result<()>;
+
+    ///
+    /// Creates a symbolic link at `dest` which refers to `src`, replacing any existing link at `dest`.
+    /// `dir` is set if `src` is a directory
+    fn symlink(&self, src: &Path, dest: &Path, dir: bool) -> io::Result<()>;
+
+    fn is_dir(&self, path: &Path) -> bool;
+
+    fn modified(&self, path: &Path) -> io::Result<SystemTime>;
+
+    fn set_modified(&self, path: &Path, time: SystemTime) -> io::Result<()>;
+
+    ///
+    /// The permission bits of `path`, as given to chmod
+    fn mode(&self, path: &Path) -> io::Result<u32>;
+
+    fn set_mode(&self, path: &Path, mode: u32) -> io::Result<()>;
+
+    ///
+    /// Changes the owner and group of `path`. `None` leaves the owner or group unchanged
+    fn chown(&self, path: &Path, owner: Option<u32>, group: Option<u32>) -> io::Result<()>;
+
+    ///
+    /// Flushes `path`, and the directory containing it, to persistent storage
+    fn sync(&self, path: &Path) -> io::Result<()>;
+}
+
+///
+/// The filesystem of the machine running the installation
+#[derive(Copy, Clone, Debug, Default)]
+pub struct NativeFilesystem;
+
+impl Filesystem for NativeFilesystem {
+    fn copy_file(&self, src: &Path, dest: &Path, opts: &Options) -> io::Result<u64> {
+        copy::copy_file(src, dest, opts)
+    }
+
+    fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        fs::create_dir_all(path)
+    }
+
+    #[allow(unused_variables)]
+    fn symlink(&self, src: &Path, dest: &Path, dir: bool) -> io::Result<()> {
+        if let Ok(md) = fs::symlink_metadata(dest) {
+            if md.file_type().is_symlink() {
+                // A link to a directory is a directory on Windows
+                fs::remove_file(dest).or_else(|_| fs::remove_dir(dest))?;
+            }
+        }
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(src, dest)
+        }
+        #[cfg(windows)]
+        {
+            if !dir {
+                std::os::windows::fs::symlink_file(src, dest)
+            } else {
+                match std::os::windows::fs::symlink_dir(src, dest) {
+                    // ERROR_PRIVILEGE_NOT_HELD, without Developer Mode or administrator privileges
+                    Err(e) if e.raw_os_error() == Some(1314) => junction(src, dest),
+                    result => result,
+                }
+            }
+        }
+        #[cfg(not(any(unix, windows)))]
+        {
+            panic!("Unsupported operating system")
+        }
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        path.is_dir()
+    }
+
+    fn modified(&self, path: &Path) -> io::Result<SystemTime> {
+        fs::metadata(path)?.modified()
+    }
+
+    fn set_modified(&self, path: &Path, time: SystemTime) -> io::Result<()> {
+        fs::File::options()
+            .write(true)
+            .open(path)?
+            .set_modified(time)
+    }
+
+    #[cfg(unix)]
+    fn mode(&self, path: &Path) -> io::Result<u32> {
+        use std::os::unix::fs::PermissionsExt;
+        Ok(fs::metadata(path)?.permissions().mode())
+    }
+
+    #[cfg(not(unix))]
+    fn mode(&self, _path: &Path) -> io::Result<u32> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "file modes are only supported on unix-like targets",
+        ))
+    }
+
+    #[cfg(unix)]
+    fn set_mode(&self, path: &Path, mode: u32) -> io::Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(mode))
+    }
+
+    #[cfg(not(unix))]
+    fn set_mode(&self, _path: &Path, _mode: u32) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "file modes are only supported on unix-like targets",
+        ))
+    }
+
+    fn chown(&self, path: &Path, owner: Option<u32>, group: Option<u32>) -> io::Result<()> {
+        privs::apply_ownership(path, owner, group)
+    }
+
+    fn sync(&self, path: &Path) -> io::Result<()> {
+        copy::sync_path(path)
+    }
+}
+
+///
+/// What is at a path of a [`MemoryFilesystem`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MemoryKind {
+    File(Vec<u8>),
+    Dir,
+    /// A symbolic link, which refers to this path
+    Symlink(PathBuf),
+}
+
+///
+/// A file, directory, or symbolic link of a [`MemoryFilesystem`], with its metadata
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MemoryEntry {
+    pub kind: MemoryKind,
+    pub mode: u32,
+    pub modified: SystemTime,
+    pub owner: Option<u32>,
+    pub group: Option<u32>,
+}
+
+///
+/// A filesystem held in memory, which installs nothing to disk, so that an installation can be tested, or inspected before it is performed.
+/// Clones share the same files, so a clone can be given to [`Options::filesystem`], and the installed files read from the original
+#[derive(Clone, Debug, Default)]
+pub struct MemoryFilesystem {
+    entries: Arc<Mutex<BTreeMap<PathBuf, MemoryEntry>>>,
+}
+
+impl MemoryFilesystem {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    ///
+    /// The entry at `path`, without following a symbolic link at `path` itself
+    pub fn get(&self, path: &Path) -> Option<MemoryEntry> {
+        self.lock().get(&normalize_path(path)).cloned()
+    }
+
+    ///
+    /// Every path in the filesystem, in order
+    pub fn paths(&self) -> Vec<PathBuf> {
+        self.lock().keys().cloned().collect()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, BTreeMap<PathBuf, MemoryEntry>> {
+        self.entries.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn not_found(path: &Path) -> io::Error {
+        io::Error::new(
+            ErrorKind::NotFound,
+            format!("{} does not exist", path.display()),
+        )
+    }
+
+    ///
+    /// Applies `f` to the entry at `path`, following symbolic links
+    fn with_entry<T>(&self, path: &Path, f: impl FnOnce(&mut MemoryEntry) -> T) -> io::Result<T> {
+        let mut entries = self.lock();
+        let path = resolve(&entries, path);
+        entries
+            .get_mut(&path)
+            .map(f)
+            .ok_or_else(|| Self::not_found(&path))
+    }
+
+    ///
+    /// Fails unless the parent of `path` is a directory, as creating a file would
+    fn check_parent(entries: &BTreeMap<PathBuf, MemoryEntry>, path: &Path) -> io::Result<()> {
+        match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => {
+                match entries.get(&resolve(entries, parent)) {
+                    Some(MemoryEntry {
+                        kind: MemoryKind::Dir,
+                        ..
+                    }) => Ok(()),
+                    _ => Err(Self::not_found(parent)),
+                }
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn entry(kind: MemoryKind, mode: u32) -> MemoryEntry {
+        MemoryEntry {
+            kind,
+            mode,
+            modified: SystemTime::now(),
+            owner: None,
+            group: None,
+        }
+    }
+}
+
+///
+/// `path`, with symbolic links in `entries` followed, up to a limit which stops a cycle of links
+fn resolve(entries: &BTreeMap<PathBuf, MemoryEntry>, path: &Path) -> PathBuf {
+    let mut path = normalize_path(path);
+    for _ in 0..40 {
+        match entries.get(&path).map(|entry| &entry.kind) {
+            Some(MemoryKind::Symlink(target)) => {
+                let parent = path.parent().unwrap_or_else(|| Path::new(""));
+                path = normalize_path(&parent.join(target));
+            }
+            _ => break,
+        }
+    }
+    path
+}
+
+impl Filesystem for MemoryFilesystem {
+    fn copy_file(&self, src: &Path, dest: &Path, _opts: &Options) -> io::Result<u64> {
+        let contents = fs::read(src)?;
+        #[cfg(unix)]
+        let mode = {
+            use std::os::unix::fs::PermissionsExt;
+            fs::metadata(src)?.permissions().mode() & 0o7777
+        };
+        #[cfg(not(unix))]
+        let mode = 0o644;
+        let len = contents.len() as u64;
+        let mut entries = self.lock();
+        let dest = resolve(&entries, dest);
+        Self::check_parent(&entries, &dest)?;
+        if let Some(MemoryEntry {
+            kind: MemoryKind::Dir,
+            ..
+        }) = entries.get(&dest)
+        {
+            return Err(io::Error::new(
+                ErrorKind::IsADirectory,
+                format!("{} is a directory", dest.display()),
+            ));
+        }
+        entries.insert(dest, Self::entry(MemoryKind::File(contents), mode));
+        Ok(len)
+    }
+
+    fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        let mut entries = self.lock();
+        let path = resolve(&entries, path);
+        for dir in path.ancestors().collect::<Vec<_>>().into_iter().rev() {
+            if dir.as_os_str().is_empty() {
+                continue;
+            }
+            let dir = resolve(&entries, dir);
+            match entries.get(&dir).map(|entry| &entry.kind) {
+                Some(MemoryKind::Dir) => {}
+                Some(_) => {
+                    return Err(io::Error::new(
+                        ErrorKind::AlreadyExists,
+                        format!("{} is not a directory", dir.display()),
+                    ))
+                }
+                None => {
+                    entries.insert(dir, Self::entry(MemoryKind::Dir, 0o755));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn symlink(&self, src: &Path, dest: &Path, _dir: bool) -> io::Result<()> {
+        let mut entries = self.lock();
+        let dest = normalize_path(dest);
+        Self::check_parent(&entries, &dest)?;
+        match entries.get(&dest).map(|entry| &entry.kind) {
+            None | Some(MemoryKind::Symlink(_)) => {}
+            Some(_) => {
+                return Err(io::Error::new(
+                    ErrorKind::AlreadyExists,
+                    format!("{} already exists", dest.display()),
+                ))
+            }
+        }
+        entries.insert(
+            dest,
+            Self::entry(MemoryKind::Symlink(src.to_path_buf()), 0o777),
+        );
+        Ok(())
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        self.with_entry(path, |entry| entry.kind == MemoryKind::Dir)
+            .unwrap_or(false)
+    }
+
+    fn modified(&self, path: &Path) -> io::Result<SystemTime> {
+        self.with_entry(path, |entry| entry.modified)
+    }
+
+    fn set_modified(&self, path: &Path, time: SystemTime) -> io::Result<()> {
+        self.with_entry(path, |entry| entry.modified = time)
+    }
+
+    fn mode(&self, path: &Path) -> io::Result<u32> {
+        self.with_entry(path, |entry| entry.mode)
+    }
+
+    fn set_mode(&self, path: &Path, mode: u32) -> io::Result<()> {
+        self.with_entry(path, |entry| entry.mode = mode & 0o7777)
+    }
+
+    fn chown(&self, path: &Path, owner: Option<u32>, group: Option<u32>) -> io::Result<()> {
+        self.with_entry(path, |entry| {
+            entry.owner = owner.or(entry.owner);
+            entry.group = group.or(entry.group);
+        })
+    }
+
+    fn sync(&self, path: &Path) -> io::Result<()> {
+        self.with_entry(path, |_| ())
+    }
+}
+
+///
+/// The number of times an operation which fails with a transient error is retried, unless changed by `--retries`
+pub const DEFAULT_RETRIES: u32 = 3;
+
+///
+/// How long to wait before the first retry. The wait doubles with each retry after it
+const FIRST_RETRY_DELAY: Duration = Duration::from_millis(100);
+
+///
+/// Whether `e` is an error which another attempt may not fail with, such as a file being briefly busy, or a network filesystem losing its server
+fn is_transient(e: &io::Error) -> bool {
+    matches!(
+        e.kind(),
+        ErrorKind::ResourceBusy
+            | ErrorKind::WouldBlock
+            | ErrorKind::Interrupted
+            | ErrorKind::TimedOut
+            | ErrorKind::StaleNetworkFileHandle
+            | ErrorKind::ConnectionReset
+            | ErrorKind::ConnectionAborted
+            | ErrorKind::NotConnected
+            | ErrorKind::HostUnreachable
+            | ErrorKind::NetworkUnreachable
+            | ErrorKind::NetworkDown
+    )
+}
+
+///
+/// A filesystem which retries each operation of another that fails with a transient error, up to `--retries` times, waiting twice as long before each retry.
+/// This is the filesystem returned by [`Options::fs`], so that an installation onto a network filesystem survives its server being briefly unavailable
+pub struct Retrying<'a> {
+    pub fs: &'a dyn Filesystem,
+    pub opts: &'a Options,
+}
+
+impl Retrying<'_> {
+    fn retry<T>(
+        &self,
+        action: &str,
+        path: &Path,
+        mut op: impl FnMut() -> io::Result<T>,
+    ) -> io::Result<T> {
+        let retries = self.opts.retries.unwrap_or(DEFAULT_RETRIES);
+        let mut delay = FIRST_RETRY_DELAY;
+        let mut attempt = 0;
+        loop {
+            match op() {
+                Err(e) if attempt < retries && is_transient(&e) => {
+                    attempt += 1;
+                    output::status(
+                        self.opts,
+                        Status::Warning,
+                        format_args!(
+                            "Warning: Failed to {} {}: {}, retrying in {}ms ({} of {})",
+                            action,
+                            path.display(),
+                            e,
+                            delay.as_millis(),
+                            attempt,
+                            retries
+                        ),
+                    );
+                    thread::sleep(delay);
+                    delay *= 2;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+impl Filesystem for Retrying<'_> {
+    fn copy_file(&self, src: &Path, dest: &Path, opts: &Options) -> io::Result<u64> {
+        self.retry("copy to", dest, || self.fs.copy_file(src, dest, opts))
+    }
+
+    fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        self.retry("create", path, || self.fs.create_dir_all(path))
+    }
+
+    fn symlink(&self, src: &Path, dest: &Path, dir: bool) -> io::Result<()> {
+        self.retry("link", dest, || self.fs.symlink(src, dest, dir))
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        self.fs.is_dir(path)
+    }
+
+    fn modified(&self, path: &Path) -> io::Result<SystemTime> {
+        self.retry("read the modification time of", path, || {
+            self.fs.modified(path)
+        })
+    }
+
+    fn set_modified(&self, path: &Path, time: SystemTime) -> io::Result<()> {
+        self.retry("set the modification time of", path, || {
+            self.fs.set_modified(path, time)
+        })
+    }
+
+    fn mode(&self, path: &Path) -> io::Result<u32> {
+        self.retry("read the mode of", path, || self.fs.mode(path))
+    }
+
+    fn set_mode(&self, path: &Path, mode: u32) -> io::Result<()> {
+        self.retry("chmod", path, || self.fs.set_mode(path, mode))
+    }
+
+    fn chown(&self, path: &Path, owner: Option<u32>, group: Option<u32>) -> io::Result<()> {
+        self.retry("chown", path, || self.fs.chown(path, owner, group))
+    }
+
+    fn sync(&self, path: &Path) -> io::Result<()> {
+        self.retry("sync", path, || self.fs.sync(path))
+    }
+}
+
+///
+/// Creates a directory junction at `dest` which refers to the directory `src`. Unlike a symbolic link to a directory, a junction needs no privilege to create,
+/// but it refers to an absolute path, so a relative `src` is resolved against the directory containing `dest`
+#[cfg(windows)]
+fn junction(src: &Path, dest: &Path) -> io::Result<()> {
+    use std::os::windows::{ffi::OsStrExt, fs::OpenOptionsExt, io::AsRawHandle};
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn DeviceIoControl(
+            device: *mut std::ffi::c_void,
+            code: u32,
+            input: *const std::ffi::c_void,
+            input_len: u32,
+            output: *mut std::ffi::c_void,
+            output_len: u32,
+            returned: *mut u32,
+            overlapped: *mut std::ffi::c_void,
+        ) -> i32;
+    }
+    const FSCTL_SET_REPARSE_POINT: u32 = 0x900a4;
+    const IO_REPARSE_TAG_MOUNT_POINT: u32 = 0xa000_0003;
+    const FILE_FLAG_OPEN_REPARSE_POINT: u32 = 0x0020_0000;
+    const FILE_FLAG_BACKUP_SEMANTICS: u32 = 0x0200_0000;
+
+    let src = std::path::absolute(dest.parent().unwrap_or_else(|| Path::new("")).join(src))?;
+    let print = src.as_os_str().encode_wide().collect::<Vec<_>>();
+    // The target is stored as an NT path, such as \??\C:\dir, with the \\?\ of a verbatim path replaced
+    let verbatim = r"\\?\".encode_utf16().collect::<Vec<_>>();
+    let substitute = r"\??\"
+        .encode_utf16()
+        .chain(
+            print
+                .strip_prefix(&verbatim[..])
+                .unwrap_or(&print)
+                .iter()
+                .copied(),
+        )
+        .collect::<Vec<_>>();
+
+    // A REPARSE_DATA_BUFFER for a mount point: the tag, the length of the data after the header,
+    // the offset and length of each name in bytes, then both names, each followed by a NUL
+    let names_len = (substitute.len() + 1 + print.len() + 1) * 2;
+    let mut buf = Vec::with_capacity(16 + names_len);
+    buf.extend_from_slice(&IO_REPARSE_TAG_MOUNT_POINT.to_le_bytes());
+    buf.extend_from_slice(&((8 + names_len) as u16).to_le_bytes());
+    buf.extend_from_slice(&0u16.to_le_bytes());
+    buf.extend_from_slice(&0u16.to_le_bytes());
+    buf.extend_from_slice(&((substitute.len() * 2) as u16).to_le_bytes());
+    buf.extend_from_slice(&(((substitute.len() + 1) * 2) as u16).to_le_bytes());
+    buf.extend_from_slice(&((print.len() * 2) as u16).to_le_bytes());
+    for unit in substitute.iter().chain(&[0]).chain(&print).chain(&[0]) {
+        buf.extend_from_slice(&unit.to_le_bytes());
+    }
+
+    fs::create_dir(dest)?;
+    let result = fs::OpenOptions::new()
+        .write(true)
+        .custom_flags(FILE_FLAG_OPEN_REPARSE_POINT | FILE_FLAG_BACKUP_SEMANTICS)
+        .open(dest)
+        .and_then(|dir| {
+            let mut returned = 0;
+            // SAFETY: dir is an open handle to the directory, and buf is a valid REPARSE_DATA_BUFFER of the given length
+            match unsafe {
+                DeviceIoControl(
+                    dir.as_raw_handle(),
+                    FSCTL_SET_REPARSE_POINT,
+                    buf.as_ptr().cast(),
+                    buf.len() as u32,
+                    std::ptr::null_mut(),
+                    0,
+                    &mut returned,
+                    std::ptr::null_mut(),
+                )
+            } {
+                0 => Err(io::Error::last_os_error()),
+                _ => Ok(()),
+            }
+        });
+    if result.is_err() {
+        let _ = fs::remove_dir(dest);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use install_dirs::dirs::InstallDirs;
+
+    use super::*;
+    use crate::{
+        install_target,
+        manifest::{Target, TargetType},
+        progress::Progress,
+        receipt::Receipt,
+        strip::StripQueue,
+        tempdir::TempDir,
+    };
+
+    ///
+    /// Writes the source file `name` in `src`, returning its path
+    fn source(src: &TempDir, name: &str, contents: &str) -> PathBuf {
+        let path = src.path().join(name);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    ///
+    /// Installs `target` to `fs`, returning the receipt it was recorded in
+    fn install(fs: &MemoryFilesystem, target: Target) -> Receipt {
+        let opts = Options {
+            filesystem: Some(Box::new(fs.clone())),
+            verbosity: crate::Verbosity::Quiet,
+            ..Default::default()
+        };
+        let mut dirs = InstallDirs::defaults();
+        dirs.prefix = "/mem".into();
+        let dirs = dirs.canonicalize().unwrap();
+        let mut receipt = Receipt::default();
+        install_target(
+            &dirs,
+            "test",
+            &target,
+            &opts,
+            &mut receipt,
+            &mut StripQueue::default(),
+            &mut Progress::disabled(),
+        )
+        .unwrap();
+        receipt
+    }
+
+    fn contents(fs: &MemoryFilesystem, path: &str) -> Vec<u8> {
+        match fs.get(Path::new(path)).map(|entry| entry.kind) {
+            Some(MemoryKind::File(contents)) => contents,
+            kind => panic!("{} is {:?}, not a file", path, kind),
+        }
+    }
+
+    #[test]
+    fn copies_file_into_created_directories() {
+        let src = TempDir::new("test").unwrap();
+        let fs = MemoryFilesystem::new();
+        install(
+            &fs,
+            Target {
+                type_: Some(TargetType::Bin),
+                target_file: Some(source(&src, "hello", "#!/bin/sh\n")),
+                installed_path: Some("hello".into()),
+                ..Default::default()
+            },
+        );
+        assert_eq!(contents(&fs, "/mem/bin/hello"), b"#!/bin/sh\n");
+        assert!(fs.is_dir(Path::new("/mem/bin")));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn applies_mode() {
+        let src = TempDir::new("test").unwrap();
+        let fs = MemoryFilesystem::new();
+        install(
+            &fs,
+            Target {
+                type_: Some(TargetType::Data),
+                target_file: Some(source(&src, "data.txt", "data")),
+                installed_path: Some("data.txt".into()),
+                mode: Some("u=rwx,g=rx,o=".into()),
+                ..Default::default()
+            },
+        );
+        assert_eq!(fs.mode(Path::new("/mem/share/data.txt")).unwrap(), 0o750);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn installs_file_name_which_is_not_utf8() {
+        use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+        let name = OsStr::from_bytes(b"caf\xe9.txt");
+        let src = TempDir::new("test").unwrap();
+        let fs = MemoryFilesystem::new();
+        let receipt = install(
+            &fs,
+            Target {
+                type_: Some(TargetType::Data),
+                target_file: Some(src.create_file(name, b"data").unwrap()),
+                installed_path: Some(name.into()),
+                ..Default::default()
+            },
+        );
+        let dest = Path::new("/mem/share").join(name);
+        assert!(matches!(
+            fs.get(&dest).map(|entry| entry.kind),
+            Some(MemoryKind::File(contents)) if contents == b"data"
+        ));
+        assert!(receipt.owns(&dest));
+    }
+
+    #[test]
+    fn links_aliases_to_target() {
+        let src = TempDir::new("test").unwrap();
+        let fs = MemoryFilesystem::new();
+        install(
+            &fs,
+            Target {
+                type_: Some(TargetType::Bin),
+                target_file: Some(source(&src, "hello", "hello")),
+                installed_path: Some("hello".into()),
+                installed_aliases: Some(vec!["hi".into()]),
+                ..Default::default()
+            },
+        );
+        assert!(matches!(
+            fs.get(Path::new("/mem/bin/hi")).map(|entry| entry.kind),
+            Some(MemoryKind::Symlink(_))
+        ));
+        assert_eq!(
+            resolve(&fs.lock(), Path::new("/mem/bin/hi")),
+            Path::new("/mem/bin/hello")
+        );
+        assert_eq!(contents(&fs, "/mem/bin/hello"), b"hello");
+    }
+
+    #[test]
+    fn copies_directory_tree() {
+        let src = TempDir::new("test").unwrap();
+        source(&src, "assets/a.txt", "a");
+        source(&src, "assets/sub/b.txt", "b");
+        let fs = MemoryFilesystem::new();
+        install(
+            &fs,
+            Target {
+                type_: Some(TargetType::Data),
+                target_file: Some(src.path().join("assets")),
+                installed_path: Some("assets".into()),
+                directory: true,
+                ..Default::default()
+            },
+        );
+        assert_eq!(contents(&fs, "/mem/share/assets/a.txt"), b"a");
+        assert_eq!(contents(&fs, "/mem/share/assets/sub/b.txt"), b"b");
+    }
+}
@@ -0,0 +1,155 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::Path,
+    sync::{Mutex, PoisonError},
+};
+
+use crate::{
+    filesystem::{Filesystem, NativeFilesystem},
+    output,
+    output::Status,
+    space, Options,
+};
+
+///
+/// What the filesystem of a destination supports. FAT, exFAT, and some SMB mounts support neither
+#[derive(Copy, Clone, Debug)]
+struct Capabilities {
+    modes: bool,
+    symlinks: bool,
+    /// Links to directories can be created, which on Windows may be junctions where symbolic links cannot be created
+    dir_links: bool,
+}
+
+const ALL: Capabilities = Capabilities {
+    modes: true,
+    symlinks: true,
+    dir_links: true,
+};
+
+///
+/// The capabilities of each filesystem which has been probed, by device (or volume, on Windows)
+static PROBED: Mutex<BTreeMap<u64, Capabilities>> = Mutex::new(BTreeMap::new());
+
+///
+/// Whether the file `file` keeps the modes it is given. A FAT filesystem mounted with `quiet` accepts chmod without changing anything, so each mode is read back
+#[cfg(unix)]
+fn keeps_modes(file: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    [0o600, 0o755].iter().all(|&mode| {
+        fs::set_permissions(file, fs::Permissions::from_mode(mode)).is_ok()
+            && fs::metadata(file).is_ok_and(|md| md.permissions().mode() & 0o7777 == mode)
+    })
+}
+
+#[cfg(not(unix))]
+fn keeps_modes(_file: &Path) -> bool {
+    // Modes are never applied on other platforms
+    true
+}
+
+///
+/// Finds what the filesystem of the existing directory `dir` supports, by trying it with a file created in `dir`
+fn probe(dir: &Path) -> Capabilities {
+    let file = dir.join(format!(
+        ".cargo-native-install-probe-{}",
+        std::process::id()
+    ));
+    let link = file.with_extension("link");
+    if fs::write(&file, b"").is_err() {
+        // Nothing can be installed into the directory, which the installation reports itself
+        return ALL;
+    }
+    let modes = keeps_modes(&file);
+    let symlinks = NativeFilesystem
+        .symlink(Path::new(file.file_name().unwrap()), &link, false)
+        .is_ok()
+        && fs::symlink_metadata(&link).is_ok_and(|md| md.file_type().is_symlink());
+    let _ = fs::remove_file(&link);
+    // Without the privilege to create symbolic links, Windows links directories with junctions, see `NativeFilesystem::symlink`
+    let dir_links = symlinks || cfg!(windows) && links_dirs(&file);
+    let _ = fs::remove_file(&file);
+    Capabilities {
+        modes,
+        symlinks,
+        dir_links,
+    }
+}
+
+///
+/// Whether a link to a directory can be created beside the probe file `file`
+fn links_dirs(file: &Path) -> bool {
+    let dir = file.with_extension("dir");
+    let link = file.with_extension("dirlink");
+    let linked = fs::create_dir(&dir).is_ok()
+        && NativeFilesystem
+            .symlink(Path::new(dir.file_name().unwrap()), &link, true)
+            .is_ok()
+        && fs::symlink_metadata(&link).is_ok_and(|md| md.file_type().is_symlink());
+    let _ = fs::remove_dir(&link);
+    let _ = fs::remove_dir(&dir);
+    linked
+}
+
+///
+/// What the filesystem `path` is installed to supports. Each filesystem is probed once, when a file is first installed to it,
+/// and whatever it does not support is reported then, rather than failing to install each file
+fn capabilities(path: &Path, opts: &Options) -> Capabilities {
+    // Other filesystems report what they do not support themselves
+    if opts.dry_run || opts.filesystem.is_some() {
+        return ALL;
+    }
+    let (dev, dir) = match space::filesystem_of(path) {
+        Some(fs) => fs,
+        None => return ALL,
+    };
+    let mut probed = PROBED.lock().unwrap_or_else(PoisonError::into_inner);
+    if let Some(caps) = probed.get(&dev) {
+        return *caps;
+    }
+    let caps = probe(dir);
+    if !caps.modes {
+        output::status(
+            opts,
+            Status::Warning,
+            format_args!(
+                "Warning: The filesystem of {} does not support file modes, so the files installed on it keep the modes it gives them",
+                dir.display()
+            ),
+        );
+    }
+    if !caps.symlinks {
+        let copied = match caps.dir_links {
+            true => "aliases and links of files are installed on it as copies, and directories are linked with junctions",
+            false => "aliases and links are installed on it as copies",
+        };
+        output::status(
+            opts,
+            Status::Warning,
+            format_args!(
+                "Warning: The filesystem of {} does not support symbolic links, so {}",
+                dir.display(),
+                copied
+            ),
+        );
+    }
+    probed.insert(dev, caps);
+    caps
+}
+
+///
+/// Whether file modes can be applied to `path`
+pub fn modes(path: &Path, opts: &Options) -> bool {
+    capabilities(path, opts).modes
+}
+
+///
+/// Whether a link can be created at `path`, to a directory if `dir` is set, or else to a file
+pub fn links(path: &Path, opts: &Options, dir: bool) -> bool {
+    let caps = capabilities(path, opts);
+    match dir {
+        true => caps.dir_links,
+        false => caps.symlinks,
+    }
+}
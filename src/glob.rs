@@ -0,0 +1,64 @@
+///
+/// Whether `text` matches the shell wildcard pattern `pattern`. `*` matches any characters other than `/`, `**` any characters,
+/// `?` any one character other than `/`, and `[...]` any one of the characters listed (or, after `!` or `^`, not listed), with ranges as `a-z`
+pub fn matches(pattern: &str, text: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let text = text.chars().collect::<Vec<_>>();
+    matches_at(&pattern, &text)
+}
+
+fn matches_at(pattern: &[char], text: &[char]) -> bool {
+    match pattern {
+        [] => text.is_empty(),
+        ['*', '*', rest @ ..] => (0..=text.len()).any(|skip| matches_at(rest, &text[skip..])),
+        ['*', rest @ ..] => {
+            let end = text.iter().position(|&c| c == '/').unwrap_or(text.len());
+            (0..=end).any(|skip| matches_at(rest, &text[skip..]))
+        }
+        ['?', rest @ ..] => match text {
+            [c, text @ ..] if *c != '/' => matches_at(rest, text),
+            _ => false,
+        },
+        ['[', rest @ ..] => match (class(rest), text) {
+            (Some((matched, rest)), [c, text @ ..]) => matched(*c) && matches_at(rest, text),
+            // A `[` which does not start a class matches itself
+            (None, ['[', text @ ..]) => matches_at(rest, text),
+            _ => false,
+        },
+        [p, rest @ ..] => match text {
+            [c, text @ ..] if c == p => matches_at(rest, text),
+            _ => false,
+        },
+    }
+}
+
+///
+/// The class at the start of `pattern`, just after its `[`, as a function which tells if a character is in it, and the pattern after its `]`
+fn class(pattern: &[char]) -> Option<(impl Fn(char) -> bool + '_, &[char])> {
+    let (negated, body) = match pattern {
+        ['!' | '^', body @ ..] => (true, body),
+        body => (false, body),
+    };
+    // A `]` which comes first is a member of the class
+    let end = body
+        .iter()
+        .skip(1)
+        .position(|&c| c == ']')
+        .map(|end| end + 1)?;
+    let members = &body[..end];
+    let matched = move |c: char| {
+        let mut i = 0;
+        let mut found = false;
+        while i < members.len() {
+            if i + 2 < members.len() && members[i + 1] == '-' {
+                found |= (members[i]..=members[i + 2]).contains(&c);
+                i += 3;
+            } else {
+                found |= members[i] == c;
+                i += 1;
+            }
+        }
+        found != negated
+    };
+    Some((matched, &body[end + 1..]))
+}
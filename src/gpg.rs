@@ -0,0 +1,167 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use install_dirs::dirs::InstallDirs;
+
+use crate::{
+    copy, deps,
+    manifest::{Target, TargetType},
+    output,
+    output::Status,
+    receipt::Receipt,
+    sysroot, Error, Options, Verbosity,
+};
+
+///
+/// Whether `--sign-artifacts` signs the file installed by `target`: programs and shared libraries
+pub fn is_signed(target: &Target) -> bool {
+    matches!(
+        target.type_,
+        Some(TargetType::Bin | TargetType::SBin | TargetType::Libexec | TargetType::Shared)
+    )
+}
+
+///
+/// The detached signature of the installed file `dest`, `<dest>.sig`
+pub fn signature_path(dest: &Path) -> PathBuf {
+    let mut path = dest.as_os_str().to_owned();
+    path.push(".sig");
+    PathBuf::from(path)
+}
+
+///
+/// Creates a detached signature beside each program and shared library in `targets`, with the gpg key `keyid`, and records it in `receipt`.
+/// Targets which were skipped because they have not changed keep their signatures, so that a file changed since it was installed is never signed.
+/// Returns the number of files which could not be signed
+pub fn sign(
+    keyid: &str,
+    dirs: &InstallDirs,
+    targets: &HashMap<String, Target>,
+    receipt: &mut Receipt,
+    opts: &Options,
+) -> usize {
+    let mut failed = 0;
+    for (name, target, dest) in deps::installed(dirs, targets, opts) {
+        if !is_signed(target) {
+            continue;
+        }
+        let dest = sysroot::in_sysroot(&dest, opts);
+        let signature = signature_path(&dest);
+        let signed = receipt
+            .targets
+            .get(name)
+            .and_then(|entry| entry.signature.as_ref())
+            .is_some_and(|signed| *signed == signature && signed.exists());
+        if signed {
+            continue;
+        }
+        output::verbose(opts, format_args!("Signing {}", dest.display()));
+        if opts.dry_run || !dest.exists() {
+            continue;
+        }
+        let mut cmd = Command::new("gpg");
+        cmd.args(["--batch", "--yes", "--local-user", keyid, "--detach-sign"])
+            .arg("--output")
+            .arg(&signature)
+            .arg(&dest);
+        output::command(opts, &cmd);
+        let result = match cmd.status() {
+            Ok(status) if status.success() => {
+                copy::sync(&signature, opts).map_err(|e| e.to_string())
+            }
+            Ok(status) => Err(format!("gpg exited with {}", status)),
+            Err(e) => Err(e.to_string()),
+        };
+        if let Err(e) = result {
+            output::status(
+                opts,
+                Status::Failed,
+                format_args!("Failed to sign {}, {}", dest.display(), e),
+            );
+            failed += 1;
+            continue;
+        }
+        if let Some(entry) = receipt.targets.get_mut(name) {
+            if !entry.files.contains(&signature) {
+                entry.files.push(signature.clone());
+            }
+            entry.signature = Some(signature);
+        }
+    }
+    failed
+}
+
+///
+/// Checks the signature of each file recorded in the install receipt of `package`, as made by `--sign-artifacts`, with gpg
+pub fn verify(dirs: &InstallDirs, package: &str, opts: &Options) -> Result<(), Error> {
+    let path = sysroot::in_sysroot(&Receipt::path(dirs, package), opts);
+    let receipt = Receipt::load(&path, package).map_err(|e| Error::Receipt(path.clone(), e))?;
+
+    let mut verified = 0;
+    let mut invalid = 0;
+    for (name, entry) in &receipt.targets {
+        let signature = match &entry.signature {
+            Some(signature) => signature,
+            None => continue,
+        };
+        let mut cmd = Command::new("gpg");
+        cmd.args(["--batch", "--verify"])
+            .arg(signature)
+            .arg(&entry.destination);
+        if opts.verbosity < Verbosity::Verbose {
+            cmd.stderr(Stdio::null());
+        }
+        output::command(opts, &cmd);
+        match cmd.status() {
+            Ok(status) if status.success() => {
+                verified += 1;
+                output::verbose(
+                    opts,
+                    format_args!("Verified the signature of {}", entry.destination.display()),
+                );
+            }
+            Ok(_) => {
+                invalid += 1;
+                output::status(
+                    opts,
+                    Status::Failed,
+                    format_args!(
+                        "The signature of {} (target {}) is not valid",
+                        entry.destination.display(),
+                        name
+                    ),
+                );
+            }
+            Err(e) => return Err(Error::Spawn("gpg".into(), e)),
+        }
+    }
+
+    if invalid != 0 {
+        return Err(Error::InvalidSignatures(invalid));
+    }
+    if verified == 0 {
+        output::status(
+            opts,
+            Status::Warning,
+            format_args!(
+                "Warning: the install receipt of {} records no signatures, install it with --sign-artifacts to sign it",
+                package
+            ),
+        );
+    } else {
+        output::status(
+            opts,
+            Status::Installed,
+            format_args!(
+                "Verified {} signature{} of {}",
+                verified,
+                if verified == 1 { "" } else { "s" },
+                package
+            ),
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,849 @@
+use std::{
+    fmt::Write as _,
+    io::{self, Write},
+};
+
+const DESCRIPTION: &str = "Installs the current cargo project into native system directories (like GNU make install or cmake --install)";
+
+///
+/// The options and commands of the command line, with their descriptions, in the order `--help` lists them.
+/// The completions of [`crate::completions`] and the manual page written by [`write_man`] are generated from them; the names give the values an option takes after `=`
+pub const OPTIONS: &[(&str, &str)] = &[
+    (
+        "--help",
+        "Prints this message, and exits",
+    ),
+    (
+        "--version",
+        "Prints version information, and exits",
+    ),
+    (
+        "--dry-run",
+        "Show the results of each install operation, but do not perform any operations. Each operation is labeled as new, update, unchanged, or conflict (dest newer) by comparing the source with the existing destination, along with its size, and the space needed on each filesystem is shown first",
+    ),
+    (
+        "--diff",
+        "With --dry-run, show a unified diff of each text file that would be changed",
+    ),
+    (
+        "--user-prefix",
+        "Default prefix to ~/.local, instead of a system-wide dir. This overrides the `prefix` environment variable even if `--prefix` is not set.",
+    ),
+    (
+        "--prefix=<prefix>",
+        "Sets the prefix for installation operations",
+    ),
+    (
+        "--exec-prefix=<dir>",
+        "Sets the prefix for architecture-dependent files. bindir, sbindir, libdir, and libexecdir are resolved relative to <dir> (itself resolved relative to the prefix, if relative), while includedir, datarootdir, and sysconfdir stay under the prefix",
+    ),
+    (
+        "--root=<dir>",
+        "Install into <dir>, like cargo install --root, and record the programs installed to <dir>/bin in the metadata of cargo install, so that cargo install --list shows them and cargo uninstall removes them. Implies --prefix=<dir>",
+    ),
+    (
+        "--dist=<archive>",
+        "Install into a staging directory, then pack it into <archive> (a zip file if it ends in .zip, and otherwise a tarball compressed according to its name), which can be installed with --from-archive",
+    ),
+    (
+        "--vendor-dist=<archive>",
+        "Pack the source of the package, with its dependencies vendored by cargo vendor and a .cargo/config.toml which uses them, into <archive> (as with --dist), instead of installing, so that it can be built and installed without network access",
+    ),
+    (
+        "--from-archive=<archive>",
+        "Install <archive>, made by --dist, to the paths it was made for, instead of the package in the manifest directory. Neither the source of the package nor cargo is needed",
+    ),
+    (
+        "--from-release=<repo-or-url>",
+        "Download a release archive, made by --dist, from a repository on GitHub or GitLab (with --tag), or from the URL of the archive, verify it against its published SHA-256 checksum (and signature, if any), and install it as with --from-archive. Only https is used. The checksum is published beside the archive, so it only detects a corrupted download; no signing key is pinned, so a signature is only checked against the keys gpg already trusts",
+    ),
+    (
+        "--tag=<tag>",
+        "The tag of the release to install with --from-release",
+    ),
+    (
+        "--multiarch[=<triple>]",
+        "Install libraries into libdir/<triple>, and helpers into libexecdir/<triple>, so that one prefix can hold the libraries of several architectures. Installed pkg-config files refer to these directories. <triple> defaults to the target this program was built for",
+    ),
+    (
+        "--sysroot=<dir>",
+        "Install headers, libraries, and pkg-config files beneath <dir>, a cross-compilation sysroot, and skip every other target. Installed pkg-config files refer to the installation directories without <dir>, for use with PKG_CONFIG_SYSROOT_DIR",
+    ),
+    (
+        "--stow",
+        "Install into <prefix>/stow/<package>-<version>, then link each installed file into the prefix, replacing the links of any other version of the package. Directories outside the prefix are installed to directly",
+    ),
+    (
+        "--stow-dir=<dir>",
+        "Implies --stow, and uses <dir> instead of <prefix>/stow. Either an absolute path, or a path relative to prefix",
+    ),
+    (
+        "--unstow",
+        "Remove the links created by --stow for the package, leaving the stowed files in place, instead of installing",
+    ),
+    (
+        "--slot",
+        "Install into <prefix>/opt/<package>/<version>, so that several versions can be installed side by side. The package is built for the slot",
+    ),
+    (
+        "--activate",
+        "With --slot, link the programs of the installed version into bindir and sbindir, replacing the links to any other version",
+    ),
+    (
+        "switch-version <version>",
+        "Link the programs of <version>, which must already be installed with --slot, into bindir and sbindir, instead of building or installing",
+    ),
+    (
+        "relocate [--copy] <prefix>",
+        "Move the installed package, recorded in its install receipt, to <prefix>, instead of building or installing, changing links, rpaths, and pkg-config files that refer to the old prefix. With --copy, the installed files are copied instead",
+    ),
+    (
+        "diff-receipts <old> <new>",
+        "Show the files which are added, removed, or changed between the install receipts <old> and <new>, instead of installing",
+    ),
+    (
+        "diff-receipts --against-installed",
+        "Show the files which installing the package would add, remove, or change, compared to the installed version, instead of installing",
+    ),
+    (
+        "--develop",
+        "Link each artifact into its destination, instead of copying it, so that rebuilding the package updates the installation. Manual pages which are rendered, and pkg-config files which are rewritten, are still copied. Installing without --develop replaces the links with copies",
+    ),
+    (
+        "--watch",
+        "Install, then watch the artifacts of each target, and install again whenever they change (such as when the package is rebuilt), until interrupted. Targets whose artifacts have not changed are skipped",
+    ),
+    (
+        "report-orphans",
+        "List the files installed by earlier versions of the package which no target installs now, such as targets that were removed, and old versions of versioned targets, instead of installing. Nothing is removed",
+    ),
+    (
+        "check",
+        "Validate the install targets of the package and of each workspace member, reporting unknown keys (with suggestions), targets without a type, missing required fields, conflicting options, and target files that do not exist, instead of installing",
+    ),
+    (
+        "test-install",
+        "Install the package into a temporary prefix, run each installed program with --version (or the smoke-test commands of its target), then remove the prefix, instead of installing. Fails if any of them fails",
+    ),
+    (
+        "verify",
+        "Check the signatures of the installed files, made by --sign-artifacts, with gpg, instead of installing",
+    ),
+    (
+        "config set <key> <value>",
+        "Set <key> to <value> in the configuration of the user, $XDG_CONFIG_HOME/cargo-native-install/config.toml, instead of installing. <key> is an installation directory (such as prefix), strip (true, false, or the program to strip with), mode, or a key of another table, as table.key",
+    ),
+    (
+        "config get <key>",
+        "Print the value of <key> in the configuration files which apply, instead of installing",
+    ),
+    (
+        "config list",
+        "Print each key set by the configuration files which apply, with its value, instead of installing",
+    ),
+    (
+        "--emit-schema",
+        "Print a JSON Schema for [package.metadata.install-targets] to standard output, for editors which validate Cargo.toml, and exit",
+    ),
+    (
+        "--completions=bash|zsh|fish",
+        "Print a script which completes the options of this program in bash, zsh, or fish to standard output, and exit",
+    ),
+    (
+        "--man-page",
+        "Print the manual page of this program, in roff, to standard output, and exit",
+    ),
+    (
+        "--dest=ssh://[user@]host[:port][/prefix]",
+        "Install onto a remote machine, by running commands through ssh. The path, if present, sets the prefix on the remote machine",
+    ),
+    (
+        "--bindir=<dir>",
+        "Use dir as the directory to install binary programs. Either an absolute path, or a path relative to prefix. (defaults to bin)",
+    ),
+    (
+        "--libdir=<dir>",
+        "Use dir as the directory to install libraries. Either an absolute path, or a path relative to prefix (defaults to lib)",
+    ),
+    (
+        "--sbindir=<dir>",
+        "Use dir as the directory to install system administrator programs. Either an absolute path, or a path relative to prefix (defaults to sbin)",
+    ),
+    (
+        "--libexecdir=<dir>",
+        "Use dir as the directory to install programs that aren't for direct use from the shell. Either an absolute path, or a path relative to prefix (defaults to libexec)",
+    ),
+    (
+        "--includedir=<dir>",
+        "Use dir as the directory to install header files. Either an absolute path, or a path relative to prefix (defaults to include)",
+    ),
+    (
+        "--datarootdir=<dir>",
+        "Use dir as the prefix for platform independent data, documentation, and manuals. Either an absolute path, or a path relative to prefix (defaults to share)",
+    ),
+    (
+        "--datadir=<dir>",
+        "Use dir as the directory to install platform independent data. Either an absolute path, or a path relative to the data root (defaults to the same directory as the data root)",
+    ),
+    (
+        "--mandir=<dir>",
+        "Use dir as the directory for installing manual pages. Either an absolute path, or a path relative to data root (defaults to man)",
+    ),
+    (
+        "--infodir=<dir>",
+        "Use dir as the directory for installing info pages. Either an absolute path, or a path relative to data root (defaults to info)",
+    ),
+    (
+        "--docdir=<dir>",
+        "Use dir as the directory for installing project documentation. Either an absolute path, or a path relative to data root (defaults to doc/<project>)",
+    ),
+    (
+        "--localedir=<dir>",
+        "Use dir as the directory for installing locale specific information. Either an absolute path, or a path relative to data root (defaults to locale)",
+    ),
+    (
+        "--sysconfdir=<dir>",
+        "Use dir as the directory for system configuration files. Either an absolute path, or a path relative to the prefix (defaults to etc)",
+    ),
+    (
+        "--localstatedir=<dir>",
+        "Use dir as the directory for local system state. Either an absolute path, or a path relative to the prefix (defaults to var)",
+    ),
+    (
+        "--sharedstatedir=<dir>",
+        "Use dir as the directory for shared system state. Either an absolute path, or a path relative to the prefix (defaults to com)",
+    ),
+    (
+        "--no-strip",
+        "Do not strip programs, even if strip is found",
+    ),
+    (
+        "--without-strip",
+        "Same as --no-strip",
+    ),
+    (
+        "--strip=<prg>",
+        "Use <prg> to strip, instead of the default (strip, or if it is not found, llvm-strip or rust-objcopy from the Rust toolchain)",
+    ),
+    (
+        "--strip-mode=all|debug|unneeded",
+        "Remove every symbol, only debug information, or only unneeded symbols, from targets which do not set strip-mode (default: unneeded for shared libraries, all otherwise)",
+    ),
+    (
+        "--separate-debug",
+        "Before stripping a binary, keep its debug information in <debugdir>/.build-id/xx/yyyy.debug, named after its build ID, where debuggers and debuginfod find it",
+    ),
+    (
+        "--debugdir=<dir>",
+        "The directory --separate-debug keeps debug information in. Either an absolute path, or a path relative to prefix (defaults to lib/debug)",
+    ),
+    (
+        "--strip-arg=<arg>",
+        "Pass <arg> to strip, for every target. May be given more than once",
+    ),
+    (
+        "--install[=<prg>]",
+        "Use <prg> (default: install) to install each file, instead of copying files natively. Directory trees are always copied natively",
+    ),
+    (
+        "--install-flavor=gnu|bsd|busybox",
+        "The implementation of the install program, which decides the options it is given (default: detected from the output of <prg> --version)",
+    ),
+    (
+        "--pandoc=<prg>",
+        "Use <prg> to render man targets written in Markdown, instead of the default (pandoc)",
+    ),
+    (
+        "--asciidoctor=<prg>",
+        "Use <prg> to render man targets written in AsciiDoc, instead of the default (asciidoctor)",
+    ),
+    (
+        "--patchelf=<prg>",
+        "Use <prg> to change the rpath of targets which set rpath, instead of the default (patchelf). If it is not found, binaries are edited directly, which cannot lengthen an rpath",
+    ),
+    (
+        "--internal-install",
+        "Do not invoke any programs to install. Instead, copy files natively (the default)",
+    ),
+    (
+        "--mode=<mode>",
+        "Force installed files to use <mode> in the form of a chmod mode (X is the executable bit if the file is a binary target, or a directory). Only guaranteed to be effective on unix-like targets",
+    ),
+    (
+        "--umask=<mask>",
+        "Use the octal file mode creation mask <mask> while installing, instead of the umask of the invoking shell. Only effective on unix-like targets",
+    ),
+    (
+        "--lock-timeout=<seconds>",
+        "Wait at most <seconds> for another installation into the prefix to finish (default: 300)",
+    ),
+    (
+        "--retries=<count>",
+        "Retry a copy, chmod, or link which fails with a transient error, such as a busy file or an unavailable network filesystem, up to <count> times (default: 3)",
+    ),
+    (
+        "--owner=<user>",
+        "Make installed files and directories owned by <user>, given as a name or uid. Usually requires root. Only effective on unix-like targets",
+    ),
+    (
+        "--group=<group>",
+        "Make installed files and directories owned by <group>, given as a name or gid. Only effective on unix-like targets",
+    ),
+    (
+        "--copy=<method>",
+        "How the internal installer copies files. One of auto (default, uses reflinks and preserves sparse files where possible), buffered, or sendfile",
+    ),
+    (
+        "--copy-buffer-size=<size>",
+        "The buffer size for --copy=buffered, in bytes, optionally followed by K or M. Defaults to 64K",
+    ),
+    (
+        "--sync",
+        "Flush each installed file, and the directory containing it, to disk before continuing. Slower, but the installation survives a crash or power loss",
+    ),
+    (
+        "--no-sync",
+        "Do not flush installed files to disk (default)",
+    ),
+    (
+        "--preserve-timestamps",
+        "Give installed files the modification time of their source, rather than the current time",
+    ),
+    (
+        "--clamp-timestamps[=<time>]",
+        "Give installed files the modification time of their source, but no later than <time> (in seconds since the unix epoch). Defaults to the value of SOURCE_DATE_EPOCH",
+    ),
+    (
+        "--no-create",
+        "Do not create installed directories. Also do not create any prefix directories",
+    ),
+    (
+        "--log-file=<path>",
+        "Append a timestamped log of every action, command invoked, and error to <path>, regardless of --verbose or --quiet",
+    ),
+    (
+        "--report=junit|tap:<path>",
+        "Write whether each target was installed, skipped, or failed to <path>, as JUnit XML or TAP, for CI systems to display",
+    ),
+    (
+        "--verbose, -v",
+        "Print additional messages for each action. May be given twice (or as -vv) to also print each command invoked",
+    ),
+    (
+        "--quiet, -q",
+        "Only print errors and failed operations",
+    ),
+    (
+        "--color=<when>",
+        "Whether to color status markers. One of auto (default), always, or never",
+    ),
+    (
+        "--annotations=<format>",
+        "Also write warnings and errors as annotations, which CI shows on the pull request. One of auto (default, github in GitHub Actions), github, or never",
+    ),
+    (
+        "--force",
+        "Install all files, even if this would replace files that are newer, or the target is unchanged since it was last installed",
+    ),
+    (
+        "--adopt",
+        "Replace existing files which were not installed by this package, such as files placed by the administrator or another tool, and record them in the install receipt. Without this option or --force, such targets fail to install",
+    ),
+    (
+        "--keep-going, -k",
+        "Continue installing other targets after a target fails to install. The program still exits with a non-zero status if any target failed",
+    ),
+    (
+        "--run-checks",
+        "Once every file is installed, run the check targets of the package, which validate the installation in place, like make installcheck. Without this option, check targets are skipped",
+    ),
+    (
+        "--check-packages[=refuse|warn|off]",
+        "Before overwriting a file under a system directory (such as /usr or /etc), ask the system package manager (dpkg, rpm, or pacman) whether a package owns it. With refuse (the default if no argument is given), such targets fail to install unless --force is given. With warn, a warning is printed instead",
+    ),
+    (
+        "--check-build-paths[=deny|warn|off]",
+        "After installing, report installed programs and libraries which contain paths in the source directory, the cargo home directory, or the home directory of the build machine, and the --remap-path-prefix flags that remove them. With deny, the installation fails if any are found. With warn (the default if no argument is given), they are only reported",
+    ),
+    (
+        "--no-privileged",
+        "Do not install privileged binaries (those installed to sbin)",
+    ),
+    (
+        "--privileged",
+        "Install privilged binaries to sbindir, even if a user-specific prefix is used",
+    ),
+    (
+        "--locales=<locale>,...",
+        "Only install the targets whose lang is one of the given locales, or a variant of one (so pt includes pt_BR), and the targets without a lang",
+    ),
+    (
+        "--all-locales",
+        "Install the targets of every locale. This is the default, and undoes an earlier --locales",
+    ),
+    (
+        "--allow-outside-prefix",
+        "Allow targets to be installed to paths outside of every installation directory, such as through absolute paths or .. components in installed-path",
+    ),
+    (
+        "--elevate",
+        "If the current user cannot write to an installation directory, re-execute the installation using sudo, doas, or pkexec without asking first",
+    ),
+    (
+        "--no-elevate",
+        "Never re-execute the installation with elevated privileges. By default, if the current user cannot write to an installation directory and stdin is a terminal, you are asked whether to retry using sudo, doas, or pkexec. Building is always done unprivileged",
+    ),
+    (
+        "--elevate-with=<prg>",
+        "Use <prg> to elevate privileges, instead of the first of sudo, doas, or pkexec that is found",
+    ),
+    (
+        "--target=<target>",
+        "Install only this target",
+    ),
+    (
+        "--no-libexec",
+        "Install libexec targets to bin instead",
+    ),
+    (
+        "--no-sbin",
+        "Install privileged binaries to bin instead of sbin (note that this does not enable privileged binaries)",
+    ),
+    (
+        "--arch-target[=<target>]",
+        "Install bin, lib, include, libexec, sbin targets to an an architecture specific prefix.",
+    ),
+    (
+        "build [--install]",
+        "Build the package with cargo build, exporting each installation directory to cargo as an environment variable (see BUILD ENVIRONMENT in the manual), instead of installing. With --install, install the package once it is built. Must be the first argument",
+    ),
+    (
+        "--env-prefix=<prefix>",
+        "With build, export each installation directory as <prefix> followed by its name, such as <prefix>bindir",
+    ),
+    (
+        "--env-name=<dir>=<var>",
+        "With build, export the installation directory <dir> (such as bindir) as <var>, instead of its name. May be given once for each directory",
+    ),
+    (
+        "--build",
+        "Deprecated: use build --install",
+    ),
+    (
+        "--build-only",
+        "Deprecated: use build",
+    ),
+    (
+        "--no-space-check",
+        "Install even if the files do not fit in the space available on the filesystems they are installed to. Without this option, the installation fails before anything is installed if they do not",
+    ),
+    (
+        "--require-built",
+        "Check that the artifact of every target to be installed exists before installing anything, and fail, listing each missing artifact and the cargo command that builds it, if any do not",
+    ),
+    (
+        "--devel",
+        "Also install the library of the package for system Rust consumers: its rlib (and rmeta, if cargo produced one) to libdir/rustlib/<triple>/lib, and its source, as cargo package includes it, to datadir/cargo/registry/<package>-<version>, which can be used as a directory source for offline builds",
+    ),
+    (
+        "--third-party-licenses",
+        "Also install <docdir>/<package>/THIRD_PARTY_LICENSES, which lists each dependency the package is built from, with its license and license texts, for the attribution that statically linked dependencies require",
+    ),
+    (
+        "--bundle-libs",
+        "Copy the shared libraries needed by installed programs and shared libraries, which are not in the directories of the dynamic linker, to libdir, and set the rpath of the binaries which need them, so the installation does not need the libraries of the build machine. Only ELF binaries are examined, not Mach-O (macOS) or PE (Windows) ones",
+    ),
+    (
+        "--fail-on-missing-deps",
+        "Fail if an installed program or shared library needs a shared library which is not installed on this system, instead of warning. Only ELF binaries are examined, not Mach-O (macOS) or PE (Windows) ones",
+    ),
+    (
+        "--sign-artifacts=<keyid>",
+        "After installing, sign each installed program and shared library with the gpg key <keyid>, creating a detached signature <file>.sig beside it, which is recorded in the install receipt and checked by verify",
+    ),
+    (
+        "--emit-config-h=<file>",
+        "Write a C header to <file>, defining each resolved installation directory (such as BINDIR) as a string literal, before building",
+    ),
+    (
+        "--emit-paths-rs=<file>",
+        "Write a Rust module to <file>, defining each resolved installation directory (such as BINDIR) as a &str constant, before building",
+    ),
+    (
+        "--install-emitted",
+        "Also install the files written by --emit-config-h and --emit-paths-rs to <includedir>/<package>",
+    ),
+    (
+        "--emit-env=<file>",
+        "Write a shell script to <file>, assigning each resolved installation directory to a variable named as its environment variable (such as bindir)",
+    ),
+    (
+        "--emit-dotenv=<file>",
+        "Write the variables of --emit-env to <file> in dotenv format, which can also be included by make",
+    ),
+    (
+        "--print-dirs[=human|shell|json]",
+        "Print each resolved installation directory, after the configuration files, environment variables, and options are applied, and exit. With shell, the directories are printed as export commands, and with json, as a JSON object (default: human)",
+    ),
+    (
+        "--emit-ninja=<file>",
+        "Write a ninja file to <file>, with an edge for each install step and a phony edge, install-<package>, which depends on all of them, instead of installing. The package is built first, as usual. Include it in another ninja file with subninja",
+    ),
+    (
+        "--emit-script=sh|ps1",
+        "Print a standalone shell (sh) or PowerShell (ps1) script to standard output, which installs the package with plain commands such as install, mkdir, and ln -s, instead of installing. The package is built first, as usual, and the script installs from the build directory",
+    ),
+    (
+        "--keep-privileges",
+        "When running as root through sudo, doas, or pkexec, build as root instead of as the invoking user",
+    ),
+    (
+        "--container",
+        "Skip the steps which fail in an unprivileged container: elevating, unless --elevate is given, and running install-docs, with a single warning. Options which are given, such as --owner, are still applied. The default when a container is detected",
+    ),
+    (
+        "--no-container",
+        "Do not skip any steps in a container",
+    ),
+    (
+        "--shared=lib",
+        "Treat cdylib targets as library targets by default and install to libdir. This is the default on unix-like targets",
+    ),
+    (
+        "--shared=bin",
+        "Treat cdylib targets as binary targets by default and install to bindir. This is the default on windows",
+    ),
+    (
+        "--manifest-dir=<dir>",
+        "Install the package in <dir>, instead of the current directory",
+    ),
+    (
+        "--manifest-path=<path>",
+        "Install the package whose Cargo.toml is at <path>, like cargo's --manifest-path",
+    ),
+    (
+        "--out-dir=<dir>",
+        "Consider cargo targets to be stored in <dir> instead of <manifest-dir>/target. A relative <dir> is relative to the manifest directory",
+    ),
+    (
+        "--release",
+        "Consider cargo targets to have been built in release mode (default)",
+    ),
+    (
+        "--debug",
+        "Consider cargo targets to have been built in debug mode",
+    ),
+    (
+        "--config=<file>",
+        "Parse project configuration from the specified file if it exists, rather than config.toml in the manifest directory. A relative <file> is relative to the manifest directory. The system configuration, /etc/cargo-native-install/config.toml, and user configuration, $XDG_CONFIG_HOME/cargo-native-install/config.toml, are read first, and take lower precedence",
+    ),
+];
+
+///
+/// The environment variables which are read, with their descriptions
+const ENVIRONMENT: &[(&str, &str)] = &[
+    (
+        "prefix",
+        "Install directories may be specified as environment variables, as well as with options. If both the environment variable and the CLI option is present, the option takes precedence",
+    ),
+    (
+        "exec_prefix",
+        "Similar to prefix. bindir, sbindir, libdir, and libexecdir are resolved relative to it, if set",
+    ),
+    (
+        "bindir",
+        "Similar to prefix.",
+    ),
+    (
+        "libdir",
+        "Similar to prefix.",
+    ),
+    (
+        "sbindir",
+        "Similar to prefix.",
+    ),
+    (
+        "libexecdir",
+        "Similar to prefix.",
+    ),
+    (
+        "includedir",
+        "Similar to prefix.",
+    ),
+    (
+        "datarootdir",
+        "Similar to prefix.",
+    ),
+    (
+        "datadir",
+        "Similar to prefix.",
+    ),
+    (
+        "docdir",
+        "Similar to prefix.",
+    ),
+    (
+        "mandir",
+        "Similar to prefix.",
+    ),
+    (
+        "infodir",
+        "Similar to prefix.",
+    ),
+    (
+        "localedir",
+        "Similar to prefix.",
+    ),
+    (
+        "localstatedir",
+        "Similar to prefix.",
+    ),
+    (
+        "sharedstatedir",
+        "Similar to prefix.",
+    ),
+    (
+        "sysconfdir",
+        "Similar to prefix.",
+    ),
+    (
+        "runstatedir",
+        "If specified, the variable is propagate to run targets, and to cargo. Has no further effect on the program",
+    ),
+    (
+        "PATH",
+        "Searches for install and strip in these paths",
+    ),
+    (
+        "RUSTC",
+        "The compiler whose toolchain is searched for llvm-strip or rust-objcopy if strip is not found (default: rustc)",
+    ),
+    (
+        "SOURCE_DATE_EPOCH",
+        "The default time for --clamp-timestamps",
+    ),
+    (
+        "NO_COLOR",
+        "If set, status markers are not colored unless --color=always is given",
+    ),
+    (
+        "GITHUB_ACTIONS",
+        "If true, warnings and errors are also written as GitHub Actions annotations, unless --annotations=never is given",
+    ),
+    (
+        "CARGO_NATIVE_INSTALL_FLAGS",
+        "Whitespace-separated options which are read before those on the command line, so that options on the command line take precedence. Options cannot contain whitespace, as there is no quoting",
+    ),
+];
+
+///
+/// The variables which `build` exports to cargo, with their descriptions, for the manual page
+const BUILD_ENVIRONMENT: &[(&str, &str)] = &[
+    (
+        "CARGO_NATIVE_INSTALL_ENV",
+        "The version of this set of variables, currently 1",
+    ),
+    (
+        "prefix, exec_prefix, bindir, sbindir, libdir, libexecdir, includedir, datarootdir, datadir, mandir, infodir, docdir, localedir, sysconfdir, localstatedir, runstatedir, sharedstatedir",
+        "The installation directory of that name, as the absolute path the installed package finds it at when it runs, without the directory given by --dest or --sysroot. Renamed by --env-prefix and --env-name",
+    ),
+    (
+        "DESTDIR",
+        "With --sysroot, the sysroot, beneath which the files are staged",
+    ),
+];
+
+///
+/// The files which are read or written, with their descriptions, for the manual page. Each line of a description is a paragraph
+const FILES: &[(&str, &str)] = &[
+    (
+        "/etc/cargo-native-install/config.toml, $XDG_CONFIG_HOME/cargo-native-install/config.toml, <manifest-dir>/config.toml",
+        "The default installation directories, read from the [dirs] table of each file that exists. Directories set by the configuration of the project take precedence over those set by the configuration of the user (in ~/.config if XDG_CONFIG_HOME is not set), which take precedence over the system configuration. Environment variables and options take precedence over all of them.
+The [defaults] table sets options which are used when they are not given: strip, false to not strip, true to strip with the default program, or the program to strip with, and mode, as --mode.
+The [signtool] table signs the installed Windows programs, libraries, and installers with signtool, after stripping and before packing with --dist, using the certificate with the SHA-1 thumbprint, the PFX file certificate (with the password in the environment variable password-env), or otherwise the one signtool chooses. timestamp-url, digest (sha256 by default), description, and program are passed to signtool as well.
+The [notarize] table submits zip archives made by --dist to Apple's notary service with xcrun notarytool, using the credentials in the keychain profile keychain-profile, and staples the ticket to each application bundle in the archive",
+    ),
+    (
+        "<localstatedir>/lib/cargo-native-install/<package>.json",
+        "The install receipt, listing the files installed by each target and a fingerprint of the artifact it was installed from. Targets whose fingerprint is unchanged since they were last installed are skipped, unless --force is given",
+    ),
+];
+
+///
+/// The exit statuses of the program, with their meanings
+const EXIT_STATUS: &[(&str, &str)] = &[
+    (
+        "0",
+        "Success",
+    ),
+    (
+        "1",
+        "Any other failure",
+    ),
+    (
+        "2",
+        "Invalid command line, such as an unknown option, or options which cannot be used together",
+    ),
+    (
+        "3",
+        "Invalid manifest, install targets, or configuration",
+    ),
+    (
+        "4",
+        "The artifact of a target does not exist, usually because the project has not been built",
+    ),
+    (
+        "5",
+        "A destination could not be written, because of its permissions",
+    ),
+    (
+        "6",
+        "With --keep-going, one or more targets failed to install, and the remaining targets were still installed",
+    ),
+    (
+        "7",
+        "A program that is needed, such as cargo or install, could not be found",
+    ),
+];
+
+///
+/// Writes the message of `--help` to `out`
+pub fn write(out: &mut impl Write, prg_name: &str) -> io::Result<()> {
+    writeln!(out, "Usage: {} [options]...", prg_name)?;
+    writeln!(out, "{}\n", DESCRIPTION)?;
+    writeln!(out, "Options:")?;
+    for (name, description) in OPTIONS {
+        writeln!(out, "\t{}: {}", name, description)?;
+    }
+    writeln!(out)?;
+    writeln!(out, "Environment:")?;
+    for (name, description) in ENVIRONMENT {
+        writeln!(out, "{}\n\t\t{}", name, description)?;
+    }
+    writeln!(out)?;
+    writeln!(out, "Exit status:")?;
+    for (status, meaning) in EXIT_STATUS {
+        writeln!(out, "{}\t{}", status, meaning)?;
+    }
+    Ok(())
+}
+
+///
+/// `s` escaped for roff
+fn roff(s: &str) -> String {
+    s.replace('\\', "\\e").replace('-', "\\-")
+}
+
+///
+/// `s` escaped for roff, with each option it mentions (such as `--dry-run`, or `--color=always`) in bold
+fn roff_text(s: &str) -> String {
+    let mut out = String::new();
+    let mut rest = s;
+    while let Some(start) = rest.find("--") {
+        let (before, option) = rest.split_at(start);
+        let is_option = option[2..].starts_with(|c: char| c.is_ascii_lowercase())
+            && !before.ends_with(|c: char| c.is_ascii_alphanumeric() || c == '-');
+        if !is_option {
+            out.push_str(&roff(&rest[..start + 2]));
+            rest = &rest[start + 2..];
+            continue;
+        }
+        let name = option
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '-'))
+            .unwrap_or(option.len());
+        let len = match option[name..].strip_prefix('=') {
+            Some(value) => {
+                name + 1
+                    + value
+                        .find(|c: char| !c.is_ascii_alphanumeric())
+                        .unwrap_or(value.len())
+            }
+            None => name,
+        };
+        write!(out, "{}\\fB{}\\fR", roff(before), roff(&option[..len])).unwrap();
+        rest = &option[len..];
+    }
+    out.push_str(&roff(rest));
+    out
+}
+
+///
+/// The name of an option or command of [`OPTIONS`] for roff, in bold up to the value it takes, or, for a command, up to its arguments
+fn roff_option(name: &str) -> String {
+    let end = match name.starts_with('-') {
+        true => name
+            .find(['=', '[', ' ', ','])
+            .map_or(name.len(), |i| i + usize::from(name[i..].starts_with('='))),
+        false => [" -", " <", " ["]
+            .iter()
+            .filter_map(|args| name.find(args))
+            .min()
+            .unwrap_or(name.len()),
+    };
+    format!("\\fB{}\\fR{}", roff(&name[..end]), roff_text(&name[end..]))
+}
+
+///
+/// Writes the manual page, `cargo-native-install.1`, to `out`
+pub fn write_man(out: &mut impl Write) -> io::Result<()> {
+    writeln!(
+        out,
+        ".\\\" Generated by `cargo-native-install --man-page`, from the options listed by --help. Do not edit"
+    )?;
+    writeln!(
+        out,
+        ".TH CARGO-NATIVE-INSTALL \"1\" \"\" \"cargo-native-install {}\" \"User Commands\"",
+        crate::VERSION
+    )?;
+    writeln!(out, ".SH NAME")?;
+    writeln!(
+        out,
+        "cargo-native-install \\- Installs cargo packages, similar to cmake \\-\\-install"
+    )?;
+    writeln!(out, ".SH SYNOPSIS")?;
+    writeln!(out, ".B cargo-native-install")?;
+    writeln!(out, "[\\fI\\,options\\/\\fR]...")?;
+    writeln!(out, ".SH DESCRIPTION")?;
+    writeln!(out, "{}", roff_text(DESCRIPTION))?;
+    writeln!(out, ".SH OPTIONS")?;
+    for (name, description) in OPTIONS {
+        writeln!(
+            out,
+            ".HP\n{}: {}",
+            roff_option(name),
+            roff_text(description)
+        )?;
+    }
+    writeln!(out, ".SH EXIT STATUS")?;
+    for (status, meaning) in EXIT_STATUS {
+        writeln!(out, ".TP\n{}\n{}", status, roff_text(meaning))?;
+    }
+    writeln!(out, ".SH BUILD ENVIRONMENT")?;
+    writeln!(out, "{}", roff_text("cargo native-install build exports the following variables to cargo build, for build scripts, env!, and option_env!. Variables may be added, but are only removed, or change their meaning, along with an increase of CARGO_NATIVE_INSTALL_ENV."))?;
+    for (name, description) in BUILD_ENVIRONMENT {
+        writeln!(out, ".PP\n{}\n.IP\n{}", roff(name), roff_text(description))?;
+    }
+    writeln!(out, ".SH ENVIRONMENT")?;
+    for (name, description) in ENVIRONMENT {
+        writeln!(out, ".PP\n{}\n.IP\n{}", roff(name), roff_text(description))?;
+    }
+    writeln!(out, ".SH FILES")?;
+    for (paths, description) in FILES {
+        let paths = paths
+            .split(", ")
+            .map(|path| format!("\\fI{}\\fR", roff(path)))
+            .collect::<Vec<_>>();
+        writeln!(out, ".TP\n{}", paths.join(", "))?;
+        for paragraph in description.lines() {
+            writeln!(out, "{}", roff_text(paragraph))?;
+        }
+    }
+    writeln!(out, ".SH COPYRIGHT")?;
+    writeln!(out, "Copyright \\(co 2020 Connor Horman")?;
+    writeln!(out, "This program is a free software, distributed under the terms of the GNU General Public License, at version 3.0, or (at your option) any later version")?;
+    writeln!(
+        out,
+        "This program is distributed AS\\-IS without any waranty."
+    )?;
+    Ok(())
+}
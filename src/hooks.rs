@@ -0,0 +1,71 @@
+use std::{
+    io,
+    process::{Command, Stdio},
+};
+
+use install_dirs::dirs::InstallDirs;
+
+use crate::{manifest::Target, output, Options, Verbosity};
+
+///
+/// Replaces each placeholder for an installation directory in `command`, written as `<libdir>`, `@libdir@`, or `${libdir}`, with the directory.
+/// A directory which is not UTF-8 cannot be written into the command, so it is replaced by the variable of the environment the command is run with, which holds it exactly
+pub fn expand(command: &str, dirs: &InstallDirs) -> String {
+    let mut out = command.to_owned();
+    for (name, dir) in dirs.as_env() {
+        let dir = match dir.to_str() {
+            Some(dir) => dir.to_owned(),
+            None if cfg!(windows) => format!("%{}%", name),
+            None => format!("${{{}}}", name),
+        };
+        for placeholder in [
+            format!("<{}>", name),
+            format!("@{}@", name),
+            format!("${{{}}}", name),
+        ] {
+            out = out.replace(&placeholder, &dir);
+        }
+    }
+    out
+}
+
+///
+/// The `pre-uninstall` commands of `target`, with the placeholders expanded, as they are recorded in the install receipt.
+/// They are expanded when the target is installed, so that they can be run without the manifest or the options it was installed with
+pub fn pre_uninstall(target: &Target, dirs: &InstallDirs) -> Vec<String> {
+    target
+        .pre_uninstall
+        .iter()
+        .flatten()
+        .map(|command| expand(command, dirs))
+        .collect()
+}
+
+///
+/// A command which runs `command` with the shell (`cmd` on Windows), with the installation directories in its environment, as `run` targets receive them
+pub fn shell(command: &str, dirs: &InstallDirs) -> Command {
+    let mut cmd = if cfg!(windows) {
+        let mut cmd = Command::new("cmd");
+        cmd.arg("/C");
+        cmd
+    } else {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c");
+        cmd
+    };
+    cmd.arg(command);
+    cmd.envs(dirs.as_env());
+    cmd
+}
+
+///
+/// Runs `command`, the `only-if` command of a target, with the placeholders expanded, and returns whether it succeeded, which enables the target.
+/// Its output is only shown with `-v`
+pub fn only_if(command: &str, dirs: &InstallDirs, opts: &Options) -> io::Result<bool> {
+    let mut cmd = shell(&expand(command, dirs), dirs);
+    if opts.verbosity < Verbosity::Verbose {
+        cmd.stdout(Stdio::null()).stderr(Stdio::null());
+    }
+    output::command(opts, &cmd);
+    Ok(cmd.status()?.success())
+}
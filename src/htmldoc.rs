@@ -0,0 +1,119 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use install_dirs::dirs::InstallDirs;
+
+use crate::{
+    create_alias, filesystem::Filesystem, manifest::Target, output, output::Status, sysroot,
+    tempdir::TempDir, Options,
+};
+
+///
+/// The page `index.html` refers to for the package `package`, which is where rustdoc puts the documentation of its library
+pub fn default_index(package: &str) -> PathBuf {
+    Path::new(&package.replace('-', "_")).join("index.html")
+}
+
+///
+/// Creates `index.html` in the installed directory `dest`, as a link to the `index` of `target`,
+/// if the documentation does not have its own. Returns the path of the link, if one was created
+pub fn link_index(target: &Target, dest: &Path, opts: &Options) -> io::Result<Option<PathBuf>> {
+    let (src, index) = match (&target.target_file, &target.index) {
+        (Some(src), Some(index)) => (src, index),
+        _ => return Ok(None),
+    };
+    if src.join("index.html").exists() || !src.join(index).is_file() {
+        return Ok(None);
+    }
+    let link = dest.join("index.html");
+    create_alias(index, &link, opts, false)?;
+    Ok(Some(link))
+}
+
+///
+/// The name the documentation installed to `installed` is registered under, which is the name of the directory containing it
+fn document_name(installed: &Path) -> Option<String> {
+    let name = installed
+        .parent()?
+        .file_name()?
+        .to_str()?
+        .to_ascii_lowercase();
+    // doc-base only accepts these characters in a document name
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+    {
+        return None;
+    }
+    Some(name)
+}
+
+///
+/// The doc-base control file, describing the HTML documentation installed to `installed`
+fn control_file(name: &str, installed: &Path) -> String {
+    let installed = installed.to_string_lossy();
+    format!(
+        "Document: {name}\nTitle: {name} documentation\nSection: Programming\n\nFormat: HTML\nIndex: {dir}/index.html\nFiles: {dir}/*.html\n",
+        name = name,
+        dir = installed.trim_end_matches('/')
+    )
+}
+
+///
+/// Registers the documentation installed to `installed` with doc-base, by installing its control file to `<datarootdir>/doc-base`,
+/// and running `install-docs` if it is available. Returns the path of the control file
+pub fn register_doc_base(
+    dirs: &InstallDirs,
+    installed: &Path,
+    opts: &Options,
+) -> Result<PathBuf, String> {
+    let name = document_name(installed).ok_or_else(|| {
+        format!(
+            "{} does not name a valid doc-base document",
+            installed.display()
+        )
+    })?;
+    let control = sysroot::in_sysroot(&dirs.datarootdir.join("doc-base").join(&name), opts);
+
+    let result = TempDir::new("doc-base").and_then(|tmp| {
+        let file = tmp.create_file(name.as_ref(), control_file(&name, installed).as_bytes())?;
+        if let Some(parent) = control.parent().filter(|_| !opts.no_create_dirs) {
+            opts.fs().create_dir_all(parent)?;
+        }
+        opts.fs().copy_file(&file, &control, opts)
+    });
+    result.map_err(|e| format!("Failed to write {}, {}", control.display(), e))?;
+    output::verbose(
+        opts,
+        format_args!("Registered {} with doc-base", installed.display()),
+    );
+
+    // install-docs only knows about the local system, and cannot update its databases in a container.
+    // The throwaway prefix of test-install is not registered either
+    if opts.filesystem.is_some() || opts.sysroot.is_some() || opts.container || opts.test_install {
+        return Ok(control);
+    }
+    if let Ok(prg) = which::which("install-docs") {
+        let mut cmd = Command::new(prg);
+        cmd.arg("--install").arg(&control);
+        output::command(opts, &cmd);
+        match cmd.status() {
+            Ok(status) if status.success() => {}
+            Ok(status) => output::status(
+                opts,
+                Status::Warning,
+                format_args!("Warning: install-docs exited with {}", status),
+            ),
+            Err(e) => output::status(
+                opts,
+                Status::Warning,
+                format_args!("Warning: failed to run install-docs, {}", e),
+            ),
+        }
+    }
+    Ok(control)
+}
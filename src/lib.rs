@@ -0,0 +1,4340 @@
+use std::{
+    borrow::Cow,
+    collections::{hash_map::Entry, BTreeMap, HashMap},
+    ffi::{CStr, OsStr, OsString},
+    fmt::Display,
+    fs::{self, metadata},
+    io::ErrorKind,
+    path::{Component, Path, PathBuf},
+    process::Command,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+#[cfg(unix)]
+use std::os::unix::prelude::*;
+#[cfg(unix)]
+use std::sync::atomic::{AtomicU32, Ordering};
+
+pub use buildpaths::BuildPathCheck;
+use cargo_toml::Manifest;
+pub use completions::Shell;
+pub use config::ConfigCommand;
+pub use copy::CopyMethod;
+pub use dialect::InstallFlavor;
+pub use diff::DiffReceipts;
+pub use elevate::Elevate;
+pub use emit::DirsFormat;
+pub use error::{Error, ExitCode};
+use events::{Event, EventHandler};
+use filesystem::{Filesystem, NativeFilesystem, Retrying, SshFilesystem};
+use install_dirs::dirs::InstallDirs;
+use manifest::{
+    Config, ExitMeaning, InstallMethod, NativeInstallMetadata, StripDefault, Target, TargetType,
+};
+use manpage::{ManPage, Rendered};
+use output::Status;
+pub use output::{Annotations, ColorChoice, Verbosity};
+pub use pkgdb::PackageCheck;
+use progress::{human_bytes, Progress};
+use receipt::{Receipt, TargetReceipt};
+pub use report::ReportFormat;
+pub use script::ScriptKind;
+use strip::{StripJob, StripQueue};
+use sysroot::PkgConfig;
+use template::Template;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum Timestamps {
+    /// Installed files are given the current time, as they are created
+    #[default]
+    Current,
+    /// Installed files keep the modification time of their source
+    Preserve,
+    /// Installed files are given the modification time of their source, but no later than the given time
+    Clamp(SystemTime),
+}
+
+#[derive(Default)]
+pub struct Options {
+    // Programs
+    pub install: Option<PathBuf>,
+    /// The dialect of `install`, detected when the options are parsed, unless given by `--install-flavor`
+    pub install_flavor: Option<InstallFlavor>,
+    pub strip: Option<PathBuf>,
+    /// What strip removes from targets which do not set `strip-mode`, see [`strip::args`]
+    pub strip_mode: Option<manifest::StripMode>,
+    /// Arguments passed to strip for every target, after the strip mode
+    pub strip_args: Vec<String>,
+    /// Keep the debug information of stripped binaries in `debugdir`, see [`strip::debug_file`]
+    pub separate_debug: bool,
+    pub debugdir: Option<PathBuf>,
+
+    // Paths
+    pub prefix: Option<PathBuf>,
+    pub exec_prefix: Option<PathBuf>,
+    /// The target triple whose subdirectory of libdir and libexecdir libraries and helpers are installed into, from `--multiarch`
+    pub multiarch: Option<String>,
+    pub bindir: Option<PathBuf>,
+    pub sbindir: Option<PathBuf>,
+    pub libdir: Option<PathBuf>,
+    pub libexecdir: Option<PathBuf>,
+    pub datarootdir: Option<PathBuf>,
+    pub datadir: Option<PathBuf>,
+    pub includedir: Option<PathBuf>,
+    pub sysconfdir: Option<PathBuf>,
+    pub sharedstatedir: Option<PathBuf>,
+    pub localstatedir: Option<PathBuf>,
+    pub infodir: Option<PathBuf>,
+    pub mandir: Option<PathBuf>,
+    pub localedir: Option<PathBuf>,
+    pub docdir: Option<PathBuf>,
+
+    pub user_prefix: bool,
+    pub dry_run: bool,
+    pub manifest_dir: Option<PathBuf>,
+    pub mode: Option<String>,
+    pub no_create_dirs: bool,
+    pub verbosity: Verbosity,
+    pub color: ColorChoice,
+    /// Whether warnings and errors are also written as GitHub Actions annotations, from `--annotations`
+    pub annotations: Annotations,
+    pub force: bool,
+    /// Replace existing files which the install receipt does not record, and record them
+    pub adopt: bool,
+    pub install_privileged: Option<bool>,
+    /// The locales whose targets are installed, with `--locales`. Targets without a `lang` are always installed, see [`is_skipped_locale`]
+    pub locales: Option<Vec<String>>,
+    pub install_target: Option<String>,
+    pub no_libexec: bool,
+    pub no_sbin: bool,
+    pub build: bool,
+    /// Check that the artifact of every target exists before installing any of them
+    pub require_built: bool,
+    /// Install even if the files do not fit on the filesystems they are installed to, see [`space::check`]
+    pub no_space_check: bool,
+    /// Fail if an installed binary needs a shared library which is not installed, see [`deps::audit`]
+    pub fail_on_missing_deps: bool,
+    /// The gpg key which signs each installed binary, see [`gpg::sign`]
+    pub sign_artifacts: Option<String>,
+    /// Copy the shared libraries needed by installed binaries which are not system libraries to libdir, see [`deps::bundle`]
+    pub bundle_libs: bool,
+    /// Also install the rlib and source of the library of the package, for system Rust consumers, see [`devel::add_targets`]
+    pub devel: bool,
+    /// Also install the licenses of the dependencies of the package, see [`licenses::add_target`]
+    pub third_party_licenses: bool,
+    pub no_install: bool,
+    /// The prefix of the variables the installation directories are exported to cargo as, see [`build::environment`]
+    pub env_prefix: Option<String>,
+    /// The variables which installation directories are exported to cargo as, instead of their names
+    pub env_names: Vec<(String, String)>,
+    pub shared_targets_are_libraries: Option<bool>,
+    pub out_dir: Option<PathBuf>,
+    pub debug: bool,
+    pub config: Option<PathBuf>,
+    /// The configuration file of the user, instead of [`config::user_path`], which an elevated process is given so that it reads the same files
+    pub user_config: Option<PathBuf>,
+    pub keep_going: bool,
+    pub show_diff: bool,
+    pub package_check: PackageCheck,
+    /// Report the directories of the build machine which appear in installed binaries, see [`buildpaths::audit`]
+    pub build_path_check: BuildPathCheck,
+    pub allow_outside_prefix: bool,
+    pub elevate: Elevate,
+    pub elevate_with: Option<PathBuf>,
+    /// This process was re-executed with elevated privileges by [`elevate::elevate_if_needed`], after the unprivileged steps ran in the process which did
+    pub elevated: bool,
+    /// The options from `CARGO_NATIVE_INSTALL_FLAGS` and the command line, without the program name and the `build` command, which an elevated process is given
+    pub args: Vec<OsString>,
+    pub keep_privileges: bool,
+    /// Skip the steps which fail in an unprivileged container, unless they are asked for: elevating, unless `--elevate` is given, and running install-docs.
+    /// Set by `--container`, or when a container is detected
+    pub container: bool,
+    pub umask: Option<u32>,
+    /// How long to wait for another installation into the prefix to finish, from `--lock-timeout`
+    pub lock_timeout: Option<Duration>,
+    /// How many times to retry a filesystem operation which fails with a transient error, from `--retries`, see [`filesystem::Retrying`]
+    pub retries: Option<u32>,
+    pub timestamps: Timestamps,
+    pub owner: Option<u32>,
+    pub group: Option<u32>,
+    pub log: Option<std::sync::Mutex<fs::File>>,
+    pub copy_method: CopyMethod,
+    pub copy_buffer_size: usize,
+    pub sync: bool,
+    /// Write the result of each target to this file, as JUnit XML or TAP, from `--report`
+    pub report: Option<(ReportFormat, PathBuf)>,
+    /// Receives installation events, for programs using this crate as a library
+    pub events: Option<EventHandler>,
+    /// The filesystem the internal installer writes to, instead of the native filesystem.
+    /// When set, files are always installed by the internal installer, binaries are not stripped, and no install receipt is written
+    pub filesystem: Option<Box<dyn Filesystem>>,
+    pub sysroot: Option<PathBuf>,
+    /// Install into `<stow-dir>/<package>-<version>`, and link the installed files into the prefix
+    pub stow: bool,
+    pub stow_dir: Option<PathBuf>,
+    /// Remove the links created by `stow`, instead of installing
+    pub unstow: bool,
+    /// Install into `<prefix>/opt/<package>/<version>`
+    pub slot: bool,
+    /// With `slot`, link the programs of the installed slot into `bindir` and `sbindir`
+    pub activate: bool,
+    /// Activate the slot for this version, instead of installing
+    pub switch_version: Option<String>,
+    /// Move the installed package to this prefix, instead of installing, see [`relocate::relocate`]
+    pub relocate: Option<PathBuf>,
+    /// With `relocate`, copy the installed package, rather than moving it
+    pub relocate_copy: bool,
+    /// Compare two install receipts, instead of installing
+    pub diff_receipts: Option<DiffReceipts>,
+    /// List the files installed by earlier versions of the package, which no target installs now, instead of installing
+    pub report_orphans: bool,
+    /// Validate the install targets of the package, instead of installing
+    pub check: bool,
+    /// Run the `check` targets once the package is installed, see [`run_checks`]
+    pub run_checks: bool,
+    /// Install into a temporary prefix, run the smoke tests of the installed targets, then remove it, see [`smoke::run`]
+    pub test_install: bool,
+    /// Check the signatures made by `--sign-artifacts`, instead of installing, see [`gpg::verify`]
+    pub verify: bool,
+    /// Print a JSON Schema for the install targets, instead of installing
+    pub emit_schema: bool,
+    /// Show or change the configuration of the user, instead of installing
+    pub config_command: Option<ConfigCommand>,
+    /// Print a completion script for the shell, instead of installing
+    pub completions: Option<Shell>,
+    /// Print the manual page, instead of installing
+    pub man_page: bool,
+    /// Print the usage, for the name the program was run as, instead of installing
+    pub help: Option<String>,
+    /// Print the version and license, instead of installing
+    pub version: bool,
+    /// Install, then install again whenever an artifact changes, until interrupted
+    pub watch: bool,
+    /// Link each artifact into its destination, instead of copying it
+    pub develop: bool,
+    /// The root of a `cargo install` tree. Programs installed to `<root>/bin` are recorded in its metadata
+    pub cargo_root: Option<PathBuf>,
+    /// Install into a staging directory, then pack it into this archive, instead of installing onto this machine
+    pub dist: Option<PathBuf>,
+    /// Pack the source of the package, with its dependencies vendored, into this archive, instead of installing
+    pub vendor_dist: Option<PathBuf>,
+    /// Install the archive made by `dist`, instead of the package in the manifest directory
+    pub from_archive: Option<PathBuf>,
+    /// Download and install a release archive made by `dist`, from a repository or the URL of the archive
+    pub from_release: Option<String>,
+    /// The tag of the release to install with `from_release`
+    pub tag: Option<String>,
+    pub emit_config_h: Option<PathBuf>,
+    pub emit_paths_rs: Option<PathBuf>,
+    /// Install the files written by `--emit-config-h` and `--emit-paths-rs` to `<includedir>/<package>`
+    pub install_emitted: bool,
+    pub emit_env: Option<PathBuf>,
+    pub emit_dotenv: Option<PathBuf>,
+    /// Print the installation directories, instead of installing
+    pub print_dirs: Option<DirsFormat>,
+    /// Print a script which installs the package, instead of installing
+    pub emit_script: Option<ScriptKind>,
+    /// Write a ninja file with an edge for each install step, instead of installing
+    pub emit_ninja: Option<PathBuf>,
+    /// Changes the rpath of targets which set `rpath`. If not found, binaries are edited directly, see [`rpath::apply`]
+    pub patchelf: Option<PathBuf>,
+    /// Renders Markdown manual pages, instead of `pandoc`
+    pub pandoc: Option<PathBuf>,
+    /// Renders AsciiDoc manual pages, instead of `asciidoctor`
+    pub asciidoctor: Option<PathBuf>,
+}
+
+impl Options {
+    pub(crate) fn fs(&self) -> Retrying<'_> {
+        Retrying {
+            fs: self.filesystem.as_deref().unwrap_or(&NativeFilesystem),
+            opts: self,
+        }
+    }
+}
+
+const VERSION: &str = std::env!("CARGO_PKG_VERSION");
+
+const DEFAULT_TARGET: &str = std::env!("TARGET");
+
+///
+/// Parses the command line. Parsing stops at `--help` or `--version`, and [`run`] prints their message
+pub fn parse<I: IntoIterator<Item = OsString>>(args: I) -> Result<Options, Error> {
+    #[cfg(unix)]
+    current_umask();
+    let mut opts = Options {
+        strip: which::which("strip").ok().or_else(strip::find_in_toolchain),
+        patchelf: which::which("patchelf").ok(),
+        copy_buffer_size: copy::DEFAULT_BUFFER_SIZE,
+        container: container::detect(),
+        ..Default::default()
+    };
+
+    let mut args = args.into_iter().peekable();
+    let prg_name = args.next().unwrap();
+    // cargo passes the name of the subcommand first. `build` is only the command as the first argument after it, not an option or its value
+    args.next_if_eq("native-install");
+    if args.next_if_eq("build").is_some() {
+        opts.build = true;
+        // `--install` right after `build` installs the package once it is built, rather than choosing the install program
+        opts.no_install = args.next_if_eq("--install").is_none();
+    }
+    // Flags from the environment come first, so that those given on the command line take precedence
+    let env_flags = std::env::var("CARGO_NATIVE_INSTALL_FLAGS").unwrap_or_default();
+    opts.args = env_flags
+        .split_whitespace()
+        .map(OsString::from)
+        .chain(args)
+        .collect();
+    let mut args = opts.args.clone().into_iter().peekable();
+
+    // Whether the program to strip with is set on the command line, so that the configuration does not change it
+    let mut strip_given = false;
+    // A deprecated option which was given, and the command which replaces it
+    let mut deprecated = None;
+    while let Some(arg) = args.next() {
+        if let Some((name, value)) = split_option(&arg) {
+            if set_path_option(&mut opts, name, value) {
+                continue;
+            }
+        }
+        // Only the options which take a path accept a value which is not UTF-8
+        let arg = utf8_argument(arg, "the command line")?;
+        match &*arg {
+            "--help" => {
+                opts.help = Some(prg_name.to_string_lossy().into_owned());
+                return Ok(opts);
+            }
+            "--version" => {
+                opts.version = true;
+                return Ok(opts);
+            }
+            "--dry-run" => opts.dry_run = true,
+            "--diff" => opts.show_diff = true,
+            "--user-prefix" => opts.user_prefix = true,
+            x if x.starts_with("--manifest-path=") => {
+                let path = Path::new(&x[16..]);
+                if path.file_name() != Some(OsStr::new("Cargo.toml")) {
+                    return Err(Error::InvalidArgument {
+                        option: "--manifest-path",
+                        value: x[16..].to_owned(),
+                        expected: "the path to a Cargo.toml file",
+                    });
+                }
+                opts.manifest_dir = Some(match path.parent() {
+                    Some(dir) if dir != Path::new("") => dir.to_path_buf(),
+                    _ => PathBuf::from("."),
+                });
+            }
+            "--no-create" => opts.no_create_dirs = true,
+            "--no-strip" | "--without-strip" => {
+                opts.strip = None;
+                strip_given = true;
+            }
+            x if x.starts_with("--strip=") => {
+                opts.strip = x.get(8..).and_then(|x| which::which(x).ok());
+                strip_given = true;
+            }
+            x if x.starts_with("--strip-mode=") => {
+                opts.strip_mode = match manifest::StripMode::parse(&x[13..]) {
+                    Some(mode) => Some(mode),
+                    None => {
+                        return Err(Error::InvalidArgument {
+                            option: "--strip-mode",
+                            value: x[13..].to_owned(),
+                            expected: "one of all, debug, or unneeded",
+                        })
+                    }
+                }
+            }
+            x if x.starts_with("--strip-arg=") => opts.strip_args.push(x[12..].to_owned()),
+            "--separate-debug" => opts.separate_debug = true,
+            "--install" => opts.install = which::which("install").ok(),
+            x if x.starts_with("--install=") => {
+                opts.install = x.get(10..).and_then(|x| which::which(x).ok())
+            }
+            x if x.starts_with("--install-flavor=") => {
+                opts.install_flavor = match InstallFlavor::parse(&x[17..]) {
+                    Some(flavor) => Some(flavor),
+                    None => {
+                        return Err(Error::InvalidArgument {
+                            option: "--install-flavor",
+                            value: x[17..].to_owned(),
+                            expected: "one of gnu, bsd, or busybox",
+                        })
+                    }
+                }
+            }
+            "--internal-install" => opts.install = None,
+            x if x.starts_with("--mode=") => opts.mode = x.get(7..).map(ToOwned::to_owned),
+            x if x.starts_with("--lock-timeout=") => {
+                opts.lock_timeout = match x[15..].parse() {
+                    Ok(secs) => Some(Duration::from_secs(secs)),
+                    Err(_) => {
+                        return Err(Error::InvalidArgument {
+                            option: "--lock-timeout",
+                            value: x[15..].to_owned(),
+                            expected: "a number of seconds",
+                        })
+                    }
+                }
+            }
+            x if x.starts_with("--retries=") => {
+                opts.retries = match x[10..].parse() {
+                    Ok(retries) => Some(retries),
+                    Err(_) => {
+                        return Err(Error::InvalidArgument {
+                            option: "--retries",
+                            value: x[10..].to_owned(),
+                            expected: "a number of retries",
+                        })
+                    }
+                }
+            }
+            x if x.starts_with("--umask=") => {
+                opts.umask = match u32::from_str_radix(&x[8..], 8) {
+                    Ok(mask) if mask <= 0o777 => Some(mask),
+                    _ => {
+                        return Err(Error::InvalidArgument {
+                            option: "--umask",
+                            value: x[8..].to_owned(),
+                            expected: "an octal number no greater than 777",
+                        })
+                    }
+                }
+            }
+            "--verbose" | "-v" => opts.verbosity = opts.verbosity.increase(),
+            "-vv" => opts.verbosity = Verbosity::VeryVerbose,
+            "--quiet" | "-q" => opts.verbosity = Verbosity::Quiet,
+            x if x.starts_with("--color=") => {
+                opts.color = match x.get(8..).and_then(ColorChoice::parse) {
+                    Some(color) => color,
+                    None => {
+                        return Err(Error::InvalidArgument {
+                            option: "--color",
+                            value: x[8..].to_owned(),
+                            expected: "auto, always, or never",
+                        })
+                    }
+                }
+            }
+            x if x.starts_with("--annotations=") => {
+                opts.annotations = match Annotations::parse(&x[14..]) {
+                    Some(annotations) => annotations,
+                    None => {
+                        return Err(Error::InvalidArgument {
+                            option: "--annotations",
+                            value: x[14..].to_owned(),
+                            expected: "auto, github, or never",
+                        })
+                    }
+                }
+            }
+            "--force" => opts.force = true,
+            "--adopt" => opts.adopt = true,
+            "--keep-going" | "-k" => opts.keep_going = true,
+            "--allow-outside-prefix" => opts.allow_outside_prefix = true,
+            "--elevate" => opts.elevate = Elevate::Always,
+            "--no-elevate" => opts.elevate = Elevate::Never,
+            // Not documented, as only `elevate_if_needed` passes it
+            "--elevated" => {
+                opts.elevate = Elevate::Never;
+                opts.elevated = true;
+            }
+            "--keep-privileges" => opts.keep_privileges = true,
+            "--container" => opts.container = true,
+            "--no-container" => opts.container = false,
+            x if x.starts_with("--owner=") => {
+                opts.owner = match privs::lookup_user(&x[8..]) {
+                    Some(uid) => Some(uid),
+                    None => {
+                        return Err(Error::InvalidArgument {
+                            option: "--owner",
+                            value: x[8..].to_owned(),
+                            expected: "an existing user name or uid",
+                        })
+                    }
+                }
+            }
+            x if x.starts_with("--group=") => {
+                opts.group = match privs::lookup_group(&x[8..]) {
+                    Some(gid) => Some(gid),
+                    None => {
+                        return Err(Error::InvalidArgument {
+                            option: "--group",
+                            value: x[8..].to_owned(),
+                            expected: "an existing group name or gid",
+                        })
+                    }
+                }
+            }
+            "--install-emitted" => opts.install_emitted = true,
+            x if x.starts_with("--patchelf=") => {
+                opts.patchelf = x.get(11..).and_then(|x| which::which(x).ok())
+            }
+            "--print-dirs" => opts.print_dirs = Some(DirsFormat::Human),
+            x if x.starts_with("--report=") => {
+                opts.report = match ReportFormat::parse(&x[9..]) {
+                    Some(report) => Some(report),
+                    None => {
+                        return Err(Error::InvalidArgument {
+                            option: "--report",
+                            value: x[9..].to_owned(),
+                            expected: "junit:<path> or tap:<path>",
+                        })
+                    }
+                }
+            }
+            x if x.starts_with("--print-dirs=") => {
+                opts.print_dirs = match DirsFormat::parse(&x[13..]) {
+                    Some(format) => Some(format),
+                    None => {
+                        return Err(Error::InvalidArgument {
+                            option: "--print-dirs",
+                            value: x[13..].to_owned(),
+                            expected: "human, shell, or json",
+                        })
+                    }
+                }
+            }
+            x if x.starts_with("--emit-script=") => {
+                opts.emit_script = match x.get(14..).and_then(ScriptKind::parse) {
+                    Some(kind) => Some(kind),
+                    None => {
+                        return Err(Error::InvalidArgument {
+                            option: "--emit-script",
+                            value: x[14..].to_owned(),
+                            expected: "sh or ps1",
+                        })
+                    }
+                }
+            }
+            x if x.starts_with("--from-release=") => {
+                opts.from_release = x.get(15..).map(Into::into)
+            }
+            x if x.starts_with("--tag=") => opts.tag = x.get(6..).map(Into::into),
+            "--stow" => opts.stow = true,
+            "--unstow" => opts.unstow = true,
+            "--slot" => opts.slot = true,
+            "--activate" => opts.activate = true,
+            "switch-version" => match args.next() {
+                Some(version) => {
+                    opts.switch_version = Some(utf8_argument(version, "switch-version")?)
+                }
+                None => {
+                    return Err(Error::MissingArgument {
+                        option: "switch-version",
+                        expected: "a version",
+                    })
+                }
+            },
+            "relocate" => {
+                opts.relocate_copy = args.next_if_eq("--copy").is_some();
+                match args.next() {
+                    Some(prefix) => opts.relocate = Some(prefix.into()),
+                    None => {
+                        return Err(Error::MissingArgument {
+                            option: "relocate",
+                            expected: "a prefix",
+                        })
+                    }
+                }
+            }
+            "report-orphans" => opts.report_orphans = true,
+            "check" => opts.check = true,
+            "test-install" => opts.test_install = true,
+            "--run-checks" => opts.run_checks = true,
+            "verify" => opts.verify = true,
+            "config" => {
+                let mut word = || {
+                    args.next()
+                        .map(|arg| utf8_argument(arg, "config"))
+                        .transpose()
+                };
+                opts.config_command = Some(match word()?.as_deref() {
+                    Some("set") => match (word()?, word()?) {
+                        (Some(key), Some(value)) => ConfigCommand::Set(key, value),
+                        _ => {
+                            return Err(Error::MissingArgument {
+                                option: "config set",
+                                expected: "a key and a value",
+                            })
+                        }
+                    },
+                    Some("get") => match word()? {
+                        Some(key) => ConfigCommand::Get(key),
+                        None => {
+                            return Err(Error::MissingArgument {
+                                option: "config get",
+                                expected: "a key",
+                            })
+                        }
+                    },
+                    Some("list") => ConfigCommand::List,
+                    _ => {
+                        return Err(Error::MissingArgument {
+                            option: "config",
+                            expected: "set, get, or list",
+                        })
+                    }
+                })
+            }
+            "--emit-schema" => opts.emit_schema = true,
+            "--man-page" => opts.man_page = true,
+            x if x.starts_with("--completions=") => {
+                opts.completions = match Shell::parse(&x[14..]) {
+                    Some(shell) => Some(shell),
+                    None => {
+                        return Err(Error::InvalidArgument {
+                            option: "--completions",
+                            value: x[14..].to_owned(),
+                            expected: "bash, zsh, or fish",
+                        })
+                    }
+                }
+            }
+            "--watch" => opts.watch = true,
+            "--develop" => opts.develop = true,
+            "diff-receipts" => {
+                let receipts = match args.next() {
+                    Some(x) if x == "--against-installed" => Some(DiffReceipts::Installed),
+                    Some(old) => args
+                        .next()
+                        .map(|new| DiffReceipts::Files(old.into(), new.into())),
+                    None => None,
+                };
+                if receipts.is_none() {
+                    return Err(Error::MissingArgument {
+                        option: "diff-receipts",
+                        expected: "two receipts, or --against-installed",
+                    });
+                }
+                opts.diff_receipts = receipts;
+            }
+            x if x.starts_with("--dest=") => match SshFilesystem::parse_url(&x[7..]) {
+                Some((fs, prefix)) => {
+                    if prefix.is_some() {
+                        opts.prefix = prefix;
+                    }
+                    opts.filesystem = Some(Box::new(fs));
+                }
+                None => {
+                    return Err(Error::InvalidArgument {
+                        option: "--dest",
+                        value: x[7..].to_owned(),
+                        expected: "a URL of the form ssh://[user@]host[:port][/prefix]",
+                    })
+                }
+            },
+            x if x.starts_with("--log-file=") => match output::open_log(Path::new(&x[11..])) {
+                Ok(log) => opts.log = Some(log),
+                Err(e) => return Err(Error::LogFile(x[11..].into(), e)),
+            },
+            x if x.starts_with("--copy=") => {
+                opts.copy_method = match CopyMethod::parse(&x[7..]) {
+                    Some(method) => method,
+                    None => {
+                        return Err(Error::InvalidArgument {
+                            option: "--copy",
+                            value: x[7..].to_owned(),
+                            expected: "auto, buffered, or sendfile",
+                        })
+                    }
+                }
+            }
+            x if x.starts_with("--copy-buffer-size=") => {
+                opts.copy_buffer_size = match parse_size(&x[19..]) {
+                    Some(size) if size != 0 => size,
+                    _ => {
+                        return Err(Error::InvalidArgument {
+                            option: "--copy-buffer-size",
+                            value: x[19..].to_owned(),
+                            expected: "a size in bytes, optionally followed by K or M",
+                        })
+                    }
+                }
+            }
+            "--sync" => opts.sync = true,
+            "--no-sync" => opts.sync = false,
+            "--preserve-timestamps" => opts.timestamps = Timestamps::Preserve,
+            x if x == "--clamp-timestamps" || x.starts_with("--clamp-timestamps=") => {
+                let epoch = match x.get(19..) {
+                    Some(epoch) => Some(epoch.to_owned()),
+                    None => std::env::var("SOURCE_DATE_EPOCH").ok(),
+                };
+                opts.timestamps = match epoch.as_deref().map(str::parse::<u64>) {
+                    Some(Ok(secs)) => Timestamps::Clamp(UNIX_EPOCH + Duration::from_secs(secs)),
+                    Some(Err(_)) => {
+                        return Err(Error::InvalidArgument {
+                            option: "--clamp-timestamps",
+                            value: epoch.unwrap(),
+                            expected: "seconds since the unix epoch",
+                        })
+                    }
+                    None => {
+                        return Err(Error::MissingArgument {
+                            option: "--clamp-timestamps",
+                            expected: "a timestamp, or SOURCE_DATE_EPOCH to be set",
+                        })
+                    }
+                }
+            }
+            "--check-packages" => opts.package_check = PackageCheck::Refuse,
+            x if x.starts_with("--check-packages=") => {
+                opts.package_check = match x.get(17..).and_then(PackageCheck::parse) {
+                    Some(check) => check,
+                    None => {
+                        return Err(Error::InvalidArgument {
+                            option: "--check-packages",
+                            value: x[17..].to_owned(),
+                            expected: "refuse, warn, or off",
+                        })
+                    }
+                }
+            }
+            "--check-build-paths" => opts.build_path_check = BuildPathCheck::Warn,
+            x if x.starts_with("--check-build-paths=") => {
+                opts.build_path_check = match x.get(20..).and_then(BuildPathCheck::parse) {
+                    Some(check) => check,
+                    None => {
+                        return Err(Error::InvalidArgument {
+                            option: "--check-build-paths",
+                            value: x[20..].to_owned(),
+                            expected: "deny, warn, or off",
+                        })
+                    }
+                }
+            }
+            "--no-privileged" => opts.install_privileged = Some(false),
+            "--privileged" => opts.install_privileged = Some(true),
+            x if x.starts_with("--locales=") => {
+                opts.locales = Some(
+                    x[10..]
+                        .split(',')
+                        .filter(|locale| !locale.is_empty())
+                        .map(ToOwned::to_owned)
+                        .collect(),
+                )
+            }
+            "--all-locales" => opts.locales = None,
+            x if x.starts_with("--target=") => {
+                opts.install_target = x.get(9..).map(ToOwned::to_owned)
+            }
+            "--no-libexec" => opts.no_libexec = true,
+            "--no-sbin" => opts.no_sbin = true,
+            "--arch-target" => opts.exec_prefix = Some(DEFAULT_TARGET.into()),
+            x if x.starts_with("--arch-target=") => opts.exec_prefix = x.get(14..).map(Into::into),
+            "--multiarch" => opts.multiarch = Some(DEFAULT_TARGET.into()),
+            x if x.starts_with("--multiarch=") => opts.multiarch = x.get(12..).map(Into::into),
+            "--build" => {
+                opts.build = true;
+                deprecated = Some(("--build", "build --install"));
+            }
+            "--require-built" => opts.require_built = true,
+            "--no-space-check" => opts.no_space_check = true,
+            "--fail-on-missing-deps" => opts.fail_on_missing_deps = true,
+            "--bundle-libs" => opts.bundle_libs = true,
+            "--devel" => opts.devel = true,
+            "--third-party-licenses" => opts.third_party_licenses = true,
+            x if x.starts_with("--sign-artifacts=") => {
+                opts.sign_artifacts = x.get(17..).map(Into::into)
+            }
+            "--build-only" => {
+                opts.build = true;
+                opts.no_install = true;
+                deprecated = Some(("--build-only", "build"));
+            }
+            x if x.starts_with("--env-prefix=") => opts.env_prefix = Some(x[13..].to_owned()),
+            x if x.starts_with("--env-name=") => match x[11..].split_once('=') {
+                Some((dir, var)) if build::is_exported(dir) && !var.is_empty() => {
+                    opts.env_names.push((dir.to_owned(), var.to_owned()))
+                }
+                _ => return Err(Error::InvalidArgument {
+                    option: "--env-name",
+                    value: x[11..].to_owned(),
+                    expected:
+                        "<dir>=<variable>, where <dir> is an installation directory, such as bindir",
+                }),
+            },
+            "--shared=lib" => opts.shared_targets_are_libraries = Some(true),
+            "--shared=bin" => opts.shared_targets_are_libraries = Some(false),
+            "--debug" => opts.debug = true,
+            "--release" => opts.debug = false,
+            x => return Err(Error::UnknownOption(x.to_owned())),
+        }
+    }
+
+    if opts.user_prefix && opts.prefix.is_none() {
+        opts.prefix = home::home_dir().map(|mut x| {
+            x.push(".local");
+            x
+        });
+    }
+
+    if let (Some(install), None) = (&opts.install, opts.install_flavor) {
+        opts.install_flavor = Some(InstallFlavor::detect(install));
+    }
+
+    if let Some((option, replacement)) = deprecated {
+        output::status(
+            &opts,
+            Status::Warning,
+            format_args!(
+                "Warning: {} is deprecated, use `cargo native-install {}` instead",
+                option, replacement
+            ),
+        );
+    }
+
+    // The shell leaves `~` and variables after `=` as they are. Installation directories are expanded with the configuration, by `resolve_config`
+    let home = home::home_dir();
+    let mut unset = Vec::new();
+    for (option, path) in [
+        ("--manifest-dir", &mut opts.manifest_dir),
+        ("--out-dir", &mut opts.out_dir),
+        ("--config", &mut opts.config),
+        ("--debugdir", &mut opts.debugdir),
+        ("--stow-dir", &mut opts.stow_dir),
+        ("--sysroot", &mut opts.sysroot),
+        ("--root", &mut opts.cargo_root),
+    ] {
+        if let Some(path) = path {
+            match vars::expand_shell(path, home.as_deref()) {
+                Ok(expanded) => *path = expanded,
+                Err(var) => unset.push((option, var)),
+            }
+        }
+    }
+    for (option, var) in unset {
+        output::status(
+            &opts,
+            Status::Warning,
+            format_args!(
+                "Warning: {} refers to {}, which is not set, so it is not expanded",
+                option, var
+            ),
+        );
+    }
+
+    // Relative paths are resolved against the manifest directory, as cargo does, rather than the working directory
+    if let Some(manifest_dir) = &opts.manifest_dir {
+        for path in [&mut opts.out_dir, &mut opts.config] {
+            if let Some(path) = path.as_mut().filter(|path| path.is_relative()) {
+                *path = manifest_dir.join(&*path);
+            }
+        }
+    }
+
+    // The configuration is not read for `config`, so that it can fix a configuration which cannot be read
+    if opts.config_command.is_none() {
+        let defaults = config::defaults(&opts)?;
+        match defaults.strip {
+            Some(_) if strip_given => {}
+            Some(StripDefault::Enabled(false)) => opts.strip = None,
+            Some(StripDefault::Program(prg)) => opts.strip = which::which(prg).ok(),
+            Some(StripDefault::Enabled(true)) | None => {}
+        }
+        if opts.mode.is_none() {
+            opts.mode = defaults.mode;
+        }
+    }
+
+    Ok(opts)
+}
+
+mod build;
+mod buildpaths;
+mod check;
+mod completions;
+mod config;
+mod container;
+mod copy;
+mod crates;
+mod deps;
+mod devel;
+mod dialect;
+mod diff;
+mod dist;
+mod elevate;
+mod elf;
+mod emit;
+mod error;
+pub mod events;
+pub mod filesystem;
+mod fscaps;
+mod glob;
+mod gpg;
+mod help;
+mod hooks;
+mod htmldoc;
+mod licenses;
+mod lock;
+pub mod manifest;
+mod manpage;
+mod ninja;
+mod notarize;
+mod orphans;
+mod output;
+mod pkgdb;
+mod preview;
+mod privs;
+mod progress;
+mod receipt;
+mod release;
+mod relocate;
+mod report;
+mod rpath;
+mod script;
+mod sign;
+mod slot;
+mod smoke;
+mod space;
+mod ssh;
+mod stow;
+mod strip;
+mod sysroot;
+mod tempdir;
+mod template;
+mod vars;
+mod vendor;
+mod watch;
+
+///
+/// Finds the targets of each workspace member. Member manifests are read and parsed concurrently,
+/// and the targets of each member are returned in the same order as `members`
+fn load_members(
+    members: &[String],
+    opts: &Options,
+    manifest_dir: &Path,
+) -> Vec<Result<HashMap<String, Target>, Error>> {
+    let load = |member: &String| {
+        let mut manifest = manifest_dir.to_path_buf();
+        for component in member.split(&['/', '\\'] as &[_]) {
+            manifest.push(component);
+        }
+        manifest.push("Cargo.toml");
+        let mut targets = HashMap::new();
+        if let Ok(mut manifest) =
+            Manifest::<NativeInstallMetadata>::from_path_with_metadata(manifest)
+        {
+            check_fields(&manifest)?;
+            get_package_targets(&mut manifest, &mut targets, opts, manifest_dir)?;
+            check_installed_paths(&targets)?;
+            if let Some(package) = &manifest.package {
+                separate_member_docs(&mut targets, &package.name);
+            }
+        }
+        Ok(targets)
+    };
+    let threads = std::thread::available_parallelism()
+        .map_or(1, usize::from)
+        .min(members.len());
+    if threads <= 1 {
+        return members.iter().map(load).collect();
+    }
+    let chunk_size = members.len().div_ceil(threads);
+    std::thread::scope(|scope| {
+        let load = &load;
+        let workers = members
+            .chunks(chunk_size)
+            .map(|chunk| scope.spawn(move || chunk.iter().map(load).collect::<Vec<_>>()))
+            .collect::<Vec<_>>();
+        workers
+            .into_iter()
+            .zip(members.chunks(chunk_size))
+            .flat_map(|(worker, chunk)| {
+                worker.join().unwrap_or_else(|panic| {
+                    let message = panic
+                        .downcast_ref::<&str>()
+                        .copied()
+                        .or_else(|| panic.downcast_ref::<String>().map(String::as_str))
+                        .unwrap_or("the thread panicked")
+                        .to_owned();
+                    chunk
+                        .iter()
+                        .map(|_| Err(Error::LoadMembers(message.clone())))
+                        .collect()
+                })
+            })
+            .collect()
+    })
+}
+
+///
+/// Adds the targets of the package in `manifest` to `targets`, expands the variables in their paths, then applies `versioned`
+fn get_package_targets(
+    manifest: &mut Manifest<NativeInstallMetadata>,
+    targets: &mut HashMap<String, Target>,
+    opts: &Options,
+    manifest_dir: &Path,
+) -> Result<(), Error> {
+    get_auto_targets(manifest, targets, opts, manifest_dir)?;
+    devel::add_targets(manifest, targets, opts, manifest_dir);
+    licenses::add_target(manifest, targets, opts, manifest_dir);
+    if let Some(package) = &manifest.package {
+        for (name, target) in targets.iter_mut() {
+            expand_target_paths(name, target, &package.name, &package.version, opts);
+        }
+        for target in targets.values_mut().filter(|target| target.versioned) {
+            version_target(target, &package.version);
+        }
+        let info = template::package(package, &manifest.features);
+        for target in targets
+            .values_mut()
+            .filter(|target| target.template_engine.is_some())
+        {
+            target.package = Some(info.clone());
+        }
+    }
+    Ok(())
+}
+
+///
+/// When the members of a workspace are installed together, installs the documentation of the member `package` to `<docdir>/<package>`,
+/// so that members do not overwrite each other's files. `doc` and `html-doc` targets whose `installed-path` already starts with the name of the package,
+/// is absolute or starts with a directory placeholder, or which have an `install-dir`, are left where they are.
+/// `data` targets are only moved if their `installed-path` is the default, the name of their file
+fn separate_member_docs(targets: &mut HashMap<String, Target>, package: &str) {
+    for target in targets
+        .values_mut()
+        .filter(|target| target.install_dir.is_none())
+    {
+        let default = target.target_file.as_deref().and_then(Path::file_name);
+        let separate = match target.type_ {
+            Some(TargetType::Doc | TargetType::HtmlDoc) => true,
+            Some(TargetType::Data) => {
+                target.installed_path.as_deref().map(Path::as_os_str) == default
+            }
+            _ => false,
+        };
+        if !separate {
+            continue;
+        }
+        if let Some(path) = &mut target.installed_path {
+            if !path.has_root() && !path.starts_with(package) && !vars::has_placeholder(path) {
+                *path = Path::new(package).join(&*path);
+            }
+        }
+    }
+}
+
+/// Expands `${CARGO_PKG_NAME}`, `${CARGO_PKG_VERSION}`, and environment variables in the `installed-path` and `install-dir` of `target`.
+/// A path which refers to a variable that is not set is left as it is, with a warning
+fn expand_target_paths(
+    name: &str,
+    target: &mut Target,
+    package: &str,
+    version: &str,
+    opts: &Options,
+) {
+    for (field, path) in [
+        ("installed-path", &mut target.installed_path),
+        ("install-dir", &mut target.install_dir),
+    ] {
+        if let Some(path) = path {
+            match vars::expand(path, Some((package, version))) {
+                Ok(expanded) => *path = expanded,
+                Err(var) => output::status(
+                    opts,
+                    Status::Warning,
+                    format_args!(
+                        "Warning: the `{}` of target {} refers to {}, which is not set, so it is not expanded",
+                        field, name, var
+                    ),
+                ),
+            }
+        }
+    }
+}
+
+///
+/// Installs `target` as `<name>-<version>`, with an alias of its unversioned name
+fn version_target(target: &mut Target, version: &str) {
+    let path = match &mut target.installed_path {
+        Some(path) => path,
+        None => return,
+    };
+    let name = match path.file_name() {
+        Some(name) => name.to_owned(),
+        None => return,
+    };
+    // The version goes before the executable extension, so `foo.exe` is installed as `foo-1.0.exe`
+    let ext = std::env::consts::EXE_EXTENSION;
+    let has_ext = !ext.is_empty() && path.extension() == Some(OsStr::new(ext));
+    let mut versioned = if has_ext {
+        path.file_stem().unwrap_or(&name).to_owned()
+    } else {
+        name.clone()
+    };
+    versioned.push("-");
+    versioned.push(version);
+    if has_ext {
+        versioned.push(".");
+        versioned.push(ext);
+    }
+    path.set_file_name(versioned);
+    target
+        .installed_aliases
+        .get_or_insert_with(Vec::new)
+        .push(PathBuf::from(name));
+}
+
+fn get_auto_targets(
+    manifest: &mut Manifest<NativeInstallMetadata>,
+    targets: &mut HashMap<String, Target>,
+    opts: &Options,
+    manifest_dir: &Path,
+) -> Result<(), Error> {
+    let project_name;
+    if let Some(package) = &mut manifest.package {
+        project_name = package.name.clone();
+
+        if let Some(metadata) = &mut package.metadata {
+            for (k, mut v) in metadata.install_targets.drain() {
+                if v.type_ == Some(TargetType::Man) {
+                    if v.installed_path.is_none() {
+                        v.installed_path =
+                            v.target_file.as_deref().and_then(manpage::installed_path);
+                    }
+                    if v.lang.is_none() {
+                        v.lang = v
+                            .target_file
+                            .as_deref()
+                            .and_then(manpage::detect_lang)
+                            .map(ToOwned::to_owned);
+                    }
+                    if let (Some(lang), Some(path)) = (&v.lang, &v.installed_path) {
+                        v.installed_path = Some(manpage::localize(path, lang));
+                    }
+                }
+                if matches!(v.type_, Some(TargetType::Doc) | Some(TargetType::Data))
+                    && v.installed_path.is_none()
+                {
+                    v.installed_path = v
+                        .target_file
+                        .as_deref()
+                        .and_then(Path::file_name)
+                        .map(PathBuf::from);
+                }
+                if v.type_ == Some(TargetType::HtmlDoc) {
+                    v.directory = true;
+                    if v.installed_path.is_none() {
+                        v.installed_path = Some(Path::new(&project_name).join("html"));
+                    }
+                    if v.index.is_none() {
+                        v.index = Some(htmldoc::default_index(&project_name));
+                    }
+                }
+                targets.insert(k, v);
+            }
+        }
+    } else {
+        return Ok(());
+    }
+    for product in &manifest.bin {
+        let name = if let Some(name) = &product.name {
+            name.clone()
+        } else {
+            project_name.replace("-", "_")
+        };
+
+        let target = match targets.get_mut(&name) {
+            Some(target) => target,
+            None => {
+                targets.insert(
+                    name.clone(),
+                    Target {
+                        type_: Some(TargetType::Bin),
+                        ..Default::default()
+                    },
+                );
+                targets.get_mut(&name).unwrap()
+            }
+        };
+
+        if target.exclude {
+            continue;
+        }
+
+        if target.mode.is_none() {
+            target.mode = Some("u=rwx,g=rx,o=rx".to_string());
+        }
+
+        if target.type_.is_none() {
+            target.type_ = Some(if target.privileged {
+                TargetType::SBin
+            } else {
+                TargetType::Bin
+            })
+        }
+
+        if target.installed_path.is_none() {
+            target.installed_path = Some((&*name).into());
+        }
+
+        if target.strip.is_none() {
+            target.strip = Some(true)
+        }
+
+        if let Some(buf) = &mut target.installed_path {
+            match std::env::consts::EXE_EXTENSION {
+                "" => (),
+                x => {
+                    buf.set_extension(x);
+                }
+            }
+        }
+
+        // Like the artifacts of libraries, relative to the manifest rather than the working directory
+        let mut target_path = PathBuf::new();
+        if let Some(dir) = &opts.out_dir {
+            target_path.push(dir);
+        } else {
+            target_path.push(manifest_dir);
+            target_path.push("target");
+        }
+
+        if opts.debug {
+            target_path.push("debug");
+        } else {
+            target_path.push("release");
+        }
+
+        target_path.push(&name);
+
+        match std::env::consts::EXE_EXTENSION {
+            "" => (),
+            x => {
+                target_path.set_extension(x);
+            }
+        }
+
+        target.target_file = Some(target_path)
+    }
+
+    if let Some(product) = &manifest.lib {
+        if product.crate_type.len() != 1 {
+            for crate_type in &product.crate_type {
+                let name = product
+                    .name
+                    .as_ref()
+                    .cloned()
+                    .unwrap_or(project_name.replace("-", "_"))
+                    + "-"
+                    + crate_type;
+                let target = match targets.get_mut(&name) {
+                    Some(target) => target,
+                    None => {
+                        targets.insert(
+                            name.clone(),
+                            Target {
+                                type_: Some(if crate_type == "staticlib" {
+                                    TargetType::Library
+                                } else if crate_type == "cdylib" {
+                                    TargetType::Shared
+                                } else {
+                                    continue;
+                                }),
+                                ..Default::default()
+                            },
+                        );
+                        targets.get_mut(&name).unwrap()
+                    }
+                };
+                if target.mode.is_none() {
+                    target.mode = Some("u=rw,g=r,o=r".to_string());
+                }
+
+                if target.strip.is_none() {
+                    target.strip = Some(crate_type == "cdylib");
+                }
+
+                if target.prefix.is_none() {
+                    target.prefix = Some(std::env::consts::DLL_PREFIX.to_string())
+                }
+
+                if target.installed_path.is_none() {
+                    let mut path = PathBuf::new();
+                    let fname = target.prefix.as_ref().cloned().unwrap() + &*name;
+                    path.push(&fname);
+                    path.set_extension(library_extension(&name, crate_type)?);
+                    target.installed_path = Some(path);
+                }
+
+                if target.target_file.is_none() {
+                    let mut path = PathBuf::new();
+                    let fname = "lib".to_string() + &*name;
+                    if let Some(dir) = &opts.out_dir {
+                        path.push(dir);
+                    } else {
+                        path.push(manifest_dir);
+                        path.push("target");
+                        path.push(if opts.debug { "debug" } else { "release" });
+                    }
+                    path.push(&fname);
+                    path.set_extension(library_extension(&name, crate_type)?);
+                    target.target_file = Some(path);
+                }
+            }
+        } else {
+            let crate_type = product.crate_type[0].clone();
+            let name = product
+                .name
+                .as_ref()
+                .cloned()
+                .unwrap_or(project_name.replace("-", "_"));
+            let target = match targets.get_mut(&name) {
+                Some(target) => target,
+                None => {
+                    targets.insert(
+                        name.clone(),
+                        Target {
+                            type_: Some(if crate_type == "staticlib" {
+                                TargetType::Library
+                            } else if crate_type == "cdylib" {
+                                TargetType::Shared
+                            } else {
+                                return Ok(());
+                            }),
+                            ..Default::default()
+                        },
+                    );
+                    targets.get_mut(&name).unwrap()
+                }
+            };
+            if target.mode.is_none() {
+                target.mode = Some("u=rw,g=r,o=r".to_string());
+            }
+
+            if target.prefix.is_none() {
+                target.prefix = Some(std::env::consts::DLL_PREFIX.to_string())
+            }
+
+            if target.installed_path.is_none() {
+                let mut path = PathBuf::new();
+                let fname = target.prefix.as_ref().cloned().unwrap() + &*name;
+                path.push(&fname);
+                path.set_extension(library_extension(&name, &crate_type)?);
+                target.installed_path = Some(path);
+            }
+
+            if target.target_file.is_none() {
+                let mut path = PathBuf::new();
+                let fname = "lib".to_string() + &*name;
+                if let Some(dir) = &opts.out_dir {
+                    path.push(dir);
+                } else {
+                    path.push(manifest_dir);
+                    path.push("target");
+                    path.push(if opts.debug { "debug" } else { "release" });
+                }
+                path.push(&fname);
+                path.set_extension(library_extension(&name, &crate_type)?);
+                target.target_file = Some(path);
+            }
+        }
+    }
+    Ok(())
+}
+
+///
+/// The extension of the library cargo builds for the `crate-type` `crate_type` of the library `name`
+fn library_extension(name: &str, crate_type: &str) -> Result<&'static str, Error> {
+    match crate_type {
+        "dylib" | "cdylib" => Ok(std::env::consts::DLL_EXTENSION),
+        "staticlib" if cfg!(windows) => Ok("lib"),
+        "staticlib" => Ok("a"),
+        "rlib" => Ok("rlib"),
+        _ => Err(Error::UnsupportedCrateType {
+            target: name.to_owned(),
+            crate_type: crate_type.to_owned(),
+        }),
+    }
+}
+
+///
+/// Fails if a target in the metadata of `manifest` has a key which is not one of its fields, rather than ignoring it
+fn check_fields(manifest: &Manifest<NativeInstallMetadata>) -> Result<(), Error> {
+    let metadata = match manifest.package.as_ref().and_then(|p| p.metadata.as_ref()) {
+        Some(metadata) => metadata,
+        None => return Ok(()),
+    };
+    let mut names = metadata.install_targets.keys().collect::<Vec<_>>();
+    names.sort();
+    for name in names {
+        if let Some((field, suggestion)) = metadata.install_targets[name].unknown_fields().next() {
+            return Err(Error::UnknownField {
+                target: name.clone(),
+                field: field.to_owned(),
+                suggestion,
+            });
+        }
+    }
+    Ok(())
+}
+
+///
+/// Fails if a target which is installed to a path has no `installed-path` once the defaults of its type are applied
+fn check_installed_paths(targets: &HashMap<String, Target>) -> Result<(), Error> {
+    let mut names = targets
+        .iter()
+        .filter(|(_, target)| !target.exclude && target.installed_path.is_none())
+        .filter(|(_, target)| {
+            !matches!(
+                target.type_,
+                Some(TargetType::Run | TargetType::Check) | None
+            )
+        })
+        .map(|(name, _)| name)
+        .collect::<Vec<_>>();
+    names.sort();
+    match names.first() {
+        Some(name) => Err(Error::MissingInstalledPath((*name).clone())),
+        None => Ok(()),
+    }
+}
+
+///
+/// Reads the manifest in `manifest_dir`, and finds the targets of its package and of every workspace member
+pub fn load_targets(
+    manifest_dir: &Path,
+    opts: &Options,
+) -> Result<(Manifest<NativeInstallMetadata>, HashMap<String, Target>), Error> {
+    let mut manifest = Manifest::from_path_with_metadata(manifest_dir.join("Cargo.toml"))?;
+    check_fields(&manifest)?;
+    let mut targets = HashMap::new();
+    get_package_targets(&mut manifest, &mut targets, opts, manifest_dir)?;
+    check_installed_paths(&targets)?;
+    emit::add_targets(&manifest, &mut targets, opts);
+
+    if let Some(workspace) = manifest
+        .workspace
+        .as_ref()
+        .filter(|ws| !ws.members.is_empty())
+    {
+        if let Some(package) = &manifest.package {
+            separate_member_docs(&mut targets, &package.name);
+        }
+        // Targets are recorded in the receipt by name, so a name cannot be shared by two packages
+        for (member, member_targets) in
+            workspace
+                .members
+                .iter()
+                .zip(load_members(&workspace.members, opts, manifest_dir))
+        {
+            for (name, target) in member_targets? {
+                match targets.entry(name) {
+                    Entry::Occupied(entry) => {
+                        return Err(Error::DuplicateTarget {
+                            target: entry.key().clone(),
+                            member: member.clone(),
+                        })
+                    }
+                    Entry::Vacant(entry) => {
+                        entry.insert(target);
+                    }
+                }
+            }
+        }
+    }
+    Ok((manifest, targets))
+}
+
+///
+/// Determines the installation directories. Directories given on the command line take precedence over the environment,
+/// which takes precedence over the config files
+pub fn resolve_dirs(opts: &Options) -> Result<InstallDirs, Error> {
+    resolve_config(opts).map(|config| config.dirs)
+}
+
+///
+/// Expands the variables in each installation directory, as [`expand_target_paths`] does for targets.
+/// `CARGO_PKG_NAME` and `CARGO_PKG_VERSION` are those of the package in the manifest directory, if it can be read
+fn expand_dirs(dirs: &mut InstallDirs, opts: &Options) {
+    let warn = |name: &str, var: &str| {
+        output::status(
+            opts,
+            Status::Warning,
+            format_args!(
+                "Warning: {} refers to {}, which is not set, so it is not expanded",
+                name, var
+            ),
+        )
+    };
+    // With `--dest`, `~` is the home directory on the remote machine, which is not known
+    let home = home::home_dir().filter(|_| opts.filesystem.is_none());
+    let mut package = None;
+    for name in emit::DIRS {
+        let dir = vars::dir_mut(dirs, name);
+        match vars::expand_shell(dir, home.as_deref()) {
+            Ok(expanded) => *dir = expanded,
+            Err(var) => warn(name, &var),
+        }
+        if !dir.to_str().is_some_and(|dir| dir.contains("${")) {
+            continue;
+        }
+        let package = package.get_or_insert_with(|| {
+            let manifest_dir = opts.manifest_dir.as_deref().unwrap_or(Path::new("."));
+            Manifest::<NativeInstallMetadata>::from_path_with_metadata(
+                manifest_dir.join("Cargo.toml"),
+            )
+            .ok()
+            .and_then(|manifest| manifest.package)
+            .map(|package| (package.name, package.version))
+        });
+        match vars::expand(
+            dir,
+            package
+                .as_ref()
+                .map(|(name, version)| (name.as_str(), version.as_str())),
+        ) {
+            Ok(expanded) => *dir = expanded,
+            Err(var) => warn(name, &var),
+        }
+    }
+}
+
+///
+/// Reads the config files, with the installation directories determined as by [`resolve_dirs`]
+fn resolve_config(opts: &Options) -> Result<Config, Error> {
+    let config = config::load(opts)?;
+    let mut dirs = config.dirs;
+
+    dirs.read_env();
+
+    if let Some(dir) = &opts.prefix {
+        dirs.prefix = dir.clone()
+    }
+
+    if let Some(dir) = &opts.exec_prefix {
+        dirs.exec_prefix = dir.clone()
+    }
+
+    if let Some(dir) = &opts.bindir {
+        dirs.bindir = dir.clone()
+    }
+
+    if let Some(dir) = &opts.libdir {
+        dirs.libdir = dir.clone()
+    }
+    if let Some(dir) = &opts.sbindir {
+        dirs.sbindir = dir.clone()
+    }
+    if let Some(dir) = &opts.libexecdir {
+        dirs.libexecdir = dir.clone()
+    }
+    if let Some(dir) = &opts.includedir {
+        dirs.includedir = dir.clone()
+    }
+
+    if let Some(dir) = &opts.datarootdir {
+        dirs.datarootdir = dir.clone()
+    }
+    if let Some(dir) = &opts.datadir {
+        dirs.datadir = dir.clone()
+    }
+    if let Some(dir) = &opts.mandir {
+        dirs.mandir = dir.clone()
+    }
+    if let Some(dir) = &opts.docdir {
+        dirs.docdir = dir.clone()
+    }
+    if let Some(dir) = &opts.infodir {
+        dirs.infodir = dir.clone()
+    }
+    if let Some(dir) = &opts.localedir {
+        dirs.localedir = dir.clone()
+    }
+
+    if let Some(dir) = &opts.sharedstatedir {
+        dirs.sharedstatedir = dir.clone()
+    }
+    if let Some(dir) = &opts.localstatedir {
+        dirs.localstatedir = dir.clone()
+    }
+    if let Some(dir) = &opts.sysconfdir {
+        dirs.sysconfdir = dir.clone()
+    }
+
+    expand_dirs(&mut dirs, opts);
+    let mut dirs = vars::resolve_placeholders(dirs, |mut dirs| {
+        // Headers are architecture-independent, so unlike the other directories resolved by `canonicalize`, includedir stays under the prefix
+        if dirs.prefix.has_root() && !dirs.includedir.has_root() {
+            let prefix = if dirs.prefix == Path::new("/") {
+                Path::new("/usr")
+            } else {
+                &dirs.prefix
+            };
+            dirs.includedir = prefix.join(&dirs.includedir);
+        }
+        dirs.canonicalize().map_err(Error::Prefix)
+    })?;
+    if let Some(triple) = &opts.multiarch {
+        dirs.libdir.push(triple);
+        dirs.libexecdir.push(triple);
+    }
+    Ok(Config { dirs, ..config })
+}
+
+///
+/// Resolves the path each target of the manifest in `opts.manifest_dir` (or the current directory) would be installed to,
+/// without installing or building anything. The project config file and `${CARGO_PKG_NAME}` in the directories are read from the same manifest directory.
+/// Targets which are not installed to a path (such as `run` targets), and targets excluded by `opts`, are omitted
+pub fn resolve_destinations(opts: &Options) -> Result<BTreeMap<String, PathBuf>, Error> {
+    let manifest_dir = opts.manifest_dir.as_deref().unwrap_or(Path::new("."));
+    let (manifest, targets) = load_targets(manifest_dir, opts)?;
+    let dirs = resolve_dirs(opts)?;
+    let dirs = match package_root(&manifest, manifest_dir, &dirs, opts) {
+        Some(root) => stow::rebase(&dirs, &root),
+        None => dirs,
+    };
+    Ok(targets
+        .iter()
+        .filter(|(name, _)| {
+            opts.install_target
+                .as_ref()
+                .is_none_or(|target| target == *name)
+        })
+        .filter(|(_, target)| target.is_selected(opts))
+        .filter_map(|(name, target)| {
+            target_destination(&dirs, target, opts)
+                .map(|dest| (name.clone(), sysroot::in_sysroot(&dest, opts)))
+        })
+        .collect())
+}
+
+///
+/// The name of the package in `manifest`, or the name of the manifest directory if it has no package
+fn package_name(manifest: &Manifest<NativeInstallMetadata>, manifest_dir: &Path) -> String {
+    manifest
+        .package
+        .as_ref()
+        .map(|package| package.name.clone())
+        .or_else(|| {
+            manifest_dir
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+        })
+        .unwrap_or_default()
+}
+
+///
+/// The version of the package in `manifest`, if it has a package
+fn package_version(manifest: &Manifest<NativeInstallMetadata>) -> Option<&str> {
+    manifest
+        .package
+        .as_ref()
+        .map(|package| package.version.as_str())
+}
+
+///
+/// The directory the package is installed to with `--stow` or `--slot`
+fn package_root(
+    manifest: &Manifest<NativeInstallMetadata>,
+    manifest_dir: &Path,
+    dirs: &InstallDirs,
+    opts: &Options,
+) -> Option<PathBuf> {
+    let package = package_name(manifest, manifest_dir);
+    if opts.stow {
+        Some(stow::package_dir(
+            dirs,
+            opts,
+            &package,
+            package_version(manifest),
+        ))
+    } else if opts.slot {
+        // Cargo's default version, for a manifest without one
+        let version = package_version(manifest).unwrap_or("0.0.0");
+        Some(slot::slot_dir(dirs, &package, version))
+    } else {
+        None
+    }
+}
+
+///
+/// Builds (if requested) and installs the package in the manifest directory given by `opts`
+pub fn run(opts: &Options) -> Result<(), Error> {
+    if let Some(prg_name) = &opts.help {
+        let _ = help::write(&mut std::io::stdout().lock(), prg_name);
+        return Ok(());
+    }
+    if opts.version {
+        println!("cargo-native-install v{}", VERSION);
+        println!("Copyright (C) 2020 Connor Horman");
+        println!("This program is a free software, distributed under the terms of the GNU General Public License, at version 3.0, or (at your option) any later version");
+        println!("This program is distributed AS-IS without any waranty.");
+        return Ok(());
+    }
+    #[cfg(unix)]
+    current_umask();
+    // The options in effect, including those of CARGO_NATIVE_INSTALL_FLAGS, rather than the command line alone
+    output::log(opts, "start", format_args!("{:?}", opts.args));
+
+    let manifest_dir = if let Some(dir) = &opts.manifest_dir {
+        dir.clone()
+    } else {
+        std::env::current_dir()?
+    };
+    if opts.emit_schema {
+        println!("{:#}", check::schema());
+        return Ok(());
+    }
+    if let Some(cmd) = &opts.config_command {
+        return config::command(cmd, opts);
+    }
+    if let Some(shell) = opts.completions {
+        print!("{}", completions::script(shell));
+        return Ok(());
+    }
+    if opts.man_page {
+        help::write_man(&mut std::io::stdout().lock())?;
+        return Ok(());
+    }
+    if opts.watch {
+        return watch::watch(opts, &manifest_dir);
+    }
+    let result = install_package(opts, &manifest_dir);
+    // The elevated process writes the report itself
+    if let Err(Error::Elevated(_)) = result {
+        return result;
+    }
+    let written = report::write(opts, result.as_ref().err());
+    result.and(written)
+}
+
+///
+/// Does the work of [`run`] once
+fn install_package(opts: &Options, manifest_dir: &Path) -> Result<(), Error> {
+    let manifest_dir = manifest_dir.to_path_buf();
+    if opts.check {
+        return check::check(&manifest_dir, opts);
+    }
+    if let Some(DiffReceipts::Files(old, new)) = &opts.diff_receipts {
+        return diff::files_diff(old, new, opts);
+    }
+    if let Some(archive) = &opts.from_archive {
+        return dist::install(archive, opts);
+    }
+    if let Some(archive) = &opts.vendor_dist {
+        return vendor::dist(&manifest_dir, archive, opts);
+    }
+    if let Some(source) = &opts.from_release {
+        let dir = tempdir::TempDir::new("release")?;
+        return release::fetch(source, opts.tag.as_deref(), dir.path(), opts)
+            .and_then(|archive| dist::install(&archive, opts));
+    }
+
+    let (manifest, targets) = load_targets(&manifest_dir, opts)?;
+    let Config {
+        dirs,
+        signtool,
+        notarize,
+        ..
+    } = resolve_config(opts)?;
+
+    if opts.dist.is_some() {
+        if opts.sysroot.is_some() {
+            return Err(Error::Conflict("--dist", "--sysroot"));
+        }
+        if opts.filesystem.is_some() {
+            return Err(Error::Conflict("--dist", "--dest"));
+        }
+    }
+    if opts.filesystem.is_some() {
+        let local_only = [
+            ("--stow", opts.stow),
+            ("--unstow", opts.unstow),
+            ("--activate", opts.activate),
+            ("switch-version", opts.switch_version.is_some()),
+            ("relocate", opts.relocate.is_some()),
+            ("verify", opts.verify),
+        ];
+        if let Some((option, _)) = local_only.iter().find(|(_, set)| *set) {
+            return Err(Error::Conflict(option, "--dest"));
+        }
+    }
+    if opts.stow && opts.slot {
+        return Err(Error::Conflict("--stow", "--slot"));
+    }
+    if opts.bundle_libs {
+        // The rpath of a linked artifact cannot be changed, and remote binaries cannot be examined
+        let conflicts = [
+            ("--develop", opts.develop),
+            ("--dest", opts.filesystem.is_some()),
+        ];
+        if let Some((option, _)) = conflicts.iter().find(|(_, set)| *set) {
+            return Err(Error::Conflict("--bundle-libs", option));
+        }
+    }
+    if opts.sign_artifacts.is_some() {
+        // A signature of a linked artifact would not match once it is rebuilt, and gpg cannot read remote files
+        let conflicts = [
+            ("--develop", opts.develop),
+            ("--dest", opts.filesystem.is_some()),
+        ];
+        if let Some((option, _)) = conflicts.iter().find(|(_, set)| *set) {
+            return Err(Error::Conflict("--sign-artifacts", option));
+        }
+    }
+    if opts.develop {
+        if opts.filesystem.is_some() {
+            return Err(Error::Conflict("--develop", "--dest"));
+        }
+        if opts.dist.is_some() {
+            return Err(Error::Conflict("--develop", "--dist"));
+        }
+    }
+    let emit_plan = match (&opts.emit_script, &opts.emit_ninja) {
+        (Some(_), Some(_)) => return Err(Error::Conflict("--emit-script", "--emit-ninja")),
+        (Some(_), None) => Some("--emit-script"),
+        (None, Some(_)) => Some("--emit-ninja"),
+        (None, None) => None,
+    };
+    if let Some(emit) = emit_plan {
+        // The script or ninja file can only reproduce a plain installation
+        let conflicts = [
+            ("--dest", opts.filesystem.is_some()),
+            ("--sysroot", opts.sysroot.is_some()),
+            ("--stow", opts.stow),
+            ("--activate", opts.activate),
+            ("--dist", opts.dist.is_some()),
+            ("--root", opts.cargo_root.is_some()),
+        ];
+        if let Some((option, _)) = conflicts.iter().find(|(_, set)| *set) {
+            return Err(Error::Conflict(emit, option));
+        }
+    }
+    if opts.cargo_root.is_some() {
+        let conflicts = [
+            ("--dest", opts.filesystem.is_some()),
+            ("--stow", opts.stow),
+            ("--slot", opts.slot),
+        ];
+        if let Some((option, _)) = conflicts.iter().find(|(_, set)| *set) {
+            return Err(Error::Conflict("--root", option));
+        }
+    }
+    if opts.run_checks {
+        // The checks validate the installation in place, on this machine
+        let conflicts = [
+            ("--dest", opts.filesystem.is_some()),
+            ("--dist", opts.dist.is_some()),
+            ("--emit-script", opts.emit_script.is_some()),
+            ("--emit-ninja", opts.emit_ninja.is_some()),
+        ];
+        if let Some((option, _)) = conflicts.iter().find(|(_, set)| *set) {
+            return Err(Error::Conflict("--run-checks", option));
+        }
+    }
+    if opts.test_install {
+        // The temporary prefix is on this machine, and is only installed into to be tested
+        let conflicts = [
+            ("--dry-run", opts.dry_run),
+            ("--dest", opts.filesystem.is_some()),
+            ("--sysroot", opts.sysroot.is_some()),
+            ("--dist", opts.dist.is_some()),
+            ("--stow", opts.stow),
+            ("--slot", opts.slot),
+            ("--root", opts.cargo_root.is_some()),
+            ("--no-install", opts.no_install),
+            ("--emit-script", opts.emit_script.is_some()),
+            ("--emit-ninja", opts.emit_ninja.is_some()),
+        ];
+        if let Some((option, _)) = conflicts.iter().find(|(_, set)| *set) {
+            return Err(Error::Conflict("test-install", option));
+        }
+    }
+    // A private directory, which is removed, with everything installed into it, once the package is tested
+    let test_prefix = match opts.test_install {
+        true => Some(tempdir::TempDir::new("test-install")?),
+        false => None,
+    };
+    let dirs = match &test_prefix {
+        Some(prefix) => {
+            output::status(
+                opts,
+                Status::Installed,
+                format_args!("Installing into {} to test", prefix.path().display()),
+            );
+            smoke::rebase(&dirs, prefix.path())
+        }
+        None => dirs,
+    };
+
+    let package = package_name(&manifest, &manifest_dir);
+    if opts.unstow {
+        return stow::unlink(&dirs, opts, &package);
+    }
+    if let Some(version) = &opts.switch_version {
+        return slot::activate(&dirs, opts, &package, version);
+    }
+    if let Some(prefix) = &opts.relocate {
+        return relocate::relocate(&dirs, opts, &package, prefix);
+    }
+
+    let live_dirs = dirs.clone();
+    let root = package_root(&manifest, &manifest_dir, &dirs, opts);
+    // A slot is self-contained, so the package is built for the slot. A stowed package is built for the prefix,
+    // which it is linked into, but installed into the stow directory
+    let dirs = match &root {
+        Some(root) if opts.slot => stow::rebase(&dirs, root),
+        _ => dirs,
+    };
+
+    if let Some(format) = opts.print_dirs {
+        print!("{}", emit::print_dirs(&dirs, format));
+        return Ok(());
+    }
+
+    if opts.diff_receipts == Some(DiffReceipts::Installed) || opts.report_orphans || opts.verify {
+        let dirs = match &root {
+            Some(root) if opts.stow => stow::rebase(&dirs, root),
+            _ => dirs,
+        };
+        if opts.report_orphans {
+            return orphans::report(&dirs, &package, &targets, opts);
+        }
+        if opts.verify {
+            return gpg::verify(&dirs, &package, opts);
+        }
+        let version = package_version(&manifest).map(ToOwned::to_owned);
+        return diff::installed_diff(&dirs, &package, version, &targets, opts);
+    }
+
+    emit::emit(&dirs, opts)?;
+
+    if opts.build {
+        build::build(&manifest_dir, &dirs, opts)?;
+    }
+
+    // The process which elevated has already packaged the source, as the user who owns the build directory
+    if opts.devel && !opts.dry_run && !opts.elevated {
+        devel::prepare(&targets, &manifest_dir, opts)?;
+    }
+    if opts.third_party_licenses && !opts.dry_run {
+        licenses::generate(&targets, &manifest_dir, opts)?;
+    }
+
+    if let Some(kind) = opts.emit_script {
+        print!("{}", script::script(kind, &dirs, &package, &targets, opts)?);
+        return Ok(());
+    }
+    if let Some(path) = &opts.emit_ninja {
+        return emit::write(
+            opts,
+            path,
+            ninja::fragment(&dirs, &package, &targets, opts)?,
+        );
+    }
+
+    if opts.require_built && !opts.no_install {
+        require_built(&dirs, &targets, opts)?;
+    }
+
+    let dirs = match &root {
+        Some(root) if opts.stow => stow::rebase(&dirs, root),
+        _ => dirs,
+    };
+
+    if !opts.no_install {
+        container::warn(&targets, opts);
+    }
+
+    // Links made by `--develop` take no space
+    if !opts.no_install && !opts.no_space_check && !opts.develop && opts.filesystem.is_none() {
+        space::check(&dirs, &targets, opts)?;
+    }
+
+    if !opts.no_install && !opts.dry_run && opts.filesystem.is_none() {
+        let dests = targets
+            .iter()
+            .filter(|(name, _)| {
+                opts.install_target
+                    .as_ref()
+                    .is_none_or(|target| target == *name)
+            })
+            .filter(|(_, target)| target.is_selected(opts))
+            .filter_map(|(_, target)| target_destination(&dirs, target, opts))
+            .chain(root.as_ref().map(|_| live_dirs.prefix.clone()))
+            .map(|dest| sysroot::in_sysroot(&dest, opts))
+            .collect::<Vec<_>>();
+        // The elevated process moves the directories into the stow directory or slot itself
+        elevate::elevate_if_needed(
+            opts,
+            &live_dirs,
+            &manifest_dir,
+            elevate::find_unwritable(dests.iter().map(PathBuf::as_path)),
+        )?;
+    }
+
+    // Held until the installation finishes, including reading and writing the receipt
+    let _lock = match opts.no_install {
+        true => None,
+        false => lock::lock(&sysroot::in_sysroot(&live_dirs.prefix, opts), opts)?,
+    };
+    let receipt_path = sysroot::in_sysroot(&Receipt::path(&dirs, &package), opts);
+    let mut receipt = match Receipt::load(&receipt_path, &package) {
+        Ok(receipt) => receipt,
+        Err(e) => {
+            output::status(
+                opts,
+                Status::Warning,
+                format_args!(
+                    "Warning: ignoring unreadable install receipt {}: {}",
+                    receipt_path.display(),
+                    e
+                ),
+            );
+            Receipt {
+                package: package.clone(),
+                missing: true,
+                ..Default::default()
+            }
+        }
+    };
+    receipt.version = manifest
+        .package
+        .as_ref()
+        .map(|package| package.version.clone());
+    receipt.prefix = dirs.prefix.clone();
+
+    if opts.no_install {
+        return Ok(());
+    }
+
+    #[cfg(unix)]
+    let old_umask = opts.umask.map(set_umask);
+
+    if opts.dist.is_some() {
+        dist::create_stage()?;
+    }
+    let mut strips = StripQueue::default();
+    let result = install_targets(&dirs, &targets, opts, &mut receipt, &mut strips);
+    // Setting the rpath of a binary rewrites it, so libraries are bundled before binaries are stripped
+    let bundle_failed = if opts.bundle_libs {
+        deps::bundle(&dirs, &targets, &mut receipt, opts)
+    } else {
+        0
+    };
+    let mut failed = strips.run(opts, &mut receipt) + bundle_failed;
+    // Signing rewrites the file, so it happens after stripping, and before the installation is packed by --dist
+    if let Some(signtool) = &signtool {
+        failed += sign::sign(signtool, &dirs, &targets, &receipt, opts);
+    }
+    // gpg signs the final contents of each file, after any signature embedded by signtool
+    if let Some(keyid) = &opts.sign_artifacts {
+        failed += gpg::sign(keyid, &dirs, &targets, &mut receipt, opts);
+    }
+    let missing_deps = deps::audit(&dirs, &targets, opts);
+    let leaking = match opts.build_path_check {
+        BuildPathCheck::Off => 0,
+        _ => buildpaths::audit(&dirs, &targets, &manifest_dir, opts),
+    };
+    save_receipt(opts, &receipt, &receipt_path);
+    let result = match (result, &root) {
+        (Ok(0), Some(root)) if failed == 0 && opts.stow => {
+            stow::link(&live_dirs, opts, &package, root).map(|()| 0)
+        }
+        (Ok(0), Some(root)) if failed == 0 && opts.activate => {
+            let version = root.file_name().unwrap_or_default().to_string_lossy();
+            slot::activate(&live_dirs, opts, &package, &version).map(|()| 0)
+        }
+        (result, _) => result,
+    };
+    let result = match result {
+        // The checks see the package as it is used, through the links into the prefix of a stowed package
+        Ok(0) if failed == 0 && opts.run_checks => match &root {
+            Some(_) if opts.stow => run_checks(&live_dirs, &targets, opts, &mut receipt),
+            _ => run_checks(&dirs, &targets, opts, &mut receipt),
+        },
+        result => result,
+    };
+    let result = match (result, &opts.dist) {
+        (Ok(0), Some(archive)) if failed == 0 && !opts.dry_run => {
+            let stage = dist::stage_dir();
+            let pack = || dist::pack(&stage, archive, &receipt, &receipt_path, opts);
+            pack()
+                .and_then(|()| match &notarize {
+                    Some(notarize) => notarize::notarize(notarize, archive, &stage, pack, opts),
+                    None => Ok(()),
+                })
+                .map(|()| 0)
+        }
+        (result, _) => result,
+    };
+    if opts.dist.is_some() {
+        let _ = std::fs::remove_dir_all(dist::stage_dir());
+    }
+    let result = match (result, &opts.cargo_root) {
+        (Ok(0), Some(root)) if failed == 0 && !opts.dry_run => {
+            register_cargo_bins(root, &dirs, &receipt, &manifest, &manifest_dir, opts).map(|()| 0)
+        }
+        (result, _) => result,
+    };
+    let result = match result {
+        Ok(0) if failed == 0 && opts.test_install => {
+            match smoke::run(&dirs, &targets, &receipt, opts) {
+                0 => Ok(0),
+                n => Err(Error::SmokeTests(n)),
+            }
+        }
+        result => result,
+    };
+
+    #[cfg(unix)]
+    if let Some(mask) = old_umask {
+        set_umask(mask);
+    }
+
+    match result {
+        Err(e) => Err(e),
+        Ok(n) if n + failed != 0 => Err(Error::TargetsFailed(n + failed)),
+        Ok(_) if opts.fail_on_missing_deps && missing_deps != 0 => {
+            Err(Error::MissingDependencies(missing_deps))
+        }
+        Ok(_) if opts.build_path_check == BuildPathCheck::Deny && leaking != 0 => {
+            Err(Error::BuildPaths(leaking))
+        }
+        Ok(_) => Ok(()),
+    }
+}
+
+///
+/// Records the programs installed to `<root>/bin` in the metadata of `cargo install`
+fn register_cargo_bins(
+    root: &Path,
+    dirs: &InstallDirs,
+    receipt: &Receipt,
+    manifest: &Manifest<NativeInstallMetadata>,
+    manifest_dir: &Path,
+    opts: &Options,
+) -> Result<(), Error> {
+    let bindir = sysroot::in_sysroot(&normalize_path(&dirs.prefix.join("bin")), opts);
+    let bins = receipt
+        .targets
+        .values()
+        .filter(|entry| entry.destination.parent() == Some(&*bindir))
+        .filter_map(|entry| entry.destination.file_name())
+        .map(|name| name.to_string_lossy().into_owned())
+        .collect::<Vec<_>>();
+    let package = package_name(manifest, manifest_dir);
+    let version = package_version(manifest).unwrap_or("0.0.0");
+    let id = crates::package_id(&package, version, manifest_dir);
+    crates::register(&sysroot::in_sysroot(root, opts), &id, &package, &bins, opts)
+}
+
+///
+/// Reports an error returned by [`run`], unless it was already reported when it occurred
+pub fn report(opts: &Options, err: &Error) {
+    if err.is_reported() {
+        return;
+    }
+    // Problems with the metadata are annotated on the manifest
+    if err.exit_code() == ExitCode::Manifest {
+        let manifest = opts
+            .manifest_dir
+            .as_deref()
+            .unwrap_or_else(|| Path::new(""))
+            .join("Cargo.toml");
+        output::error_in(opts, &manifest, format_args!("{}", err));
+    } else {
+        output::error(opts, format_args!("{}", err));
+    }
+}
+
+///
+/// Installs the target given by `--target`, or every target. `check` targets are left for [`run_checks`].
+/// Returns the number of targets that failed with `--keep-going`
+fn install_targets(
+    dirs: &InstallDirs,
+    targets: &HashMap<String, Target>,
+    opts: &Options,
+    receipt: &mut Receipt,
+    strips: &mut StripQueue,
+) -> Result<usize, Error> {
+    let selected = match &opts.install_target {
+        Some(name) => {
+            let target = targets
+                .get_key_value(name)
+                .ok_or_else(|| Error::NoSuchTarget(name.clone()))?;
+            vec![target]
+        }
+        None => targets.iter().collect(),
+    };
+    let selected = selected
+        .into_iter()
+        .filter(|(_, target)| target.type_ != Some(TargetType::Check))
+        .collect::<Vec<_>>();
+    // The progress counts every file installed, so that many small targets show it as well as one large directory
+    let mut progress = Progress::for_sources(
+        opts,
+        selected
+            .iter()
+            .filter_map(|(_, target)| progress_source(target, opts)),
+    );
+    let mut failed = 0;
+    for (name, target) in selected {
+        progress.clear();
+        let counted = progress.files();
+        match install_target(dirs, name, target, opts, receipt, strips, &mut progress) {
+            Ok(()) => {
+                // Files are counted as they are copied, so this is only for a target installed by other means, such as `--install` or a renderer
+                if let Some(src) =
+                    progress_source(target, opts).filter(|_| progress.files() == counted)
+                {
+                    progress.tree_installed(src);
+                }
+            }
+            Err(_) if opts.keep_going && opts.install_target.is_none() => failed += 1,
+            Err(e) => {
+                progress.finish();
+                return Err(e.into());
+            }
+        }
+    }
+    progress.finish();
+    Ok(failed)
+}
+
+///
+/// The file or tree `target` installs, which the progress of the installation counts
+fn progress_source<'a>(target: &'a Target, opts: &Options) -> Option<&'a Path> {
+    match target.type_ {
+        Some(TargetType::Run | TargetType::Check) | None => None,
+        Some(_) if !target.is_selected(opts) => None,
+        Some(_) => target.target_file.as_deref(),
+    }
+}
+
+///
+/// Runs each `check` target (or the one given by `--target`) in order of their names, once every file is installed, as `run` targets are run.
+/// Returns the number of checks that failed with `--keep-going`
+fn run_checks(
+    dirs: &InstallDirs,
+    targets: &HashMap<String, Target>,
+    opts: &Options,
+    receipt: &mut Receipt,
+) -> Result<usize, Error> {
+    let mut checks = targets
+        .iter()
+        .filter(|(name, target)| {
+            target.type_ == Some(TargetType::Check)
+                && opts
+                    .install_target
+                    .as_ref()
+                    .is_none_or(|target| target == *name)
+        })
+        .collect::<Vec<_>>();
+    checks.sort_by_key(|(name, _)| *name);
+    // Nothing is installed by a check, so there is nothing to strip, nor any progress to show
+    let mut strips = StripQueue::default();
+    let mut failed = 0;
+    for (name, target) in checks {
+        if let Err(e) = install_target(
+            dirs,
+            name,
+            target,
+            opts,
+            receipt,
+            &mut strips,
+            &mut Progress::disabled(),
+        ) {
+            if !opts.keep_going {
+                return Err(e.into());
+            }
+            failed += 1;
+        }
+    }
+    Ok(failed)
+}
+
+///
+/// The argument `arg` of `option` as text, or an error if it is not UTF-8
+fn utf8_argument(arg: OsString, option: &'static str) -> Result<String, Error> {
+    arg.into_string().map_err(|arg| Error::InvalidArgument {
+        option,
+        value: arg.to_string_lossy().into_owned(),
+        expected: "UTF-8",
+    })
+}
+
+///
+/// Splits `--name=value` into the name and the value, which is kept as it was given, as a path need not be UTF-8
+fn split_option(arg: &OsStr) -> Option<(&str, &OsStr)> {
+    let bytes = arg.as_encoded_bytes();
+    let eq = bytes.iter().position(|&b| b == b'=')?;
+    let name = std::str::from_utf8(&bytes[..eq]).ok()?;
+    // SAFETY: the bytes are split just after an ASCII `=`, so both halves are valid
+    let value = unsafe { OsStr::from_encoded_bytes_unchecked(&bytes[eq + 1..]) };
+    Some((name, value))
+}
+
+///
+/// Sets the option `name`, which takes a path, to `value`. Returns false if `name` is not such an option
+fn set_path_option(opts: &mut Options, name: &str, value: &OsStr) -> bool {
+    let field = match name {
+        "--prefix" => &mut opts.prefix,
+        "--exec-prefix" => &mut opts.exec_prefix,
+        "--bindir" => &mut opts.bindir,
+        "--libdir" => &mut opts.libdir,
+        "--libexecdir" => &mut opts.libexecdir,
+        "--includedir" => &mut opts.includedir,
+        "--sbindir" => &mut opts.sbindir,
+        "--datarootdir" => &mut opts.datarootdir,
+        "--datadir" => &mut opts.datadir,
+        "--mandir" => &mut opts.mandir,
+        "--infodir" => &mut opts.infodir,
+        "--docdir" => &mut opts.docdir,
+        "--localedir" => &mut opts.localedir,
+        "--localstatedir" => &mut opts.localstatedir,
+        "--sharedstatedir" => &mut opts.sharedstatedir,
+        "--sysconfdir" => &mut opts.sysconfdir,
+        "--manifest-dir" => &mut opts.manifest_dir,
+        "--debugdir" => &mut opts.debugdir,
+        "--emit-config-h" => &mut opts.emit_config_h,
+        "--emit-paths-rs" => &mut opts.emit_paths_rs,
+        "--pandoc" => &mut opts.pandoc,
+        "--asciidoctor" => &mut opts.asciidoctor,
+        "--emit-env" => &mut opts.emit_env,
+        "--emit-dotenv" => &mut opts.emit_dotenv,
+        "--emit-ninja" => &mut opts.emit_ninja,
+        "--dist" => &mut opts.dist,
+        "--vendor-dist" => &mut opts.vendor_dist,
+        "--from-archive" => &mut opts.from_archive,
+        "--sysroot" => &mut opts.sysroot,
+        "--elevate-with" => &mut opts.elevate_with,
+        "--out-dir" => &mut opts.out_dir,
+        "--config" => &mut opts.config,
+        // Not documented, as only `elevate_if_needed` passes it
+        "--user-config" => &mut opts.user_config,
+        "--root" => {
+            opts.cargo_root = Some(value.into());
+            &mut opts.prefix
+        }
+        "--stow-dir" => {
+            opts.stow = true;
+            &mut opts.stow_dir
+        }
+        _ => return false,
+    };
+    *field = Some(value.into());
+    true
+}
+
+///
+/// Parses a size in bytes, optionally followed by `K` or `M` (binary multiples)
+fn parse_size(s: &str) -> Option<usize> {
+    let (num, mul) = match s.as_bytes().last()? {
+        b'K' | b'k' => (&s[..s.len() - 1], 1024),
+        b'M' | b'm' => (&s[..s.len() - 1], 1024 * 1024),
+        _ => (s, 1),
+    };
+    num.parse::<usize>().ok()?.checked_mul(mul)
+}
+
+///
+/// Writes the install receipt, unless nothing was installed, or it was installed to another filesystem
+fn save_receipt(opts: &Options, receipt: &Receipt, path: &Path) {
+    if opts.dry_run || opts.filesystem.is_some() {
+        return;
+    }
+    if let Err(e) = receipt.save(path) {
+        output::status(
+            opts,
+            Status::Warning,
+            format_args!(
+                "Warning: failed to write install receipt {}: {}",
+                path.display(),
+                e
+            ),
+        );
+    }
+}
+
+///
+/// Installs a single target, delivering [`Event`]s for its progress to [`Options::events`].
+/// Failures are reported as they occur, and also returned
+pub(crate) fn install_target(
+    dirs: &InstallDirs,
+    name: &str,
+    target: &Target,
+    opts: &Options,
+    receipt: &mut Receipt,
+    strips: &mut StripQueue,
+    progress: &mut Progress,
+) -> Result<(), InstallError> {
+    events::emit(opts, Event::TargetStarted { name });
+    let result = do_install_target(dirs, name, target, opts, receipt, strips, progress);
+    match &result {
+        Ok(()) => events::emit(opts, Event::TargetFinished { name }),
+        Err(error) => events::emit(opts, Event::TargetFailed { name, error }),
+    }
+    result
+}
+
+fn do_install_target(
+    dirs: &InstallDirs,
+    name: &str,
+    target: &Target,
+    opts: &Options,
+    receipt: &mut Receipt,
+    strips: &mut StripQueue,
+    progress: &mut Progress,
+) -> Result<(), InstallError> {
+    if let Some(src) = missing_optional(target) {
+        output::status(
+            opts,
+            Status::Skipped,
+            format_args!(
+                "Skipping {}, {} does not exist, and the target is optional",
+                name,
+                src.display()
+            ),
+        );
+        events::emit(
+            opts,
+            Event::TargetSkipped {
+                name,
+                reason: "optional, and its source file does not exist",
+            },
+        );
+        return Ok(());
+    }
+    if let Some(command) = &target.only_if {
+        match hooks::only_if(command, dirs, opts) {
+            Ok(true) => {}
+            Ok(false) => {
+                output::status(
+                    opts,
+                    Status::Skipped,
+                    format_args!("Skipping {}, `{}` failed", name, command),
+                );
+                events::emit(
+                    opts,
+                    Event::TargetSkipped {
+                        name,
+                        reason: "its only-if command failed",
+                    },
+                );
+                return Ok(());
+            }
+            Err(e) => {
+                return Err(fail_with(
+                    opts,
+                    ExitCode::for_io(&e),
+                    format_args!("Failed to run the only-if command, {}", e),
+                ))
+            }
+        }
+    }
+
+    let install_dir = target
+        .install_dir
+        .as_deref()
+        .map(|path| convert_to_path(path, dirs, Path::new("")));
+
+    match target.type_ {
+        Some(type_ @ (TargetType::Run | TargetType::Check)) => match &target.target_file {
+            Some(file) => {
+                output::status(
+                    opts,
+                    Status::Installed,
+                    format_args!(
+                        "{} {}",
+                        match type_ {
+                            TargetType::Check => "Checking the installation with",
+                            _ => "Executing steps for",
+                        },
+                        file.display()
+                    ),
+                );
+                if !opts.dry_run {
+                    let mut cmd = Command::new(file);
+                    cmd.envs(dirs.as_env());
+                    if opts.verbosity >= Verbosity::Verbose {
+                        cmd.env("_VERBOSE", "1");
+                    }
+                    if let Some(dir) = &install_dir {
+                        cmd.current_dir(dir);
+                    }
+                    output::command(opts, &cmd);
+
+                    match cmd.status() {
+                        Ok(term) => match term.code().map(|c| (c, target.exit_meaning(c))) {
+                            Some((_, ExitMeaning::Success)) => {}
+                            Some((c, ExitMeaning::SoftFail)) => {
+                                output::status(
+                                    opts,
+                                    Status::Failed,
+                                    format_args!("Failed (target returned exit code {})", c),
+                                );
+                            }
+                            Some((_, ExitMeaning::Skip)) => {
+                                output::status(opts, Status::Skipped, format_args!("Skipped"));
+                                events::emit(
+                                    opts,
+                                    Event::TargetSkipped {
+                                        name,
+                                        reason: "the run target skipped itself",
+                                    },
+                                );
+                            }
+                            Some((c, ExitMeaning::Fail)) => {
+                                return Err(fail(
+                                    opts,
+                                    format_args!("Failed (target returned exit code {})", c),
+                                ));
+                            }
+                            None => {
+                                return Err(fail(
+                                    opts,
+                                    format_args!("Failed (Unexpected termination)"),
+                                ));
+                            }
+                        },
+                        // The program of a run target is its artifact
+                        Err(e) if e.kind() == ErrorKind::NotFound => {
+                            return Err(fail_with(
+                                opts,
+                                ExitCode::MissingArtifact,
+                                format_args!("Failed {}", e),
+                            ));
+                        }
+                        Err(e) => {
+                            return Err(fail_with(
+                                opts,
+                                ExitCode::for_io(&e),
+                                format_args!("Failed {}", e),
+                            ));
+                        }
+                    }
+                }
+            }
+            None => {
+                return Err(fail(
+                    opts,
+                    format_args!("Failed to parse target, run targets require a file"),
+                ));
+            }
+        },
+        Some(_) => {
+            let installed_file = target_destination(dirs, target, opts).unwrap();
+            if !opts.allow_outside_prefix && !is_within_install_dirs(&installed_file, dirs) {
+                return Err(fail(opts, format_args!(
+                        "Failed, {} is outside of the installation directories (use --allow-outside-prefix to install it anyway)",
+                        installed_file.display()
+                    )));
+            }
+            if is_skipped_privileged(target, opts) {
+                events::emit(
+                    opts,
+                    Event::TargetSkipped {
+                        name,
+                        reason: "privileged targets are not being installed",
+                    },
+                );
+                return Ok(());
+            }
+            if is_skipped_locale(target, opts) {
+                output::verbose(
+                    opts,
+                    format_args!("Skipping {}, its locale is not one of --locales", name),
+                );
+                events::emit(
+                    opts,
+                    Event::TargetSkipped {
+                        name,
+                        reason: "its locale is not one of those being installed",
+                    },
+                );
+                return Ok(());
+            }
+            if opts.sysroot.is_some() && !sysroot::is_needed(target, &installed_file) {
+                output::verbose(
+                    opts,
+                    format_args!("Skipping {}, it is not needed in a sysroot", name),
+                );
+                events::emit(
+                    opts,
+                    Event::TargetSkipped {
+                        name,
+                        reason: "not needed in a sysroot",
+                    },
+                );
+                return Ok(());
+            }
+            // `installed_file` is the path on the system the package is installed for, which differs with `--sysroot`
+            let target_file = sysroot::in_sysroot(&installed_file, opts);
+
+            let mode = target_mode(target, opts);
+            let fingerprint = receipt::fingerprint(target, &mode, dirs, opts);
+            if !opts.force && receipt.is_current(name, &target_file, fingerprint.as_deref()) {
+                // The commands do not affect the installed files, so they can change without the target being installed again
+                if let Some(entry) = receipt.targets.get_mut(name) {
+                    entry.pre_uninstall = hooks::pre_uninstall(target, dirs);
+                }
+                output::status(
+                    opts,
+                    Status::Skipped,
+                    format_args!(
+                        "Skipping {}, unchanged since it was last installed",
+                        target_file.display()
+                    ),
+                );
+                events::emit(
+                    opts,
+                    Event::TargetSkipped {
+                        name,
+                        reason: "unchanged since it was last installed",
+                    },
+                );
+                return Ok(());
+            }
+
+            if opts.package_check != PackageCheck::Off
+                && pkgdb::is_system_path(&target_file)
+                && target_file.exists()
+            {
+                if let Some(pkg) = pkgdb::owning_package(opts, &target_file) {
+                    if opts.package_check == PackageCheck::Refuse && !opts.force {
+                        return Err(fail(opts, format_args!(
+                                "Failed, {} belongs to the system package {} (use --force to overwrite it)",
+                                target_file.display(),
+                                pkg
+                            )));
+                    }
+                    output::status(
+                        opts,
+                        Status::Warning,
+                        format_args!(
+                            "Warning: overwriting {}, which belongs to the system package {}",
+                            target_file.display(),
+                            pkg
+                        ),
+                    );
+                }
+            }
+
+            // Nothing is recorded about files installed to another filesystem, so none of them would be known
+            let foreign = match opts.filesystem {
+                Some(_) => Vec::new(),
+                None => receipt.foreign_files(
+                    target.target_file.as_deref(),
+                    &target_file,
+                    target.strip_components.unwrap_or(0),
+                ),
+            };
+            if let Some(file) = foreign.first() {
+                let others = match foreign.len() {
+                    1 => String::new(),
+                    n => format!(" (and {} other files)", n - 1),
+                };
+                if receipt.missing {
+                    // As for a package installed before it had a receipt, the files may well be its own
+                    output::status(
+                        opts,
+                        Status::Warning,
+                        format_args!(
+                            "Warning: replacing {}{}, which may not have been installed by {}, as it has no install receipt",
+                            file.display(),
+                            others,
+                            receipt.package
+                        ),
+                    );
+                } else if !opts.adopt && !opts.force {
+                    return Err(fail(opts, format_args!(
+                            "Failed, {}{} was not installed by {} (use --adopt to take it over, or --force to overwrite it)",
+                            file.display(),
+                            others,
+                            receipt.package
+                        )));
+                }
+            }
+            let mut adoption = receipt::Adoption::default();
+            for file in &foreign {
+                output::verbose(
+                    opts,
+                    format_args!(
+                        "Replacing {}, which was not installed by {}",
+                        file.display(),
+                        receipt.package
+                    ),
+                );
+                // An adopted file is replaced even if it is newer than the artifact, which `--force` does anyway
+                if opts.adopt && !opts.dry_run {
+                    if let Err(e) = adoption.set_aside(file) {
+                        return Err(fail_with(
+                            opts,
+                            ExitCode::for_io(&e),
+                            format_args!("Failed to replace {}, {}", file.display(), e),
+                        ));
+                    }
+                }
+            }
+
+            let preview = match &target.target_file {
+                Some(src) if opts.dry_run => Some(preview::preview(
+                    src,
+                    &target_file,
+                    target.strip_components.unwrap_or(0),
+                )),
+                _ => None,
+            };
+            let label = match &preview {
+                Some(Ok(preview)) => format!(" ({}, {})", preview, human_bytes(preview.bytes)),
+                Some(Err(e)) => format!(" (cannot compare, {})", e),
+                None => String::new(),
+            };
+
+            if target.directory {
+                if let Some(src) = &target.target_file {
+                    output::status(
+                        opts,
+                        Status::Installed,
+                        format_args!(
+                            "Installing directory {} to {}{}",
+                            src.display(),
+                            target_file.display(),
+                            label
+                        ),
+                    )
+                } else {
+                    output::status(
+                        opts,
+                        Status::Installed,
+                        format_args!("Creating directory {}", target_file.display()),
+                    )
+                }
+            } else if let Some(src) = &target.target_file {
+                output::status(
+                    opts,
+                    Status::Installed,
+                    format_args!(
+                        "Installing {} to {}{}",
+                        src.display(),
+                        target_file.display(),
+                        label
+                    ),
+                )
+            } else {
+                return Err(fail(
+                    opts,
+                    format_args!("Invalid target, no source file given, but one is expected"),
+                ));
+            }
+            if let Some(Ok(preview)) = &preview {
+                preview.report(opts);
+            }
+
+            if !opts.dry_run {
+                if let Some(src) = target.target_file.as_ref().filter(|src| !src.exists()) {
+                    return Err(fail_with(
+                        opts,
+                        ExitCode::MissingArtifact,
+                        format_args!("Failed, {}", missing_artifact(src, opts)),
+                    ));
+                }
+            }
+
+            let template = match opts.dry_run {
+                true => None,
+                false => match Template::render(target, dirs, opts) {
+                    Ok(template) => template,
+                    Err(e) => {
+                        return Err(fail(opts, format_args!("Failed to render template, {}", e)))
+                    }
+                },
+            };
+            let target = template
+                .as_ref()
+                .map_or(target, |template| &template.target);
+
+            // Installed pkg-config files refer to the installation directories, rather than wherever the package was built
+            let pkgconfig = match opts.sysroot.is_some() || opts.multiarch.is_some() {
+                true if !opts.dry_run => match PkgConfig::rewrite(target, dirs) {
+                    Ok(pkgconfig) => pkgconfig,
+                    Err(e) => {
+                        return Err(fail(
+                            opts,
+                            format_args!("Failed to rewrite pkg-config file, {}", e),
+                        ))
+                    }
+                },
+                _ => None,
+            };
+            let target = pkgconfig
+                .as_ref()
+                .map_or(target, |pkgconfig| &pkgconfig.target);
+
+            let manpage = if opts.dry_run {
+                Rendered::NotNeeded
+            } else {
+                match ManPage::render(target, opts) {
+                    Ok(rendered) => rendered,
+                    Err(e) => {
+                        return Err(fail(
+                            opts,
+                            format_args!("Failed to render manual page, {}", e),
+                        ))
+                    }
+                }
+            };
+            let target = match &manpage {
+                Rendered::NotNeeded => target,
+                Rendered::Page(page) => &page.target,
+                Rendered::Unavailable(prg) => {
+                    output::status(
+                        opts,
+                        Status::Warning,
+                        format_args!(
+                            "Warning: skipping {}, {} is needed to render it",
+                            target_file.display(),
+                            prg
+                        ),
+                    );
+                    events::emit(
+                        opts,
+                        Event::TargetSkipped {
+                            name,
+                            reason: "the program to render the manual page was not found",
+                        },
+                    );
+                    return Ok(());
+                }
+            };
+
+            if !opts.dry_run {
+                // Rendered manual pages and templates, and rewritten pkg-config files, are temporary, so they are always copied.
+                // Links cannot be made on another machine, or into an archive
+                let linkable = template.is_none()
+                    && pkgconfig.is_none()
+                    && matches!(manpage, Rendered::NotNeeded)
+                    && target.target_file.is_some()
+                    && opts.filesystem.is_none()
+                    && opts.dist.is_none();
+                let method = target.install_method.unwrap_or_default();
+                // As root, a link into a directory only root can write to would let the owner of the artifact change the installed file
+                let exposes_root = linkable
+                    && (opts.develop || method != InstallMethod::Copy)
+                    && target.target_file.as_deref().is_some_and(|src| {
+                        privs::link_exposes_root(
+                            src,
+                            &target_file,
+                            opts.develop || method == InstallMethod::Symlink,
+                        )
+                    });
+                if exposes_root {
+                    output::status(
+                        opts,
+                        Status::Warning,
+                        format_args!(
+                            "Warning: copying {} rather than linking it, as a user other than root could change the installed file through the link",
+                            name
+                        ),
+                    );
+                }
+                let linkable = linkable && !exposes_root;
+                // Without symbolic links on the destination filesystem, the artifact is copied instead, see `fscaps`
+                let develop = (opts.develop || method == InstallMethod::Symlink)
+                    && linkable
+                    && fscaps::links(&target_file, opts, target.directory);
+                let hardlink = !develop && method == InstallMethod::Hardlink && linkable;
+                // Binaries are stripped once every target is installed, see `StripQueue`
+                let strip = opts.strip.as_deref().is_some()
+                    && !develop
+                    && !hardlink
+                    && opts.filesystem.is_none()
+                    && target.strip == Some(true)
+                    && !target.directory;
+                let strip_args = if strip {
+                    strip::args(target, opts)
+                } else {
+                    Vec::new()
+                };
+                let debug_file = match &target.target_file {
+                    Some(src) if strip && opts.separate_debug => {
+                        let debug_file = strip::debug_file(src, dirs, opts);
+                        if debug_file.is_none() {
+                            output::status(
+                                opts,
+                                Status::Warning,
+                                format_args!(
+                                    "Warning: {} has no build ID, so its debug information is not kept",
+                                    src.display()
+                                ),
+                            );
+                        }
+                        debug_file
+                    }
+                    _ => None,
+                };
+                // Changing the rpath rewrites the installed file, so it has to be copied from the artifact again
+                let already_stripped = strip
+                    && !opts.force
+                    && target.rpath.is_none()
+                    && debug_file.as_deref().is_none_or(Path::exists)
+                    && target.target_file.as_deref().is_some_and(|src| {
+                        receipt.is_stripped(name, src, &target_file, &strip_args)
+                    });
+
+                // install(1) cannot copy a directory tree without an invocation per file,
+                // so directories with contents are always installed natively
+                let install = match &target.target_file {
+                    Some(_) if target.directory => None,
+                    _ if opts.filesystem.is_some() => None,
+                    _ => opts.install.as_ref(),
+                };
+                if !develop && !hardlink && opts.filesystem.is_none() {
+                    if let Some(src) = &target.target_file {
+                        let strip = target.strip_components.unwrap_or(0);
+                        if let Err(e) = remove_artifact_links(src, &target_file, strip) {
+                            return Err(fail_with(
+                                opts,
+                                ExitCode::for_io(&e),
+                                format_args!("Failed to install target {}", e),
+                            ));
+                        }
+                    }
+                }
+                if develop {
+                    let src = target.target_file.as_deref().unwrap();
+                    if let Err(e) = develop_link(src, &target_file, opts, target.directory) {
+                        return Err(fail_with(
+                            opts,
+                            ExitCode::for_io(&e),
+                            format_args!("Failed to link {}, {}", target_file.display(), e),
+                        ));
+                    }
+                } else if hardlink {
+                    let src = target.target_file.as_deref().unwrap();
+                    let strip = target.strip_components.unwrap_or(0);
+                    if let Err(e) = hard_link_tree(src, &target_file, strip, opts) {
+                        return Err(fail_with(
+                            opts,
+                            ExitCode::for_io(&e),
+                            format_args!("Failed to link {}, {}", target_file.display(), e),
+                        ));
+                    }
+                } else if already_stripped {
+                    output::verbose(
+                        opts,
+                        format_args!(
+                            "{} already holds the stripped artifact, only updating its permissions",
+                            target_file.display()
+                        ),
+                    );
+                    if let Err(e) = update_installed(&target_file, opts, target, &mode) {
+                        return Err(fail_with(
+                            opts,
+                            e.exit_code(),
+                            format_args!("Failed to install target {}", e),
+                        ));
+                    }
+                } else if let Some(s) = install {
+                    let flavor = opts.install_flavor.unwrap_or_default();
+                    let mut cmd = Command::new(s);
+
+                    if !opts.no_create_dirs {
+                        if flavor.creates_parents() {
+                            cmd.arg("-D");
+                        } else if let Some(parent) =
+                            target_file.parent().filter(|_| !target.directory)
+                        {
+                            if let Err(e) = opts.fs().create_dir_all(parent) {
+                                return Err(fail_with(
+                                    opts,
+                                    ExitCode::for_io(&e),
+                                    format_args!("Failed to create {}, {}", parent.display(), e),
+                                ));
+                            }
+                        }
+                    }
+
+                    if opts.verbosity >= Verbosity::Verbose && flavor.has_verbose() {
+                        cmd.arg("-v");
+                    }
+
+                    let mode = match target.directory {
+                        true => entry_mode(target, true, opts),
+                        false => mode.clone(),
+                    };
+                    if let Some(mode) = &mode {
+                        cmd.arg("-m");
+                        cmd.arg(mode);
+                    }
+
+                    if opts.timestamps == Timestamps::Preserve {
+                        cmd.arg("-p");
+                    }
+
+                    let (owner, group) = (opts.owner, opts.group);
+                    if let Some(owner) = owner {
+                        cmd.arg("-o");
+                        cmd.arg(owner.to_string());
+                    }
+
+                    if let Some(group) = group {
+                        cmd.arg("-g");
+                        cmd.arg(group.to_string());
+                    }
+
+                    if target.directory {
+                        cmd.arg("-d");
+                    } else if let Some(src) = &target.target_file {
+                        if flavor.has_no_target_directory() {
+                            cmd.arg("-T");
+                        } else if target_file.is_dir() {
+                            // Without `-T`, the file would be installed into the directory
+                            return Err(fail(
+                                opts,
+                                format_args!("Failed, {} is a directory", target_file.display()),
+                            ));
+                        }
+                        cmd.arg(path_arg(src));
+                    } else {
+                        panic!();
+                    }
+                    cmd.arg(path_arg(&target_file));
+                    output::command(opts, &cmd);
+                    match cmd.status() {
+                        Ok(c) => {
+                            match c.code() {
+                                Some(0) => (),
+                                Some(x) => {
+                                    // install only says why it failed in its message, so check whether the destination is writable
+                                    let exit_code =
+                                        match elevate::find_unwritable([target_file.as_path()]) {
+                                            Some(_) => ExitCode::Permission,
+                                            None => ExitCode::Failure,
+                                        };
+                                    return Err(fail_with(
+                                        opts,
+                                        exit_code,
+                                        format_args!(
+                                            "Failed, install program exited with code {}",
+                                            x
+                                        ),
+                                    ));
+                                }
+                                None => {
+                                    #[cfg(unix)]
+                                    {
+                                        if let Some(x) = c.signal() {
+                                            // SAFETY:
+                                            // libc::strsignal, which calls strsignal from the C Standard library, cannot cause undefined behaviour
+                                            // Additionally, it is guaranteed, by the C Standard, to return pointer to a null terminated string
+                                            return Err(fail(
+                                                opts,
+                                                format_args!(
+                                                    "Failed, install program recieved signal {}",
+                                                    unsafe { CStr::from_ptr(libc::strsignal(x)) }
+                                                        .to_string_lossy()
+                                                ),
+                                            ));
+                                        }
+                                    }
+
+                                    return Err(fail(opts, format_args!("Failed, unknown result")));
+                                }
+                            }
+                        }
+                        Err(e) if e.kind() == ErrorKind::NotFound => {
+                            return Err(fail_with(
+                                opts,
+                                ExitCode::ToolNotFound,
+                                format_args!("Failed, {}", e),
+                            ));
+                        }
+                        Err(e) => {
+                            return Err(fail_with(
+                                opts,
+                                ExitCode::for_io(&e),
+                                format_args!("Failed, {}", e),
+                            ));
+                        }
+                    }
+
+                    if !target.directory {
+                        if let Ok(md) = metadata(&target_file) {
+                            events::emit(
+                                opts,
+                                Event::FileCopied {
+                                    path: &target_file,
+                                    bytes: md.len(),
+                                },
+                            );
+                        }
+                    }
+
+                    if let (Timestamps::Clamp(_), Some(src)) =
+                        (opts.timestamps, &target.target_file)
+                    {
+                        if let Err(e) = apply_timestamp(src, &target_file, opts) {
+                            return Err(fail(
+                                opts,
+                                format_args!("Failed to set modification time, {}", e),
+                            ));
+                        }
+                    }
+
+                    if let Err(e) = copy::sync(&target_file, opts) {
+                        return Err(fail(
+                            opts,
+                            format_args!(
+                                "Failed to flush {} to disk, {}",
+                                target_file.display(),
+                                e
+                            ),
+                        ));
+                    }
+                } else {
+                    let result = do_internal_install(
+                        target.target_file.as_deref(),
+                        &target_file,
+                        opts,
+                        target,
+                        &mode,
+                        progress,
+                    );
+                    if let Err(e) = result {
+                        return Err(fail_with(
+                            opts,
+                            e.exit_code(),
+                            format_args!("Failed to install target {}", e),
+                        ));
+                    }
+                }
+
+                if let Some(rpath) = target
+                    .rpath
+                    .as_ref()
+                    .filter(|_| !develop && !hardlink && !target.directory)
+                {
+                    // patchelf cannot edit the file on the destination machine
+                    if opts.filesystem.is_some() {
+                        output::status(
+                            opts,
+                            Status::Warning,
+                            format_args!(
+                                "Warning: not changing the rpath of {}, as it is installed with --dest",
+                                target_file.display()
+                            ),
+                        );
+                    } else if let Err(e) = rpath::apply(rpath, &target_file, opts) {
+                        return Err(fail_with(
+                            opts,
+                            e.exit_code(),
+                            format_args!("Failed to change the rpath, {}", e),
+                        ));
+                    }
+                }
+
+                let mut files = Vec::new();
+                receipt::installed_files(
+                    target.target_file.as_deref(),
+                    &target_file,
+                    target.strip_components.unwrap_or(0),
+                    &mut files,
+                );
+
+                let mut copied_aliases = Vec::new();
+                for alias in target.installed_aliases.iter().flatten() {
+                    let alias_path = normalize_path(&convert_to_path(
+                        alias,
+                        dirs,
+                        installed_file.parent().unwrap_or_else(|| Path::new("")),
+                    ));
+                    if !opts.allow_outside_prefix && !is_within_install_dirs(&alias_path, dirs) {
+                        return Err(fail(opts, format_args!(
+                                "Failed, alias {} is outside of the installation directories (use --allow-outside-prefix to create it anyway)",
+                                alias_path.display()
+                            )));
+                    }
+                    let alias_path = sysroot::in_sysroot(&alias_path, opts);
+                    if create_alias(&target_file, &alias_path, opts, target.directory).is_err() {
+                        return Err(fail(
+                            opts,
+                            format_args!("Failed to create alias {}", alias.display()),
+                        ));
+                    }
+                    if !fscaps::links(&alias_path, opts, target.directory) {
+                        copied_aliases.push(alias_path.clone());
+                    }
+                    files.push(alias_path);
+                }
+
+                if target.type_ == Some(TargetType::HtmlDoc) {
+                    match htmldoc::link_index(target, &target_file, opts) {
+                        Ok(Some(link)) => files.push(link),
+                        Ok(None) => {}
+                        Err(e) => {
+                            return Err(fail(
+                                opts,
+                                format_args!("Failed to create index.html, {}", e),
+                            ));
+                        }
+                    }
+                    if target.doc_base {
+                        match htmldoc::register_doc_base(dirs, &installed_file, opts) {
+                            Ok(control) => files.push(control),
+                            Err(e) => return Err(fail(opts, format_args!("{}", e))),
+                        }
+                    }
+                }
+
+                let (artifact, stripped) = match receipt.targets.get(name) {
+                    Some(entry) if already_stripped => {
+                        (entry.artifact.clone(), entry.stripped.clone())
+                    }
+                    _ => (None, None),
+                };
+                if strip && !already_stripped {
+                    if let Some(src) = &target.target_file {
+                        strips.push(StripJob {
+                            name: name.to_owned(),
+                            src: src.clone(),
+                            dest: target_file.clone(),
+                            args: strip_args,
+                            debug: debug_file.clone(),
+                            copies: copied_aliases,
+                        });
+                    }
+                }
+                files.extend(debug_file);
+                receipt.record(
+                    name,
+                    TargetReceipt {
+                        destination: target_file,
+                        fingerprint,
+                        files,
+                        artifact,
+                        stripped,
+                        signature: None,
+                        pre_uninstall: hooks::pre_uninstall(target, dirs),
+                        develop,
+                    },
+                );
+                adoption.finish();
+            }
+
+            if let Some(command) = &target.post_install {
+                let command = hooks::expand(command, dirs);
+                if opts.filesystem.is_some() || opts.sysroot.is_some() || opts.dist.is_some() {
+                    // The command would change this machine, rather than the one the package is installed for
+                    output::status(
+                        opts,
+                        Status::Warning,
+                        format_args!(
+                            "Warning: not running the post-install command of {}, it is not being installed onto this machine",
+                            name
+                        ),
+                    );
+                } else {
+                    output::status(opts, Status::Installed, format_args!("Running {}", command));
+                    if !opts.dry_run {
+                        let mut cmd = hooks::shell(&command, dirs);
+                        output::command(opts, &cmd);
+                        match cmd.status() {
+                            Ok(status) if status.success() => {}
+                            Ok(status) => {
+                                return Err(fail(
+                                    opts,
+                                    format_args!(
+                                        "Failed, the post-install command exited with {}",
+                                        status
+                                    ),
+                                ))
+                            }
+                            Err(e) => {
+                                return Err(fail_with(
+                                    opts,
+                                    ExitCode::for_io(&e),
+                                    format_args!("Failed to run the post-install command, {}", e),
+                                ))
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        None => {}
+    }
+    Ok(())
+}
+
+///
+/// The mode a target is installed with, from the target and `--mode`, which is applied after it
+fn target_mode(target: &Target, opts: &Options) -> Option<String> {
+    with_cli_mode(target.mode.as_ref(), opts)
+}
+
+///
+/// The mode of a directory (if `dir` is set) or a file installed by a directory target, which is `dir-mode` or `file-mode` instead of `mode`, if given
+fn entry_mode(target: &Target, dir: bool, opts: &Options) -> Option<String> {
+    let mode = match dir {
+        true => target.dir_mode.as_ref(),
+        false => target.file_mode.as_ref(),
+    };
+    with_cli_mode(mode.or(target.mode.as_ref()), opts)
+}
+
+///
+/// The mode which `modes` gives the file at `path`, installed by a directory target: that of the longest pattern which matches it.
+/// `path` is relative to the directory the target is installed to. A pattern without a `/` is matched against the name of the file,
+/// one with `**` against its whole path, and any other against as many of the last components of its path as the pattern has
+fn pattern_mode<'a>(target: &'a Target, path: &Path) -> Option<&'a String> {
+    let path = path.to_string_lossy().replace('\\', "/");
+    target
+        .modes
+        .iter()
+        .flatten()
+        .filter(|(pattern, _)| {
+            let depth = pattern.split('/').count();
+            let tail = match path.rmatch_indices('/').nth(depth - 1) {
+                Some((start, _)) if !pattern.contains("**") => &path[start + 1..],
+                _ => &path,
+            };
+            glob::matches(pattern, tail)
+        })
+        .max_by_key(|(pattern, _)| pattern.len())
+        .map(|(_, mode)| mode)
+}
+
+///
+/// The rules of `modes`, each with `--mode` applied after it, from the least to the most specific, so that applying them in order leaves each file with the mode of [`pattern_mode`]
+fn pattern_modes(target: &Target, opts: &Options) -> Vec<(String, String)> {
+    let mut rules = target
+        .modes
+        .iter()
+        .flatten()
+        .filter_map(|(pattern, mode)| Some((pattern.clone(), with_cli_mode(Some(mode), opts)?)))
+        .collect::<Vec<_>>();
+    rules.sort_by_key(|(pattern, _)| pattern.len());
+    rules
+}
+
+///
+/// Applies `--mode` after `mode`
+fn with_cli_mode(mode: Option<&String>, opts: &Options) -> Option<String> {
+    let mut mode = mode.cloned();
+    if let Some(m) = &opts.mode {
+        if let Some(mode) = &mut mode {
+            *mode += ",";
+            *mode += m;
+        } else {
+            mode = Some(m.clone());
+        }
+    }
+    mode
+}
+
+///
+/// Computes the path that a target is installed to. Returns `None` for `run` and `check` targets, targets without a type,
+/// and excluded targets without an `installed-path` (which [`load_targets`] rejects for any other target)
+pub fn target_destination(dirs: &InstallDirs, target: &Target, opts: &Options) -> Option<PathBuf> {
+    let type_ = match target.type_ {
+        Some(TargetType::Run | TargetType::Check) | None => return None,
+        Some(type_) => type_,
+    };
+    let install_dir = target
+        .install_dir
+        .as_deref()
+        .map(|path| convert_to_path(path, dirs, Path::new("")));
+    let installed_path = target.installed_path.as_deref()?;
+    let dir = type_.get_install_root(dirs, opts).unwrap();
+    Some(normalize_path(&convert_to_path(
+        installed_path,
+        dirs,
+        install_dir.as_deref().unwrap_or(dir),
+    )))
+}
+
+///
+/// The cargo command that builds `artifact`, if it is in a cargo target directory.
+/// The profile and target triple are taken from its path, as in `target/<triple>/<profile>/<artifact>`
+fn build_command(artifact: &Path, opts: &Options) -> Option<String> {
+    let mut cmd = String::from("cargo build");
+    if let Some(dir) = opts
+        .out_dir
+        .as_deref()
+        .filter(|dir| artifact.starts_with(dir))
+    {
+        if !opts.debug {
+            cmd.push_str(" --release");
+        }
+        cmd.push_str(&format!(" --target-dir {}", dir.display()));
+        return Some(cmd);
+    }
+    let dirs = artifact
+        .parent()?
+        .components()
+        .filter_map(|c| match c {
+            Component::Normal(c) => c.to_str(),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    let target = dirs.iter().rposition(|dir| *dir == "target")?;
+    let (triple, profile) = match dirs[target + 1..] {
+        [profile] => (None, profile),
+        [triple, profile] => (Some(triple), profile),
+        _ => return None,
+    };
+    match profile {
+        "debug" => {}
+        "release" => cmd.push_str(" --release"),
+        profile => cmd.push_str(&format!(" --profile {}", profile)),
+    }
+    if let Some(triple) = triple {
+        cmd.push_str(&format!(" --target {}", triple));
+    }
+    Some(cmd)
+}
+
+///
+/// The error for `artifact`, the source of a target, not existing
+fn missing_artifact(artifact: &Path, opts: &Options) -> Error {
+    Error::MissingArtifact(
+        std::path::absolute(artifact).unwrap_or_else(|_| artifact.to_path_buf()),
+        build_command(artifact, opts),
+    )
+}
+
+///
+/// Fails, reporting each missing artifact, if the artifact of any target that would be installed does not exist, for `--require-built`
+fn require_built(
+    dirs: &InstallDirs,
+    targets: &HashMap<String, Target>,
+    opts: &Options,
+) -> Result<(), Error> {
+    let mut names = targets
+        .iter()
+        .filter(|(name, target)| {
+            opts.install_target
+                .as_ref()
+                .is_none_or(|target| target == *name)
+                && target.is_selected(opts)
+                && !target.optional
+                && (opts.sysroot.is_none()
+                    || target_destination(dirs, target, opts)
+                        .is_none_or(|dest| sysroot::is_needed(target, &dest)))
+        })
+        .map(|(name, _)| name)
+        .collect::<Vec<_>>();
+    names.sort();
+    let mut missing = 0;
+    for name in names {
+        if let Some(src) = targets[name]
+            .target_file
+            .as_ref()
+            .filter(|src| !src.exists())
+        {
+            missing += 1;
+            output::status(
+                opts,
+                Status::Failed,
+                format_args!("Target {}: {}", name, missing_artifact(src, opts)),
+            );
+        }
+    }
+    if missing != 0 {
+        return Err(Error::MissingArtifacts(missing));
+    }
+    Ok(())
+}
+
+///
+/// The source of `target`, if it does not exist and the target is `optional`, so that it is skipped instead of failing
+pub fn missing_optional(target: &Target) -> Option<&Path> {
+    target
+        .target_file
+        .as_deref()
+        .filter(|src| target.optional && !src.exists())
+}
+
+///
+/// Whether a privileged target is not installed because of `--no-privileged` or `--user-prefix`
+pub fn is_skipped_privileged(target: &Target, opts: &Options) -> bool {
+    target.privileged
+        && match opts.install_privileged {
+            Some(false) => true,
+            None => opts.user_prefix,
+            Some(true) => false,
+        }
+}
+
+///
+/// Whether a target in another language is not installed because of `--locales`. A locale selects the targets in it, and in its variants,
+/// so `pt` selects both `pt_BR` and `pt_PT`
+pub fn is_skipped_locale(target: &Target, opts: &Options) -> bool {
+    match (&target.lang, &opts.locales) {
+        (Some(lang), Some(locales)) => !locales.iter().any(|locale| {
+            lang.strip_prefix(locale.as_str())
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with(['_', '.', '@']))
+        }),
+        _ => false,
+    }
+}
+
+pub fn convert_to_path(input: &Path, dirs: &InstallDirs, primary: &Path) -> PathBuf {
+    if input.has_root() {
+        input.to_owned()
+    } else {
+        let mut components = input.components();
+
+        match components.next() {
+            Some(Component::Normal(s))
+                if s == OsStr::new("<prefix>")
+                    || s == OsStr::new("@prefix@")
+                    || s == OsStr::new("${prefix}") =>
+            {
+                dirs.prefix
+                    .components()
+                    .chain(components)
+                    .collect::<PathBuf>()
+            }
+            Some(Component::Normal(s))
+                if s == OsStr::new("<exec_prefix>")
+                    || s == OsStr::new("@exec_prefix@")
+                    || s == OsStr::new("${exec_prefix}") =>
+            {
+                dirs.exec_prefix
+                    .components()
+                    .chain(components)
+                    .collect::<PathBuf>()
+            }
+            Some(Component::Normal(s))
+                if s == OsStr::new("<bindir>")
+                    || s == OsStr::new("@bindir@")
+                    || s == OsStr::new("${bindir}") =>
+            {
+                dirs.bindir
+                    .components()
+                    .chain(components)
+                    .collect::<PathBuf>()
+            }
+            Some(Component::Normal(s))
+                if s == OsStr::new("<sbindir>")
+                    || s == OsStr::new("@sbindir@")
+                    || s == OsStr::new("${sbindir}") =>
+            {
+                dirs.sbindir
+                    .components()
+                    .chain(components)
+                    .collect::<PathBuf>()
+            }
+            Some(Component::Normal(s))
+                if s == OsStr::new("<libdir>")
+                    || s == OsStr::new("@libdir@")
+                    || s == OsStr::new("${libdir}") =>
+            {
+                dirs.libdir
+                    .components()
+                    .chain(components)
+                    .collect::<PathBuf>()
+            }
+            Some(Component::Normal(s))
+                if s == OsStr::new("<libexecdir>")
+                    || s == OsStr::new("@libexecdir@")
+                    || s == OsStr::new("${libexecdir}") =>
+            {
+                dirs.libexecdir
+                    .components()
+                    .chain(components)
+                    .collect::<PathBuf>()
+            }
+            Some(Component::Normal(s))
+                if s == OsStr::new("<includedir>")
+                    || s == OsStr::new("@includedir@")
+                    || s == OsStr::new("${includedir}") =>
+            {
+                dirs.includedir
+                    .components()
+                    .chain(components)
+                    .collect::<PathBuf>()
+            }
+            Some(Component::Normal(s))
+                if s == OsStr::new("<datarootdir>")
+                    || s == OsStr::new("@datarootdir@")
+                    || s == OsStr::new("${datarootdir}") =>
+            {
+                dirs.datarootdir
+                    .components()
+                    .chain(components)
+                    .collect::<PathBuf>()
+            }
+            Some(Component::Normal(s))
+                if s == OsStr::new("<datadir>")
+                    || s == OsStr::new("@datadir@")
+                    || s == OsStr::new("${datadir}") =>
+            {
+                dirs.datadir
+                    .components()
+                    .chain(components)
+                    .collect::<PathBuf>()
+            }
+            Some(Component::Normal(s))
+                if s == OsStr::new("<mandir>")
+                    || s == OsStr::new("@mandir@")
+                    || s == OsStr::new("${mandir}") =>
+            {
+                dirs.mandir
+                    .components()
+                    .chain(components)
+                    .collect::<PathBuf>()
+            }
+            Some(Component::Normal(s))
+                if s == OsStr::new("<infodir>")
+                    || s == OsStr::new("@infodir@")
+                    || s == OsStr::new("${infodir}") =>
+            {
+                dirs.infodir
+                    .components()
+                    .chain(components)
+                    .collect::<PathBuf>()
+            }
+            Some(Component::Normal(s))
+                if s == OsStr::new("<docdir>")
+                    || s == OsStr::new("@docdir@")
+                    || s == OsStr::new("${docdir}") =>
+            {
+                dirs.docdir
+                    .components()
+                    .chain(components)
+                    .collect::<PathBuf>()
+            }
+            Some(Component::Normal(s))
+                if s == OsStr::new("<localedir>")
+                    || s == OsStr::new("@localedir@")
+                    || s == OsStr::new("${localedir}") =>
+            {
+                dirs.localedir
+                    .components()
+                    .chain(components)
+                    .collect::<PathBuf>()
+            }
+            Some(Component::Normal(s))
+                if s == OsStr::new("<localstatedir>")
+                    || s == OsStr::new("@localstatedir@")
+                    || s == OsStr::new("${localstatedir}") =>
+            {
+                dirs.localstatedir
+                    .components()
+                    .chain(components)
+                    .collect::<PathBuf>()
+            }
+            Some(Component::Normal(s))
+                if s == OsStr::new("<sharedstatedir>")
+                    || s == OsStr::new("@sharedstatedir@")
+                    || s == OsStr::new("${sharedstatedir}") =>
+            {
+                dirs.sharedstatedir
+                    .components()
+                    .chain(components)
+                    .collect::<PathBuf>()
+            }
+            Some(Component::Normal(s))
+                if s == OsStr::new("<sysconfdir>")
+                    || s == OsStr::new("@sysconfdir@")
+                    || s == OsStr::new("${sysconfdir}") =>
+            {
+                dirs.sysconfdir
+                    .components()
+                    .chain(components)
+                    .collect::<PathBuf>()
+            }
+            Some(c) => primary
+                .components()
+                .chain(std::iter::once(c))
+                .chain(components)
+                .collect::<PathBuf>(),
+            None => primary.to_owned(),
+        }
+    }
+}
+
+///
+/// Lexically resolves `.` and `..` components of `path`, without consulting the filesystem.
+/// `..` components that would go above the root (or the start of a relative path) are dropped
+pub fn normalize_path(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if let Some(Component::Normal(_)) = out.components().next_back() {
+                    out.pop();
+                }
+            }
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+///
+/// `path`, as it is given to an external program such as `install` or `strip`.
+/// A relative path which starts with `-` is written as `./-name`, so it is not taken for an option
+pub fn path_arg(path: &Path) -> Cow<'_, OsStr> {
+    match path.components().next() {
+        Some(Component::Normal(first)) if first.as_encoded_bytes().starts_with(b"-") => {
+            Cow::Owned(Path::new(".").join(path).into_os_string())
+        }
+        _ => Cow::Borrowed(path.as_os_str()),
+    }
+}
+
+///
+/// Checks that `path` (which should be normalized) is inside one of the installation directories
+pub fn is_within_install_dirs(path: &Path, dirs: &InstallDirs) -> bool {
+    dirs.as_env()
+        .into_iter()
+        .any(|(_, dir)| path.starts_with(normalize_path(dir)))
+}
+
+///
+/// The file mode creation mask of this process, as last set by [`set_umask`], or `u32::MAX` until it is first read.
+/// The mask can only be read by changing it, which would race with other threads creating files,
+/// so it is read once by [`parse`] and [`run`], before any threads are started
+#[cfg(unix)]
+static UMASK: AtomicU32 = AtomicU32::new(u32::MAX);
+
+///
+/// Sets the file mode creation mask of this process, returning the previous mask
+#[cfg(unix)]
+pub fn set_umask(mask: u32) -> u32 {
+    // SAFETY: umask cannot fail, and only affects this process (and its children)
+    let old = unsafe { libc::umask(mask as libc::mode_t) as u32 };
+    UMASK.store(mask, Ordering::Relaxed);
+    old
+}
+
+#[cfg(unix)]
+pub fn current_umask() -> u32 {
+    match UMASK.load(Ordering::Relaxed) {
+        u32::MAX => {
+            let umask = set_umask(0);
+            set_umask(umask);
+            umask
+        }
+        umask => umask,
+    }
+}
+
+///
+/// Applies `mode`, a chmod mode, to `target`. `exec` controls whether `X` sets the executable bits
+pub fn set_permissions<P1: AsRef<Path>>(target: P1, mode: &str, exec: bool) -> Result<(), Error> {
+    apply_mode(&NativeFilesystem, target.as_ref(), mode, exec)
+}
+
+fn apply_mode(
+    #[allow(unused_variables)] fs: &dyn Filesystem,
+    #[allow(unused_variables)] target: &Path,
+    #[allow(unused_variables)] mode: &str,
+    #[allow(unused_variables)] exec: bool,
+) -> Result<(), Error> {
+    #[cfg(unix)]
+    {
+        let mode = parse_mode(
+            mode,
+            fs.mode(target)?,
+            current_umask(),
+            fs.is_dir(target),
+            exec,
+        )?;
+        fs.set_mode(target, mode)?;
+    }
+    Ok(())
+}
+
+///
+/// Computes the bits which the chmod mode `mode` gives a file with the bits `dest_mode`, under `umask`.
+/// `dir` is whether the file is a directory, and `exec` controls whether `X` sets the executable bits
+#[cfg(unix)]
+fn parse_mode(mode: &str, dest_mode: u32, umask: u32, dir: bool, exec: bool) -> Result<u32, Error> {
+    let invalid = || Error::InvalidMode(mode.to_owned());
+    let octal = |s: &str| u32::from_str_radix(s, 8).map_err(|_| invalid());
+    Ok(if mode.starts_with(|c: char| c.is_digit(8)) {
+        octal(mode)? & !umask
+    } else if mode.starts_with('=') && mode[1..].starts_with(|c: char| c.is_digit(8)) {
+        octal(&mode[1..])?
+    } else if mode.starts_with('+') && mode[1..].starts_with(|c: char| c.is_digit(8)) {
+        octal(&mode[1..])? | dest_mode
+    } else if mode.starts_with('-') && mode[1..].starts_with(|c: char| c.is_digit(8)) {
+        dest_mode & !octal(&mode[1..])?
+    } else {
+        let mut mode_bits = dest_mode;
+        for s in mode.split(",") {
+            let mut chars = s.chars();
+            let mut type_mask = 0;
+            let mut cmode = 0;
+            let mut modifier = ' '; // Not valid
+            for c in chars.by_ref() {
+                if c == '=' || c == '+' || c == '-' {
+                    modifier = c;
+                    break;
+                }
+                match c {
+                    'u' => type_mask |= 0o4700,
+                    'g' => type_mask |= 0o2070,
+                    'o' => type_mask |= 0o1007,
+                    'a' => type_mask |= 0o7777,
+                    _ => return Err(invalid()),
+                }
+            }
+            // With no class, the bits are masked by the umask, but `=` still clears every class
+            let mut clear_mask = type_mask;
+            if type_mask == 0 {
+                type_mask = 0o7777 & !umask;
+                clear_mask = 0o7777;
+            }
+            for c in chars {
+                match c {
+                    'r' => cmode |= 0o444,
+                    'w' => cmode |= 0o222,
+                    'x' => cmode |= 0o111,
+                    'X' => {
+                        cmode |= if mode_bits & 0o111 != 0 || exec {
+                            0o111
+                        } else {
+                            0
+                        }
+                    }
+                    's' => cmode |= 0o6000,
+                    't' => cmode |= 0o1000,
+                    _ => return Err(invalid()),
+                }
+            }
+
+            match modifier {
+                '=' => {
+                    // Bits of other classes are left alone, as is the setgid bit of directories
+                    let keep = if dir {
+                        !clear_mask | 0o2000
+                    } else {
+                        !clear_mask
+                    };
+                    mode_bits = (mode_bits & keep) | (cmode & type_mask)
+                }
+                '+' => mode_bits |= cmode & type_mask,
+                '-' => mode_bits &= !(cmode & type_mask),
+                _ => return Err(invalid()),
+            }
+        }
+        mode_bits
+    })
+}
+
+///
+/// Creates a symbolic link at `dest` which refers to `src`, replacing any existing link at `dest`.
+/// On a filesystem without symbolic links, `src` is copied to `dest` instead
+pub fn create_alias<P1: AsRef<Path>, P2: AsRef<Path>>(
+    src: P1,
+    dest: P2,
+    opts: &Options,
+    dir: bool,
+) -> std::io::Result<()> {
+    if opts.dry_run {
+        Ok(())
+    } else if !fscaps::links(dest.as_ref(), opts, dir) {
+        // A relative `src` refers to a path relative to the directory containing the link
+        let src = match dest.as_ref().parent() {
+            Some(parent) => parent.join(src.as_ref()),
+            None => src.as_ref().to_path_buf(),
+        };
+        copy_alias(&src, dest.as_ref(), opts)
+    } else {
+        opts.fs().symlink(src.as_ref(), dest.as_ref(), dir)
+    }
+}
+
+///
+/// Copies `src` to the alias `dest`, for a filesystem without symbolic links. The files of a directory are copied one by one, into directories which are created
+pub(crate) fn copy_alias(src: &Path, dest: &Path, opts: &Options) -> std::io::Result<()> {
+    if src.is_dir() {
+        opts.fs().create_dir_all(dest)?;
+        for (src_item, dest_item) in tree_entries(src, dest, 0)? {
+            copy_alias(&src_item, &dest_item, opts)?;
+        }
+        return Ok(());
+    }
+    output::verbose(
+        opts,
+        format_args!("Copying {} to {}", src.display(), dest.display()),
+    );
+    opts.fs().copy_file(src, dest, opts).map(drop)
+}
+
+///
+/// Links `dest` to the artifact `src`, for `--develop`, replacing the file or link at `dest`
+fn develop_link(src: &Path, dest: &Path, opts: &Options, dir: bool) -> std::io::Result<()> {
+    let src = fs::canonicalize(src)?;
+    output::verbose(
+        opts,
+        format_args!("Linking {} to {}", dest.display(), src.display()),
+    );
+    if let Some(parent) = dest.parent().filter(|_| !opts.no_create_dirs) {
+        opts.fs().create_dir_all(parent)?;
+    }
+    match fs::symlink_metadata(dest) {
+        Ok(md) if md.is_dir() => {
+            return Err(std::io::Error::other(format!(
+                "{} is a directory, remove it first",
+                dest.display()
+            )))
+        }
+        Ok(md) if !md.file_type().is_symlink() => fs::remove_file(dest)?,
+        _ => {}
+    }
+    opts.fs().symlink(&src, dest, dir)
+}
+
+///
+/// Links `dest` to the artifact `src` with hard links, for `install-method = "hardlink"`, replacing the file at `dest`.
+/// The files of a directory are linked one by one, with the first `strip` components of their paths removed, into directories which are created.
+/// A file on another filesystem than `dest`, which cannot be linked to, is copied instead
+fn hard_link_tree(src: &Path, dest: &Path, strip: usize, opts: &Options) -> std::io::Result<()> {
+    if src.is_dir() {
+        fs::create_dir_all(dest)?;
+        for (src_item, dest_item) in tree_entries(src, dest, strip)? {
+            hard_link_tree(&src_item, &dest_item, 0, opts)?;
+        }
+        return Ok(());
+    }
+    output::verbose(
+        opts,
+        format_args!("Linking {} to {}", dest.display(), src.display()),
+    );
+    if let Some(parent) = dest.parent().filter(|_| !opts.no_create_dirs) {
+        fs::create_dir_all(parent)?;
+    }
+    match fs::symlink_metadata(dest) {
+        Ok(md) if md.is_dir() => {
+            return Err(std::io::Error::other(format!(
+                "{} is a directory, remove it first",
+                dest.display()
+            )))
+        }
+        Ok(_) => fs::remove_file(dest)?,
+        Err(_) => {}
+    }
+    match fs::hard_link(src, dest) {
+        Err(e) if e.kind() == ErrorKind::CrossesDevices => {
+            output::status(
+                opts,
+                Status::Warning,
+                format_args!(
+                    "Warning: copying {} to {}, as they are on different filesystems, so it cannot be hard linked",
+                    src.display(),
+                    dest.display()
+                ),
+            );
+            copy::copy_file(src, dest, opts).map(drop)
+        }
+        result => result,
+    }
+}
+
+///
+/// Removes `dest` if it is a link to `src` made by `--develop` or `install-method`, so that installing `src` does not write through it, onto `src` itself.
+/// In a directory, each file which is a hard link to the file of `src` it would be replaced by is removed
+fn remove_artifact_links(src: &Path, dest: &Path, strip: usize) -> std::io::Result<()> {
+    let md = match fs::symlink_metadata(dest) {
+        Ok(md) => md,
+        Err(_) => return Ok(()),
+    };
+    if md.file_type().is_symlink() {
+        if fs::canonicalize(dest).ok() == fs::canonicalize(src).ok() {
+            fs::remove_file(dest)?;
+        }
+    } else if md.is_dir() {
+        if src.is_dir() {
+            for (src_item, dest_item) in tree_entries(src, dest, strip)? {
+                remove_artifact_links(&src_item, &dest_item, 0)?;
+            }
+        }
+    } else if is_same_file(src, &md) {
+        fs::remove_file(dest)?;
+    }
+    Ok(())
+}
+
+///
+/// Whether `src` is the file `md` was read from, because they are hard links to one another
+#[cfg(unix)]
+fn is_same_file(src: &Path, md: &fs::Metadata) -> bool {
+    fs::metadata(src).is_ok_and(|src| src.dev() == md.dev() && src.ino() == md.ino())
+}
+
+// Hard links are only told apart on unix-like targets
+#[cfg(not(unix))]
+fn is_same_file(_src: &Path, _md: &fs::Metadata) -> bool {
+    false
+}
+
+///
+/// An error which prevented a target from being installed.
+/// Errors are reported as they occur, so the caller of [`install_target`] need not report them again
+#[derive(Clone, Debug)]
+pub struct InstallError {
+    message: String,
+    exit_code: ExitCode,
+}
+
+impl InstallError {
+    pub(crate) fn new(message: String) -> Self {
+        Self {
+            message,
+            exit_code: ExitCode::Failure,
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    ///
+    /// The class of the failure, which the program exits with if installation stops because of it
+    pub fn exit_code(&self) -> ExitCode {
+        self.exit_code
+    }
+}
+
+impl Display for InstallError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+///
+/// Reports that the current target failed to install, and returns the corresponding error
+fn fail(opts: &Options, args: std::fmt::Arguments) -> InstallError {
+    fail_with(opts, ExitCode::Failure, args)
+}
+
+///
+/// Reports that the current target failed to install, for a reason described by `exit_code`, and returns the corresponding error
+fn fail_with(opts: &Options, exit_code: ExitCode, args: std::fmt::Arguments) -> InstallError {
+    output::status(opts, Status::Failed, args);
+    InstallError {
+        message: args.to_string(),
+        exit_code,
+    }
+}
+
+impl std::error::Error for InstallError {}
+
+pub(crate) fn do_internal_install<P1: AsRef<Path>, P2: AsRef<Path>>(
+    src: Option<P1>,
+    dest: P2,
+    opts: &Options,
+    target: &Target,
+    mode: &Option<String>,
+    progress: &mut Progress,
+) -> Result<(), Error> {
+    install_entry(
+        src,
+        dest.as_ref(),
+        dest.as_ref(),
+        opts,
+        target,
+        mode,
+        progress,
+        target.strip_components.unwrap_or(0),
+    )
+}
+
+///
+/// The entries of the directory `src` which are installed directly into `dest`, with their destinations, after removing the first `strip` components of their paths,
+/// as with `tar --strip-components`. Entries with no more than `strip` components are not installed
+pub fn tree_entries(
+    src: &Path,
+    dest: &Path,
+    strip: usize,
+) -> std::io::Result<Vec<(PathBuf, PathBuf)>> {
+    let mut entries = Vec::new();
+    for entry in fs::read_dir(src)? {
+        let path = entry?.path();
+        if strip == 0 {
+            let dest_item = dest.join(path.file_name().unwrap());
+            entries.push((path, dest_item));
+        } else if path.is_dir() {
+            entries.extend(tree_entries(&path, dest, strip - 1)?);
+        }
+    }
+    Ok(entries)
+}
+
+///
+/// Installs `src`, an entry of a target installed to `root`, to `dest`. The contents of a directory are installed with the first `strip` components of their paths removed
+#[allow(clippy::too_many_arguments)]
+fn install_entry<P1: AsRef<Path>, P2: AsRef<Path>>(
+    src: Option<P1>,
+    dest: P2,
+    root: &Path,
+    opts: &Options,
+    target: &Target,
+    mode: &Option<String>,
+    progress: &mut Progress,
+    strip: usize,
+) -> Result<(), Error> {
+    if !opts.dry_run {
+        let is_dir = target.directory && src.as_ref().is_none_or(|src| src.as_ref().is_dir());
+        let relative = dest.as_ref().strip_prefix(root).unwrap_or(dest.as_ref());
+        let rule = pattern_mode(target, relative).filter(|_| target.directory && !is_dir);
+        let entry = match (target.directory, rule) {
+            (true, Some(rule)) => with_cli_mode(Some(rule), opts),
+            (true, None) => entry_mode(target, is_dir, opts),
+            (false, _) => mode.clone(),
+        };
+        let modes = fscaps::modes(dest.as_ref(), opts);
+        // With `file-mode` or a rule of `modes`, `X` only applies to files which are already executable, as with chmod
+        let exec = target.type_ == Some(TargetType::Bin)
+            || target.type_ == Some(TargetType::SBin)
+            || (target.directory && (is_dir || (target.file_mode.is_none() && rule.is_none())));
+        // Only files are skipped, as adding a file to a subdirectory does not make the directory newer
+        if !opts.force && !is_dir {
+            let src_time = src
+                .as_ref()
+                .map(|src| metadata(src).and_then(|m| m.modified()))
+                .transpose();
+            let dest_time = opts.fs().modified(dest.as_ref());
+            if let (Ok(Some(src_time)), Ok(dest_time)) = (src_time, dest_time) {
+                if src_time < dest_time {
+                    output::verbose(
+                        opts,
+                        format_args!("Skipping {}, destination is newer", dest.as_ref().display()),
+                    );
+                    if !opts.fs().is_dir(dest.as_ref()) {
+                        progress.file_copied(0);
+                    }
+                    // The mode may have changed since the destination was installed
+                    if let Some(mode) = entry.as_ref().filter(|_| modes) {
+                        apply_mode(&opts.fs(), dest.as_ref(), mode, exec)?;
+                    }
+                    return Ok(());
+                }
+            }
+        }
+        if is_dir {
+            if !opts.no_create_dirs && !opts.fs().is_dir(dest.as_ref()) {
+                output::verbose(
+                    opts,
+                    format_args!("Creating directory '{}'", dest.as_ref().display()),
+                );
+                opts.fs().create_dir_all(dest.as_ref())?;
+            }
+            if let Some(src) = &src {
+                for (src_item, dest_item) in tree_entries(src.as_ref(), dest.as_ref(), strip)? {
+                    install_entry(
+                        Some(src_item),
+                        dest_item,
+                        root,
+                        opts,
+                        target,
+                        mode,
+                        progress,
+                        0,
+                    )?;
+                }
+            }
+        } else if let Some(src) = &src {
+            output::verbose(
+                opts,
+                format_args!(
+                    "'{}' -> '{}'",
+                    src.as_ref().display(),
+                    dest.as_ref().display()
+                ),
+            );
+            // A file target may be installed to a directory which does not exist yet, as with `install -D`
+            if let Some(parent) = dest.as_ref().parent().filter(|_| !opts.no_create_dirs) {
+                if !opts.fs().is_dir(parent) {
+                    opts.fs().create_dir_all(parent)?;
+                }
+            }
+            let bytes = opts.fs().copy_file(src.as_ref(), dest.as_ref(), opts)?;
+            progress.file_copied(bytes);
+            events::emit(
+                opts,
+                Event::FileCopied {
+                    path: dest.as_ref(),
+                    bytes,
+                },
+            );
+            // The copy is given the permissions of the source, ignoring the umask
+            #[cfg(unix)]
+            if entry.is_none() && modes {
+                let umask = current_umask();
+                let mode = opts.fs().mode(dest.as_ref())?;
+                opts.fs().set_mode(dest.as_ref(), mode & !umask)?;
+            }
+        } else {
+            return Err(std::io::Error::new(
+                ErrorKind::NotFound,
+                "no source file given, but one is expected",
+            )
+            .into());
+        }
+
+        // Changing the owner may clear the setuid and setgid bits, so this must happen before applying the mode
+        let (owner, group) = (opts.owner, opts.group);
+        opts.fs().chown(dest.as_ref(), owner, group)?;
+
+        if let Some(mode) = entry.as_ref().filter(|_| modes) {
+            apply_mode(&opts.fs(), dest.as_ref(), mode, exec)?;
+        }
+
+        if let Some(src) = &src {
+            apply_timestamp(src.as_ref(), dest.as_ref(), opts)?;
+        }
+
+        copy::sync(dest.as_ref(), opts)?;
+
+        Ok(())
+    } else {
+        Ok(())
+    }
+}
+
+///
+/// Applies the owner, group, and mode of `target` to the already installed file `dest`, without copying it again
+fn update_installed(
+    dest: &Path,
+    opts: &Options,
+    target: &Target,
+    mode: &Option<String>,
+) -> Result<(), Error> {
+    let (owner, group) = (opts.owner, opts.group);
+    opts.fs().chown(dest, owner, group)?;
+    if let Some(mode) = mode.as_ref().filter(|_| fscaps::modes(dest, opts)) {
+        apply_mode(
+            &opts.fs(),
+            dest,
+            mode,
+            target.type_ == Some(TargetType::Bin) || target.type_ == Some(TargetType::SBin),
+        )?;
+    }
+    copy::sync(dest, opts)?;
+    Ok(())
+}
+
+///
+/// Sets the modification time of the installed file `dest` according to `--preserve-timestamps` or `--clamp-timestamps`
+pub fn apply_timestamp(src: &Path, dest: &Path, opts: &Options) -> std::io::Result<()> {
+    let time = match opts.timestamps {
+        Timestamps::Current => return Ok(()),
+        Timestamps::Preserve => metadata(src)?.modified()?,
+        Timestamps::Clamp(max) => metadata(src)?.modified()?.min(max),
+    };
+    opts.fs().set_modified(dest, time)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::parse_mode;
+
+    #[test]
+    fn octal_modes() {
+        assert_eq!(
+            parse_mode("755", 0o600, 0o022, false, false).unwrap(),
+            0o755
+        );
+        assert_eq!(
+            parse_mode("777", 0o600, 0o022, false, false).unwrap(),
+            0o755
+        );
+        assert_eq!(
+            parse_mode("=777", 0o600, 0o022, false, false).unwrap(),
+            0o777
+        );
+        assert_eq!(
+            parse_mode("+011", 0o600, 0o022, false, false).unwrap(),
+            0o611
+        );
+        assert_eq!(
+            parse_mode("-044", 0o644, 0o022, false, false).unwrap(),
+            0o600
+        );
+    }
+
+    #[test]
+    fn assign_without_class_applies_umask() {
+        assert_eq!(
+            parse_mode("=rw", 0o777, 0o022, false, false).unwrap(),
+            0o644
+        );
+        assert_eq!(
+            parse_mode("=rwx", 0o600, 0o077, false, false).unwrap(),
+            0o700
+        );
+        assert_eq!(parse_mode("=", 0o4755, 0o022, false, false).unwrap(), 0);
+    }
+
+    #[test]
+    fn assign_class_leaves_other_classes() {
+        assert_eq!(
+            parse_mode("g=rx", 0o640, 0o022, false, false).unwrap(),
+            0o650
+        );
+        assert_eq!(
+            parse_mode("u=rwx,go=r", 0o600, 0o022, false, false).unwrap(),
+            0o744
+        );
+        assert_eq!(
+            parse_mode("a=r", 0o777, 0o022, false, false).unwrap(),
+            0o444
+        );
+    }
+
+    #[test]
+    fn assign_clears_setgid_of_files_only() {
+        assert_eq!(
+            parse_mode("g=rx", 0o2755, 0o022, false, false).unwrap(),
+            0o755
+        );
+        assert_eq!(
+            parse_mode("g=rx", 0o2755, 0o022, true, false).unwrap(),
+            0o2755
+        );
+        assert_eq!(
+            parse_mode("g=rxs", 0o755, 0o022, false, false).unwrap(),
+            0o2755
+        );
+    }
+
+    #[test]
+    fn add_and_remove() {
+        assert_eq!(parse_mode("+x", 0o644, 0o022, false, false).unwrap(), 0o755);
+        assert_eq!(parse_mode("+w", 0o444, 0o022, false, false).unwrap(), 0o644);
+        assert_eq!(
+            parse_mode("o-r", 0o644, 0o022, false, false).unwrap(),
+            0o640
+        );
+        assert_eq!(
+            parse_mode("u+s", 0o755, 0o022, false, false).unwrap(),
+            0o4755
+        );
+    }
+
+    #[test]
+    fn conditional_execute() {
+        assert_eq!(
+            parse_mode("a+X", 0o644, 0o022, false, false).unwrap(),
+            0o644
+        );
+        assert_eq!(parse_mode("a+X", 0o644, 0o022, false, true).unwrap(), 0o755);
+        assert_eq!(
+            parse_mode("a+X", 0o744, 0o022, false, false).unwrap(),
+            0o755
+        );
+    }
+
+    #[test]
+    fn invalid_modes() {
+        assert!(parse_mode("z+x", 0o644, 0o022, false, false).is_err());
+        assert!(parse_mode("u+q", 0o644, 0o022, false, false).is_err());
+        assert!(parse_mode("u", 0o644, 0o022, false, false).is_err());
+        assert!(parse_mode("9", 0o644, 0o022, false, false).is_err());
+    }
+}
@@ -0,0 +1,269 @@
+use std::{
+    collections::{BTreeSet, HashMap, VecDeque},
+    fmt::Write,
+    fs,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use cargo_toml::Manifest;
+use serde_json::Value;
+
+use crate::{
+    manifest::{Generated, NativeInstallMetadata, Target, TargetType},
+    output,
+    output::Status,
+    privs, Error, Options, DEFAULT_TARGET,
+};
+
+/// The file names, in upper case, that license texts begin with
+const LICENSE_FILES: &[&str] = &["LICENSE", "LICENCE", "COPYING", "NOTICE", "UNLICENSE"];
+
+///
+/// With `--third-party-licenses`, adds the target `<package>-third-party-licenses`, which installs the licenses of the dependencies of the package in `manifest`
+/// to `<docdir>/<package>/THIRD_PARTY_LICENSES`, unless a target with that name exists
+pub fn add_target(
+    manifest: &Manifest<NativeInstallMetadata>,
+    targets: &mut HashMap<String, Target>,
+    opts: &Options,
+    manifest_dir: &Path,
+) {
+    let package = match &manifest.package {
+        Some(package) if opts.third_party_licenses => package,
+        _ => return,
+    };
+    let mut src = opts
+        .out_dir
+        .clone()
+        .unwrap_or_else(|| manifest_dir.join("target"));
+    src.push("cargo-native-install");
+    src.push(&package.name);
+    src.push("THIRD_PARTY_LICENSES");
+    targets
+        .entry(format!("{}-third-party-licenses", package.name))
+        .or_insert_with(|| Target {
+            type_: Some(TargetType::Doc),
+            mode: Some("u=rw,g=r,o=r".to_string()),
+            installed_path: Some(Path::new(&package.name).join("THIRD_PARTY_LICENSES")),
+            target_file: Some(src),
+            generated: Some(Generated::ThirdPartyLicenses(package.name.clone())),
+            ..Default::default()
+        });
+}
+
+///
+/// The packages that `root` is built from, other than itself and the members of its workspace, in the resolve graph of `cargo metadata`.
+/// Only normal dependencies are followed, as build scripts and development dependencies are not part of what is installed
+fn dependencies<'a>(metadata: &'a Value, root: &str) -> Vec<&'a Value> {
+    let str_at = |value: &'a Value, key: &str| value.get(key).and_then(Value::as_str);
+    let array_at = |value: &'a Value, key: &str| {
+        value
+            .get(key)
+            .and_then(Value::as_array)
+            .map_or(&[][..], Vec::as_slice)
+    };
+    let members = array_at(metadata, "workspace_members")
+        .iter()
+        .filter_map(Value::as_str)
+        .collect::<BTreeSet<_>>();
+    let packages = array_at(metadata, "packages")
+        .iter()
+        .filter_map(|package| Some((str_at(package, "id")?, package)))
+        .collect::<HashMap<_, _>>();
+    let nodes = metadata
+        .get("resolve")
+        .map_or(&[][..], |resolve| array_at(resolve, "nodes"))
+        .iter()
+        .filter_map(|node| Some((str_at(node, "id")?, node)))
+        .collect::<HashMap<_, _>>();
+
+    let root = match members.iter().find(|id| {
+        packages
+            .get(*id)
+            .and_then(|package| str_at(package, "name"))
+            == Some(root)
+    }) {
+        Some(root) => *root,
+        None => return Vec::new(),
+    };
+    let mut seen = BTreeSet::from([root]);
+    let mut pending = VecDeque::from([root]);
+    while let Some(id) = pending.pop_front() {
+        for dep in nodes.get(id).map_or(&[][..], |node| array_at(node, "deps")) {
+            let normal = array_at(dep, "dep_kinds")
+                .iter()
+                .any(|kind| kind.get("kind").is_none_or(Value::is_null));
+            match str_at(dep, "pkg") {
+                Some(pkg) if normal && seen.insert(pkg) => pending.push_back(pkg),
+                _ => {}
+            }
+        }
+    }
+
+    let mut deps = seen
+        .into_iter()
+        .filter(|id| !members.contains(id))
+        .filter_map(|id| packages.get(id).copied())
+        .collect::<Vec<_>>();
+    deps.sort_by_key(|package| (str_at(package, "name"), str_at(package, "version")));
+    deps
+}
+
+///
+/// The license texts of `package`, a package of `cargo metadata`: its `license-file`, and the files in its directory named like a license
+fn license_texts(package: &Value) -> Vec<PathBuf> {
+    let dir = match package
+        .get("manifest_path")
+        .and_then(Value::as_str)
+        .and_then(|path| Path::new(path).parent())
+    {
+        Some(dir) => dir,
+        None => return Vec::new(),
+    };
+    let mut texts = package
+        .get("license_file")
+        .and_then(Value::as_str)
+        .map(|file| dir.join(file))
+        .into_iter()
+        .collect::<Vec<_>>();
+    let mut files = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|entry| entry.file_type().is_ok_and(|ty| ty.is_file()))
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| {
+                    let name = name.to_ascii_uppercase();
+                    LICENSE_FILES.iter().any(|prefix| name.starts_with(prefix))
+                })
+        })
+        .collect::<Vec<_>>();
+    files.sort();
+    for file in files {
+        if !texts
+            .iter()
+            .any(|text| text.canonicalize().ok() == file.canonicalize().ok())
+        {
+            texts.push(file);
+        }
+    }
+    texts
+}
+
+///
+/// The document listing each dependency of `package` with its license, followed by its license texts
+fn document(metadata: &Value, package: &str) -> (String, Vec<String>) {
+    let mut out = format!(
+        "The following software is included in {}, under the licenses given for each.\n",
+        package
+    );
+    let mut missing = Vec::new();
+    for dep in dependencies(metadata, package) {
+        let field = |key: &str| dep.get(key).and_then(Value::as_str).unwrap_or("");
+        let _ = write!(
+            out,
+            "\n{}\n{} {}\nLicense: {}\n",
+            "=".repeat(80),
+            field("name"),
+            field("version"),
+            match field("license") {
+                "" => "not specified",
+                license => license,
+            }
+        );
+        if !field("repository").is_empty() {
+            let _ = writeln!(out, "Repository: {}", field("repository"));
+        }
+        let texts = license_texts(dep);
+        if texts.is_empty() {
+            missing.push(field("name").to_owned());
+        }
+        for text in texts {
+            let contents = fs::read(&text).unwrap_or_default();
+            let _ = write!(
+                out,
+                "\n{}\n{}\n\n{}",
+                "-".repeat(80),
+                text.file_name().unwrap_or_default().to_string_lossy(),
+                String::from_utf8_lossy(&contents)
+            );
+            if !out.ends_with('\n') {
+                out.push('\n');
+            }
+        }
+    }
+    (out, missing)
+}
+
+///
+/// Writes the file installed by each target added by [`add_target`], from the licenses of the dependencies that `cargo metadata` finds for the target triple.
+/// The file is only rewritten when its contents change, so that the target is skipped by the install receipt otherwise
+pub fn generate(
+    targets: &HashMap<String, Target>,
+    manifest_dir: &Path,
+    opts: &Options,
+) -> Result<(), Error> {
+    let mut metadata = None;
+    for target in targets.values().filter(|target| !target.exclude) {
+        let (package, dest) = match (&target.generated, &target.target_file) {
+            (Some(Generated::ThirdPartyLicenses(package)), Some(dest)) => (package, dest),
+            _ => continue,
+        };
+        let metadata = match &metadata {
+            Some(metadata) => metadata,
+            None => metadata.insert(cargo_metadata(manifest_dir, opts)?),
+        };
+
+        output::verbose(
+            opts,
+            format_args!("Collecting the licenses of the dependencies of {}", package),
+        );
+        let (contents, missing) = document(metadata, package);
+        if !missing.is_empty() {
+            output::status(
+                opts,
+                Status::Warning,
+                format_args!(
+                    "Warning: no license text was found for {} dependenc{} of {}: {}",
+                    missing.len(),
+                    if missing.len() == 1 { "y" } else { "ies" },
+                    package,
+                    missing.join(", ")
+                ),
+            );
+        }
+        if fs::read(dest).ok().as_deref() != Some(contents.as_bytes()) {
+            if let Some(parent) = dest.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(dest, contents)?;
+        }
+    }
+    Ok(())
+}
+
+///
+/// Runs `cargo metadata` in `manifest_dir`, as the invoking user if this program was run with sudo, as it may update `Cargo.lock`
+fn cargo_metadata(manifest_dir: &Path, opts: &Options) -> Result<Value, Error> {
+    let mut cmd = Command::new("cargo");
+    cmd.current_dir(manifest_dir)
+        .args(["metadata", "--format-version", "1", "--filter-platform"])
+        .arg(DEFAULT_TARGET)
+        .stderr(Stdio::inherit());
+    if !opts.keep_privileges {
+        if let Some(user) = privs::invoking_user() {
+            privs::drop_privileges(&mut cmd, &user);
+        }
+    }
+    output::command(opts, &cmd);
+    let out = cmd
+        .output()
+        .map_err(|e| Error::Spawn(PathBuf::from("cargo"), e))?;
+    if !out.status.success() {
+        return Err(Error::Tool(PathBuf::from("cargo"), out.status));
+    }
+    serde_json::from_slice(&out.stdout).map_err(|e| Error::Io(e.into()))
+}
@@ -0,0 +1,111 @@
+use std::{
+    fs::{self, File, OpenOptions, TryLockError},
+    io::{Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{output, output::Status, Error, Options};
+
+/// How long to wait for another installation into the same prefix, unless `--lock-timeout` is given
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(300);
+
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+///
+/// An advisory lock on a prefix, which is released when it is dropped, or the process exits
+pub struct PrefixLock {
+    _file: File,
+}
+
+///
+/// The process which holds the lock in `file`, as it recorded when it took the lock
+fn holder(file: &mut File) -> Option<u32> {
+    let mut pid = String::new();
+    file.seek(SeekFrom::Start(0)).ok()?;
+    file.read_to_string(&mut pid).ok()?;
+    pid.trim().parse().ok()
+}
+
+///
+/// Locks `<prefix>/.cargo-native-install.lock`, so that installations into the same prefix do not interleave their changes,
+/// waiting up to `--lock-timeout` for another installation to finish.
+/// Returns `None` if nothing is written to the prefix on this machine, or, with a warning, if the lock file cannot be created (as when only some directories of the prefix are writable)
+pub fn lock(prefix: &Path, opts: &Options) -> Result<Option<PrefixLock>, Error> {
+    // With `--dist`, the prefix is staged in a directory of this process, which would pack the lock file
+    if opts.dry_run || opts.filesystem.is_some() || opts.dist.is_some() {
+        return Ok(None);
+    }
+    let path = path(prefix);
+    let file = fs::create_dir_all(prefix).and_then(|()| {
+        OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)
+    });
+    let mut file = match file {
+        Ok(file) => file,
+        Err(e) => {
+            output::status(
+                opts,
+                Status::Warning,
+                format_args!(
+                    "Warning: not locking {}, {}, so another installation into the prefix may interleave with this one",
+                    path.display(),
+                    e
+                ),
+            );
+            return Ok(None);
+        }
+    };
+
+    let timeout = opts.lock_timeout.unwrap_or(DEFAULT_TIMEOUT);
+    let start = Instant::now();
+    let mut waiting = false;
+    loop {
+        match file.try_lock() {
+            Ok(()) => break,
+            Err(TryLockError::WouldBlock) => {}
+            Err(TryLockError::Error(e)) => return Err(Error::Lock(path, e.to_string())),
+        }
+        let pid = holder(&mut file);
+        if start.elapsed() >= timeout {
+            return Err(Error::Lock(
+                path,
+                match pid {
+                    Some(pid) => format!("it is held by process {}", pid),
+                    None => "it is held by another process".to_owned(),
+                },
+            ));
+        }
+        if !waiting {
+            waiting = true;
+            output::status(
+                opts,
+                Status::Skipped,
+                format_args!(
+                    "Waiting for another installation into {}{} to finish",
+                    prefix.display(),
+                    pid.map_or_else(String::new, |pid| format!(" (process {})", pid))
+                ),
+            );
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+
+    // The process that holds the lock is recorded for the error of the next installation which waits for it
+    let _ = file
+        .set_len(0)
+        .and_then(|()| file.seek(SeekFrom::Start(0)))
+        .and_then(|_| writeln!(file, "{}", std::process::id()));
+    Ok(Some(PrefixLock { _file: file }))
+}
+
+///
+/// The path of the lock file of `prefix`
+pub fn path(prefix: &Path) -> PathBuf {
+    prefix.join(".cargo-native-install.lock")
+}
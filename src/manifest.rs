@@ -1,10 +1,11 @@
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
+    convert::TryFrom,
     path::{Path, PathBuf},
 };
 
 use install_dirs::dirs::InstallDirs;
-use serde_derive::Deserialize;
+use serde_derive::{Deserialize, Serialize};
 
 use crate::Options;
 
@@ -20,9 +21,12 @@ pub enum TargetType {
     Sysconfig,
     Data,
     Doc,
+    #[serde(rename = "html-doc")]
+    HtmlDoc,
     Man,
     Info,
     Run,
+    Check,
 }
 impl TargetType {
     pub fn get_install_root<'a>(&self, dirs: &'a InstallDirs, opts: &Options) -> Option<&'a Path> {
@@ -52,15 +56,133 @@ impl TargetType {
             TargetType::Include => Some(&*dirs.includedir),
             TargetType::Sysconfig => Some(&*dirs.sysconfdir),
             TargetType::Data => Some(&*dirs.datadir),
-            TargetType::Doc => Some(&*dirs.docdir),
+            TargetType::Doc | TargetType::HtmlDoc => Some(&*dirs.docdir),
             TargetType::Man => Some(&*dirs.mandir),
             TargetType::Info => Some(&*dirs.infodir),
-            TargetType::Run => None,
+            TargetType::Run | TargetType::Check => None,
         }
     }
 }
 
-#[derive(Deserialize, Debug, Default)]
+///
+/// What strip removes from a binary
+#[derive(Deserialize, Debug, PartialEq, Eq, Copy, Clone)]
+#[serde(rename_all = "lowercase")]
+pub enum StripMode {
+    /// Every symbol, and debug information (`-s`)
+    All,
+    /// Only debug information (`-S`)
+    Debug,
+    /// Symbols which are not needed to link against or load the binary (`--strip-unneeded`)
+    Unneeded,
+}
+
+impl StripMode {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "all" => Some(StripMode::All),
+            "debug" => Some(StripMode::Debug),
+            "unneeded" => Some(StripMode::Unneeded),
+            _ => None,
+        }
+    }
+
+    ///
+    /// The argument to strip which selects this mode
+    pub fn arg(self) -> &'static str {
+        match self {
+            StripMode::All => "-s",
+            StripMode::Debug => "-S",
+            StripMode::Unneeded => "--strip-unneeded",
+        }
+    }
+
+    ///
+    /// The long form of [`arg`](Self::arg), which objcopy also understands
+    pub fn long_arg(self) -> &'static str {
+        match self {
+            StripMode::All => "--strip-all",
+            StripMode::Debug => "--strip-debug",
+            StripMode::Unneeded => "--strip-unneeded",
+        }
+    }
+}
+
+///
+/// How the file of a target is put at its destination
+#[derive(Deserialize, Debug, PartialEq, Eq, Copy, Clone, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum InstallMethod {
+    /// The artifact is copied
+    #[default]
+    Copy,
+    /// The destination is a symbolic link to the artifact, as with `--develop`
+    Symlink,
+    /// The destination is a hard link to the artifact. The files of a directory are linked individually
+    Hardlink,
+}
+
+///
+/// How the run-time library search path of a binary is changed when it is installed, written as `set:<path>` or `remove`
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+#[serde(try_from = "String")]
+pub enum Rpath {
+    Set(String),
+    Remove,
+}
+
+impl TryFrom<String> for Rpath {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, String> {
+        if s == "remove" {
+            Ok(Rpath::Remove)
+        } else if let Some(path) = s.strip_prefix("set:") {
+            Ok(Rpath::Set(path.to_owned()))
+        } else {
+            Err(format!(
+                "invalid rpath `{}`, expected `set:<path>` or `remove`",
+                s
+            ))
+        }
+    }
+}
+
+///
+/// The template engine the file of a target is rendered with before it is installed
+#[derive(Deserialize, Debug, PartialEq, Eq, Copy, Clone)]
+#[serde(rename_all = "lowercase")]
+pub enum TemplateEngine {
+    /// MiniJinja, which implements most of Jinja2
+    Minijinja,
+}
+
+///
+/// The package a target belongs to, as templates see it
+#[derive(Serialize, Debug, Default, Clone, PartialEq, Eq)]
+pub struct TemplatePackage {
+    pub name: String,
+    pub version: String,
+    pub description: Option<String>,
+    pub authors: Vec<String>,
+    pub license: Option<String>,
+    pub homepage: Option<String>,
+    pub repository: Option<String>,
+    /// The features the package declares, with the features and dependencies each one enables
+    pub features: BTreeMap<String, Vec<String>>,
+}
+
+///
+/// The file of a target added by an option, which is produced just before installing
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Generated {
+    /// The source of the package, as `cargo package` includes it, for `--devel`
+    CrateSource(String),
+    /// The licenses of the dependencies of the package, for `--third-party-licenses`
+    ThirdPartyLicenses(String),
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
 #[serde(rename_all = "kebab-case")]
 pub struct Target {
     #[serde(default)]
@@ -75,6 +197,14 @@ pub struct Target {
     #[serde(default)]
     pub mode: Option<String>,
     #[serde(default)]
+    pub dir_mode: Option<String>,
+    #[serde(default)]
+    pub file_mode: Option<String>,
+    #[serde(default)]
+    pub modes: Option<BTreeMap<String, String>>,
+    #[serde(default)]
+    pub strip_components: Option<usize>,
+    #[serde(default)]
     pub installed_path: Option<PathBuf>,
     #[serde(default)]
     pub target_file: Option<PathBuf>,
@@ -83,9 +213,203 @@ pub struct Target {
     #[serde(default)]
     pub installed_aliases: Option<Vec<PathBuf>>,
     #[serde(default)]
+    pub install_method: Option<InstallMethod>,
+    #[serde(default)]
     pub exclude: bool,
     #[serde(default)]
+    pub optional: bool,
+    #[serde(default)]
+    pub only_if: Option<String>,
+    #[serde(default)]
     pub strip: Option<bool>,
+    #[serde(default)]
+    pub strip_mode: Option<StripMode>,
+    #[serde(default)]
+    pub strip_args: Option<Vec<String>>,
+    #[serde(default)]
+    pub strip_keep_symbols: Option<Vec<String>>,
+    #[serde(default)]
+    pub rpath: Option<Rpath>,
+    #[serde(default)]
+    pub post_install: Option<String>,
+    #[serde(default)]
+    pub pre_uninstall: Option<Vec<String>>,
+    #[serde(default)]
+    pub smoke_test: Option<Vec<String>>,
+    #[serde(default)]
+    pub success_codes: Option<Vec<i32>>,
+    #[serde(default)]
+    pub soft_fail_codes: Option<Vec<i32>>,
+    #[serde(default)]
+    pub skip_codes: Option<Vec<i32>>,
+    #[serde(default)]
+    pub index: Option<PathBuf>,
+    #[serde(default)]
+    pub doc_base: bool,
+    #[serde(default)]
+    pub versioned: bool,
+    #[serde(default)]
+    pub lang: Option<String>,
+    #[serde(default)]
+    pub template_engine: Option<TemplateEngine>,
+    /// How `target-file` is produced before installing, for targets added by an option. This is not a field of the manifest
+    #[serde(skip)]
+    pub generated: Option<Generated>,
+    /// The package the target belongs to, which its template is rendered with. This is not a field of the manifest
+    #[serde(skip)]
+    pub package: Option<TemplatePackage>,
+    /// Keys of the target which are not one of its fields, usually misspellings of one. Installing fails if there are any
+    #[serde(flatten)]
+    pub unknown: BTreeMap<String, toml::Value>,
+}
+
+/// The fields of a target, as they are written in the manifest
+pub const TARGET_FIELDS: &[&str] = &[
+    "type",
+    "privileged",
+    "directory",
+    "install-dir",
+    "mode",
+    "dir-mode",
+    "file-mode",
+    "modes",
+    "strip-components",
+    "installed-path",
+    "target-file",
+    "prefix",
+    "installed-aliases",
+    "install-method",
+    "exclude",
+    "optional",
+    "only-if",
+    "strip",
+    "strip-mode",
+    "strip-args",
+    "strip-keep-symbols",
+    "rpath",
+    "post-install",
+    "pre-uninstall",
+    "smoke-test",
+    "success-codes",
+    "soft-fail-codes",
+    "skip-codes",
+    "index",
+    "doc-base",
+    "versioned",
+    "lang",
+    "template-engine",
+];
+
+///
+/// The number of single character insertions, deletions, and substitutions that turn `a` into `b`
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut row = (0..=b.len()).collect::<Vec<_>>();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = if ca == *cb {
+                diagonal
+            } else {
+                1 + diagonal.min(above).min(row[j])
+            };
+            diagonal = above;
+        }
+    }
+    row[b.len()]
+}
+
+///
+/// The field of a target that `key` is most likely a misspelling of, if any
+pub fn suggest_field(key: &str) -> Option<&'static str> {
+    // Fields are kebab-case, but are often written in snake_case, as in `target_file`
+    let key = key.to_ascii_lowercase().replace('_', "-");
+    TARGET_FIELDS
+        .iter()
+        .map(|field| (edit_distance(&key, field), *field))
+        .filter(|(distance, field)| *distance <= (field.len() / 3).max(1))
+        .min()
+        .map(|(_, field)| field)
+}
+
+///
+/// What the exit code of the program of a `run` or `check` target means
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitMeaning {
+    /// The target succeeded, and installing continues without a report
+    Success,
+    /// The target failed, which is reported, but installing continues
+    SoftFail,
+    /// The target skipped itself, which is reported, and installing continues
+    Skip,
+    /// The target failed, and installation fails
+    Fail,
+}
+
+/// The exit codes which `success-codes` defaults to. 20 skips the target without a report, which is the same as succeeding
+pub const DEFAULT_SUCCESS_CODES: &[i32] = &[0, 20];
+/// The exit codes which `soft-fail-codes` defaults to
+pub const DEFAULT_SOFT_FAIL_CODES: &[i32] = &[2];
+/// The exit codes which `skip-codes` defaults to
+pub const DEFAULT_SKIP_CODES: &[i32] = &[10];
+
+impl Target {
+    ///
+    /// Whether this target is installed with `opts`: it is not excluded, nor skipped by `--no-privileged` or `--user-prefix`
+    /// (see [`crate::is_skipped_privileged`]), or by `--locales` (see [`crate::is_skipped_locale`])
+    pub fn is_selected(&self, opts: &Options) -> bool {
+        !self.exclude
+            && !crate::is_skipped_privileged(self, opts)
+            && !crate::is_skipped_locale(self, opts)
+    }
+
+    ///
+    /// What the program of this `run` or `check` target exiting with `code` means, from `success-codes`, `skip-codes`, and `soft-fail-codes`,
+    /// or their defaults, in that order. Any other code fails installation
+    pub fn exit_meaning(&self, code: i32) -> ExitMeaning {
+        [
+            (
+                &self.success_codes,
+                DEFAULT_SUCCESS_CODES,
+                ExitMeaning::Success,
+            ),
+            (&self.skip_codes, DEFAULT_SKIP_CODES, ExitMeaning::Skip),
+            (
+                &self.soft_fail_codes,
+                DEFAULT_SOFT_FAIL_CODES,
+                ExitMeaning::SoftFail,
+            ),
+        ]
+        .iter()
+        .find(|(codes, default, _)| codes.as_deref().unwrap_or(default).contains(&code))
+        .map_or(ExitMeaning::Fail, |(_, _, meaning)| *meaning)
+    }
+
+    ///
+    /// The exit codes of the program of this `run` or `check` target which do not fail installation, in order
+    pub fn accepted_codes(&self) -> Vec<i32> {
+        let mut codes = [
+            (&self.success_codes, DEFAULT_SUCCESS_CODES),
+            (&self.skip_codes, DEFAULT_SKIP_CODES),
+            (&self.soft_fail_codes, DEFAULT_SOFT_FAIL_CODES),
+        ]
+        .iter()
+        .flat_map(|(codes, default)| codes.as_deref().unwrap_or(default).iter().copied())
+        .collect::<Vec<_>>();
+        codes.sort_unstable();
+        codes.dedup();
+        codes
+    }
+
+    ///
+    /// Each key of this target which is not one of its fields, with the field it is most likely a misspelling of, if any
+    pub fn unknown_fields(&self) -> impl Iterator<Item = (&str, Option<&'static str>)> {
+        self.unknown
+            .keys()
+            .map(|key| (key.as_str(), suggest_field(key)))
+    }
 }
 
 #[derive(Deserialize, Debug)]
@@ -96,5 +420,72 @@ pub struct NativeInstallMetadata {
 
 #[derive(Deserialize, Debug)]
 pub struct Config {
+    #[serde(default = "InstallDirs::defaults")]
     pub dirs: InstallDirs,
+    #[serde(default)]
+    pub signtool: Option<SignTool>,
+    #[serde(default)]
+    pub notarize: Option<Notarize>,
+    #[serde(default)]
+    pub defaults: Defaults,
+}
+
+///
+/// The `[defaults]` table of config.toml, which sets options that are used when they are not given on the command line
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "kebab-case")]
+pub struct Defaults {
+    /// Whether to strip programs, or the program to strip them with, as `--no-strip` and `--strip` set
+    #[serde(default)]
+    pub strip: Option<StripDefault>,
+    /// The mode of installed files, as `--mode` sets
+    #[serde(default)]
+    pub mode: Option<String>,
+}
+
+///
+/// The `strip` key of the `[defaults]` table: `false` to not strip, `true` to strip with the default program, or the program to strip with
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum StripDefault {
+    Enabled(bool),
+    Program(PathBuf),
+}
+
+///
+/// The `[notarize]` table of config.toml, which submits archives made by `--dist` to Apple's notary service, and staples the ticket to them
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct Notarize {
+    /// The keychain profile which holds the credentials of the notary service, as stored by `xcrun notarytool store-credentials`
+    pub keychain_profile: String,
+}
+
+///
+/// The `[signtool]` table of config.toml, which signs the installed Windows programs, libraries, and installers with signtool.
+/// The certificate is `thumbprint`, or `certificate`, or if neither is set, the one signtool chooses from the certificate store
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "kebab-case")]
+pub struct SignTool {
+    /// The signtool program, if it is not found on the `PATH`
+    #[serde(default)]
+    pub program: Option<PathBuf>,
+    /// The SHA-1 thumbprint of a certificate in the certificate store
+    #[serde(default)]
+    pub thumbprint: Option<String>,
+    /// A PFX file which holds the certificate and its private key
+    #[serde(default)]
+    pub certificate: Option<PathBuf>,
+    /// The environment variable which holds the password of `certificate`, so that it is not written in the configuration
+    #[serde(default)]
+    pub password_env: Option<String>,
+    /// An RFC 3161 timestamp server, so that signatures remain valid after the certificate expires
+    #[serde(default)]
+    pub timestamp_url: Option<String>,
+    /// The digest algorithm of the signature and timestamp, sha256 if not set
+    #[serde(default)]
+    pub digest: Option<String>,
+    /// The description shown by Windows when a signed program asks to run as an administrator
+    #[serde(default)]
+    pub description: Option<String>,
 }
@@ -0,0 +1,156 @@
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use crate::{manifest::Target, output, tempdir::TempDir, vars, Options};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Format {
+    Markdown,
+    AsciiDoc,
+}
+
+fn format(src: &Path) -> Option<Format> {
+    match src.extension()?.to_str()? {
+        "md" | "markdown" => Some(Format::Markdown),
+        "adoc" | "asciidoc" => Some(Format::AsciiDoc),
+        _ => None,
+    }
+}
+
+///
+/// The default installed path of a man target installed from `src`, such as `man1/foo.1` for `doc/foo.1.md` (which is rendered) or `man/foo.1`.
+/// Returns `None` if `src` does not name a section
+pub fn installed_path(src: &Path) -> Option<PathBuf> {
+    let page = match format(src) {
+        Some(_) => Path::new(src.file_stem()?),
+        None => Path::new(src.file_name()?),
+    };
+    let section = page.extension()?.to_str()?;
+    if !section.starts_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+    let mut path = PathBuf::from(format!("man{}", &section[..1]));
+    path.push(page);
+    Some(path)
+}
+
+///
+/// Whether `s` is named like a locale, as the directories of translated manual pages are: a language code,
+/// optionally followed by a territory, codeset, and modifier, as in `de`, `pt_BR`, `de_DE.UTF-8`, or `sr@latin`
+pub fn is_locale(s: &str) -> bool {
+    let (s, modifier) = s.split_once('@').unwrap_or((s, "a"));
+    let (s, codeset) = s.split_once('.').unwrap_or((s, "a"));
+    let (language, territory) = s.split_once('_').unwrap_or((s, "AA"));
+    (2..=3).contains(&language.len())
+        && language.bytes().all(|b| b.is_ascii_lowercase())
+        && territory.len() == 2
+        && territory.bytes().all(|b| b.is_ascii_uppercase())
+        && !codeset.is_empty()
+        && codeset
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'-')
+        && !modifier.is_empty()
+        && modifier.bytes().all(|b| b.is_ascii_lowercase())
+}
+
+///
+/// The language of the manual page `src`, detected from the directory it is in, which is named like a locale within a directory named `man`,
+/// as `de` for `man/de/foo.1`
+pub fn detect_lang(src: &Path) -> Option<&str> {
+    let dir = src.parent()?;
+    let lang = dir.file_name()?.to_str()?;
+    (dir.parent()?.file_name()? == "man" && is_locale(lang)).then_some(lang)
+}
+
+///
+/// `installed_path`, the path of a manual page relative to `mandir`, in the directory of the language `lang`, as `de/man1/foo.1` for `man1/foo.1`.
+/// Paths which are absolute, start with a directory placeholder, or are already in the directory of `lang`, are left as they are
+pub fn localize(installed_path: &Path, lang: &str) -> PathBuf {
+    if installed_path.has_root()
+        || installed_path.starts_with(lang)
+        || vars::has_placeholder(installed_path)
+    {
+        installed_path.to_path_buf()
+    } else {
+        Path::new(lang).join(installed_path)
+    }
+}
+
+///
+/// The program which renders `src` to roff, if it is Markdown or AsciiDoc, and its arguments before the path of the rendered page,
+/// which is followed by `src`
+pub fn renderer(src: &Path) -> Option<(&'static str, &'static [&'static str])> {
+    match format(src)? {
+        Format::Markdown => Some((
+            "pandoc",
+            &[
+                "--standalone",
+                "--from",
+                "markdown",
+                "--to",
+                "man",
+                "--output",
+            ],
+        )),
+        Format::AsciiDoc => Some(("asciidoctor", &["--backend", "manpage", "--out-file"])),
+    }
+}
+
+///
+/// A manual page rendered to roff from Markdown (with pandoc) or AsciiDoc (with asciidoctor).
+/// The rendered page is removed when this is dropped
+pub struct ManPage {
+    pub target: Target,
+    _dir: TempDir,
+}
+
+pub enum Rendered {
+    /// `target` does not need to be rendered
+    NotNeeded,
+    /// The program that renders the page is not available
+    Unavailable(&'static str),
+    Page(Box<ManPage>),
+}
+
+impl ManPage {
+    ///
+    /// Renders the source of `target`, if it is Markdown or AsciiDoc
+    pub fn render(target: &Target, opts: &Options) -> Result<Rendered, String> {
+        let (src, (name, args)) = match &target.target_file {
+            Some(src) if !target.directory => match renderer(src) {
+                Some(renderer) => (src, renderer),
+                None => return Ok(Rendered::NotNeeded),
+            },
+            _ => return Ok(Rendered::NotNeeded),
+        };
+        let prg = match format(src) {
+            Some(Format::AsciiDoc) => &opts.asciidoctor,
+            _ => &opts.pandoc,
+        };
+        let prg = match prg.clone().or_else(|| which::which(name).ok()) {
+            Some(prg) => prg,
+            None => return Ok(Rendered::Unavailable(name)),
+        };
+
+        let dir = TempDir::new("man")
+            .map_err(|e| format!("Failed to create a directory for the page, {}", e))?;
+        let path = dir.path().join(src.file_stem().unwrap_or_default());
+        let mut cmd = Command::new(&prg);
+        cmd.args(args).arg(&path).arg(src);
+        output::command(opts, &cmd);
+        let page = ManPage {
+            target: Target {
+                target_file: Some(path),
+                ..target.clone()
+            },
+            _dir: dir,
+        };
+        match cmd.status() {
+            Ok(status) if status.success() => Ok(Rendered::Page(Box::new(page))),
+            Ok(status) => Err(format!("{} exited with {}", prg.display(), status)),
+            Err(e) => Err(format!("Failed to run {}, {}", prg.display(), e)),
+        }
+    }
+}
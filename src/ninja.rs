@@ -0,0 +1,343 @@
+use std::{
+    collections::HashMap,
+    fmt::Write as _,
+    path::{Path, PathBuf},
+};
+
+use install_dirs::dirs::InstallDirs;
+
+use crate::{
+    hooks,
+    manifest::Target,
+    output,
+    output::Status,
+    script::{self, ScriptKind, Step},
+    Error, Options,
+};
+
+/// The rules used by the edges of the fragment. Ninja does not quote `$in` and `$out`, so the commands use `$src` and `$dest`, which each edge sets to its paths quoted for the shell.
+/// `$post` runs `chmod` and `chown` after the file is installed, and `$hard`, set to `l`, makes `cp` link the files of a directory.
+/// `run` targets and `post-install` commands receive the installation directories in their environment, from `$cni_env`, and touch the stamp file `$dest` once they succeed
+const RULES: &str = "\
+rule cni_install
+  command = install $flags $src $dest $post
+  description = INSTALL $out
+rule cni_copy_tree
+  command = mkdir -p $dest && cp -R$hard $src/. $dest $post
+  description = INSTALL $out
+rule cni_copy_stripped
+  command = mkdir -p $dest && find $src -mindepth $depth -maxdepth $depth -exec cp -R$hard {} $dest \\; $post
+  description = INSTALL $out
+rule cni_link_artifact
+  command = mkdir -p $$(dirname $dest) && ln $flags $src $dest
+  description = LINK $out
+rule cni_mkdir
+  command = mkdir -p $dest $post
+  description = MKDIR $out
+rule cni_render
+  command = mkdir -p $dir && $renderer $dest $src $post
+  description = RENDER $out
+rule cni_link
+  command = ln -sfn $src $dest
+  description = LINK $out
+rule cni_command
+  command = env $cni_env sh -c $cmd && mkdir -p $$(dirname $dest) && touch $dest
+  description = POST-INSTALL $cmd
+rule cni_run
+  command = (cd $dir && env $cni_env $src); case $$? in $codes) mkdir -p $$(dirname $dest) && touch $dest ;; *) exit 1 ;; esac
+  description = RUN $in
+";
+
+///
+/// Escapes `path` for a `build` line
+fn escape_path(path: &Path) -> String {
+    let mut out = String::new();
+    for c in path.to_string_lossy().chars() {
+        if matches!(c, '$' | ' ' | ':') {
+            out.push('$');
+        }
+        out.push(c);
+    }
+    out
+}
+
+///
+/// Escapes `value`, which is passed to the shell by ninja, for a variable
+fn escape(value: &str) -> String {
+    value.replace('$', "$$")
+}
+
+fn quote(path: impl AsRef<Path>) -> String {
+    escape(&ScriptKind::Sh.quote(path))
+}
+
+///
+/// Writes the `src` (if any) and `dest` variables of an edge, which are its paths quoted for the shell
+fn write_paths(out: &mut String, src: Option<&Path>, dest: &Path) {
+    if let Some(src) = src {
+        writeln!(out, "  src = {}", quote(src)).unwrap();
+    }
+    writeln!(out, "  dest = {}", quote(dest)).unwrap();
+}
+
+///
+/// Writes the `post` variable of an edge, which applies `mode` and the owner and group from `opts` to `path`, if either is set
+fn write_post(out: &mut String, path: &Path, mode: Option<&str>, opts: &Options) {
+    write_post_with(out, path, mode, Vec::new(), opts)
+}
+
+///
+/// Like `write_post`, running the commands of `post` after applying `mode`
+fn write_post_with(
+    out: &mut String,
+    path: &Path,
+    mode: Option<&str>,
+    mut post: Vec<String>,
+    opts: &Options,
+) {
+    if let Some(mode) = mode {
+        post.insert(0, format!("chmod {} {}", quote(mode), quote(path)));
+    }
+    if let Some(owner) = script::chown_spec(opts) {
+        post.push(format!("chown {} {}", quote(owner), quote(path)));
+    }
+    for (i, cmd) in post.iter().enumerate() {
+        out.push_str(if i == 0 { "  post = && " } else { " && " });
+        out.push_str(cmd);
+    }
+    if !post.is_empty() {
+        out.push('\n');
+    }
+}
+
+///
+/// A ninja file, meant to be included with `subninja`, with an edge for each step that installs `targets` to `dirs`,
+/// and a phony edge, `install-<package>`, which depends on all of them
+pub fn fragment(
+    dirs: &InstallDirs,
+    package: &str,
+    targets: &HashMap<String, Target>,
+    opts: &Options,
+) -> Result<String, Error> {
+    let steps = script::steps(dirs, targets, opts)?;
+    // Ninja reads its files as text, and has no way to escape a newline in a path
+    let paths = dirs.as_env().into_iter().map(|(_, dir)| dir);
+    let steps_paths = steps
+        .iter()
+        .flat_map(|(_, steps)| steps)
+        .flat_map(Step::paths);
+    if let Some(path) = paths
+        .chain(steps_paths)
+        .find(|path| path.to_str().is_none_or(|path| path.contains('\n')))
+    {
+        return Err(Error::Unrepresentable {
+            path: path.to_owned(),
+            format: "a ninja file",
+            reason: "as it is not UTF-8, or contains a newline",
+        });
+    }
+
+    let mut out = String::new();
+    writeln!(
+        out,
+        "# Generated by cargo-native-install. Installs {}\n",
+        package
+    )
+    .unwrap();
+    let mut env = dirs.as_env().into_iter().collect::<Vec<_>>();
+    env.sort();
+    let env = env
+        .iter()
+        .map(|(name, dir)| format!("{}={}", name, quote(dir)))
+        .collect::<Vec<_>>()
+        .join(" ");
+    writeln!(out, "cni_env = {}\n", env).unwrap();
+    out.push_str(RULES);
+
+    let stamp_dir = Path::new("cargo-native-install").join(package);
+    let mut outputs = Vec::<PathBuf>::new();
+    for (name, steps) in steps {
+        // An edge cannot be skipped when ninja runs, so the `only-if` command is run when the fragment is generated
+        if let Some(command) = &targets[&name].only_if {
+            if !hooks::only_if(command, dirs, opts).map_err(|e| Error::Spawn("sh".into(), e))? {
+                output::status(
+                    opts,
+                    Status::Skipped,
+                    format_args!("Skipping {}, `{}` failed", name, command),
+                );
+                continue;
+            }
+        }
+        writeln!(out, "\n# {}", name).unwrap();
+        let first = outputs.len();
+        for step in steps {
+            let output = match &step {
+                Step::Run { file, dir, codes } => {
+                    let stamp = stamp_dir.join(format!("{}.stamp", name));
+                    writeln!(
+                        out,
+                        "build {}: cni_run {}",
+                        escape_path(&stamp),
+                        escape_path(file)
+                    )
+                    .unwrap();
+                    write_paths(&mut out, Some(file), &stamp);
+                    let dir = dir.as_deref().unwrap_or_else(|| Path::new("."));
+                    writeln!(out, "  dir = {}", quote(dir)).unwrap();
+                    // No exit code is written as a pattern which matches none, rather than an empty one, which sh cannot parse
+                    let codes = match codes.is_empty() {
+                        true => "none".to_owned(),
+                        false => codes
+                            .iter()
+                            .map(ToString::to_string)
+                            .collect::<Vec<_>>()
+                            .join("|"),
+                    };
+                    writeln!(out, "  codes = {}", codes).unwrap();
+                    stamp
+                }
+                Step::Mkdir { dir, mode } => {
+                    writeln!(out, "build {}: cni_mkdir", escape_path(dir)).unwrap();
+                    write_paths(&mut out, None, dir);
+                    write_post(&mut out, dir, mode.as_deref(), opts);
+                    dir.clone()
+                }
+                Step::Install { src, dest, mode } => {
+                    writeln!(
+                        out,
+                        "build {}: cni_install {}",
+                        escape_path(dest),
+                        escape_path(src)
+                    )
+                    .unwrap();
+                    write_paths(&mut out, Some(src), dest);
+                    let mut flags = String::new();
+                    if !opts.no_create_dirs {
+                        flags.push_str("-D");
+                    }
+                    if let Some(mode) = mode {
+                        write!(flags, " -m {}", quote(mode)).unwrap();
+                    }
+                    writeln!(out, "  flags = {}", flags.trim_start()).unwrap();
+                    write_post(&mut out, dest, None, opts);
+                    dest.clone()
+                }
+                Step::CopyTree {
+                    src,
+                    dest,
+                    strip,
+                    hard_link,
+                    dir_mode,
+                    file_mode,
+                    modes,
+                } => {
+                    writeln!(
+                        out,
+                        "build {}: {} {}",
+                        escape_path(dest),
+                        if *strip == 0 {
+                            "cni_copy_tree"
+                        } else {
+                            "cni_copy_stripped"
+                        },
+                        escape_path(src)
+                    )
+                    .unwrap();
+                    write_paths(&mut out, Some(src), dest);
+                    if *strip != 0 {
+                        // The entries below the components being removed are copied
+                        writeln!(out, "  depth = {}", strip + 1).unwrap();
+                    }
+                    if *hard_link {
+                        writeln!(out, "  hard = l").unwrap();
+                    }
+                    let chmod = [("d", dir_mode), ("f", file_mode)]
+                        .iter()
+                        .filter_map(|(kind, mode)| {
+                            Some(format!(
+                                "find {} -type {} -exec chmod {} {{}} +",
+                                quote(dest),
+                                kind,
+                                quote(mode.as_deref()?)
+                            ))
+                        })
+                        .chain(modes.iter().map(|(pattern, mode)| {
+                            format!(
+                                "find {} -type f {} -exec chmod {} {{}} +",
+                                quote(dest),
+                                escape(&script::find_test(dest, pattern)),
+                                quote(mode)
+                            )
+                        }))
+                        .collect();
+                    write_post_with(&mut out, dest, None, chmod, opts);
+                    dest.clone()
+                }
+                Step::Render {
+                    prg,
+                    args,
+                    src,
+                    dest,
+                    mode,
+                } => {
+                    writeln!(
+                        out,
+                        "build {}: cni_render {}",
+                        escape_path(dest),
+                        escape_path(src)
+                    )
+                    .unwrap();
+                    write_paths(&mut out, Some(src), dest);
+                    let dir = dest.parent().unwrap_or_else(|| Path::new("/"));
+                    writeln!(out, "  dir = {}", quote(dir)).unwrap();
+                    writeln!(out, "  renderer = {} {}", prg, args.join(" ")).unwrap();
+                    write_post(&mut out, dest, mode.as_deref(), opts);
+                    dest.clone()
+                }
+                Step::Link { src, link } => {
+                    // The link depends on the file it refers to, so that it is created afterwards
+                    writeln!(
+                        out,
+                        "build {}: cni_link | {}",
+                        escape_path(link),
+                        escape_path(src)
+                    )
+                    .unwrap();
+                    write_paths(&mut out, Some(src), link);
+                    link.clone()
+                }
+                Step::LinkArtifact { src, dest, hard } => {
+                    writeln!(
+                        out,
+                        "build {}: cni_link_artifact {}",
+                        escape_path(dest),
+                        escape_path(src)
+                    )
+                    .unwrap();
+                    write_paths(&mut out, Some(src), dest);
+                    writeln!(out, "  flags = {}", if *hard { "-f" } else { "-sfn" }).unwrap();
+                    dest.clone()
+                }
+                Step::Command { command } => {
+                    // The command runs once everything else of the target is installed
+                    let stamp = stamp_dir.join(format!("{}.post-install.stamp", name));
+                    write!(out, "build {}: cni_command |", escape_path(&stamp)).unwrap();
+                    for output in &outputs[first..] {
+                        write!(out, " {}", escape_path(output)).unwrap();
+                    }
+                    writeln!(out, "\n  cmd = {}", quote(command)).unwrap();
+                    write_paths(&mut out, None, &stamp);
+                    stamp
+                }
+            };
+            outputs.push(output);
+        }
+    }
+
+    write!(out, "\nbuild install-{}: phony", package).unwrap();
+    for output in &outputs {
+        write!(out, " {}", escape_path(output)).unwrap();
+    }
+    out.push('\n');
+    Ok(out)
+}
@@ -0,0 +1,105 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use serde_json::Value;
+
+use crate::{manifest::Notarize, output, output::Status, Error, Options};
+
+///
+/// Runs `cmd`, a command of `xcrun`, and returns its standard output
+fn run(mut cmd: Command, opts: &Options) -> Result<String, Error> {
+    output::command(opts, &cmd);
+    let out = cmd.output().map_err(|e| Error::Spawn("xcrun".into(), e))?;
+    if !out.status.success() {
+        return Err(Error::Tool("xcrun".into(), out.status));
+    }
+    Ok(String::from_utf8_lossy(&out.stdout).into_owned())
+}
+
+///
+/// Finds the application bundles in `dir`, which are stapled instead of the zip archive that contains them
+fn app_bundles(dir: &Path, bundles: &mut Vec<PathBuf>) {
+    for entry in fs::read_dir(dir).into_iter().flatten().flatten() {
+        if !entry.file_type().is_ok_and(|ty| ty.is_dir()) {
+            continue;
+        }
+        let path = entry.path();
+        if path.extension().is_some_and(|ext| ext == "app") {
+            bundles.push(path);
+        } else {
+            app_bundles(&path, bundles);
+        }
+    }
+}
+
+///
+/// Submits `archive`, made by `--dist` from the installation staged in `stage`, to the notary service, and waits for the result.
+/// A zip archive cannot be stapled, so the ticket is stapled to each application bundle in it instead, so that Gatekeeper accepts them
+/// without contacting Apple, and the archive is packed again with `repack`
+pub fn notarize(
+    config: &Notarize,
+    archive: &Path,
+    stage: &Path,
+    repack: impl Fn() -> Result<(), Error>,
+    opts: &Options,
+) -> Result<(), Error> {
+    if !archive
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("zip"))
+    {
+        output::status(
+            opts,
+            Status::Warning,
+            format_args!(
+                "Warning: not notarizing {}, the notary service does not accept tarballs",
+                archive.display()
+            ),
+        );
+        return Ok(());
+    }
+
+    output::status(
+        opts,
+        Status::Installed,
+        format_args!("Notarizing {}", archive.display()),
+    );
+    let mut cmd = Command::new("xcrun");
+    cmd.args(["notarytool", "submit"])
+        .arg(archive)
+        .args(["--keychain-profile", &config.keychain_profile])
+        .args(["--wait", "--output-format", "json"]);
+    let result = serde_json::from_str::<Value>(&run(cmd, opts)?).unwrap_or_default();
+    let field = |name: &str| result.get(name).and_then(Value::as_str).unwrap_or("");
+    if field("status") != "Accepted" {
+        return Err(Error::Notarization(
+            archive.to_path_buf(),
+            format!(
+                "the status of submission {} is {}, see `xcrun notarytool log {} --keychain-profile {}`",
+                field("id"),
+                match field("status") {
+                    "" => "unknown",
+                    status => status,
+                },
+                field("id"),
+                config.keychain_profile
+            ),
+        ));
+    }
+
+    let mut bundles = Vec::new();
+    app_bundles(stage, &mut bundles);
+    for bundle in &bundles {
+        output::verbose(opts, format_args!("Stapling {}", bundle.display()));
+        let mut cmd = Command::new("xcrun");
+        cmd.args(["stapler", "staple"]).arg(bundle);
+        run(cmd, opts)?;
+    }
+    if bundles.is_empty() {
+        Ok(())
+    } else {
+        repack()
+    }
+}
@@ -0,0 +1,100 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    fs,
+    path::PathBuf,
+};
+
+use install_dirs::dirs::InstallDirs;
+
+use crate::{
+    diff, manifest::Target, output, output::Status, receipt::Receipt, sysroot, Error, Options,
+};
+
+///
+/// Whether `name` is `<file>-<version>`, for one of `files`, as left behind by an earlier version of a `versioned` target
+fn is_old_version(name: &str, files: &BTreeSet<&str>) -> bool {
+    files.iter().any(|file| {
+        name.strip_prefix(file)
+            .and_then(|rest| rest.strip_prefix('-'))
+            .is_some_and(|version| version.starts_with(|c: char| c.is_ascii_digit()))
+    })
+}
+
+///
+/// Lists the files installed by earlier versions of `package`, which none of `targets` installs now.
+/// These are the files recorded in the install receipt which are no longer produced, and files beside them named after
+/// a file that is produced, with another version. Nothing is removed
+pub fn report(
+    dirs: &InstallDirs,
+    package: &str,
+    targets: &HashMap<String, Target>,
+    opts: &Options,
+) -> Result<(), Error> {
+    let path = sysroot::in_sysroot(&Receipt::path(dirs, package), opts);
+    let installed = Receipt::load(&path, package).map_err(|e| Error::Receipt(path.clone(), e))?;
+    let planned = diff::planned(dirs, targets, opts);
+    let current = planned
+        .targets
+        .values()
+        .flat_map(|entry| entry.files.iter().map(PathBuf::as_path))
+        .collect::<BTreeSet<_>>();
+
+    let mut orphans = BTreeSet::new();
+    let mut dirs_scanned = BTreeSet::new();
+    for (name, entry) in &installed.targets {
+        // Targets which still exist, but are not being installed with these options, are not orphaned
+        if targets.contains_key(name) && !planned.targets.contains_key(name) {
+            continue;
+        }
+        for file in &entry.files {
+            if !current.contains(file.as_path()) && fs::symlink_metadata(file).is_ok() {
+                orphans.insert(file.clone());
+            }
+            if let Some(parent) = file.parent() {
+                dirs_scanned.insert(parent.to_path_buf());
+            }
+        }
+    }
+
+    for dir in &dirs_scanned {
+        let names = current
+            .iter()
+            .filter(|file| file.parent() == Some(dir))
+            .filter_map(|file| file.file_name()?.to_str())
+            .collect::<BTreeSet<_>>();
+        if names.is_empty() {
+            continue;
+        }
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let name = entry.file_name();
+            if !current.contains(path.as_path())
+                && name
+                    .to_str()
+                    .is_some_and(|name| is_old_version(name, &names))
+            {
+                orphans.insert(path);
+            }
+        }
+    }
+
+    for orphan in &orphans {
+        println!("{}", orphan.display());
+    }
+    output::status(
+        opts,
+        Status::Installed,
+        format_args!(
+            "{} file{} left by earlier versions of {} in {}",
+            orphans.len(),
+            if orphans.len() == 1 { "" } else { "s" },
+            package,
+            dirs.prefix.display()
+        ),
+    );
+    Ok(())
+}
@@ -0,0 +1,283 @@
+use std::{
+    fmt::Arguments,
+    fs::{File, OpenOptions},
+    io::{self, IsTerminal, Write},
+    path::Path,
+    process::Command,
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::Options;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum Verbosity {
+    Quiet,
+    #[default]
+    Normal,
+    Verbose,
+    VeryVerbose,
+}
+
+impl Verbosity {
+    pub fn increase(self) -> Self {
+        match self {
+            Verbosity::Quiet | Verbosity::Normal => Verbosity::Verbose,
+            Verbosity::Verbose | Verbosity::VeryVerbose => Verbosity::VeryVerbose,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum ColorChoice {
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "auto" => Some(ColorChoice::Auto),
+            "always" => Some(ColorChoice::Always),
+            "never" => Some(ColorChoice::Never),
+            _ => None,
+        }
+    }
+}
+
+///
+/// Whether warnings and errors are also written as annotations, which show them on the pull request or commit being checked
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum Annotations {
+    /// GitHub Actions workflow commands, when running in GitHub Actions
+    #[default]
+    Auto,
+    Github,
+    Never,
+}
+
+impl Annotations {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "auto" => Some(Annotations::Auto),
+            "github" => Some(Annotations::Github),
+            "never" => Some(Annotations::Never),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Status {
+    Installed,
+    Skipped,
+    Warning,
+    Failed,
+}
+
+impl Status {
+    fn indent(&self) -> &'static str {
+        match self {
+            Status::Installed => "",
+            Status::Skipped | Status::Warning | Status::Failed => "  ",
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Status::Installed => "status",
+            Status::Skipped => "skipped",
+            Status::Warning => "warning",
+            Status::Failed => "failed",
+        }
+    }
+
+    fn color(&self) -> &'static str {
+        match self {
+            Status::Installed => "\x1b[1;32m",
+            Status::Skipped | Status::Warning => "\x1b[1;33m",
+            Status::Failed => "\x1b[1;31m",
+        }
+    }
+}
+
+///
+/// Whether status markers written to stderr should be colored
+pub fn use_color(opts: &Options) -> bool {
+    match opts.color {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto => {
+            std::env::var_os("NO_COLOR").is_none()
+                && std::env::var_os("TERM").is_none_or(|term| term != "dumb")
+                && std::io::stderr().is_terminal()
+        }
+    }
+}
+
+///
+/// Whether warnings and errors are written as GitHub Actions workflow commands
+fn use_github(opts: &Options) -> bool {
+    match opts.annotations {
+        Annotations::Github => true,
+        Annotations::Never => false,
+        Annotations::Auto => std::env::var_os("GITHUB_ACTIONS").is_some_and(|ci| ci == "true"),
+    }
+}
+
+///
+/// Escapes `s` for a workflow command, in which a property (such as `file`) also cannot contain `:` or `,`
+fn escape_workflow(s: &str, property: bool) -> String {
+    let s = s
+        .replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A");
+    match property {
+        true => s.replace(':', "%3A").replace(',', "%2C"),
+        false => s,
+    }
+}
+
+///
+/// Writes a warning or failure as a GitHub Actions `::warning` or `::error` workflow command, with `--annotations`.
+/// The annotation is shown on `file` if given, as a path relative to the repository, and is written to stderr, so that it cannot end up in output such as `--emit-script`
+fn annotate(opts: &Options, status: Status, file: Option<&Path>, args: Arguments) {
+    let kind = match status {
+        Status::Warning => "warning",
+        Status::Failed => "error",
+        Status::Installed | Status::Skipped => return,
+    };
+    if !use_github(opts) {
+        return;
+    }
+    let file = file.map_or_else(String::new, |file| {
+        let file = std::path::absolute(file).unwrap_or_else(|_| file.to_path_buf());
+        let file = match std::env::var_os("GITHUB_WORKSPACE") {
+            Some(workspace) => file.strip_prefix(workspace).unwrap_or(&file).to_path_buf(),
+            None => file,
+        };
+        format!(" file={}", escape_workflow(&file.to_string_lossy(), true))
+    });
+    let message = args.to_string();
+    let message = message.strip_prefix("Warning: ").unwrap_or(&message);
+    eprintln!("::{}{}::{}", kind, file, escape_workflow(message, false));
+}
+
+///
+/// Opens the file given by `--log-file`. If the file exists, the log is appended to it
+pub fn open_log(path: &Path) -> io::Result<Mutex<File>> {
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map(Mutex::new)
+}
+
+///
+/// Formats the current time as an RFC 3339 timestamp in UTC
+fn timestamp() -> String {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    let (days, secs) = (secs / 86400, secs % 86400);
+    // Converts days since 1970-01-01 to a date in the proleptic gregorian calendar,
+    // see <http://howardhinnant.github.io/date_algorithms.html#civil_from_days>
+    let z = days + 719468;
+    let era = z / 146097;
+    let doe = z % 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
+
+///
+/// Writes a timestamped line to the file given by `--log-file`, if any, regardless of verbosity
+pub fn log(opts: &Options, kind: &str, args: Arguments) {
+    if let Some(log) = &opts.log {
+        let mut log = log.lock().unwrap_or_else(|e| e.into_inner());
+        let _ = writeln!(log, "[{}] {}: {}", timestamp(), kind, args);
+    }
+}
+
+///
+/// Prints an error message which is not associated with a particular target
+pub fn error(opts: &Options, args: Arguments) {
+    log(opts, "error", args);
+    eprintln!("{}", args);
+    annotate(opts, Status::Failed, None, args);
+}
+
+///
+/// Prints an error message about the file `file`, such as a problem with the manifest, which is annotated on that file
+pub fn error_in(opts: &Options, file: &Path, args: Arguments) {
+    log(opts, "error", args);
+    eprintln!("{}", args);
+    annotate(opts, Status::Failed, Some(file), args);
+}
+
+///
+/// Prints a status line (`-- message`), with the marker colored according to `status`.
+/// Installed, Skipped, and Warning lines are suppressed by `--quiet`, Failed lines are always printed
+pub fn status(opts: &Options, status: Status, args: Arguments) {
+    print_status(opts, status, None, args);
+}
+
+///
+/// Prints a status line about the file `file`, such as a problem with the manifest, as [`status`] does, which is annotated on that file
+pub fn status_in(opts: &Options, status: Status, file: &Path, args: Arguments) {
+    print_status(opts, status, Some(file), args);
+}
+
+fn print_status(opts: &Options, status: Status, file: Option<&Path>, args: Arguments) {
+    log(opts, status.name(), args);
+    // Annotations are for CI, which `--quiet` does not hide them from
+    annotate(opts, status, file, args);
+    if opts.verbosity == Verbosity::Quiet && status != Status::Failed {
+        return;
+    }
+    let stderr = std::io::stderr();
+    let mut stderr = stderr.lock();
+    let _ = if use_color(opts) {
+        writeln!(
+            stderr,
+            "{}{}--\x1b[0m {}",
+            status.indent(),
+            status.color(),
+            args
+        )
+    } else {
+        writeln!(stderr, "{}-- {}", status.indent(), args)
+    };
+}
+
+///
+/// Prints an informational message, only if `-v` was given
+pub fn verbose(opts: &Options, args: Arguments) {
+    log(opts, "info", args);
+    if opts.verbosity >= Verbosity::Verbose {
+        eprintln!("   {}", args);
+    }
+}
+
+///
+/// Prints the command line of an external program about to be invoked, only if `-vv` was given
+pub fn command(opts: &Options, cmd: &Command) {
+    log(opts, "command", format_args!("{:?}", cmd));
+    if opts.verbosity >= Verbosity::VeryVerbose {
+        eprintln!("   + {:?}", cmd);
+    }
+}
@@ -0,0 +1,87 @@
+use std::{
+    path::Path,
+    process::{Command, Stdio},
+};
+
+use crate::{output, Options};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum PackageCheck {
+    #[default]
+    Off,
+    Warn,
+    Refuse,
+}
+
+impl PackageCheck {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "off" => Some(PackageCheck::Off),
+            "warn" => Some(PackageCheck::Warn),
+            "refuse" => Some(PackageCheck::Refuse),
+            _ => None,
+        }
+    }
+}
+
+/// Directories whose contents are normally managed by the system package manager
+const SYSTEM_DIRS: [&str; 9] = [
+    "/usr", "/etc", "/opt", "/var", "/bin", "/sbin", "/lib", "/lib32", "/lib64",
+];
+
+pub fn is_system_path(path: &Path) -> bool {
+    SYSTEM_DIRS.iter().any(|dir| path.starts_with(dir))
+}
+
+///
+/// Queries the system package manager (dpkg, rpm, or pacman, whichever is found first) for the package that owns `path`.
+/// Returns `None` if the path is not owned by a package, or no package manager could be found.
+///
+/// On merged-/usr systems, packages may record files under `/bin`, `/sbin`, or `/lib` rather than the `/usr` equivalent,
+/// so both locations are queried.
+pub fn owning_package(opts: &Options, path: &Path) -> Option<String> {
+    query_owner(opts, path).or_else(|| {
+        let unmerged = Path::new("/").join(path.strip_prefix("/usr").ok()?);
+        if ["/bin", "/sbin", "/lib", "/lib32", "/lib64"]
+            .iter()
+            .any(|dir| unmerged.starts_with(dir))
+        {
+            query_owner(opts, &unmerged)
+        } else {
+            None
+        }
+    })
+}
+
+fn query_owner(opts: &Options, path: &Path) -> Option<String> {
+    if let Ok(dpkg) = which::which("dpkg") {
+        // dpkg -S prints `package[, package...]: path`
+        let out = query(opts, Command::new(dpkg).arg("-S").arg(path))?;
+        return out.split(": ").next().map(ToOwned::to_owned);
+    }
+    if let Ok(rpm) = which::which("rpm") {
+        // rpm -qf prints the full name of each owning package, one per line
+        let out = query(opts, Command::new(rpm).arg("-qf").arg(path))?;
+        return out.lines().next().map(ToOwned::to_owned);
+    }
+    if let Ok(pacman) = which::which("pacman") {
+        // pacman -Qo prints `path is owned by package version`
+        let out = query(opts, Command::new(pacman).arg("-Qo").arg(path))?;
+        return out
+            .split(" is owned by ")
+            .nth(1)
+            .map(|pkg| pkg.trim().to_owned());
+    }
+    None
+}
+
+fn query(opts: &Options, cmd: &mut Command) -> Option<String> {
+    cmd.stdin(Stdio::null()).stderr(Stdio::null());
+    output::command(opts, cmd);
+    let out = cmd.output().ok()?;
+    if out.status.success() {
+        Some(String::from_utf8_lossy(&out.stdout).trim().to_owned())
+    } else {
+        None
+    }
+}
@@ -0,0 +1,199 @@
+use std::{
+    fmt::Display,
+    fs::{self, File},
+    io::{self, Read},
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use crate::{output, tree_entries, Options};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Change {
+    New,
+    Update,
+    Unchanged,
+    Conflict,
+}
+
+impl Display for Change {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Change::New => "new",
+            Change::Update => "update",
+            Change::Unchanged => "unchanged",
+            Change::Conflict => "conflict (dest newer)",
+        })
+    }
+}
+
+///
+/// Summary of the changes installing a target would make, used to label operations in `--dry-run`
+#[derive(Clone, Debug, Default)]
+pub struct Preview {
+    pub new: usize,
+    pub update: usize,
+    pub unchanged: usize,
+    pub conflict: usize,
+    /// The total size of the files
+    pub bytes: u64,
+    entries: Vec<(PathBuf, PathBuf, Change)>,
+}
+
+impl Preview {
+    fn record(&mut self, src: &Path, dest: &Path, change: Change) {
+        self.bytes += fs::metadata(src).map_or(0, |md| md.len());
+        self.entries
+            .push((src.to_path_buf(), dest.to_path_buf(), change));
+        match change {
+            Change::New => self.new += 1,
+            Change::Update => self.update += 1,
+            Change::Unchanged => self.unchanged += 1,
+            Change::Conflict => self.conflict += 1,
+        }
+    }
+
+    fn single(&self) -> Option<Change> {
+        match (self.new, self.update, self.unchanged, self.conflict) {
+            (1, 0, 0, 0) => Some(Change::New),
+            (0, 1, 0, 0) => Some(Change::Update),
+            (0, 0, 1, 0) => Some(Change::Unchanged),
+            (0, 0, 0, 1) => Some(Change::Conflict),
+            _ => None,
+        }
+    }
+
+    ///
+    /// With `-v`, lists each file along with its label. With `--diff`, changed text files are shown as a unified diff
+    pub fn report(&self, opts: &Options) {
+        for (src, dest, change) in &self.entries {
+            if self.entries.len() > 1 {
+                output::verbose(opts, format_args!("{} ({})", dest.display(), change));
+            }
+            if opts.show_diff && (*change == Change::Update || *change == Change::Conflict) {
+                show_diff(opts, src, dest);
+            }
+        }
+    }
+}
+
+impl Display for Preview {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(change) = self.single() {
+            return change.fmt(f);
+        }
+        let mut sep = "";
+        for (count, change) in [
+            (self.new, Change::New),
+            (self.update, Change::Update),
+            (self.unchanged, Change::Unchanged),
+            (self.conflict, Change::Conflict),
+        ] {
+            if count != 0 {
+                write!(f, "{}{} {}", sep, count, change)?;
+                sep = ", ";
+            }
+        }
+        if sep.is_empty() {
+            f.write_str("empty")?;
+        }
+        Ok(())
+    }
+}
+
+///
+/// Compares the file at `src` with the existing file at `dest`
+pub fn classify(src: &Path, dest: &Path) -> io::Result<Change> {
+    let dest_md = match fs::metadata(dest) {
+        Ok(md) => md,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Change::New),
+        Err(e) => return Err(e),
+    };
+    let src_md = fs::metadata(src)?;
+    if src_md.len() == dest_md.len() && same_contents(src, dest)? {
+        return Ok(Change::Unchanged);
+    }
+    if let (Ok(src_time), Ok(dest_time)) = (src_md.modified(), dest_md.modified()) {
+        if src_time < dest_time {
+            return Ok(Change::Conflict);
+        }
+    }
+    Ok(Change::Update)
+}
+
+fn same_contents(a: &Path, b: &Path) -> io::Result<bool> {
+    let mut a = File::open(a)?;
+    let mut b = File::open(b)?;
+    let mut abuf = [0u8; 8192];
+    let mut bbuf = [0u8; 8192];
+    loop {
+        let n = read_full(&mut a, &mut abuf)?;
+        let m = read_full(&mut b, &mut bbuf)?;
+        if abuf[..n] != bbuf[..m] {
+            return Ok(false);
+        } else if n == 0 {
+            return Ok(true);
+        }
+    }
+}
+
+fn read_full(f: &mut File, buf: &mut [u8]) -> io::Result<usize> {
+    let mut total = 0;
+    while total < buf.len() {
+        match f.read(&mut buf[total..])? {
+            0 => break,
+            n => total += n,
+        }
+    }
+    Ok(total)
+}
+
+fn is_text(path: &Path) -> bool {
+    let mut buf = [0u8; 8192];
+    match File::open(path).and_then(|mut f| read_full(&mut f, &mut buf)) {
+        Ok(n) => !buf[..n].contains(&0),
+        Err(_) => false,
+    }
+}
+
+///
+/// Classifies every file that would be installed from `src` to `dest`, removing the first `strip` components of the paths in a directory
+pub fn preview(src: &Path, dest: &Path, strip: usize) -> io::Result<Preview> {
+    let mut preview = Preview::default();
+    preview_into(src, dest, strip, &mut preview)?;
+    Ok(preview)
+}
+
+fn preview_into(src: &Path, dest: &Path, strip: usize, preview: &mut Preview) -> io::Result<()> {
+    if src.is_dir() {
+        for (src_item, dest_item) in tree_entries(src, dest, strip)? {
+            preview_into(&src_item, &dest_item, 0, preview)?;
+        }
+        return Ok(());
+    }
+    let change = classify(src, dest)?;
+    preview.record(src, dest, change);
+    Ok(())
+}
+
+fn show_diff(opts: &Options, src: &Path, dest: &Path) {
+    if !is_text(src) || !is_text(dest) {
+        eprintln!(
+            "   Binary files {} and {} differ",
+            dest.display(),
+            src.display()
+        );
+        return;
+    }
+    match which::which("diff") {
+        Ok(diff) => {
+            let mut cmd = Command::new(diff);
+            cmd.arg("-u").arg(dest).arg(src);
+            output::command(opts, &cmd);
+            if let Err(e) = cmd.status() {
+                eprintln!("   Failed to run diff, {}", e);
+            }
+        }
+        Err(_) => eprintln!("   Cannot show differences, diff was not found"),
+    }
+}
@@ -0,0 +1,238 @@
+use std::process::Command;
+
+#[cfg(unix)]
+use std::{
+    ffi::{CStr, CString, OsStr},
+    os::unix::{ffi::OsStrExt, process::CommandExt},
+    path::{Path, PathBuf},
+};
+
+///
+/// The unprivileged user that invoked this program through sudo, doas, or pkexec
+#[cfg(unix)]
+pub struct InvokingUser {
+    uid: libc::uid_t,
+    gid: libc::gid_t,
+    name: CString,
+    home: PathBuf,
+}
+
+#[cfg(not(unix))]
+pub struct InvokingUser;
+
+#[cfg(unix)]
+impl InvokingUser {
+    pub fn name(&self) -> std::borrow::Cow<'_, str> {
+        self.name.to_string_lossy()
+    }
+}
+
+#[cfg(not(unix))]
+impl InvokingUser {
+    pub fn name(&self) -> std::borrow::Cow<'_, str> {
+        "".into()
+    }
+}
+
+///
+/// If this program is running as root on behalf of another user (through `sudo`, `doas`, or `pkexec`), finds that user.
+#[cfg(unix)]
+pub fn invoking_user() -> Option<InvokingUser> {
+    // SAFETY: geteuid has no preconditions and cannot fail
+    if unsafe { libc::geteuid() } != 0 {
+        return None;
+    }
+    // SAFETY: getpwuid and getpwnam return either null or a pointer to a valid passwd entry,
+    // which remains valid until the next call to either function.
+    // This program is single threaded while this function runs, and the entry is copied before returning
+    let pw = if let Some(uid) = env_id("SUDO_UID").or_else(|| env_id("PKEXEC_UID")) {
+        unsafe { libc::getpwuid(uid) }
+    } else if let Some(name) = std::env::var_os("DOAS_USER") {
+        let name = CString::new(name.as_bytes()).ok()?;
+        unsafe { libc::getpwnam(name.as_ptr()) }
+    } else {
+        return None;
+    };
+    if pw.is_null() {
+        return None;
+    }
+    let pw = unsafe { &*pw };
+    if pw.pw_uid == 0 {
+        return None;
+    }
+    Some(InvokingUser {
+        uid: pw.pw_uid,
+        gid: env_id("SUDO_GID").unwrap_or(pw.pw_gid),
+        name: unsafe { CStr::from_ptr(pw.pw_name) }.to_owned(),
+        home: OsStr::from_bytes(unsafe { CStr::from_ptr(pw.pw_dir) }.to_bytes()).into(),
+    })
+}
+
+#[cfg(not(unix))]
+pub fn invoking_user() -> Option<InvokingUser> {
+    None
+}
+
+#[cfg(unix)]
+fn env_id(var: &str) -> Option<u32> {
+    std::env::var(var).ok()?.parse().ok()
+}
+
+///
+/// Configures `cmd` to run as `user` rather than root, with that user's groups and home directory
+#[cfg(unix)]
+pub fn drop_privileges(cmd: &mut Command, user: &InvokingUser) {
+    let name = user.name.clone();
+    let (uid, gid) = (user.uid, user.gid);
+    // SAFETY: These calls only affect the child process. Commands are only spawned while this program is single threaded,
+    // so no lock can be held by another thread at the time of the fork.
+    // The supplementary groups and gid must be set before giving up root with setuid
+    unsafe {
+        cmd.pre_exec(move || {
+            if libc::initgroups(name.as_ptr(), gid as _) != 0
+                || libc::setgid(gid) != 0
+                || libc::setuid(uid) != 0
+            {
+                return Err(std::io::Error::last_os_error());
+            }
+            Ok(())
+        });
+    }
+    cmd.env("HOME", &user.home);
+    cmd.env("USER", OsStr::from_bytes(user.name.as_bytes()));
+    cmd.env("LOGNAME", OsStr::from_bytes(user.name.as_bytes()));
+}
+
+#[cfg(not(unix))]
+pub fn drop_privileges(_cmd: &mut Command, _user: &InvokingUser) {}
+
+///
+/// Gives `path`, and everything under it if it is a directory, to `user`, for files written as root on their behalf.
+/// Symbolic links are changed themselves, and not followed
+#[cfg(unix)]
+pub fn give_to(path: &Path, user: &InvokingUser) -> std::io::Result<()> {
+    std::os::unix::fs::lchown(path, Some(user.uid), Some(user.gid))?;
+    if std::fs::symlink_metadata(path)?.is_dir() {
+        for entry in std::fs::read_dir(path)? {
+            give_to(&entry?.path(), user)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn give_to(_path: &std::path::Path, _user: &InvokingUser) -> std::io::Result<()> {
+    Ok(())
+}
+
+///
+/// Finds the uid of the user `name`, which may also be given as a numeric uid
+#[cfg(unix)]
+pub fn lookup_user(name: &str) -> Option<u32> {
+    if let Ok(uid) = name.parse() {
+        return Some(uid);
+    }
+    let name = CString::new(name).ok()?;
+    // SAFETY: name is a valid, NUL-terminated string. getpwnam returns either null or a pointer to a valid passwd entry
+    let pw = unsafe { libc::getpwnam(name.as_ptr()) };
+    if pw.is_null() {
+        None
+    } else {
+        Some(unsafe { (*pw).pw_uid })
+    }
+}
+
+#[cfg(not(unix))]
+pub fn lookup_user(name: &str) -> Option<u32> {
+    name.parse().ok()
+}
+
+///
+/// Finds the gid of the group `name`, which may also be given as a numeric gid
+#[cfg(unix)]
+pub fn lookup_group(name: &str) -> Option<u32> {
+    if let Ok(gid) = name.parse() {
+        return Some(gid);
+    }
+    let name = CString::new(name).ok()?;
+    // SAFETY: name is a valid, NUL-terminated string. getgrnam returns either null or a pointer to a valid group entry
+    let gr = unsafe { libc::getgrnam(name.as_ptr()) };
+    if gr.is_null() {
+        None
+    } else {
+        Some(unsafe { (*gr).gr_gid })
+    }
+}
+
+#[cfg(not(unix))]
+pub fn lookup_group(name: &str) -> Option<u32> {
+    name.parse().ok()
+}
+
+///
+/// Changes the owner and group of `path` to those given by `--owner` and `--group`, if any
+#[cfg(unix)]
+pub fn apply_ownership(path: &Path, owner: Option<u32>, group: Option<u32>) -> std::io::Result<()> {
+    if owner.is_none() && group.is_none() {
+        return Ok(());
+    }
+    std::os::unix::fs::chown(path, owner, group)
+}
+
+#[cfg(not(unix))]
+pub fn apply_ownership(
+    _path: &std::path::Path,
+    _owner: Option<u32>,
+    _group: Option<u32>,
+) -> std::io::Result<()> {
+    Ok(())
+}
+
+///
+/// Whether linking `dest` to the artifact `src` would let another user change a file which root installed, where only root can write.
+/// That is so when running as root, the directory `dest` is installed into is owned by root, and a user other than root owns `src`, anything in it,
+/// or, for a symbolic link, a directory it is in (which could be renamed, and replaced)
+#[cfg(unix)]
+pub fn link_exposes_root(src: &Path, dest: &Path, symlink: bool) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    // SAFETY: geteuid has no preconditions and cannot fail
+    if unsafe { libc::geteuid() } != 0 {
+        return false;
+    }
+    let root_owned = dest
+        .ancestors()
+        .skip(1)
+        .find_map(|dir| std::fs::metadata(dir).ok())
+        .is_some_and(|md| md.uid() == 0);
+    let src = std::fs::canonicalize(src).unwrap_or_else(|_| src.to_path_buf());
+    root_owned
+        && (owned_by_other_user(&src)
+            || symlink
+                && src
+                    .ancestors()
+                    .skip(1)
+                    .any(|dir| std::fs::metadata(dir).is_ok_and(|md| md.uid() != 0)))
+}
+
+#[cfg(not(unix))]
+pub fn link_exposes_root(_src: &std::path::Path, _dest: &std::path::Path, _symlink: bool) -> bool {
+    false
+}
+
+///
+/// Whether a user other than root owns `path`, or, if it is a directory, anything in it
+#[cfg(unix)]
+fn owned_by_other_user(path: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    match std::fs::symlink_metadata(path) {
+        Ok(md) if md.uid() != 0 => true,
+        Ok(md) if md.is_dir() => std::fs::read_dir(path)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .any(|entry| owned_by_other_user(&entry.path())),
+        _ => false,
+    }
+}
@@ -0,0 +1,185 @@
+use std::{
+    fs,
+    io::{IsTerminal, Write},
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use crate::{output::Verbosity, Options};
+
+/// Installations of fewer files than this are made without a progress display
+const MIN_FILES: u64 = 64;
+
+const BAR_WIDTH: usize = 30;
+
+const REDRAW_INTERVAL: Duration = Duration::from_millis(100);
+
+///
+/// Tracks the number of files and bytes copied while installing the targets, counting each file of a directory target.
+/// On a terminal, this draws a progress bar on stderr. Otherwise, a plain counter line is printed every 10%.
+pub struct Progress {
+    enabled: bool,
+    tty: bool,
+    total_files: u64,
+    total_bytes: u64,
+    files: u64,
+    bytes: u64,
+    last_draw: Option<Instant>,
+    last_decile: u64,
+}
+
+impl Progress {
+    pub fn disabled() -> Self {
+        Self {
+            enabled: false,
+            tty: false,
+            total_files: 0,
+            total_bytes: 0,
+            files: 0,
+            bytes: 0,
+            last_draw: None,
+            last_decile: 0,
+        }
+    }
+
+    ///
+    /// Creates a progress display for installing `srcs`, the files and trees installed by each target.
+    /// The display is disabled for few files, with `--quiet`, or with `-v` (where each file is already reported)
+    pub fn for_sources<'a, I: IntoIterator<Item = &'a Path>>(opts: &Options, srcs: I) -> Self {
+        if opts.verbosity != Verbosity::Normal || opts.dry_run {
+            return Self::disabled();
+        }
+        // A source which cannot be read, such as that of an optional target, fails to install (or is skipped) without being counted
+        let (total_files, total_bytes) = srcs
+            .into_iter()
+            .filter_map(|src| measure(src).ok())
+            .fold((0, 0), |(files, bytes), (f, b)| (files + f, bytes + b));
+        if total_files < MIN_FILES {
+            return Self::disabled();
+        }
+        Self {
+            enabled: true,
+            tty: std::io::stderr().is_terminal(),
+            total_files,
+            total_bytes,
+            ..Self::disabled()
+        }
+    }
+
+    pub fn file_copied(&mut self, bytes: u64) {
+        self.advance(1, bytes);
+    }
+
+    ///
+    /// Counts the files under `src` at once, for a target which was installed without copying them one by one
+    pub fn tree_installed(&mut self, src: &Path) {
+        if let Ok((files, bytes)) = measure(src) {
+            self.advance(files, bytes);
+        }
+    }
+
+    ///
+    /// The number of files counted so far
+    pub fn files(&self) -> u64 {
+        self.files
+    }
+
+    fn advance(&mut self, files: u64, bytes: u64) {
+        if !self.enabled {
+            return;
+        }
+        self.files += files;
+        self.bytes += bytes;
+        if self.tty {
+            let now = Instant::now();
+            if self
+                .last_draw
+                .is_none_or(|last| now.duration_since(last) >= REDRAW_INTERVAL)
+            {
+                self.last_draw = Some(now);
+                self.draw();
+            }
+        } else {
+            let decile = self.files * 10 / self.total_files;
+            if decile > self.last_decile {
+                self.last_decile = decile;
+                eprintln!("   {}", self.counters());
+            }
+        }
+    }
+
+    ///
+    /// Erases the progress bar, so that a message can be printed on its line. It is drawn again with the next file
+    pub fn clear(&mut self) {
+        if self.enabled && self.tty && self.last_draw.take().is_some() {
+            eprint!("\r{}\r", " ".repeat(BAR_WIDTH + 60));
+        }
+    }
+
+    pub fn finish(&mut self) {
+        if self.enabled && self.tty {
+            self.draw();
+            eprintln!();
+        }
+        self.enabled = false;
+    }
+
+    fn counters(&self) -> String {
+        format!(
+            "{}/{} files, {}/{}",
+            self.files,
+            self.total_files,
+            human_bytes(self.bytes),
+            human_bytes(self.total_bytes)
+        )
+    }
+
+    fn draw(&self) {
+        let filled = (self.files.min(self.total_files) as usize * BAR_WIDTH)
+            / self.total_files.max(1) as usize;
+        let stderr = std::io::stderr();
+        let mut stderr = stderr.lock();
+        let _ = write!(
+            stderr,
+            "\r   [{}{}] {}",
+            "=".repeat(filled),
+            " ".repeat(BAR_WIDTH - filled),
+            self.counters()
+        );
+        let _ = stderr.flush();
+    }
+}
+
+///
+/// Counts the files under `path` and their total size
+pub fn measure(path: &Path) -> std::io::Result<(u64, u64)> {
+    let md = fs::metadata(path)?;
+    if md.is_dir() {
+        let mut totals = (0, 0);
+        for entry in fs::read_dir(path)? {
+            let (files, bytes) = measure(&entry?.path())?;
+            totals.0 += files;
+            totals.1 += bytes;
+        }
+        Ok(totals)
+    } else {
+        Ok((1, md.len()))
+    }
+}
+
+///
+/// `bytes`, in the largest binary unit it is at least one of, as in `1.5 MiB`
+pub fn human_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
@@ -0,0 +1,443 @@
+use std::{
+    collections::BTreeMap,
+    ffi::OsString,
+    fs,
+    io::{self, ErrorKind},
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+use install_dirs::dirs::InstallDirs;
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{
+    manifest::{InstallMethod, Rpath, Target},
+    strip, template, tree_entries, Options,
+};
+
+///
+/// A record of a previous installation of a package, stored as JSON under `<localstatedir>/lib/cargo-native-install`.
+/// The receipt is used to skip targets which have not changed since they were last installed
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct Receipt {
+    pub package: String,
+    #[serde(default)]
+    pub version: Option<String>,
+    #[serde(default, with = "path_serde")]
+    pub prefix: PathBuf,
+    #[serde(default)]
+    pub targets: BTreeMap<String, TargetReceipt>,
+    /// Whether there was no receipt to load, so which files the package installed is unknown
+    #[serde(skip)]
+    pub missing: bool,
+}
+
+///
+/// The files installed for a single target
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct TargetReceipt {
+    /// The path the target was installed to
+    #[serde(with = "path_serde")]
+    pub destination: PathBuf,
+    /// Identifies the artifact the target was installed from, and the options that affect the installed files
+    #[serde(default)]
+    pub fingerprint: Option<String>,
+    /// Every file, directory, and alias created by installing the target
+    #[serde(default, with = "path_serde::list")]
+    pub files: Vec<PathBuf>,
+    /// Identifies the artifact which was stripped to produce the installed file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub artifact: Option<String>,
+    /// Identifies the installed file, after it was stripped
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stripped: Option<String>,
+    /// The detached signature of the installed file, made by `--sign-artifacts`
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "path_serde::option"
+    )]
+    pub signature: Option<PathBuf>,
+    /// Commands to run with the shell before the files of the target are removed, from its `pre-uninstall`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pre_uninstall: Vec<String>,
+    /// The target was installed with `--develop`, so its destination is a link to the artifact, rather than a copy
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub develop: bool,
+}
+
+///
+/// Paths are recorded as strings, or, if they are not UTF-8, as arrays of their bytes, so that a receipt can record every file that was installed
+mod path_serde {
+    use std::path::{Path, PathBuf};
+
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(untagged)]
+    enum Repr {
+        Str(String),
+        Bytes(Vec<u8>),
+    }
+
+    fn to_repr(path: &Path) -> Repr {
+        match path.to_str() {
+            Some(s) => Repr::Str(s.to_owned()),
+            #[cfg(unix)]
+            None => Repr::Bytes(std::os::unix::ffi::OsStrExt::as_bytes(path.as_os_str()).to_vec()),
+            #[cfg(not(unix))]
+            None => Repr::Str(path.to_string_lossy().into_owned()),
+        }
+    }
+
+    fn from_repr(repr: Repr) -> PathBuf {
+        match repr {
+            Repr::Str(s) => PathBuf::from(s),
+            #[cfg(unix)]
+            Repr::Bytes(bytes) => {
+                <std::ffi::OsString as std::os::unix::ffi::OsStringExt>::from_vec(bytes).into()
+            }
+            #[cfg(not(unix))]
+            Repr::Bytes(bytes) => PathBuf::from(String::from_utf8_lossy(&bytes).into_owned()),
+        }
+    }
+
+    pub fn serialize<S: Serializer>(path: &Path, s: S) -> Result<S::Ok, S::Error> {
+        to_repr(path).serialize(s)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<PathBuf, D::Error> {
+        Repr::deserialize(d).map(from_repr)
+    }
+
+    pub mod list {
+        use super::*;
+
+        pub fn serialize<S: Serializer>(paths: &[PathBuf], s: S) -> Result<S::Ok, S::Error> {
+            s.collect_seq(paths.iter().map(|path| to_repr(path)))
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Vec<PathBuf>, D::Error> {
+            Vec::<Repr>::deserialize(d).map(|paths| paths.into_iter().map(from_repr).collect())
+        }
+    }
+
+    pub mod option {
+        use super::*;
+
+        pub fn serialize<S: Serializer>(path: &Option<PathBuf>, s: S) -> Result<S::Ok, S::Error> {
+            path.as_deref().map(to_repr).serialize(s)
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Option<PathBuf>, D::Error> {
+            Option::<Repr>::deserialize(d).map(|path| path.map(from_repr))
+        }
+    }
+}
+
+impl Receipt {
+    ///
+    /// The location of the receipt for `package`
+    pub fn path(dirs: &InstallDirs, package: &str) -> PathBuf {
+        let mut path = dirs.localstatedir.join("lib");
+        path.push("cargo-native-install");
+        path.push(format!("{}.json", package));
+        path
+    }
+
+    ///
+    /// Reads the receipt at `path`. If no package has been installed there yet, returns an empty receipt
+    pub fn load(path: &Path, package: &str) -> io::Result<Self> {
+        match fs::read(path) {
+            Ok(buf) => {
+                serde_json::from_slice(&buf).map_err(|e| io::Error::new(ErrorKind::InvalidData, e))
+            }
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(Receipt {
+                package: package.to_owned(),
+                missing: true,
+                ..Default::default()
+            }),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let buf = serde_json::to_vec_pretty(self)
+            .map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?;
+        fs::write(path, buf)
+    }
+
+    ///
+    /// Whether `name` was last installed to `dest` from an artifact with the given fingerprint, and so can be skipped
+    pub fn is_current(&self, name: &str, dest: &Path, fingerprint: Option<&str>) -> bool {
+        match (self.targets.get(name), fingerprint) {
+            (Some(entry), Some(fingerprint)) => {
+                entry.destination == dest
+                    && entry.fingerprint.as_deref() == Some(fingerprint)
+                    && dest.exists()
+            }
+            _ => false,
+        }
+    }
+
+    ///
+    /// Whether any target of the package installed `path`, the last time it was installed
+    pub fn owns(&self, path: &Path) -> bool {
+        self.targets
+            .values()
+            .any(|entry| entry.destination == path || entry.files.iter().any(|file| file == path))
+    }
+
+    ///
+    /// The existing files which installing `src` to `dest` would replace, and which the package did not install.
+    /// Existing directories are not replaced, only installed into, so they are never foreign
+    pub fn foreign_files(&self, src: Option<&Path>, dest: &Path, strip: usize) -> Vec<PathBuf> {
+        let mut files = Vec::new();
+        installed_files(src, dest, strip, &mut files);
+        files.retain(|file| {
+            fs::symlink_metadata(file).is_ok_and(|md| !md.is_dir()) && !self.owns(file)
+        });
+        files
+    }
+
+    pub fn record(&mut self, name: &str, entry: TargetReceipt) {
+        self.targets.insert(name.to_owned(), entry);
+    }
+
+    ///
+    /// Whether `dest` still holds the file produced by stripping the artifact at `src` with `args`, when `name` was last installed.
+    /// If so, the artifact does not need to be copied and stripped again
+    pub fn is_stripped(&self, name: &str, src: &Path, dest: &Path, args: &[String]) -> bool {
+        match self.targets.get(name) {
+            Some(TargetReceipt {
+                destination,
+                artifact: Some(artifact),
+                stripped: Some(stripped),
+                ..
+            }) => {
+                destination == dest
+                    && strip_fingerprint(src, args).as_ref() == Some(artifact)
+                    && tree_fingerprint(dest).as_ref() == Some(stripped)
+            }
+            _ => false,
+        }
+    }
+
+    pub fn record_stripped(
+        &mut self,
+        name: &str,
+        artifact: Option<String>,
+        stripped: Option<String>,
+    ) {
+        if let Some(entry) = self.targets.get_mut(name) {
+            entry.artifact = artifact;
+            entry.stripped = stripped;
+        }
+    }
+}
+
+///
+/// 64-bit FNV-1a, which is stable across versions of this program (unlike [`std::collections::hash_map::DefaultHasher`])
+struct Fnv(u64);
+
+impl Fnv {
+    fn write(&mut self, bytes: &[u8]) {
+        for b in bytes {
+            self.0 ^= u64::from(*b);
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+        // Separates consecutive fields, so that ("ab", "c") and ("a", "bc") differ
+        self.0 ^= 0xff;
+        self.0 = self.0.wrapping_mul(0x100000001b3);
+    }
+}
+
+///
+/// Computes a fingerprint for the artifact installed by `target`, from the size and modification time of each file,
+/// along with the options that affect how it is installed.
+/// Returns `None` for targets which have no artifact, which are never skipped
+pub fn fingerprint(
+    target: &Target,
+    mode: &Option<String>,
+    dirs: &InstallDirs,
+    opts: &Options,
+) -> Option<String> {
+    let src = target.target_file.as_deref()?;
+    let mut hasher = Fnv(0xcbf29ce484222325);
+    hasher.write(src.as_os_str().as_encoded_bytes());
+    hasher.write(mode.as_deref().unwrap_or("").as_bytes());
+    hasher.write(&target.strip_components.unwrap_or(0).to_le_bytes());
+    for mode in [&target.dir_mode, &target.file_mode] {
+        hasher.write(&[0]);
+        hasher.write(mode.as_deref().unwrap_or("").as_bytes());
+        hasher.write(&target.strip_components.unwrap_or(0).to_le_bytes());
+    }
+    let (owner, group) = (opts.owner, opts.group);
+    hasher.write(&owner.unwrap_or(u32::MAX).to_le_bytes());
+    hasher.write(&group.unwrap_or(u32::MAX).to_le_bytes());
+    hasher.write(&[opts.strip.as_deref().is_some() as u8]);
+    if opts.strip.as_deref().is_some() && target.strip == Some(true) {
+        for arg in strip::args(target, opts) {
+            hasher.write(arg.as_bytes());
+            hasher.write(&[0]);
+        }
+        hasher.write(&[opts.separate_debug as u8]);
+        hasher.write(
+            opts.debugdir
+                .as_deref()
+                .unwrap_or_else(|| Path::new(""))
+                .as_os_str()
+                .as_encoded_bytes(),
+        );
+    }
+    match &target.rpath {
+        Some(Rpath::Set(path)) => hasher.write(format!("set:{}", path).as_bytes()),
+        Some(Rpath::Remove) => hasher.write(b"remove"),
+        None => hasher.write(b""),
+    }
+    // A link made by `--develop` must be replaced by a copy when the target is next installed without it, and vice versa
+    hasher.write(&[opts.develop as u8]);
+    // Likewise for `install-method`. Copying is left out, so that the fingerprints of targets without one are unchanged
+    if let Some(method) = target
+        .install_method
+        .filter(|method| *method != InstallMethod::Copy)
+    {
+        hasher.write(&[method as u8]);
+    }
+    // A template is rendered again when what it is rendered with changes
+    if target.template_engine.is_some() {
+        hasher.write(template::context(target, dirs, opts).to_string().as_bytes());
+    }
+    hash_tree(src, Path::new(""), &mut hasher).ok()?;
+    Some(format!("{:016x}", hasher.0))
+}
+
+///
+/// Identifies the artifact at `src`, as stripped with `args`
+pub fn strip_fingerprint(src: &Path, args: &[String]) -> Option<String> {
+    let mut hasher = Fnv(0xcbf29ce484222325);
+    hash_tree(src, Path::new(""), &mut hasher).ok()?;
+    for arg in args {
+        hasher.write(arg.as_bytes());
+        hasher.write(&[0]);
+    }
+    Some(format!("{:016x}", hasher.0))
+}
+
+///
+/// Identifies the current contents of `path` from the size and modification time of each file
+pub fn tree_fingerprint(path: &Path) -> Option<String> {
+    let mut hasher = Fnv(0xcbf29ce484222325);
+    hash_tree(path, Path::new(""), &mut hasher).ok()?;
+    Some(format!("{:016x}", hasher.0))
+}
+
+fn hash_tree(src: &Path, rel: &Path, hasher: &mut Fnv) -> io::Result<()> {
+    let md = fs::metadata(src)?;
+    hasher.write(rel.as_os_str().as_encoded_bytes());
+    if md.is_dir() {
+        let mut entries = fs::read_dir(src)?
+            .map(|entry| entry.map(|entry| entry.file_name()))
+            .collect::<io::Result<Vec<_>>>()?;
+        entries.sort();
+        for name in entries {
+            hash_tree(&src.join(&name), &rel.join(&name), hasher)?;
+        }
+    } else {
+        let mtime = md
+            .modified()?
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        hasher.write(&md.len().to_le_bytes());
+        hasher.write(&mtime.as_nanos().to_le_bytes());
+    }
+    Ok(())
+}
+
+///
+/// Lists the files that installing `src` to `dest` creates, including `dest` itself, removing the first `strip` components of the paths in a directory
+pub fn installed_files(src: Option<&Path>, dest: &Path, strip: usize, files: &mut Vec<PathBuf>) {
+    files.push(dest.to_path_buf());
+    if let Some(src) = src.filter(|src| src.is_dir()) {
+        if let Ok(entries) = tree_entries(src, dest, strip) {
+            for (src_item, dest_item) in entries {
+                installed_files(Some(&src_item), &dest_item, 0, files);
+            }
+        }
+    }
+}
+
+///
+/// The existing files which `--adopt` replaces for a target. Each is moved aside, beside itself, while the target is installed,
+/// and removed once it is installed ([`Adoption::finish`]). If the installation fails, they are moved back when this is dropped
+#[derive(Debug, Default)]
+pub struct Adoption {
+    files: Vec<(PathBuf, PathBuf)>,
+}
+
+impl Adoption {
+    ///
+    /// Moves `file` aside, so that the target can be installed in its place
+    pub fn set_aside(&mut self, file: &Path) -> io::Result<()> {
+        let mut name = OsString::from(".");
+        name.push(file.file_name().unwrap_or_default());
+        name.push(".cni-adopted");
+        let aside = file.with_file_name(name);
+        fs::rename(file, &aside)?;
+        self.files.push((file.to_path_buf(), aside));
+        Ok(())
+    }
+
+    ///
+    /// Removes the files which were replaced
+    pub fn finish(mut self) {
+        for (_, aside) in self.files.drain(..) {
+            let _ = fs::remove_file(aside);
+        }
+    }
+}
+
+impl Drop for Adoption {
+    fn drop(&mut self) {
+        for (file, aside) in self.files.drain(..) {
+            let _ = fs::rename(aside, file);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tempdir::TempDir;
+
+    #[test]
+    #[cfg(unix)]
+    fn round_trips_path_which_is_not_utf8() {
+        use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+        let path = Path::new(OsStr::from_bytes(b"/usr/share/caf\xe9.txt"));
+        let mut receipt = Receipt {
+            package: "test".into(),
+            ..Default::default()
+        };
+        receipt.record(
+            "data",
+            TargetReceipt {
+                destination: path.to_path_buf(),
+                files: vec![path.to_path_buf()],
+                ..Default::default()
+            },
+        );
+        let dir = TempDir::new("test").unwrap();
+        let file = dir.path().join("test.json");
+        receipt.save(&file).unwrap();
+
+        let loaded = Receipt::load(&file, "test").unwrap();
+        assert_eq!(loaded.targets["data"].destination, path);
+        assert_eq!(loaded.targets["data"].files, [path]);
+    }
+}
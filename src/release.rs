@@ -0,0 +1,355 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use serde_json::Value;
+
+use crate::{output, output::Status, Error, Options, DEFAULT_TARGET};
+
+/// The extensions of archives made by `--dist`
+const ARCHIVE_EXTENSIONS: [&str; 8] = [
+    ".tar.gz", ".tgz", ".tar.xz", ".txz", ".tar.zst", ".tar.bz2", ".tar", ".zip",
+];
+
+fn is_archive(name: &str) -> bool {
+    ARCHIVE_EXTENSIONS.iter().any(|ext| name.ends_with(ext))
+}
+
+///
+/// A repository which publishes releases
+enum Repository {
+    /// `owner/repo` on github.com
+    GitHub(String),
+    /// A project on a GitLab instance, with its full path
+    GitLab { host: String, path: String },
+}
+
+impl Repository {
+    ///
+    /// Parses `https://github.com/owner/repo`, `https://gitlab.example.com/group/repo`, or `owner/repo` (on GitHub)
+    fn parse(source: &str) -> Option<Self> {
+        let rest = source.strip_prefix("https://").unwrap_or(source);
+        let rest = rest.trim_end_matches('/');
+        let rest = rest.strip_suffix(".git").unwrap_or(rest);
+        let (host, path) = match rest.split_once('/') {
+            Some((host, path)) if host.contains('.') => (host, path),
+            _ if rest.matches('/').count() == 1 => ("github.com", rest),
+            _ => return None,
+        };
+        if path.is_empty() {
+            return None;
+        }
+        if host == "github.com" {
+            Some(Repository::GitHub(path.to_owned()))
+        } else if host.starts_with("gitlab.") {
+            Some(Repository::GitLab {
+                host: host.to_owned(),
+                path: path.to_owned(),
+            })
+        } else {
+            None
+        }
+    }
+
+    ///
+    /// The URL of the API which describes the release `tag`
+    fn release_url(&self, tag: &str) -> String {
+        match self {
+            Repository::GitHub(repo) => {
+                format!(
+                    "https://api.github.com/repos/{}/releases/tags/{}",
+                    repo, tag
+                )
+            }
+            Repository::GitLab { host, path } => format!(
+                "https://{}/api/v4/projects/{}/releases/{}",
+                host,
+                path.replace('/', "%2F"),
+                tag.replace('/', "%2F")
+            ),
+        }
+    }
+
+    ///
+    /// The name and download URL of each file published with a release, given the response of [`Repository::release_url`]
+    fn assets(&self, release: &Value) -> Vec<(String, String)> {
+        let (list, url) = match self {
+            Repository::GitHub(_) => (release.get("assets"), "browser_download_url"),
+            Repository::GitLab { .. } => (
+                release.get("assets").and_then(|assets| assets.get("links")),
+                "direct_asset_url",
+            ),
+        };
+        list.and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(|asset| {
+                let name = asset.get("name")?.as_str()?;
+                let url = asset
+                    .get(url)
+                    .or_else(|| asset.get("url"))
+                    .and_then(Value::as_str)?;
+                Some((name.to_owned(), url.to_owned()))
+            })
+            .collect()
+    }
+}
+
+///
+/// The last component of the published file name `name`, so that a name such as `../x` cannot be downloaded outside the directory
+fn file_name(name: &str) -> Option<&str> {
+    name.rsplit(['/', '\\'])
+        .next()
+        .filter(|name| !matches!(*name, "" | "." | ".."))
+}
+
+///
+/// Downloads `url` to `dest` with curl, or wget if curl is not installed. Errors are not printed if the file is `optional`.
+/// Only https is used, even when following redirects, as the checksum is published beside the archive
+fn download(url: &str, dest: &Path, optional: bool, opts: &Options) -> Result<(), Error> {
+    if !url.starts_with("https://") {
+        return Err(Error::Release(format!(
+            "Refusing to download {}, only https URLs are used",
+            url
+        )));
+    }
+    let mut cmd = if let Ok(curl) = which::which("curl") {
+        let mut cmd = Command::new(curl);
+        cmd.args([
+            "-f",
+            "-s",
+            "-S",
+            "-L",
+            "--proto",
+            "=https",
+            "--proto-redir",
+            "=https",
+            "-o",
+        ])
+        .arg(dest)
+        .arg(url);
+        cmd
+    } else if let Ok(wget) = which::which("wget") {
+        let mut cmd = Command::new(wget);
+        cmd.args(["-q", "--https-only", "-O"]).arg(dest).arg(url);
+        cmd
+    } else {
+        return Err(Error::Spawn(
+            "curl".into(),
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                "neither curl nor wget is installed",
+            ),
+        ));
+    };
+    if optional {
+        cmd.stderr(Stdio::null());
+    }
+    output::verbose(opts, format_args!("Downloading {}", url));
+    output::command(opts, &cmd);
+    match cmd.status() {
+        Ok(status) if status.success() => Ok(()),
+        Ok(_) => Err(Error::Release(format!("Failed to download {}", url))),
+        Err(e) => Err(Error::Spawn(cmd.get_program().into(), e)),
+    }
+}
+
+///
+/// The SHA-256 digest of `path`, in hexadecimal, computed with `sha256sum` or `shasum`
+pub fn sha256(path: &Path) -> Result<String, Error> {
+    let mut cmd = match which::which("sha256sum") {
+        Ok(prg) => Command::new(prg),
+        Err(_) => {
+            let mut cmd = Command::new("shasum");
+            cmd.args(["-a", "256"]);
+            cmd
+        }
+    };
+    cmd.arg(path).stderr(Stdio::inherit());
+    let output = cmd
+        .output()
+        .map_err(|e| Error::Spawn(cmd.get_program().into(), e))?;
+    if !output.status.success() {
+        return Err(Error::Tool(cmd.get_program().into(), output.status));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .split_whitespace()
+        .next()
+        .unwrap_or_default()
+        .to_ascii_lowercase())
+}
+
+///
+/// Finds the digest of `name` in `sums`, which is either a single digest, or lines of `<digest> <file name>` (as written by sha256sum)
+fn find_digest(sums: &str, name: &str) -> Option<String> {
+    let mut single = None;
+    for line in sums.lines().filter(|line| !line.trim().is_empty()) {
+        let mut words = line.split_whitespace();
+        let digest = words.next()?;
+        match words.next() {
+            Some(file) if file.trim_start_matches('*') == name => {
+                return Some(digest.to_ascii_lowercase())
+            }
+            Some(_) => {}
+            None => single = Some(digest.to_ascii_lowercase()),
+        }
+    }
+    single
+}
+
+///
+/// Checks the archive downloaded to `archive` against `checksum`, the published checksum file, and `signature`, a detached signature, if one was published
+fn verify(
+    archive: &Path,
+    name: &str,
+    checksum: &Path,
+    signature: Option<&Path>,
+    opts: &Options,
+) -> Result<(), Error> {
+    let sums = fs::read_to_string(checksum)?;
+    let expected = find_digest(&sums, name).ok_or_else(|| {
+        Error::Release(format!("The published checksums do not include {}", name))
+    })?;
+    let actual = sha256(archive)?;
+    if actual != expected {
+        return Err(Error::Release(format!(
+            "Checksum mismatch for {}: expected {}, downloaded {}",
+            name, expected, actual
+        )));
+    }
+    output::verbose(opts, format_args!("Checksum of {} verified", name));
+
+    let signature = match signature {
+        Some(signature) => signature,
+        None => return Ok(()),
+    };
+    let gpg = match which::which("gpg") {
+        Ok(gpg) => gpg,
+        Err(_) => {
+            output::status(
+                opts,
+                Status::Warning,
+                format_args!(
+                    "Warning: not verifying the signature of {}, gpg is not installed",
+                    name
+                ),
+            );
+            return Ok(());
+        }
+    };
+    let mut cmd = Command::new(&gpg);
+    cmd.args(["--batch", "--verify"])
+        .arg(signature)
+        .arg(archive);
+    if opts.verbosity < crate::Verbosity::Verbose {
+        cmd.stderr(Stdio::null());
+    }
+    output::command(opts, &cmd);
+    match cmd.status() {
+        Ok(status) if status.success() => Ok(()),
+        Ok(_) => Err(Error::Release(format!(
+            "The signature of {} is not valid",
+            name
+        ))),
+        Err(e) => Err(Error::Spawn(gpg, e)),
+    }
+}
+
+///
+/// Downloads the release archive given by `source` (the URL of an archive, or a repository with `tag`) into `dir`,
+/// and verifies it. Returns the path of the archive
+pub fn fetch(
+    source: &str,
+    tag: Option<&str>,
+    dir: &Path,
+    opts: &Options,
+) -> Result<PathBuf, Error> {
+    let name = source.rsplit('/').next().unwrap_or_default();
+    let (name, url, checksum, signature) = if is_archive(name) {
+        // Only the archive itself is known, so look for its checksum beside it
+        let checksum = format!("{}.sha256", source);
+        let signature = format!("{}.asc", source);
+        (
+            name.to_owned(),
+            source.to_owned(),
+            Some(checksum),
+            Some(signature),
+        )
+    } else {
+        let repo = Repository::parse(source).ok_or_else(|| {
+            Error::Release(format!(
+                "{} is not an archive, or a repository on GitHub or GitLab",
+                source
+            ))
+        })?;
+        let tag = tag.ok_or(Error::MissingArgument {
+            option: "--from-release",
+            expected: "--tag, unless it is the URL of an archive",
+        })?;
+        let release_path = dir.join("release.json");
+        download(&repo.release_url(tag), &release_path, false, opts)?;
+        let release = fs::read(&release_path)?;
+        let release = serde_json::from_slice(&release)
+            .map_err(|e| Error::Release(format!("Invalid release description: {}", e)))?;
+        let assets = repo.assets(&release);
+        let archives = assets
+            .iter()
+            .filter(|(name, _)| is_archive(name))
+            .collect::<Vec<_>>();
+        let (name, url) = match archives
+            .iter()
+            .find(|(name, _)| name.contains(DEFAULT_TARGET))
+        {
+            Some(archive) => archive,
+            None if archives.len() == 1 => archives[0],
+            None => {
+                return Err(Error::Release(format!(
+                    "Release {} has no archive for {}",
+                    tag, DEFAULT_TARGET
+                )))
+            }
+        };
+        let find = |names: &[String]| {
+            assets
+                .iter()
+                .find(|(asset, _)| names.iter().any(|n| n.eq_ignore_ascii_case(asset)))
+                .map(|(_, url)| url.clone())
+        };
+        let checksum = find(&[
+            format!("{}.sha256", name),
+            format!("{}.sha256sum", name),
+            "SHA256SUMS".to_owned(),
+            "sha256sums.txt".to_owned(),
+            "checksums.txt".to_owned(),
+        ]);
+        let signature = find(&[format!("{}.asc", name), format!("{}.sig", name)]);
+        (name.clone(), url.clone(), checksum, signature)
+    };
+
+    let name = file_name(&name)
+        .ok_or_else(|| Error::Release(format!("{} is not a valid file name", name)))?
+        .to_owned();
+    output::status(opts, Status::Installed, format_args!("Downloading {}", url));
+    let archive = dir.join(&name);
+    download(&url, &archive, false, opts)?;
+
+    let checksum_path = dir.join("checksum");
+    match checksum {
+        Some(url) if download(&url, &checksum_path, true, opts).is_ok() => {}
+        _ => {
+            return Err(Error::Release(format!(
+                "No checksum is published for {}, so it cannot be verified",
+                name
+            )))
+        }
+    }
+    let signature_path = dir.join("signature");
+    let signature = match signature {
+        Some(url) if download(&url, &signature_path, true, opts).is_ok() => Some(&*signature_path),
+        _ => None,
+    };
+    verify(&archive, &name, &checksum_path, signature, opts)?;
+    Ok(archive)
+}
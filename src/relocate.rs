@@ -0,0 +1,336 @@
+use std::{
+    collections::BTreeSet,
+    fs,
+    io::{self, ErrorKind},
+    path::{Path, PathBuf},
+};
+
+use install_dirs::dirs::InstallDirs;
+
+use crate::{
+    elf, lock, manifest::Rpath, output, output::Status, receipt::Receipt, rpath, stow, sysroot,
+    Error, Options,
+};
+
+///
+/// `path`, moved from beneath `old` to beneath `new`, or `None` if it is not beneath `old`
+fn rebase(path: &Path, old: &Path, new: &Path) -> Option<PathBuf> {
+    path.strip_prefix(old).ok().map(|rel| new.join(rel))
+}
+
+///
+/// `text` with each occurrence of the directory `old` replaced by `new`, or `None` if it does not contain `old`.
+/// An occurrence must be followed by a separator, or by a character which cannot be part of a file name in a path list or command,
+/// so that `/usr/local` is not found in `/usr/localized`. The directories are compared as bytes, as they need not be UTF-8
+fn replace_prefix(text: &[u8], old: &[u8], new: &[u8]) -> Option<Vec<u8>> {
+    if old.is_empty() {
+        return None;
+    }
+    let mut out = Vec::with_capacity(text.len());
+    let mut rest = text;
+    let mut found = false;
+    while let Some(at) = rest.windows(old.len()).position(|window| window == old) {
+        let after = &rest[at + old.len()..];
+        let whole = after.first().copied().is_none_or(ends_name);
+        out.extend_from_slice(&rest[..at]);
+        out.extend_from_slice(if whole { new } else { old });
+        found |= whole;
+        rest = after;
+    }
+    out.extend_from_slice(rest);
+    found.then_some(out)
+}
+
+///
+/// [`replace_prefix`] in text, such as an rpath or a command. The text is left as it is if the new prefix would make it invalid UTF-8
+fn replace_prefix_text(text: &str, old: &[u8], new: &[u8]) -> Option<String> {
+    replace_prefix(text.as_bytes(), old, new).and_then(|out| String::from_utf8(out).ok())
+}
+
+///
+/// Whether `b`, after a directory, ends its name rather than continuing it
+fn ends_name(b: u8) -> bool {
+    !(b.is_ascii_alphanumeric() || b"._-+".contains(&b))
+}
+
+///
+/// Whether `buf` contains the directory `old`, as found by [`replace_prefix`]
+fn contains_prefix(buf: &[u8], old: &[u8]) -> bool {
+    (0..buf.len().saturating_sub(old.len()) + 1).any(|at| {
+        buf[at..].starts_with(old) && buf.get(at + old.len()).copied().is_none_or(ends_name)
+    })
+}
+
+///
+/// Moves `src` to `dest`, copying it then removing it if they are on different filesystems, or only copies it with `copy`.
+/// The modification time and permissions of a copy are kept, but not its owner
+fn move_file(src: &Path, dest: &Path, copy: bool) -> io::Result<()> {
+    if !copy && fs::rename(src, dest).is_ok() {
+        return Ok(());
+    }
+    fs::copy(src, dest)?;
+    if let Ok(time) = fs::metadata(src).and_then(|md| md.modified()) {
+        fs::File::options()
+            .write(true)
+            .open(dest)?
+            .set_modified(time)?;
+    }
+    if !copy {
+        fs::remove_file(src)?;
+    }
+    Ok(())
+}
+
+///
+/// Recreates the link at `src` as `dest`, pointing into the new prefix if it pointed into the old one by an absolute path
+fn move_link(src: &Path, dest: &Path, roots: &[(&Path, &Path)], copy: bool) -> io::Result<()> {
+    let target = fs::read_link(src)?;
+    let target = roots
+        .iter()
+        .find_map(|(old, new)| rebase(&target, old, new))
+        .unwrap_or(target);
+    let _ = fs::remove_file(dest);
+    #[cfg(unix)]
+    std::os::unix::fs::symlink(&target, dest)?;
+    #[cfg(windows)]
+    match fs::metadata(src).is_ok_and(|md| md.is_dir()) {
+        true => std::os::windows::fs::symlink_dir(&target, dest)?,
+        false => std::os::windows::fs::symlink_file(&target, dest)?,
+    }
+    if !copy {
+        fs::remove_file(src)?;
+    }
+    Ok(())
+}
+
+///
+/// Changes each directory of the rpath of the ELF file at `file` which is beneath `old` to be beneath `new`
+fn fix_rpath(file: &Path, old: &[u8], new: &[u8], opts: &Options) -> Result<(), Error> {
+    let search_path = match elf::dependencies(file) {
+        Ok(Some(deps)) => deps.search_path,
+        _ => return Ok(()),
+    };
+    let mut changed = false;
+    let search_path = search_path
+        .iter()
+        .map(|dir| match replace_prefix_text(dir, old, new) {
+            Some(dir) => {
+                changed = true;
+                dir
+            }
+            None => dir.clone(),
+        })
+        .collect::<Vec<_>>();
+    if changed {
+        rpath::apply(&Rpath::Set(search_path.join(":")), file, opts)?;
+    }
+    Ok(())
+}
+
+///
+/// Moves the installation of `package` recorded in its install receipt from the prefix of `dirs` to `new_prefix`,
+/// or copies it with `relocate --copy`. Symbolic links that point into the old prefix, the rpaths of binaries, and pkg-config files are changed to refer to the new prefix,
+/// and files which still contain the old prefix are reported, as they must be rebuilt for the new prefix.
+/// Files installed outside of the prefix (such as to `/etc`) are left where they are
+pub fn relocate(
+    dirs: &InstallDirs,
+    opts: &Options,
+    package: &str,
+    new_prefix: &Path,
+) -> Result<(), Error> {
+    let new_prefix = std::path::absolute(new_prefix)
+        .map_err(|e| Error::Relocate(new_prefix.to_path_buf(), e))?
+        .components()
+        .collect::<PathBuf>();
+    let receipt_path = sysroot::in_sysroot(&Receipt::path(dirs, package), opts);
+    let mut receipt = Receipt::load(&receipt_path, package)
+        .map_err(|e| Error::Receipt(receipt_path.clone(), e))?;
+    if receipt.targets.is_empty() {
+        return Err(Error::Receipt(receipt_path, ErrorKind::NotFound.into()));
+    }
+    let old_prefix = match receipt.prefix.as_os_str().is_empty() {
+        true => dirs.prefix.clone(),
+        false => receipt.prefix.clone(),
+    };
+    if new_prefix == old_prefix {
+        return Ok(());
+    }
+    let new_dirs = stow::rebase(dirs, &new_prefix);
+    let (old_root, new_root) = (
+        sysroot::in_sysroot(&old_prefix, opts),
+        sysroot::in_sysroot(&new_prefix, opts),
+    );
+    // The receipt records the files beneath the sysroot, while links and rpaths refer to the prefix itself
+    let roots = [
+        (old_root.as_path(), new_root.as_path()),
+        (old_prefix.as_path(), new_prefix.as_path()),
+    ];
+    let (old_bytes, new_bytes) = (
+        old_prefix.as_os_str().as_encoded_bytes(),
+        new_prefix.as_os_str().as_encoded_bytes(),
+    );
+
+    output::status(
+        opts,
+        Status::Installed,
+        format_args!(
+            "{} {} from {} to {}",
+            if opts.relocate_copy {
+                "Copying"
+            } else {
+                "Relocating"
+            },
+            package,
+            old_prefix.display(),
+            new_prefix.display()
+        ),
+    );
+    let _locks = match opts.dry_run {
+        true => (None, None),
+        false => (lock::lock(&old_root, opts)?, lock::lock(&new_root, opts)?),
+    };
+
+    let files = receipt
+        .targets
+        .values()
+        .flat_map(|entry| {
+            entry
+                .files
+                .iter()
+                .chain(std::iter::once(&entry.destination))
+                .chain(&entry.signature)
+        })
+        .cloned()
+        .collect::<BTreeSet<_>>();
+    let mut moves = Vec::new();
+    for file in files {
+        let md = match fs::symlink_metadata(&file) {
+            Ok(md) => md,
+            Err(_) => {
+                output::verbose(
+                    opts,
+                    format_args!("Skipping {}, which no longer exists", file.display()),
+                );
+                continue;
+            }
+        };
+        let dest = match rebase(&file, &old_root, &new_root) {
+            Some(dest) => dest,
+            None => {
+                output::verbose(
+                    opts,
+                    format_args!("Leaving {}, which is outside the prefix", file.display()),
+                );
+                continue;
+            }
+        };
+        // Nothing which does not belong to the package is replaced
+        if fs::symlink_metadata(&dest).is_ok_and(|existing| !(existing.is_dir() && md.is_dir())) {
+            return Err(Error::Relocate(
+                dest,
+                io::Error::new(ErrorKind::AlreadyExists, "it already exists"),
+            ));
+        }
+        moves.push((file, dest, md));
+    }
+
+    for (src, dest, md) in &moves {
+        output::verbose(
+            opts,
+            format_args!("{} -> {}", src.display(), dest.display()),
+        );
+        if opts.dry_run {
+            continue;
+        }
+        let moved = fs::create_dir_all(dest.parent().unwrap_or(dest)).and_then(|()| {
+            if md.file_type().is_symlink() {
+                move_link(src, dest, &roots, opts.relocate_copy)
+            } else if md.is_dir() {
+                fs::create_dir_all(dest).and_then(|()| fs::set_permissions(dest, md.permissions()))
+            } else {
+                move_file(src, dest, opts.relocate_copy)
+            }
+        });
+        moved.map_err(|e| Error::Relocate(src.clone(), e))?;
+    }
+    if opts.dry_run {
+        return Ok(());
+    }
+
+    if !opts.relocate_copy {
+        // Directories of the old prefix which the package alone used are now empty. Any which are not (as they hold files the package did not install) are kept
+        let mut emptied = moves
+            .iter()
+            .flat_map(|(src, _, _)| src.ancestors().skip(1))
+            .filter(|dir| dir.starts_with(&old_root) && *dir != old_root)
+            .collect::<BTreeSet<_>>();
+        emptied.extend(
+            moves
+                .iter()
+                .filter(|(_, _, md)| md.is_dir())
+                .map(|(src, _, _)| src.as_path()),
+        );
+        for dir in emptied.into_iter().rev() {
+            let _ = fs::remove_dir(dir);
+        }
+    }
+
+    for (_, dest, md) in &moves {
+        if !md.is_file() {
+            continue;
+        }
+        if sysroot::is_pkgconfig(dest) {
+            if let Ok(Some(text)) =
+                fs::read(dest).map(|text| replace_prefix(&text, old_bytes, new_bytes))
+            {
+                output::verbose(opts, format_args!("Rewriting {}", dest.display()));
+                fs::write(dest, text).map_err(|e| Error::Relocate(dest.clone(), e))?;
+            }
+            continue;
+        }
+        fix_rpath(dest, old_bytes, new_bytes, opts)?;
+        if fs::read(dest).is_ok_and(|buf| contains_prefix(&buf, old_bytes)) {
+            output::status(
+                opts,
+                Status::Warning,
+                format_args!(
+                    "Warning: {} refers to {}, rebuild the package with --prefix={} to change it",
+                    dest.display(),
+                    old_prefix.display(),
+                    new_prefix.display()
+                ),
+            );
+        }
+    }
+
+    let relocated = |path: &mut PathBuf| {
+        if let Some(moved) = roots.iter().find_map(|(old, new)| rebase(path, old, new)) {
+            *path = moved;
+        }
+    };
+    receipt.prefix = new_prefix.clone();
+    for entry in receipt.targets.values_mut() {
+        relocated(&mut entry.destination);
+        for file in entry.files.iter_mut().chain(&mut entry.signature) {
+            relocated(file);
+        }
+        for command in &mut entry.pre_uninstall {
+            if let Some(changed) = replace_prefix_text(command, old_bytes, new_bytes) {
+                *command = changed;
+            }
+        }
+    }
+    let new_receipt_path = sysroot::in_sysroot(&Receipt::path(&new_dirs, package), opts);
+    receipt
+        .save(&new_receipt_path)
+        .map_err(|e| Error::Receipt(new_receipt_path.clone(), e))?;
+    if !opts.relocate_copy && new_receipt_path != receipt_path {
+        fs::remove_file(&receipt_path).map_err(|e| Error::Receipt(receipt_path.clone(), e))?;
+        for dir in receipt_path.ancestors().skip(1) {
+            if !dir.starts_with(&old_root) || dir == old_root || fs::remove_dir(dir).is_err() {
+                break;
+            }
+        }
+    }
+    Ok(())
+}
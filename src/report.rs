@@ -0,0 +1,201 @@
+use std::{
+    fmt::Write,
+    path::PathBuf,
+    sync::{Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+
+use crate::{emit, events::Event, Error, Options};
+
+///
+/// The format of the file `--report` writes the result of each target to
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ReportFormat {
+    Junit,
+    Tap,
+}
+
+impl ReportFormat {
+    ///
+    /// Parses the value of `--report`, `<format>:<path>`
+    pub fn parse(s: &str) -> Option<(Self, PathBuf)> {
+        let (format, path) = s.split_once(':')?;
+        let format = match format {
+            "junit" => ReportFormat::Junit,
+            "tap" => ReportFormat::Tap,
+            _ => return None,
+        };
+        match path.is_empty() {
+            true => None,
+            false => Some((format, path.into())),
+        }
+    }
+}
+
+enum Outcome {
+    Passed,
+    Skipped(String),
+    Failed(String),
+}
+
+///
+/// The result of installing a target, as recorded from its [`Event`]s
+struct TargetResult {
+    name: String,
+    started: Instant,
+    time: Option<Duration>,
+    outcome: Outcome,
+}
+
+impl TargetResult {
+    fn new(name: &str, outcome: Outcome) -> Self {
+        TargetResult {
+            name: name.to_owned(),
+            started: Instant::now(),
+            time: None,
+            outcome,
+        }
+    }
+}
+
+///
+/// The results recorded since the last report was written
+static RESULTS: Mutex<Vec<TargetResult>> = Mutex::new(Vec::new());
+
+///
+/// Records the result of a target from `event`, with `--report`
+pub fn record(opts: &Options, event: &Event) {
+    if opts.report.is_none() {
+        return;
+    }
+    let mut results = RESULTS.lock().unwrap_or_else(PoisonError::into_inner);
+    let (name, outcome) = match *event {
+        Event::TargetStarted { name } => {
+            results.push(TargetResult::new(name, Outcome::Passed));
+            return;
+        }
+        Event::TargetSkipped { name, reason } => (name, Some(Outcome::Skipped(reason.to_owned()))),
+        Event::TargetFailed { name, error } => {
+            (name, Some(Outcome::Failed(error.message().to_owned())))
+        }
+        Event::TargetFinished { name } => (name, None),
+        Event::FileCopied { .. } => return,
+    };
+    // A binary is stripped once every target is installed, so a target may fail after it finished
+    let index = match results.iter().rposition(|result| result.name == name) {
+        Some(index) => index,
+        None => {
+            results.push(TargetResult::new(name, Outcome::Passed));
+            results.len() - 1
+        }
+    };
+    let result = &mut results[index];
+    if let Some(outcome) = outcome {
+        result.outcome = outcome;
+    }
+    if result.time.is_none() && !matches!(event, Event::TargetSkipped { .. }) {
+        result.time = Some(result.started.elapsed());
+    }
+}
+
+fn escape_xml(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+fn junit(results: &[TargetResult]) -> String {
+    let count = |skipped: bool| {
+        results
+            .iter()
+            .filter(|result| match result.outcome {
+                Outcome::Passed => false,
+                Outcome::Skipped(_) => skipped,
+                Outcome::Failed(_) => !skipped,
+            })
+            .count()
+    };
+    let time = |result: &TargetResult| result.time.unwrap_or_default().as_secs_f64();
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<testsuites>\n");
+    let _ = writeln!(
+        out,
+        "  <testsuite name=\"cargo-native-install\" tests=\"{}\" failures=\"{}\" skipped=\"{}\" time=\"{:.3}\">",
+        results.len(),
+        count(false),
+        count(true),
+        results.iter().map(time).sum::<f64>()
+    );
+    for result in results {
+        let _ = write!(
+            out,
+            "    <testcase classname=\"install-targets\" name=\"{}\" time=\"{:.3}\"",
+            escape_xml(&result.name),
+            time(result)
+        );
+        let _ = match &result.outcome {
+            Outcome::Passed => writeln!(out, "/>"),
+            Outcome::Skipped(reason) => writeln!(
+                out,
+                ">\n      <skipped message=\"{}\"/>\n    </testcase>",
+                escape_xml(reason)
+            ),
+            Outcome::Failed(message) => writeln!(
+                out,
+                ">\n      <failure message=\"{}\">{}</failure>\n    </testcase>",
+                escape_xml(message.lines().next().unwrap_or_default()),
+                escape_xml(message)
+            ),
+        };
+    }
+    out.push_str("  </testsuite>\n</testsuites>\n");
+    out
+}
+
+fn tap(results: &[TargetResult]) -> String {
+    let mut out = format!("TAP version 13\n1..{}\n", results.len());
+    for (number, result) in (1..).zip(results) {
+        // `#` starts a directive, such as SKIP
+        let name = result.name.replace('#', "\\#");
+        let _ = match &result.outcome {
+            Outcome::Passed => writeln!(out, "ok {} - {}", number, name),
+            Outcome::Skipped(reason) => {
+                writeln!(out, "ok {} - {} # SKIP {}", number, name, reason)
+            }
+            Outcome::Failed(message) => writeln!(
+                out,
+                "not ok {} - {}\n  ---\n  message: {}\n  ...",
+                number,
+                name,
+                serde_json::Value::from(message.as_str())
+            ),
+        };
+    }
+    out
+}
+
+///
+/// Writes the results recorded since the last report to the file given by `--report`, as JUnit XML or TAP.
+/// If the installation failed with `error` without a target failing (as when the manifest cannot be read), the error is reported as the failure of a result of its own,
+/// so that a failed installation never has a passing report
+pub fn write(opts: &Options, error: Option<&Error>) -> Result<(), Error> {
+    let (format, path) = match &opts.report {
+        Some(report) => report,
+        None => return Ok(()),
+    };
+    let mut results = std::mem::take(&mut *RESULTS.lock().unwrap_or_else(PoisonError::into_inner));
+    let target_failed = results
+        .iter()
+        .any(|result| matches!(result.outcome, Outcome::Failed(_)));
+    if let (Some(error), false) = (error, target_failed) {
+        let mut result = TargetResult::new("installation", Outcome::Failed(error.to_string()));
+        result.time = Some(Duration::ZERO);
+        results.push(result);
+    }
+    let contents = match format {
+        ReportFormat::Junit => junit(&results),
+        ReportFormat::Tap => tap(&results),
+    };
+    emit::write(opts, path, contents)
+}
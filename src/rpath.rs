@@ -0,0 +1,40 @@
+use std::{path::Path, process::Command};
+
+use crate::{
+    elf::{self, RpathEdit},
+    manifest::Rpath,
+    output, path_arg, Error, Options,
+};
+
+///
+/// Changes the rpath of the installed binary at `file`, with patchelf if it is found.
+/// Otherwise, the binary is edited directly, which can remove its rpath, or replace it with one that is no longer than it
+pub fn apply(rpath: &Rpath, file: &Path, opts: &Options) -> Result<(), Error> {
+    output::verbose(
+        opts,
+        format_args!("Changing the rpath of {}", file.display()),
+    );
+    if let Some(patchelf) = &opts.patchelf {
+        let mut cmd = Command::new(patchelf);
+        match rpath {
+            Rpath::Set(path) => cmd.arg("--set-rpath").arg(path),
+            Rpath::Remove => cmd.arg("--remove-rpath"),
+        };
+        cmd.arg(path_arg(file));
+        output::command(opts, &cmd);
+        return match cmd.status() {
+            Ok(status) if status.success() => Ok(()),
+            Ok(status) => Err(Error::Tool(patchelf.clone(), status)),
+            Err(e) => Err(Error::Spawn(patchelf.clone(), e)),
+        };
+    }
+
+    let edit = match rpath {
+        Rpath::Set(path) => RpathEdit::Set(path),
+        Rpath::Remove => RpathEdit::Remove,
+    };
+    match elf::edit_rpath(file, edit)? {
+        true => Ok(()),
+        false => Err(Error::NoPatchelf(file.to_path_buf())),
+    }
+}
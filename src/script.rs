@@ -0,0 +1,826 @@
+use std::{
+    collections::HashMap,
+    ffi::{OsStr, OsString},
+    fmt::Write as _,
+    path::{Path, PathBuf},
+};
+
+use install_dirs::dirs::InstallDirs;
+
+use crate::{
+    convert_to_path, entry_mode, hooks, manifest::InstallMethod, manifest::Target,
+    manifest::TargetType, manpage, missing_optional, normalize_path, output, output::Status,
+    pattern_modes, target_destination, target_mode, Error, Options,
+};
+
+///
+/// A single operation of an installation, as reproduced by `--emit-script` and `--emit-ninja`
+#[derive(Clone, Debug)]
+pub enum Step {
+    /// Runs the `run` target `file`, in `dir`, which fails unless it exits with one of `codes`
+    Run {
+        file: PathBuf,
+        dir: Option<PathBuf>,
+        codes: Vec<i32>,
+    },
+    /// Creates the directory `dir`, for a directory target without a source
+    Mkdir { dir: PathBuf, mode: Option<String> },
+    /// Copies the file `src` to `dest`, creating its parent directories
+    Install {
+        src: PathBuf,
+        dest: PathBuf,
+        mode: Option<String>,
+    },
+    /// Copies the contents of the directory `src` into `dest`, removing the first `strip` components of their paths, then applies `dir-mode` and `file-mode` to what it contains,
+    /// followed by each pattern of `modes` and its mode, from the least to the most specific. With `hard_link`, the files are hard links to those of `src`, rather than copies
+    CopyTree {
+        src: PathBuf,
+        dest: PathBuf,
+        strip: usize,
+        hard_link: bool,
+        dir_mode: Option<String>,
+        file_mode: Option<String>,
+        modes: Vec<(String, String)>,
+    },
+    /// Renders the manual page `src` to `dest` with `prg`, which is given `args`, then `dest` and `src`
+    Render {
+        prg: &'static str,
+        args: &'static [&'static str],
+        src: PathBuf,
+        dest: PathBuf,
+        mode: Option<String>,
+    },
+    /// Creates the symbolic link `link` to `src`
+    Link { src: PathBuf, link: PathBuf },
+    /// Links `dest` to the artifact `src`, with a hard link if `hard` is set, and otherwise a symbolic link, creating its parent directories
+    LinkArtifact {
+        src: PathBuf,
+        dest: PathBuf,
+        hard: bool,
+    },
+    /// Runs `command`, the `post-install` command of the target, with the shell
+    Command { command: String },
+}
+
+impl Step {
+    ///
+    /// The paths on the filesystem which the step names
+    pub fn paths(&self) -> Vec<&Path> {
+        match self {
+            Step::Run { file, dir, .. } => std::iter::once(file.as_path())
+                .chain(dir.as_deref())
+                .collect(),
+            Step::Mkdir { dir, .. } => vec![dir],
+            Step::Install { src, dest, .. }
+            | Step::CopyTree { src, dest, .. }
+            | Step::Render { src, dest, .. } => vec![src, dest],
+            Step::Link { src, link } => vec![src, link],
+            Step::LinkArtifact { src, dest, .. } => vec![src, dest],
+            Step::Command { .. } => Vec::new(),
+        }
+    }
+}
+
+///
+/// Quotes `s` for a POSIX shell, in single quotes. Bytes which are not UTF-8 are written as `"$(printf '\377')"`,
+/// so that the shell sees exactly the bytes of `s`, rather than replacement characters
+pub fn quote_sh(s: &OsStr) -> String {
+    #[cfg(unix)]
+    let bytes = std::os::unix::ffi::OsStrExt::as_bytes(s);
+    #[cfg(not(unix))]
+    let lossy = s.to_string_lossy();
+    #[cfg(not(unix))]
+    let bytes = lossy.as_bytes();
+    let mut out = String::new();
+    for chunk in bytes.utf8_chunks() {
+        if !chunk.valid().is_empty() {
+            write!(out, "'{}'", chunk.valid().replace('\'', "'\\''")).unwrap();
+        }
+        for byte in chunk.invalid() {
+            write!(out, "\"$(printf '\\{:03o}')\"", byte).unwrap();
+        }
+    }
+    if out.is_empty() {
+        out.push_str("''");
+    }
+    out
+}
+
+///
+/// `dir` with the wildcards of `find -path` escaped, so that it only matches itself
+fn escape_wildcards(dir: &Path) -> OsString {
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::{OsStrExt, OsStringExt};
+        let mut out = Vec::new();
+        for &byte in dir.as_os_str().as_bytes() {
+            if matches!(byte, b'*' | b'?' | b'[' | b'\\') {
+                out.push(b'\\');
+            }
+            out.push(byte);
+        }
+        OsString::from_vec(out)
+    }
+    #[cfg(not(unix))]
+    {
+        let mut out = String::new();
+        for c in dir.to_string_lossy().chars() {
+            if matches!(c, '*' | '?' | '[' | '\\') {
+                out.push('\\');
+            }
+            out.push(c);
+        }
+        out.into()
+    }
+}
+
+///
+/// The test of `find` which selects the files in `dir` that the pattern of `modes` matches, quoted for the shell.
+/// As when installing natively, the pattern is matched against the paths relative to `dir`.
+/// `*` in `find -path` also matches `/`, so `**` is the same as `*`
+pub fn find_test(dir: &Path, pattern: &str) -> String {
+    if !pattern.contains('/') {
+        return format!("-name {}", quote_sh(OsStr::new(pattern)));
+    }
+    let path = |within: &str| {
+        let mut path = escape_wildcards(dir);
+        path.push(within);
+        path.push(pattern.replace("**", "*"));
+        format!("-path {}", quote_sh(&path))
+    };
+    match pattern.contains("**") {
+        true => path("/"),
+        // The pattern matches the last components of the path, which are either the whole path, or follow a directory
+        false => format!("\\( {} -o {} \\)", path("/"), path("/*/")),
+    }
+}
+
+///
+/// The steps that install each of `targets` to `dirs`, in order, with the name of the target they belong to.
+/// Sources are absolute, so that the steps can be run from anywhere, but they must already be built
+pub fn steps(
+    dirs: &InstallDirs,
+    targets: &HashMap<String, Target>,
+    opts: &Options,
+) -> Result<Vec<(String, Vec<Step>)>, Error> {
+    let cwd = std::env::current_dir()?;
+    let mut names = match &opts.install_target {
+        Some(name) if !targets.contains_key(name) => return Err(Error::NoSuchTarget(name.clone())),
+        Some(name) => vec![name],
+        None => targets.keys().collect(),
+    };
+    names.sort();
+    let mut out = Vec::new();
+    for name in names {
+        let target = &targets[name];
+        if !target.is_selected(opts) {
+            continue;
+        }
+        if let Some(src) = missing_optional(target) {
+            output::status(
+                opts,
+                Status::Skipped,
+                format_args!(
+                    "Skipping {}, {} does not exist, and the target is optional",
+                    name,
+                    src.display()
+                ),
+            );
+            continue;
+        }
+        // Checks are only run by --run-checks, which cannot be used with a script
+        if target.type_ == Some(TargetType::Check) {
+            continue;
+        }
+        // The rendered copy only exists while installing, so the script would have nothing to copy
+        if target.template_engine.is_some() && !target.directory {
+            return Err(Error::EmitTemplate(name.clone()));
+        }
+        let src = target.target_file.as_ref().map(|src| cwd.join(src));
+        if target.type_ == Some(TargetType::Run) {
+            let dir = target
+                .install_dir
+                .as_deref()
+                .map(|path| convert_to_path(path, dirs, Path::new("")));
+            match src {
+                Some(file) => {
+                    let codes = target.accepted_codes();
+                    out.push((name.clone(), vec![Step::Run { file, dir, codes }]))
+                }
+                None => {
+                    return Err(Error::InvalidArgument {
+                        option: "run target",
+                        value: name.clone(),
+                        expected: "a file",
+                    })
+                }
+            }
+            continue;
+        }
+        let dest = match target_destination(dirs, target, opts) {
+            Some(dest) => dest,
+            None => continue,
+        };
+        let mode = target_mode(target, opts);
+        let renderer = src
+            .as_deref()
+            .filter(|_| target.type_ == Some(TargetType::Man) && !target.directory)
+            .and_then(manpage::renderer);
+        let method = target.install_method.unwrap_or_default();
+        let step = match (src, renderer) {
+            (Some(src), Some((prg, args))) => Step::Render {
+                prg,
+                args,
+                src,
+                dest: dest.clone(),
+                mode,
+            },
+            (Some(src), None) if method == InstallMethod::Symlink => Step::LinkArtifact {
+                src,
+                dest: dest.clone(),
+                hard: false,
+            },
+            // A linked artifact is shared with the build directory, so its modes are left as they are
+            (Some(src), None) if target.directory && method == InstallMethod::Hardlink => {
+                Step::CopyTree {
+                    src,
+                    dest: dest.clone(),
+                    strip: target.strip_components.unwrap_or(0),
+                    hard_link: true,
+                    dir_mode: None,
+                    file_mode: None,
+                    modes: Vec::new(),
+                }
+            }
+            (Some(src), None) if method == InstallMethod::Hardlink => Step::LinkArtifact {
+                src,
+                dest: dest.clone(),
+                hard: true,
+            },
+            (Some(src), None) if target.directory => Step::CopyTree {
+                src,
+                dest: dest.clone(),
+                strip: target.strip_components.unwrap_or(0),
+                hard_link: false,
+                dir_mode: target
+                    .dir_mode
+                    .as_ref()
+                    .and_then(|_| entry_mode(target, true, opts)),
+                file_mode: target
+                    .file_mode
+                    .as_ref()
+                    .and_then(|_| entry_mode(target, false, opts)),
+                modes: pattern_modes(target, opts),
+            },
+            (Some(src), None) => Step::Install {
+                src,
+                dest: dest.clone(),
+                mode,
+            },
+            (None, _) if target.directory => Step::Mkdir {
+                dir: dest.clone(),
+                mode: entry_mode(target, true, opts),
+            },
+            (None, _) => {
+                output::status(
+                    opts,
+                    Status::Warning,
+                    format_args!("Warning: skipping {}, no source file given", name),
+                );
+                continue;
+            }
+        };
+        let mut steps = vec![step];
+        for alias in target.installed_aliases.iter().flatten() {
+            let link = normalize_path(&convert_to_path(
+                alias,
+                dirs,
+                dest.parent().unwrap_or_else(|| Path::new("")),
+            ));
+            steps.push(Step::Link {
+                src: dest.clone(),
+                link,
+            });
+        }
+        if let Some(command) = &target.post_install {
+            steps.push(Step::Command {
+                command: hooks::expand(command, dirs),
+            });
+        }
+        out.push((name.clone(), steps));
+    }
+    Ok(out)
+}
+
+///
+/// The owner and group given to `chown`, from `--owner` and `--group`
+pub fn chown_spec(opts: &Options) -> Option<String> {
+    match (opts.owner, opts.group) {
+        (None, None) => None,
+        (Some(owner), None) => Some(owner.to_string()),
+        (None, Some(group)) => Some(format!(":{}", group)),
+        (Some(owner), Some(group)) => Some(format!("{}:{}", owner, group)),
+    }
+}
+
+///
+/// The language of the script written by `--emit-script`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ScriptKind {
+    /// A POSIX shell script, using `install`, `mkdir`, `cp`, and `ln`
+    Sh,
+    /// A PowerShell script
+    Ps1,
+}
+
+impl ScriptKind {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "sh" => Some(ScriptKind::Sh),
+            "ps1" => Some(ScriptKind::Ps1),
+            _ => None,
+        }
+    }
+
+    pub fn quote(self, path: impl AsRef<Path>) -> String {
+        let path = path.as_ref();
+        match self {
+            ScriptKind::Sh => quote_sh(path.as_os_str()),
+            ScriptKind::Ps1 => format!("'{}'", path.to_string_lossy().replace('\'', "''")),
+        }
+    }
+
+    fn mkdir(self, out: &mut String, dir: &Path) {
+        match self {
+            ScriptKind::Sh => writeln!(out, "mkdir -p {}", self.quote(dir)),
+            ScriptKind::Ps1 => writeln!(
+                out,
+                "New-Item -ItemType Directory -Force -Path {} | Out-Null",
+                self.quote(dir)
+            ),
+        }
+        .unwrap();
+    }
+
+    fn chmod(self, out: &mut String, path: &Path, mode: Option<&str>) {
+        if let (ScriptKind::Sh, Some(mode)) = (self, mode) {
+            writeln!(out, "chmod {} {}", self.quote(mode), self.quote(path)).unwrap();
+        }
+    }
+
+    ///
+    /// Applies `mode` to everything of the type `kind` (as given to `find -type`) in the directory `dir`
+    fn chmod_tree(self, out: &mut String, dir: &Path, kind: &str, mode: Option<&str>) {
+        if let (ScriptKind::Sh, Some(mode)) = (self, mode) {
+            writeln!(
+                out,
+                "find {} -type {} -exec chmod {} {{}} +",
+                self.quote(dir),
+                kind,
+                self.quote(mode)
+            )
+            .unwrap();
+        }
+    }
+
+    fn chmod_matching(self, out: &mut String, dir: &Path, pattern: &str, mode: &str) {
+        if self == ScriptKind::Sh {
+            writeln!(
+                out,
+                "find {} -type f {} -exec chmod {} {{}} +",
+                self.quote(dir),
+                find_test(dir, pattern),
+                self.quote(mode)
+            )
+            .unwrap();
+        }
+    }
+
+    fn chown(self, out: &mut String, path: &Path, opts: &Options) {
+        // PowerShell has no portable equivalent, so ownership is only set by shell scripts
+        if let (ScriptKind::Sh, Some(owner)) = (self, chown_spec(opts)) {
+            writeln!(out, "chown {} {}", self.quote(owner), self.quote(path)).unwrap();
+        }
+    }
+
+    fn install(
+        self,
+        out: &mut String,
+        src: &Path,
+        dest: &Path,
+        mode: Option<&str>,
+        opts: &Options,
+    ) {
+        match self {
+            ScriptKind::Sh => {
+                out.push_str("install");
+                if !opts.no_create_dirs {
+                    out.push_str(" -D");
+                }
+                if let Some(mode) = mode {
+                    write!(out, " -m {}", self.quote(mode)).unwrap();
+                }
+                writeln!(out, " {} {}", self.quote(src), self.quote(dest)).unwrap();
+            }
+            ScriptKind::Ps1 => {
+                if let Some(parent) = dest.parent().filter(|_| !opts.no_create_dirs) {
+                    self.mkdir(out, parent);
+                }
+                writeln!(
+                    out,
+                    "Copy-Item -Force -LiteralPath {} -Destination {}",
+                    self.quote(src),
+                    self.quote(dest)
+                )
+                .unwrap();
+            }
+        }
+    }
+
+    ///
+    /// Copies the contents of `src` into `dest`. With `hard_link`, shell scripts link the files with `cp -l`, but PowerShell scripts copy them
+    fn copy_tree(self, out: &mut String, src: &Path, dest: &Path, strip: usize, hard_link: bool) {
+        self.mkdir(out, dest);
+        let cp = if hard_link { "cp -Rl" } else { "cp -R" };
+        match self {
+            ScriptKind::Sh if strip == 0 => writeln!(
+                out,
+                "{} {} {}",
+                cp,
+                self.quote(src.join(".")),
+                self.quote(dest)
+            ),
+            // The entries below the components being removed are copied
+            ScriptKind::Sh => writeln!(
+                out,
+                "find {} -mindepth {depth} -maxdepth {depth} -exec {} {{}} {} \\;",
+                self.quote(src),
+                cp,
+                self.quote(dest),
+                depth = strip + 1
+            ),
+            ScriptKind::Ps1 => writeln!(
+                out,
+                "Copy-Item -Recurse -Force -Path {} -Destination {}",
+                self.quote(
+                    std::iter::repeat_n("*", strip + 1)
+                        .fold(src.to_path_buf(), |path, c| path.join(c))
+                ),
+                self.quote(dest)
+            ),
+        }
+        .unwrap();
+    }
+
+    ///
+    /// Links `dest` to the artifact `src`, replacing the file at `dest`
+    fn link_artifact(self, out: &mut String, src: &Path, dest: &Path, hard: bool) {
+        if let Some(parent) = dest.parent() {
+            self.mkdir(out, parent);
+        }
+        match (self, hard) {
+            (ScriptKind::Sh, false) => self.link(out, src, dest),
+            (ScriptKind::Sh, true) => {
+                writeln!(out, "ln -f {} {}", self.quote(src), self.quote(dest)).unwrap()
+            }
+            (ScriptKind::Ps1, false) => self.link(out, src, dest),
+            (ScriptKind::Ps1, true) => writeln!(
+                out,
+                "New-Item -ItemType HardLink -Force -Path {} -Target {} | Out-Null",
+                self.quote(dest),
+                self.quote(src)
+            )
+            .unwrap(),
+        }
+    }
+
+    fn link(self, out: &mut String, src: &Path, link: &Path) {
+        match self {
+            ScriptKind::Sh => writeln!(out, "ln -sfn {} {}", self.quote(src), self.quote(link)),
+            ScriptKind::Ps1 => writeln!(
+                out,
+                "New-Item -ItemType SymbolicLink -Force -Path {} -Target {} | Out-Null",
+                self.quote(link),
+                self.quote(src)
+            ),
+        }
+        .unwrap();
+    }
+
+    ///
+    /// Runs `prg` with `args`, then `dest` and `src`, as a manual page is rendered, followed by the commands in `then`.
+    /// Like the installer, the page is skipped with a warning if `prg` is not installed
+    fn render(
+        self,
+        out: &mut String,
+        (prg, args): (&str, &[&str]),
+        src: &Path,
+        dest: &Path,
+        then: &str,
+    ) {
+        match self {
+            ScriptKind::Sh => writeln!(out, "if command -v {} >/dev/null 2>&1; then", prg),
+            ScriptKind::Ps1 => writeln!(
+                out,
+                "if (Get-Command {} -ErrorAction SilentlyContinue) {{",
+                prg
+            ),
+        }
+        .unwrap();
+        if let Some(parent) = dest.parent() {
+            out.push_str("    ");
+            self.mkdir(out, parent);
+        }
+        out.push_str("    ");
+        if self == ScriptKind::Ps1 {
+            out.push_str("& ");
+        }
+        out.push_str(prg);
+        for arg in args {
+            write!(out, " {}", arg).unwrap();
+        }
+        writeln!(out, " {} {}", self.quote(dest), self.quote(src)).unwrap();
+        for line in then.lines() {
+            writeln!(out, "    {}", line).unwrap();
+        }
+        let warning = format!(
+            "skipping {}, {} is needed to render it",
+            dest.display(),
+            prg
+        );
+        match self {
+            ScriptKind::Sh => writeln!(
+                out,
+                "else\n    echo {} >&2\nfi",
+                self.quote(format!("Warning: {}", warning))
+            ),
+            ScriptKind::Ps1 => writeln!(
+                out,
+                "}} else {{\n    Write-Warning {}\n}}",
+                self.quote(warning)
+            ),
+        }
+        .unwrap();
+    }
+
+    ///
+    /// Runs `body`, the steps of the target `name`, only if `command` succeeds
+    fn only_if(self, out: &mut String, name: &str, command: &str, body: &str) {
+        match self {
+            ScriptKind::Sh => writeln!(out, "if ( {} ) >/dev/null 2>&1; then", command),
+            ScriptKind::Ps1 => writeln!(
+                out,
+                "& cmd /C {} | Out-Null\nif ($LASTEXITCODE -eq 0) {{",
+                self.quote(command)
+            ),
+        }
+        .unwrap();
+        for line in body.lines() {
+            writeln!(out, "    {}", line).unwrap();
+        }
+        let notice = format!("skipping {}, its only-if command failed", name);
+        match self {
+            ScriptKind::Sh => writeln!(out, "else\n    echo {} >&2\nfi", self.quote(notice)),
+            ScriptKind::Ps1 => {
+                writeln!(out, "}} else {{\n    Write-Host {}\n}}", self.quote(notice))
+            }
+        }
+        .unwrap();
+    }
+
+    ///
+    /// Runs the `run` target `file` in `dir`. Like the installer, exit codes 2, 10, and 20 do not stop the script
+    fn run(self, out: &mut String, file: &Path, dir: Option<&Path>, codes: &[i32]) {
+        match self {
+            ScriptKind::Sh => {
+                let cd = dir.map_or_else(String::new, |dir| format!("cd {} && ", self.quote(dir)));
+                write!(out, "({}{})", cd, self.quote(file)).unwrap();
+                // The script stops at the first command which fails, so a success which is not accepted must exit explicitly
+                if !codes.contains(&0) {
+                    out.push_str(" && exit 1");
+                }
+                let others = codes
+                    .iter()
+                    .filter(|code| **code != 0)
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>();
+                match others.is_empty() {
+                    // Failing exits the script by itself, unless the command is followed by `&& exit 1`
+                    true if codes.contains(&0) => out.push('\n'),
+                    true => out.push_str(" || exit 1\n"),
+                    false => writeln!(
+                        out,
+                        " || case $? in {}) ;; *) exit 1 ;; esac",
+                        others.join("|")
+                    )
+                    .unwrap(),
+                }
+            }
+            ScriptKind::Ps1 => {
+                if let Some(dir) = dir {
+                    writeln!(out, "Push-Location {}", self.quote(dir)).unwrap();
+                }
+                writeln!(out, "& {}", self.quote(file)).unwrap();
+                let codes = codes
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(",");
+                writeln!(out, "if ($LASTEXITCODE -notin @({})) {{ exit 1 }}", codes).unwrap();
+                if dir.is_some() {
+                    out.push_str("Pop-Location\n");
+                }
+            }
+        }
+    }
+
+    fn step(self, out: &mut String, step: &Step, opts: &Options) {
+        match step {
+            Step::Run { file, dir, codes } => self.run(out, file, dir.as_deref(), codes),
+            Step::Mkdir { dir, mode } => {
+                self.mkdir(out, dir);
+                self.chmod(out, dir, mode.as_deref());
+                self.chown(out, dir, opts);
+            }
+            Step::Install { src, dest, mode } => {
+                self.install(out, src, dest, mode.as_deref(), opts);
+                self.chown(out, dest, opts);
+            }
+            Step::CopyTree {
+                src,
+                dest,
+                strip,
+                hard_link,
+                dir_mode,
+                file_mode,
+                modes,
+            } => {
+                self.copy_tree(out, src, dest, *strip, *hard_link);
+                self.chmod_tree(out, dest, "d", dir_mode.as_deref());
+                self.chmod_tree(out, dest, "f", file_mode.as_deref());
+                for (pattern, mode) in modes {
+                    self.chmod_matching(out, dest, pattern, mode);
+                }
+                self.chown(out, dest, opts);
+            }
+            Step::Render {
+                prg,
+                args,
+                src,
+                dest,
+                mode,
+            } => {
+                let mut then = String::new();
+                self.chmod(&mut then, dest, mode.as_deref());
+                self.chown(&mut then, dest, opts);
+                self.render(out, (prg, args), src, dest, &then);
+            }
+            Step::Link { src, link } => self.link(out, src, link),
+            Step::LinkArtifact { src, dest, hard } => self.link_artifact(out, src, dest, *hard),
+            Step::Command { command } => match self {
+                ScriptKind::Sh => writeln!(out, "{}", command).unwrap(),
+                ScriptKind::Ps1 => {
+                    writeln!(out, "& cmd /C {}", self.quote(command)).unwrap();
+                    out.push_str("if ($LASTEXITCODE -ne 0) { exit 1 }\n");
+                }
+            },
+        }
+    }
+}
+
+///
+/// A standalone script which installs `targets` to `dirs`, as the installer would, with the tools of the shell.
+/// The script installs from the artifacts in the build directory, so the package must already be built
+pub fn script(
+    kind: ScriptKind,
+    dirs: &InstallDirs,
+    package: &str,
+    targets: &HashMap<String, Target>,
+    opts: &Options,
+) -> Result<String, Error> {
+    let steps = steps(dirs, targets, opts)?;
+    // A PowerShell script is text, so it cannot name a path which is not UTF-8. The shell can, with printf
+    if kind == ScriptKind::Ps1 {
+        let paths = dirs.as_env().into_iter().map(|(_, dir)| dir);
+        let steps = steps
+            .iter()
+            .flat_map(|(_, steps)| steps)
+            .flat_map(Step::paths);
+        if let Some(path) = paths.chain(steps).find(|path| path.to_str().is_none()) {
+            return Err(Error::Unrepresentable {
+                path: path.to_owned(),
+                format: "a PowerShell script",
+                reason: "as it is not UTF-8",
+            });
+        }
+    }
+
+    let mut out = String::new();
+    if kind == ScriptKind::Sh {
+        out.push_str("#!/bin/sh\n");
+    }
+    writeln!(
+        out,
+        "# Generated by cargo-native-install. Installs {}",
+        package
+    )
+    .unwrap();
+    match kind {
+        ScriptKind::Sh => out.push_str("set -e\n\n"),
+        ScriptKind::Ps1 => out.push_str("$ErrorActionPreference = 'Stop'\n\n"),
+    }
+    // run targets receive the installation directories in their environment
+    let mut env = dirs.as_env().into_iter().collect::<Vec<_>>();
+    env.sort();
+    for (name, dir) in env {
+        match kind {
+            ScriptKind::Sh => writeln!(out, "export {}={}", name, kind.quote(dir)),
+            ScriptKind::Ps1 => writeln!(out, "$env:{} = {}", name, kind.quote(dir)),
+        }
+        .unwrap();
+    }
+
+    for (name, steps) in steps {
+        writeln!(out, "\n# {}", name).unwrap();
+        let mut body = String::new();
+        for step in &steps {
+            kind.step(&mut body, step, opts);
+        }
+        match &targets[&name].only_if {
+            Some(command) => kind.only_if(&mut out, &name, &hooks::expand(command, dirs), &body),
+            None => out.push_str(&body),
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tempdir::TempDir;
+
+    #[test]
+    #[cfg(unix)]
+    fn shell_sees_bytes_which_are_not_utf8() {
+        use std::os::unix::ffi::OsStrExt;
+        let name = OsStr::from_bytes(b"it's caf\xe9");
+        let output = std::process::Command::new("sh")
+            .arg("-c")
+            .arg(format!("printf %s {}", quote_sh(name)))
+            .output()
+            .unwrap();
+        assert_eq!(output.stdout, name.as_bytes());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn sh_script_installs_file_name_which_is_not_utf8() {
+        use std::os::unix::ffi::OsStrExt;
+        let name = OsStr::from_bytes(b"caf\xe9.txt");
+        let src = TempDir::new("test").unwrap();
+        let target = Target {
+            type_: Some(TargetType::Data),
+            target_file: Some(src.create_file(name, b"data").unwrap()),
+            installed_path: Some(name.into()),
+            ..Default::default()
+        };
+        let mut dirs = InstallDirs::defaults();
+        dirs.prefix = "/usr".into();
+        let dirs = dirs.canonicalize().unwrap();
+        let targets = HashMap::from([("data".to_owned(), target)]);
+        let opts = Options {
+            verbosity: crate::Verbosity::Quiet,
+            ..Default::default()
+        };
+
+        let sh = script(ScriptKind::Sh, &dirs, "test", &targets, &opts).unwrap();
+        assert!(sh.contains(&quote_sh(Path::new("/usr/share").join(name).as_os_str())));
+        assert!(matches!(
+            script(ScriptKind::Ps1, &dirs, "test", &targets, &opts),
+            Err(Error::Unrepresentable { .. })
+        ));
+    }
+
+    #[test]
+    fn template_targets_are_not_emitted() {
+        let src = TempDir::new("test").unwrap();
+        let target = Target {
+            type_: Some(TargetType::Data),
+            target_file: Some(src.create_file("unit".as_ref(), b"{{ bindir }}").unwrap()),
+            installed_path: Some("unit".into()),
+            template_engine: Some(crate::manifest::TemplateEngine::Minijinja),
+            ..Default::default()
+        };
+        let dirs = InstallDirs::defaults().canonicalize().unwrap();
+        let targets = HashMap::from([("unit".to_owned(), target)]);
+        let opts = Options {
+            verbosity: crate::Verbosity::Quiet,
+            ..Default::default()
+        };
+
+        assert!(matches!(
+            script(ScriptKind::Sh, &dirs, "test", &targets, &opts),
+            Err(Error::EmitTemplate(name)) if name == "unit"
+        ));
+    }
+}
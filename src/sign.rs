@@ -0,0 +1,182 @@
+use std::{
+    collections::HashMap,
+    convert::TryFrom,
+    env,
+    ffi::OsStr,
+    fs,
+    io::{self, Read},
+    path::Path,
+    process::Command,
+};
+
+use install_dirs::dirs::InstallDirs;
+
+use crate::{
+    apply_timestamp, copy, deps,
+    manifest::{SignTool, Target},
+    output,
+    output::Status,
+    receipt::Receipt,
+    sysroot, Options,
+};
+
+///
+/// Whether the PE image (a Windows program or library) which begins with `buf` has an Authenticode signature, or `None` if it is not a PE image
+fn pe_signed(buf: &[u8]) -> Option<bool> {
+    let u16_at = |pos: usize| {
+        buf.get(pos..pos + 2)
+            .map(|b| u16::from_le_bytes([b[0], b[1]]))
+    };
+    let u32_at = |pos: usize| {
+        buf.get(pos..pos + 4)
+            .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    };
+    if !buf.starts_with(b"MZ") {
+        return None;
+    }
+    let pe = usize::try_from(u32_at(0x3c)?).ok()?;
+    if buf.get(pe..pe + 4)? != b"PE\0\0" {
+        return None;
+    }
+    // The optional header follows the 20 byte file header, and ends with the data directories
+    let optional = pe + 24;
+    let (count, directories) = match u16_at(optional)? {
+        0x10b => (optional + 92, optional + 96),
+        0x20b => (optional + 108, optional + 112),
+        _ => return None,
+    };
+    // The signature is the certificate table, the fifth data directory
+    if u32_at(count)? <= 4 {
+        return Some(false);
+    }
+    Some(u32_at(directories + 4 * 8 + 4)? != 0)
+}
+
+fn is_msi(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("msi"))
+}
+
+///
+/// Whether the file at `path` is a Windows program, library, or installer which is not signed yet
+fn needs_signature(path: &Path) -> io::Result<bool> {
+    if is_msi(path) {
+        // An installer is signed if it has a `\x05DigitalSignature` stream, whose name is stored in UTF-16
+        let name = "\u{5}DigitalSignature"
+            .encode_utf16()
+            .flat_map(u16::to_le_bytes)
+            .collect::<Vec<_>>();
+        let buf = fs::read(path)?;
+        return Ok(!buf.windows(name.len()).any(|window| window == name));
+    }
+    // The headers of a PE image are within its first page
+    let mut buf = Vec::new();
+    fs::File::open(path)?.take(4096).read_to_end(&mut buf)?;
+    Ok(pe_signed(&buf) == Some(false))
+}
+
+///
+/// The signtool command which signs `file`. The password is only passed when the command is run, so that it is never logged
+fn command(config: &SignTool, password: Option<&OsStr>, file: &Path) -> Command {
+    let digest = config.digest.as_deref().unwrap_or("sha256");
+    let mut cmd = Command::new(
+        config
+            .program
+            .as_deref()
+            .unwrap_or_else(|| Path::new("signtool")),
+    );
+    cmd.args(["sign", "/fd", digest]);
+    match (&config.thumbprint, &config.certificate) {
+        (Some(thumbprint), _) => {
+            cmd.args(["/sha1", thumbprint]);
+        }
+        (None, Some(certificate)) => {
+            cmd.arg("/f").arg(certificate);
+            if let Some(password) = password {
+                cmd.arg("/p").arg(password);
+            }
+        }
+        (None, None) => {
+            cmd.arg("/a");
+        }
+    }
+    if let Some(url) = &config.timestamp_url {
+        cmd.args(["/tr", url, "/td", digest]);
+    }
+    if let Some(description) = &config.description {
+        cmd.args(["/d", description]);
+    }
+    cmd.arg(file);
+    cmd
+}
+
+///
+/// Signs the Windows programs, libraries, and installers in `targets` which were installed without a signature, with the settings of the `[signtool]` table.
+/// Targets installed with `--develop` are links to their artifacts, so they are not signed.
+/// Returns the number of files which could not be signed
+pub fn sign(
+    config: &SignTool,
+    dirs: &InstallDirs,
+    targets: &HashMap<String, Target>,
+    receipt: &Receipt,
+    opts: &Options,
+) -> usize {
+    // The installed files are on the remote machine, where signtool cannot run
+    if opts.filesystem.is_some() {
+        return 0;
+    }
+    let password = config.password_env.as_ref().and_then(env::var_os);
+    if let (Some(var), None) = (&config.password_env, &password) {
+        output::status(
+            opts,
+            Status::Warning,
+            format_args!(
+                "Warning: {} is not set, so the certificate is used without a password",
+                var
+            ),
+        );
+    }
+
+    let mut failed = 0;
+    for (name, target, dest) in deps::installed(dirs, targets, opts) {
+        if receipt.targets.get(name).is_some_and(|entry| entry.develop) {
+            continue;
+        }
+        let dest = sysroot::in_sysroot(&dest, opts);
+        let file = match &target.target_file {
+            Some(src) if opts.dry_run => src.clone(),
+            _ => dest.clone(),
+        };
+        if !needs_signature(&file).unwrap_or(false) {
+            continue;
+        }
+        output::verbose(opts, format_args!("Signing {}", dest.display()));
+        if opts.dry_run {
+            continue;
+        }
+        output::command(opts, &command(config, None, &dest));
+        let result = command(config, password.as_deref(), &dest)
+            .status()
+            .map_err(|e| e.to_string())
+            .and_then(|status| match status.success() {
+                true => Ok(()),
+                false => Err(format!("signtool exited with {}", status)),
+            })
+            .and_then(|()| {
+                // Signing rewrites the file, so the modification time has to be restored
+                if let Some(src) = &target.target_file {
+                    apply_timestamp(src, &dest, opts).map_err(|e| e.to_string())?;
+                }
+                copy::sync(&dest, opts).map_err(|e| e.to_string())
+            });
+        if let Err(e) = result {
+            output::status(
+                opts,
+                Status::Failed,
+                format_args!("Failed to sign {}, {}", dest.display(), e),
+            );
+            failed += 1;
+        }
+    }
+    failed
+}
@@ -0,0 +1,67 @@
+use std::{fs, path::PathBuf};
+
+use install_dirs::dirs::InstallDirs;
+
+use crate::{output, output::Status, stow, sysroot, Error, Options};
+
+///
+/// The directory containing every slot of `package`, `<prefix>/opt/<package>`
+pub fn slots_dir(dirs: &InstallDirs, package: &str) -> PathBuf {
+    dirs.prefix.join("opt").join(package)
+}
+
+///
+/// The directory `version` of `package` is installed to with `--slot`
+pub fn slot_dir(dirs: &InstallDirs, package: &str, version: &str) -> PathBuf {
+    slots_dir(dirs, package).join(version)
+}
+
+///
+/// Links the programs in the `bindir` and `sbindir` of the slot for `version` into the `bindir` and `sbindir` of `dirs`,
+/// replacing the links to any other slot of `package`
+pub fn activate(
+    dirs: &InstallDirs,
+    opts: &Options,
+    package: &str,
+    version: &str,
+) -> Result<(), Error> {
+    let slots = slots_dir(dirs, package);
+    let slot = slots.join(version);
+    if !sysroot::in_sysroot(&slot, opts).is_dir() {
+        return Err(Error::NoSuchVersion(package.to_owned(), version.to_owned()));
+    }
+    output::status(
+        opts,
+        Status::Installed,
+        format_args!("Activating {} {}", package, version),
+    );
+    if opts.dry_run {
+        return Ok(());
+    }
+
+    let others = fs::read_dir(sysroot::in_sysroot(&slots, opts))
+        .map_err(|e| Error::Stow(slots.clone(), e))?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| slots.join(entry.file_name()))
+        .filter(|other| *other != slot)
+        .collect::<Vec<_>>();
+    let active = stow::rebase(dirs, &slot);
+    let store = sysroot::in_sysroot(&slots, opts);
+    let mut kinds: Vec<fn(&InstallDirs) -> &PathBuf> = vec![|dirs| &dirs.bindir];
+    if dirs.sbindir != dirs.bindir {
+        kinds.push(|dirs| &dirs.sbindir);
+    }
+    for dir in kinds {
+        let live = sysroot::in_sysroot(dir(dirs), opts);
+        for other in &others {
+            let other = stow::rebase(dirs, other);
+            stow::unlink_tree(&sysroot::in_sysroot(dir(&other), opts), &live, &store, opts)?;
+        }
+        let src = sysroot::in_sysroot(dir(&active), opts);
+        if src.is_dir() {
+            fs::create_dir_all(&live).map_err(|e| Error::Stow(live.clone(), e))?;
+            stow::link_tree(&src, &live, &store, opts)?;
+        }
+    }
+    Ok(())
+}
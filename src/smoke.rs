@@ -0,0 +1,101 @@
+use std::{collections::HashMap, path::Path, process::Command};
+
+use install_dirs::dirs::InstallDirs;
+
+use crate::{
+    hooks,
+    manifest::{Target, TargetType},
+    output,
+    output::Status,
+    receipt::Receipt,
+    stow, sysroot, Options, Verbosity,
+};
+
+///
+/// `dirs`, moved into `prefix`. Directories inside the prefix are moved as by [`stow::rebase`], and those outside of it (such as `/etc` for a prefix of `/usr`)
+/// are moved to their absolute path beneath `prefix`, so that nothing is installed outside of it
+pub fn rebase(dirs: &InstallDirs, prefix: &Path) -> InstallDirs {
+    let mut out = stow::rebase(dirs, prefix);
+    for dir in stow::dirs_mut(&mut out) {
+        if dir.is_absolute() && !dir.starts_with(prefix) {
+            *dir = prefix.join(sysroot::without_root(dir));
+        }
+    }
+    out
+}
+
+///
+/// The commands which test the installed target, each with how it is described in messages: its `smoke-test` commands, run with the shell,
+/// or for a program, the program with `--version`
+fn commands(target: &Target, dest: &Path, dirs: &InstallDirs) -> Vec<(String, Command)> {
+    match &target.smoke_test {
+        Some(commands) => commands
+            .iter()
+            .map(|command| {
+                let command = hooks::expand(command, dirs);
+                let cmd = hooks::shell(&command, dirs);
+                (format!("`{}`", command), cmd)
+            })
+            .collect(),
+        None if !target.directory
+            && matches!(target.type_, Some(TargetType::Bin | TargetType::SBin)) =>
+        {
+            let mut cmd = Command::new(dest);
+            cmd.arg("--version");
+            cmd.envs(dirs.as_env());
+            vec![(format!("{} --version", dest.display()), cmd)]
+        }
+        None => Vec::new(),
+    }
+}
+
+///
+/// Runs the smoke tests of each target of `targets` recorded in `receipt`, from the prefix of `dirs`, with `bindir` and `sbindir` first in `PATH`.
+/// The output of a test is only shown with `-v`, or if it fails. Returns the number of tests which failed
+pub fn run(
+    dirs: &InstallDirs,
+    targets: &HashMap<String, Target>,
+    receipt: &Receipt,
+    opts: &Options,
+) -> usize {
+    let path = std::env::var_os("PATH").unwrap_or_default();
+    let path = std::env::join_paths(
+        vec![dirs.bindir.clone(), dirs.sbindir.clone()]
+            .into_iter()
+            .chain(std::env::split_paths(&path)),
+    )
+    .unwrap_or(path);
+
+    let mut failed = 0;
+    for (name, entry) in &receipt.targets {
+        let target = match targets.get(name) {
+            Some(target) => target,
+            None => continue,
+        };
+        for (command, mut cmd) in commands(target, &entry.destination, dirs) {
+            cmd.current_dir(&dirs.prefix).env("PATH", &path);
+            output::status(opts, Status::Installed, format_args!("Testing {}", name));
+            output::command(opts, &cmd);
+            let verbose = opts.verbosity >= Verbosity::Verbose;
+            let result = match verbose {
+                true => cmd.status().map(|status| (status, Vec::new(), Vec::new())),
+                false => cmd.output().map(|out| (out.status, out.stdout, out.stderr)),
+            };
+            let message = match result {
+                Ok((status, _, _)) if status.success() => continue,
+                Ok((status, stdout, stderr)) => {
+                    eprint!(
+                        "{}{}",
+                        String::from_utf8_lossy(&stdout),
+                        String::from_utf8_lossy(&stderr)
+                    );
+                    format!("Failed, {} exited with {}", command, status)
+                }
+                Err(e) => format!("Failed to run {}: {}", command, e),
+            };
+            output::status(opts, Status::Failed, format_args!("{}", message));
+            failed += 1;
+        }
+    }
+    failed
+}
@@ -0,0 +1,210 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::{Path, PathBuf},
+};
+
+use install_dirs::dirs::InstallDirs;
+
+use crate::{
+    manifest::{InstallMethod, Target},
+    missing_optional, output,
+    output::Status,
+    progress::{human_bytes, measure},
+    sysroot, target_destination, Error, Options,
+};
+
+///
+/// The space installing the package needs on one filesystem, and the space which is available on it
+pub struct Usage {
+    /// The existing directory the destinations on the filesystem were found beneath, which names it in messages
+    pub dir: PathBuf,
+    pub needed: u64,
+    /// The space available to this user, if it can be found
+    pub available: Option<u64>,
+}
+
+///
+/// The filesystem which `path` would be created on, identified by the nearest existing directory above it, and the device that directory is on
+/// (the serial number of its volume, on Windows)
+pub fn filesystem_of(path: &Path) -> Option<(u64, &Path)> {
+    let dir = path.ancestors().skip(1).find(|dir| dir.is_dir())?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        Some((std::fs::metadata(dir).ok()?.dev(), dir))
+    }
+    #[cfg(windows)]
+    {
+        Some((volume_serial(dir)?, dir))
+    }
+    #[cfg(not(any(unix, windows)))]
+    {
+        Some((0, dir))
+    }
+}
+
+///
+/// The serial number of the volume `dir` is on
+#[cfg(windows)]
+fn volume_serial(dir: &Path) -> Option<u64> {
+    use std::os::windows::ffi::OsStrExt;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GetVolumePathNameW(file: *const u16, volume: *mut u16, len: u32) -> i32;
+        fn GetVolumeInformationW(
+            root: *const u16,
+            name: *mut u16,
+            name_len: u32,
+            serial: *mut u32,
+            max_component_len: *mut u32,
+            flags: *mut u32,
+            fs_name: *mut u16,
+            fs_name_len: u32,
+        ) -> i32;
+    }
+
+    let path = std::path::absolute(dir).ok()?;
+    let path = path
+        .as_os_str()
+        .encode_wide()
+        .chain(Some(0))
+        .collect::<Vec<_>>();
+    // The root of the volume, such as C:\, or the folder it is mounted on, which is no longer than the path within it
+    let mut root = vec![0u16; path.len() + 1];
+    let mut serial = 0;
+    // SAFETY: path is a valid, NUL-terminated wide string, and root is valid for writes of its length
+    if unsafe { GetVolumePathNameW(path.as_ptr(), root.as_mut_ptr(), root.len() as u32) } == 0 {
+        return None;
+    }
+    // SAFETY: root is the NUL-terminated root of a volume, and the names which are not wanted are null, with no length
+    if unsafe {
+        GetVolumeInformationW(
+            root.as_ptr(),
+            std::ptr::null_mut(),
+            0,
+            &mut serial,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+            0,
+        )
+    } == 0
+    {
+        return None;
+    }
+    Some(serial.into())
+}
+
+///
+/// The space available to this user on the filesystem which `dir` is on
+#[cfg(unix)]
+fn available(dir: &Path) -> Option<u64> {
+    use std::{ffi::CString, os::unix::ffi::OsStrExt};
+
+    let path = CString::new(dir.as_os_str().as_bytes()).ok()?;
+    // SAFETY: statvfs is plain data, which statvfs fills in when it succeeds
+    let mut stat = unsafe { std::mem::zeroed::<libc::statvfs>() };
+    // SAFETY: path is a valid, NUL-terminated string, and stat is valid for writes
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+    Some(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
+#[cfg(not(unix))]
+fn available(_dir: &Path) -> Option<u64> {
+    None
+}
+
+///
+/// The space installing each target that would be installed needs on each filesystem: the size of its files, less the size of the files they replace.
+/// Stripping only makes the files smaller, so the estimate errs on the side of needing more space
+pub fn plan(dirs: &InstallDirs, targets: &HashMap<String, Target>, opts: &Options) -> Vec<Usage> {
+    let mut filesystems = BTreeMap::<u64, Usage>::new();
+    let selected = targets.iter().filter(|(name, target)| {
+        opts.install_target
+            .as_ref()
+            .is_none_or(|target| target == *name)
+            && target.is_selected(opts)
+            && missing_optional(target).is_none()
+            // Links take no space
+            && target
+                .install_method
+                .is_none_or(|method| method == InstallMethod::Copy)
+    });
+    for (_, target) in selected {
+        let (src, dest) = match (&target.target_file, target_destination(dirs, target, opts)) {
+            (Some(src), Some(dest)) => (src, sysroot::in_sysroot(&dest, opts)),
+            _ => continue,
+        };
+        let (size, replaced) = match measure(src) {
+            Ok((_, size)) => (size, measure(&dest).map_or(0, |(_, size)| size)),
+            Err(_) => continue,
+        };
+        let (dev, dir) = match filesystem_of(&dest) {
+            Some(fs) => fs,
+            None => continue,
+        };
+        let fs = filesystems.entry(dev).or_insert_with(|| Usage {
+            dir: dir.to_path_buf(),
+            needed: 0,
+            available: available(dir),
+        });
+        // The shortest directory names the filesystem best, as in `/usr` rather than `/usr/share/doc`
+        if dir.components().count() < fs.dir.components().count() {
+            fs.dir = dir.to_path_buf();
+        }
+        fs.needed += size.saturating_sub(replaced);
+    }
+    filesystems.into_values().collect()
+}
+
+///
+/// Fails before anything is installed if the package does not fit on any filesystem it would be installed to.
+/// With `--dry-run`, the space needed on each filesystem is reported instead, with a warning for those it does not fit on
+pub fn check(
+    dirs: &InstallDirs,
+    targets: &HashMap<String, Target>,
+    opts: &Options,
+) -> Result<(), Error> {
+    for fs in plan(dirs, targets, opts) {
+        let fits = fs.available.is_none_or(|available| fs.needed <= available);
+        if opts.dry_run {
+            let available = fs
+                .available
+                .map_or_else(|| "unknown space".to_owned(), human_bytes);
+            output::status(
+                opts,
+                if fits {
+                    Status::Installed
+                } else {
+                    Status::Warning
+                },
+                format_args!(
+                    "{}{} needed on the filesystem of {} ({} available)",
+                    if fits { "" } else { "Warning: " },
+                    human_bytes(fs.needed),
+                    fs.dir.display(),
+                    available
+                ),
+            );
+        } else if let (false, Some(available)) = (fits, fs.available) {
+            return Err(Error::NoSpace {
+                dir: fs.dir,
+                needed: fs.needed,
+                available,
+            });
+        } else {
+            output::verbose(
+                opts,
+                format_args!(
+                    "{} needed on the filesystem of {}",
+                    human_bytes(fs.needed),
+                    fs.dir.display()
+                ),
+            );
+        }
+    }
+    Ok(())
+}
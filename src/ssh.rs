@@ -0,0 +1,242 @@
+use std::{
+    fs,
+    io::{self, ErrorKind},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{filesystem::Filesystem, script::quote_sh, Options};
+
+///
+/// A filesystem on a remote machine, which is modified by running commands through `ssh`.
+///
+/// The remote machine needs a POSIX shell, and the GNU versions of `stat` and `touch`.
+/// All commands share one connection, so a password is only asked for once
+#[derive(Clone, Debug)]
+pub struct SshFilesystem {
+    /// The destination given to ssh, `host` or `user@host`
+    pub host: String,
+    pub port: Option<u16>,
+    /// The ssh program to use
+    pub ssh: PathBuf,
+}
+
+impl SshFilesystem {
+    ///
+    /// Parses a URL of the form `ssh://[user@]host[:port][/prefix]`, returning the filesystem and the prefix, if any.
+    /// An IPv6 address is written in brackets to be followed by a port, as in `ssh://[::1]:22`
+    pub fn parse_url(url: &str) -> Option<(Self, Option<PathBuf>)> {
+        let rest = url.strip_prefix("ssh://")?;
+        let (authority, path) = match rest.find('/') {
+            Some(n) => (&rest[..n], Some(&rest[n..])),
+            None => (rest, None),
+        };
+        let (user, host) = match authority.rfind('@') {
+            Some(n) => authority.split_at(n + 1),
+            None => ("", authority),
+        };
+        let (host, port) = if let Some(bracketed) = host.strip_prefix('[') {
+            // An IPv6 address, as in `[::1]:22`, which ssh is given without the brackets
+            let (host, rest) = bracketed.split_once(']')?;
+            match rest {
+                "" => (host, None),
+                rest => (host, Some(rest.strip_prefix(':')?.parse().ok()?)),
+            }
+        } else if host.matches(':').count() > 1 {
+            // An IPv6 address without brackets, which cannot be followed by a port
+            (host, None)
+        } else {
+            match host.rsplit_once(':') {
+                Some((host, port)) => (host, Some(port.parse().ok()?)),
+                None => (host, None),
+            }
+        };
+        if host.is_empty() {
+            return None;
+        }
+        let fs = SshFilesystem {
+            host: format!("{}{}", user, host),
+            port,
+            ssh: PathBuf::from("ssh"),
+        };
+        Some((fs, path.filter(|path| *path != "/").map(PathBuf::from)))
+    }
+
+    fn command(&self, script: &str) -> Command {
+        let mut cmd = Command::new(&self.ssh);
+        // Every operation is a separate invocation, so reuse a single connection for all of them
+        let control_path = std::env::temp_dir().join("cargo-native-install-ssh-%C");
+        cmd.arg("-o").arg("ControlMaster=auto");
+        cmd.arg("-o")
+            .arg(format!("ControlPath={}", control_path.display()));
+        cmd.arg("-o").arg("ControlPersist=60");
+        if let Some(port) = self.port {
+            cmd.arg("-p").arg(port.to_string());
+        }
+        cmd.arg("--").arg(&self.host).arg(script);
+        cmd
+    }
+
+    ///
+    /// Runs `script` with the remote shell, returning its standard output
+    fn run(&self, script: &str, stdin: Option<fs::File>) -> io::Result<String> {
+        let mut cmd = self.command(script);
+        cmd.stdin(stdin.map_or_else(Stdio::null, Stdio::from));
+        let output = cmd.output()?;
+        if output.status.success() {
+            Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+        } else {
+            Err(io::Error::other(format!(
+                "`{}` failed on {}: {}",
+                script,
+                self.host,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )))
+        }
+    }
+}
+
+///
+/// Quotes `path` for the remote shell
+fn quote(path: &Path) -> String {
+    quote_sh(path.as_os_str())
+}
+
+impl Filesystem for SshFilesystem {
+    fn copy_file(&self, src: &Path, dest: &Path, opts: &Options) -> io::Result<u64> {
+        let file = fs::File::open(src)?;
+        let md = file.metadata()?;
+        #[cfg(unix)]
+        let mode = std::os::unix::fs::PermissionsExt::mode(&md.permissions()) & 0o7777;
+        #[cfg(not(unix))]
+        let mode = if md.permissions().readonly() {
+            0o444
+        } else {
+            0o644
+        };
+        // Upload beside the destination, then rename over it, so that a running program is never overwritten in place
+        let mut tmp = dest.as_os_str().to_owned();
+        tmp.push(".cargo-native-install.tmp");
+        let tmp = quote(Path::new(&tmp));
+        let mut script = format!(
+            "cat > {tmp} && chmod {:o} {tmp} && mv -f {tmp} {}",
+            mode,
+            quote(dest),
+            tmp = tmp
+        );
+        // Like `install -D`, which is used for local installs
+        match dest.parent() {
+            Some(parent) if !opts.no_create_dirs && !parent.as_os_str().is_empty() => {
+                script = format!("mkdir -p {} && {}", quote(parent), script);
+            }
+            _ => {}
+        }
+        self.run(&script, Some(file))?;
+        Ok(md.len())
+    }
+
+    fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        self.run(&format!("mkdir -p {}", quote(path)), None)
+            .map(drop)
+    }
+
+    fn symlink(&self, src: &Path, dest: &Path, _dir: bool) -> io::Result<()> {
+        self.run(&format!("ln -sfn {} {}", quote(src), quote(dest)), None)
+            .map(drop)
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        self.run(&format!("test -d {}", quote(path)), None).is_ok()
+    }
+
+    fn modified(&self, path: &Path) -> io::Result<SystemTime> {
+        let out = self.run(&format!("stat -c %Y {}", quote(path)), None)?;
+        let secs = out
+            .trim()
+            .parse()
+            .map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?;
+        Ok(UNIX_EPOCH + Duration::from_secs(secs))
+    }
+
+    fn set_modified(&self, path: &Path, time: SystemTime) -> io::Result<()> {
+        let time = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+        self.run(
+            &format!(
+                "touch -m -d @{}.{:09} {}",
+                time.as_secs(),
+                time.subsec_nanos(),
+                quote(path)
+            ),
+            None,
+        )
+        .map(drop)
+    }
+
+    fn mode(&self, path: &Path) -> io::Result<u32> {
+        let out = self.run(&format!("stat -c %a {}", quote(path)), None)?;
+        u32::from_str_radix(out.trim(), 8).map_err(|e| io::Error::new(ErrorKind::InvalidData, e))
+    }
+
+    fn set_mode(&self, path: &Path, mode: u32) -> io::Result<()> {
+        self.run(&format!("chmod {:o} {}", mode & 0o7777, quote(path)), None)
+            .map(drop)
+    }
+
+    fn chown(&self, path: &Path, owner: Option<u32>, group: Option<u32>) -> io::Result<()> {
+        let spec = match (owner, group) {
+            (None, None) => return Ok(()),
+            (Some(owner), None) => owner.to_string(),
+            (None, Some(group)) => format!(":{}", group),
+            (Some(owner), Some(group)) => format!("{}:{}", owner, group),
+        };
+        self.run(&format!("chown {} {}", spec, quote(path)), None)
+            .map(drop)
+    }
+
+    fn sync(&self, path: &Path) -> io::Result<()> {
+        self.run(&format!("sync {}", quote(path)), None).map(drop)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(url: &str) -> Option<(String, Option<u16>, Option<PathBuf>)> {
+        SshFilesystem::parse_url(url).map(|(fs, prefix)| (fs.host, fs.port, prefix))
+    }
+
+    #[test]
+    fn parses_host_port_and_prefix() {
+        assert_eq!(
+            parse("ssh://user@example.com:2222/opt/app"),
+            Some((
+                "user@example.com".into(),
+                Some(2222),
+                Some("/opt/app".into())
+            ))
+        );
+        assert_eq!(
+            parse("ssh://example.com/"),
+            Some(("example.com".into(), None, None))
+        );
+        assert_eq!(parse("ssh://example.com:port"), None);
+        assert_eq!(parse("ssh://user@"), None);
+    }
+
+    #[test]
+    fn parses_ipv6_hosts() {
+        assert_eq!(
+            parse("ssh://[::1]:22/usr"),
+            Some(("::1".into(), Some(22), Some("/usr".into())))
+        );
+        assert_eq!(
+            parse("ssh://root@[fe80::1]"),
+            Some(("root@fe80::1".into(), None, None))
+        );
+        assert_eq!(parse("ssh://::1"), Some(("::1".into(), None, None)));
+        assert_eq!(parse("ssh://[::1"), None);
+        assert_eq!(parse("ssh://[::1]22"), None);
+    }
+}
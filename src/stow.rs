@@ -0,0 +1,276 @@
+use std::{
+    fs, io,
+    path::{Component, Path, PathBuf},
+};
+
+use install_dirs::dirs::InstallDirs;
+
+use crate::{
+    filesystem::Filesystem, normalize_path, output, output::Status, sysroot, Error, Options,
+};
+
+///
+/// The directory containing every stowed package, given by `--stow-dir` (relative to the prefix), or `<prefix>/stow`
+pub fn stow_dir(dirs: &InstallDirs, opts: &Options) -> PathBuf {
+    match &opts.stow_dir {
+        Some(dir) => dirs.prefix.join(dir),
+        None => dirs.prefix.join("stow"),
+    }
+}
+
+///
+/// The directory `package` is installed to with `--stow`, `<stow-dir>/<package>-<version>`
+pub fn package_dir(
+    dirs: &InstallDirs,
+    opts: &Options,
+    package: &str,
+    version: Option<&str>,
+) -> PathBuf {
+    match version {
+        Some(version) => stow_dir(dirs, opts).join(format!("{}-{}", package, version)),
+        None => stow_dir(dirs, opts).join(package),
+    }
+}
+
+///
+/// Moves each of `dirs` that is inside the prefix into `package_dir`. Directories outside the prefix (such as `/etc`) are unchanged,
+/// as they cannot be linked into it
+pub fn rebase(dirs: &InstallDirs, package_dir: &Path) -> InstallDirs {
+    let rebase = |dir: &mut PathBuf| {
+        if let Ok(rel) = dir.strip_prefix(&dirs.prefix) {
+            *dir = package_dir.join(rel).components().collect();
+        }
+    };
+    let mut out = dirs.clone();
+    for dir in dirs_mut(&mut out) {
+        rebase(dir);
+    }
+    out.prefix = package_dir.to_path_buf();
+    out
+}
+
+///
+/// Each of `dirs` other than the prefix itself
+pub fn dirs_mut(dirs: &mut InstallDirs) -> [&mut PathBuf; 16] {
+    [
+        &mut dirs.exec_prefix,
+        &mut dirs.bindir,
+        &mut dirs.sbindir,
+        &mut dirs.libdir,
+        &mut dirs.libexecdir,
+        &mut dirs.includedir,
+        &mut dirs.datarootdir,
+        &mut dirs.datadir,
+        &mut dirs.mandir,
+        &mut dirs.docdir,
+        &mut dirs.infodir,
+        &mut dirs.localedir,
+        &mut dirs.localstatedir,
+        &mut dirs.runstatedir,
+        &mut dirs.sharedstatedir,
+        &mut dirs.sysconfdir,
+    ]
+}
+
+///
+/// The path of `to` relative to the directory `from`. Both paths must be absolute and normalized
+pub fn relative(from: &Path, to: &Path) -> PathBuf {
+    let mut from = from.components().peekable();
+    let mut to = to.components().peekable();
+    while let (Some(a), Some(b)) = (from.peek(), to.peek()) {
+        if a != b {
+            break;
+        }
+        from.next();
+        to.next();
+    }
+    from.map(|_| Component::ParentDir).chain(to).collect()
+}
+
+///
+/// Whether `link` is a symbolic link to `entry`
+fn links_to(link: &Path, entry: &Path) -> bool {
+    match fs::read_link(link) {
+        Ok(dest) => {
+            normalize_path(&link.parent().unwrap_or_else(|| Path::new("")).join(dest))
+                == normalize_path(entry)
+        }
+        Err(_) => false,
+    }
+}
+
+///
+/// Whether `s` is a semantic version, as cargo requires of packages: `major.minor.patch`, optionally followed by `-prerelease` or `+build`
+fn is_version(s: &str) -> bool {
+    let core = s.split(['-', '+']).next().unwrap_or("");
+    let rest = &s[core.len()..];
+    let mut parts = core.split('.');
+    (&mut parts)
+        .take(3)
+        .filter(|part| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit()))
+        .count()
+        == 3
+        && parts.next().is_none()
+        && (rest.is_empty() || rest.len() > 1)
+}
+
+///
+/// The directories `src` and `link` resolved, for the symbolic link to a directory `link` in the prefix (such as `lib` linked to `lib64`),
+/// so that links created through it are relative to where they really are. A link into `store` (as GNU stow folds a directory that only one package uses)
+/// is a conflict, as links made through it would be made inside another package
+fn resolve_dir_link(src: &Path, link: &Path, store: &Path) -> Result<(PathBuf, PathBuf), Error> {
+    let resolved = fs::canonicalize(link).map_err(|e| Error::Stow(link.to_path_buf(), e))?;
+    let store = fs::canonicalize(store).unwrap_or_else(|_| store.to_path_buf());
+    if resolved.starts_with(&store) {
+        return Err(Error::StowConflict(link.to_path_buf()));
+    }
+    let src = fs::canonicalize(src).map_err(|e| Error::Stow(src.to_path_buf(), e))?;
+    Ok((src, resolved))
+}
+
+///
+/// Every stowed version of `package` other than `package_dir`
+fn other_versions(stow_dir: &Path, package: &str, package_dir: &Path) -> Vec<PathBuf> {
+    let entries = match fs::read_dir(stow_dir) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+    let versioned = format!("{}-", package);
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            // The rest of the name must be a whole version, so that `foo` matches neither `foo-bar-1.0.0` nor `foo-2d-1.0.0`
+            name == package || name.strip_prefix(&versioned).is_some_and(is_version)
+        })
+        .map(|entry| entry.path())
+        .filter(|path| path != package_dir)
+        .collect()
+}
+
+///
+/// Links each file in `src` to the corresponding path in `dest`, creating directories in `dest` as needed.
+/// `store` is the directory holding every package (or slot) which is linked into `dest`
+pub fn link_tree(src: &Path, dest: &Path, store: &Path, opts: &Options) -> Result<(), Error> {
+    let entries = fs::read_dir(src).map_err(|e| Error::Stow(src.to_path_buf(), e))?;
+    for entry in entries {
+        let entry = entry.map_err(|e| Error::Stow(src.to_path_buf(), e))?;
+        let path = entry.path();
+        let link = dest.join(entry.file_name());
+        let is_dir = entry.file_type().is_ok_and(|ty| ty.is_dir());
+        let existing = fs::symlink_metadata(&link).ok();
+        if is_dir {
+            match existing {
+                Some(md) if md.is_dir() => link_tree(&path, &link, store, opts)?,
+                Some(md) if md.file_type().is_symlink() && link.is_dir() => {
+                    let (path, link) = resolve_dir_link(&path, &link, store)?;
+                    link_tree(&path, &link, store, opts)?;
+                }
+                Some(_) => return Err(Error::StowConflict(link)),
+                None => {
+                    fs::create_dir(&link).map_err(|e| Error::Stow(link.clone(), e))?;
+                    link_tree(&path, &link, store, opts)?;
+                }
+            }
+        } else {
+            match existing {
+                Some(_) if links_to(&link, &path) => continue,
+                Some(_) => return Err(Error::StowConflict(link)),
+                None => {}
+            }
+            output::verbose(
+                opts,
+                format_args!("Linking {} to {}", link.display(), path.display()),
+            );
+            // Relative links keep working if the prefix is moved, or mounted elsewhere
+            let target = relative(dest, &path);
+            opts.fs()
+                .symlink(&target, &link, false)
+                .map_err(|e| Error::Stow(link.clone(), e))?;
+        }
+    }
+    Ok(())
+}
+
+///
+/// Removes each link in `dest` to the corresponding file in `src`, and any directory which is left empty.
+/// `store` is as for [`link_tree`]
+pub fn unlink_tree(src: &Path, dest: &Path, store: &Path, opts: &Options) -> Result<(), Error> {
+    let entries = match fs::read_dir(src) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(Error::Stow(src.to_path_buf(), e)),
+    };
+    for entry in entries {
+        let entry = entry.map_err(|e| Error::Stow(src.to_path_buf(), e))?;
+        let path = entry.path();
+        let link = dest.join(entry.file_name());
+        if entry.file_type().is_ok_and(|ty| ty.is_dir()) {
+            match fs::symlink_metadata(&link) {
+                Ok(md) if md.is_dir() => {
+                    unlink_tree(&path, &link, store, opts)?;
+                    // Fails if another package still has files there
+                    let _ = fs::remove_dir(&link);
+                }
+                Ok(md) if md.file_type().is_symlink() && link.is_dir() => {
+                    if let Ok((path, link)) = resolve_dir_link(&path, &link, store) {
+                        unlink_tree(&path, &link, store, opts)?;
+                    }
+                }
+                _ => {}
+            }
+        } else if links_to(&link, &path) {
+            output::verbose(opts, format_args!("Removing link {}", link.display()));
+            fs::remove_file(&link).map_err(|e| Error::Stow(link.clone(), e))?;
+        }
+    }
+    Ok(())
+}
+
+///
+/// Links the files installed to `package_dir` into the prefix of `dirs` (the live prefix), replacing the links of any other version of `package`
+pub fn link(
+    dirs: &InstallDirs,
+    opts: &Options,
+    package: &str,
+    package_dir: &Path,
+) -> Result<(), Error> {
+    let prefix = sysroot::in_sysroot(&dirs.prefix, opts);
+    let stow_dir = sysroot::in_sysroot(&stow_dir(dirs, opts), opts);
+    let package_dir = sysroot::in_sysroot(package_dir, opts);
+    output::status(
+        opts,
+        Status::Installed,
+        format_args!(
+            "Linking {} into {}",
+            package_dir.display(),
+            prefix.display()
+        ),
+    );
+    if opts.dry_run {
+        return Ok(());
+    }
+    for old in other_versions(&stow_dir, package, &package_dir) {
+        unlink_tree(&old, &prefix, &stow_dir, opts)?;
+    }
+    link_tree(&package_dir, &prefix, &stow_dir, opts)
+}
+
+///
+/// Removes the links to every stowed version of `package` from the prefix of `dirs`. The stowed files are not removed
+pub fn unlink(dirs: &InstallDirs, opts: &Options, package: &str) -> Result<(), Error> {
+    let prefix = sysroot::in_sysroot(&dirs.prefix, opts);
+    let stow_dir = sysroot::in_sysroot(&stow_dir(dirs, opts), opts);
+    for dir in other_versions(&stow_dir, package, Path::new("")) {
+        output::status(
+            opts,
+            Status::Installed,
+            format_args!("Unlinking {} from {}", dir.display(), prefix.display()),
+        );
+        if !opts.dry_run {
+            unlink_tree(&dir, &prefix, &stow_dir, opts)?;
+        }
+    }
+    Ok(())
+}
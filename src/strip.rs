@@ -0,0 +1,253 @@
+use std::{
+    ffi::OsString,
+    fs,
+    path::{Path, PathBuf},
+    process::{Child, Command, Stdio},
+};
+
+use install_dirs::dirs::InstallDirs;
+
+use crate::{
+    apply_timestamp, copy, copy_alias, elf,
+    events::{self, Event},
+    manifest::{StripMode, Target, TargetType},
+    output,
+    output::Status,
+    path_arg,
+    receipt::{self, Receipt},
+    sysroot, InstallError, Options, DEFAULT_TARGET,
+};
+
+///
+/// Where `--separate-debug` keeps the debug information of the binary at `src`: `<debugdir>/.build-id/xx/yyyy.debug`,
+/// named after the GNU build ID of the binary, which is where debuggers and debuginfod look for it.
+/// Returns `None` if the binary has no build ID
+pub fn debug_file(src: &Path, dirs: &InstallDirs, opts: &Options) -> Option<PathBuf> {
+    let id = elf::build_id(src)
+        .ok()
+        .flatten()
+        .filter(|id| id.len() > 1)?;
+    let hex = id.iter().map(|b| format!("{:02x}", b)).collect::<String>();
+    let mut path = match &opts.debugdir {
+        Some(dir) => dirs.prefix.join(dir),
+        None => dirs.prefix.join("lib").join("debug"),
+    };
+    path.push(".build-id");
+    path.push(&hex[..2]);
+    path.push(format!("{}.debug", &hex[2..]));
+    Some(sysroot::in_sysroot(&path, opts))
+}
+
+///
+/// Finds `llvm-strip`, or failing that `rust-objcopy`, in the Rust toolchain that `rustc` (or `$RUSTC`) belongs to.
+/// These are used when there is no system strip, as on Windows or in minimal containers without binutils
+pub fn find_in_toolchain() -> Option<PathBuf> {
+    let rustc = std::env::var_os("RUSTC").unwrap_or_else(|| OsString::from("rustc"));
+    let output = Command::new(rustc)
+        .args(["--print", "sysroot"])
+        .stderr(Stdio::null())
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+    let sysroot = PathBuf::from(String::from_utf8(output.stdout).ok()?.trim());
+    let bin = sysroot
+        .join("lib")
+        .join("rustlib")
+        .join(DEFAULT_TARGET)
+        .join("bin");
+    ["llvm-strip", "rust-objcopy"]
+        .iter()
+        .map(|name| bin.join(format!("{}{}", name, std::env::consts::EXE_SUFFIX)))
+        .find(|prg| prg.is_file())
+}
+
+///
+/// Whether `strip` is an objcopy, rather than a strip program. objcopy modifies a file in place when given no output file,
+/// but only understands the long forms of the strip modes (its `-S` removes every symbol)
+fn is_objcopy(strip: &Path) -> bool {
+    strip
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .is_some_and(|stem| stem.ends_with("objcopy"))
+}
+
+///
+/// The arguments strip is run with for `target`, before the path of the binary.
+/// The mode of the target takes precedence over `--strip-mode`. Shared libraries only have their unneeded symbols removed by default,
+/// since they must keep the symbols they export, and other binaries have every symbol removed.
+/// Each of the `strip-keep-symbols` of the target follows the mode, as a `--keep-symbol`, with `--wildcard` if any of them is a pattern.
+/// The arguments from `--strip-arg`, then those of the target, come last
+pub fn args(target: &Target, opts: &Options) -> Vec<String> {
+    let mode = target
+        .strip_mode
+        .or(opts.strip_mode)
+        .unwrap_or(match target.type_ {
+            Some(TargetType::Shared) => StripMode::Unneeded,
+            _ => StripMode::All,
+        });
+    let mode = match &opts.strip {
+        Some(strip) if is_objcopy(strip) => mode.long_arg(),
+        _ => mode.arg(),
+    };
+    let keep = target.strip_keep_symbols.as_deref().unwrap_or_default();
+    let wildcard = keep
+        .iter()
+        .any(|sym| sym.contains(&['*', '?', '['] as &[_]));
+    std::iter::once(mode.to_owned())
+        .chain(wildcard.then(|| "--wildcard".to_owned()))
+        .chain(keep.iter().map(|sym| format!("--keep-symbol={}", sym)))
+        .chain(opts.strip_args.iter().cloned())
+        .chain(target.strip_args.iter().flatten().cloned())
+        .collect()
+}
+
+///
+/// A binary which has been installed, but not yet stripped
+pub struct StripJob {
+    /// The name of the target which installed the binary
+    pub name: String,
+    pub src: PathBuf,
+    pub dest: PathBuf,
+    /// The arguments strip is run with, from [`args`]
+    pub args: Vec<String>,
+    /// The file the debug information is kept in, before it is stripped, from [`debug_file`]
+    pub debug: Option<PathBuf>,
+    /// Aliases of the binary which were installed as copies of it, on a filesystem without symbolic links, which are copied again once it is stripped
+    pub copies: Vec<PathBuf>,
+}
+
+///
+/// Copies the debug information of the binary at `job.dest` to `job.debug`, before it is stripped
+fn keep_debug(strip: &Path, job: &StripJob, opts: &Options) -> Result<(), String> {
+    let debug = match &job.debug {
+        Some(debug) => debug,
+        None => return Ok(()),
+    };
+    output::verbose(
+        opts,
+        format_args!("Keeping debug information in {}", debug.display()),
+    );
+    if let Some(parent) = debug.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let mut cmd = Command::new(strip);
+    cmd.arg("--only-keep-debug");
+    if is_objcopy(strip) {
+        cmd.arg(path_arg(&job.dest)).arg(path_arg(debug));
+    } else {
+        cmd.arg("-o").arg(debug).arg(path_arg(&job.dest));
+    }
+    cmd.stdin(Stdio::null());
+    cmd.stdout(Stdio::null());
+    output::command(opts, &cmd);
+    match cmd.status() {
+        Ok(status) if status.success() => Ok(()),
+        Ok(status) => Err(format!("strip program exited with {}", status)),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+///
+/// Binaries to strip once every target has been installed, so that multiple strip programs can run at once
+#[derive(Default)]
+pub struct StripQueue {
+    jobs: Vec<StripJob>,
+}
+
+impl StripQueue {
+    pub fn push(&mut self, job: StripJob) {
+        self.jobs.push(job);
+    }
+
+    ///
+    /// Strips every queued binary, running up to one strip program per available CPU at once.
+    /// Each binary which is stripped successfully is recorded in `receipt`, so it need not be stripped again while the artifact is unchanged.
+    /// Returns the number of binaries which could not be stripped
+    pub fn run(&mut self, opts: &Options, receipt: &mut Receipt) -> usize {
+        let strip = match &opts.strip {
+            Some(strip) => strip,
+            None => return 0,
+        };
+        let limit = std::thread::available_parallelism().map_or(1, usize::from);
+        let mut failed = 0;
+        let mut pending = std::mem::take(&mut self.jobs).into_iter();
+        let mut running: Vec<(StripJob, Child)> = Vec::new();
+        loop {
+            while running.len() < limit {
+                let job = match pending.next() {
+                    Some(job) => job,
+                    None => break,
+                };
+                if let Err(e) = keep_debug(strip, &job, opts) {
+                    output::status(
+                        opts,
+                        Status::Failed,
+                        format_args!(
+                            "Failed to keep the debug information of {}, {}",
+                            job.dest.display(),
+                            e
+                        ),
+                    );
+                    failed += 1;
+                    continue;
+                }
+                output::verbose(opts, format_args!("Stripping {}", job.dest.display()));
+                let mut cmd = Command::new(strip);
+                cmd.args(&job.args);
+                cmd.arg(path_arg(&job.dest));
+                cmd.stdin(Stdio::null());
+                cmd.stdout(Stdio::null());
+                output::command(opts, &cmd);
+                match cmd.spawn() {
+                    Ok(child) => running.push((job, child)),
+                    Err(e) => {
+                        output::status(
+                            opts,
+                            Status::Failed,
+                            format_args!("Failed to strip {}, {}", job.dest.display(), e),
+                        );
+                        failed += 1;
+                    }
+                }
+            }
+            if running.is_empty() {
+                break;
+            }
+            let (job, mut child) = running.remove(0);
+            let result = match child.wait() {
+                Ok(status) if status.success() => finish(&job, opts, receipt),
+                Ok(status) => Err(format!("strip program exited with {}", status)),
+                Err(e) => Err(e.to_string()),
+            };
+            if let Err(e) = result {
+                let error =
+                    InstallError::new(format!("Failed to strip {}, {}", job.dest.display(), e));
+                output::status(opts, Status::Failed, format_args!("{}", error));
+                events::emit(
+                    opts,
+                    Event::TargetFailed {
+                        name: &job.name,
+                        error: &error,
+                    },
+                );
+                failed += 1;
+            }
+        }
+        failed
+    }
+}
+
+fn finish(job: &StripJob, opts: &Options, receipt: &mut Receipt) -> Result<(), String> {
+    // Stripping rewrites the file, so the modification time has to be restored
+    apply_timestamp(&job.src, &job.dest, opts).map_err(|e| e.to_string())?;
+    copy::sync(&job.dest, opts).map_err(|e| e.to_string())?;
+    for alias in &job.copies {
+        copy_alias(&job.dest, alias, opts).map_err(|e| e.to_string())?;
+    }
+    receipt.record_stripped(
+        &job.name,
+        receipt::strip_fingerprint(&job.src, &job.args),
+        receipt::tree_fingerprint(&job.dest),
+    );
+    Ok(())
+}
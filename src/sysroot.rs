@@ -0,0 +1,109 @@
+use std::{
+    fs, io,
+    path::{Component, Path, PathBuf},
+};
+
+use install_dirs::dirs::InstallDirs;
+
+use crate::{
+    dist,
+    manifest::{Target, TargetType},
+    tempdir::TempDir,
+    Options,
+};
+
+///
+/// The path `path` is written to. With `--sysroot`, this is `path` beneath the sysroot, and with `--dist`, beneath the staging directory
+pub fn in_sysroot(path: &Path, opts: &Options) -> PathBuf {
+    let root = opts
+        .sysroot
+        .clone()
+        .or_else(|| opts.dist.as_ref().map(|_| dist::stage_dir()));
+    match root {
+        Some(sysroot) => sysroot.join(without_root(path)),
+        None => path.to_path_buf(),
+    }
+}
+
+///
+/// `path` relative to the root directory (and without the drive, on Windows), so that it can be joined to another directory
+pub fn without_root(path: &Path) -> PathBuf {
+    path.components()
+        .filter(|c| !matches!(c, Component::Prefix(_) | Component::RootDir))
+        .collect()
+}
+
+pub fn is_pkgconfig(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "pc")
+}
+
+///
+/// Whether a target installed to `dest` is used to compile against the package: headers, libraries, and pkg-config files.
+/// Other targets are not installed into a sysroot
+pub fn is_needed(target: &Target, dest: &Path) -> bool {
+    match target.type_ {
+        Some(TargetType::Include | TargetType::Library | TargetType::Shared) => true,
+        _ => !target.directory && is_pkgconfig(dest),
+    }
+}
+
+///
+/// A copy of a pkg-config file, with the `prefix`, `exec_prefix`, `libdir`, and `includedir` variables set to the installation directories.
+/// pkg-config prepends the sysroot to these paths itself, when `PKG_CONFIG_SYSROOT_DIR` is set.
+/// The copy is removed when this is dropped
+pub struct PkgConfig {
+    pub target: Target,
+    _dir: TempDir,
+}
+
+impl PkgConfig {
+    ///
+    /// Rewrites the pkg-config file installed by `target`, if it installs one
+    pub fn rewrite(target: &Target, dirs: &InstallDirs) -> io::Result<Option<Self>> {
+        let src = match &target.target_file {
+            Some(src) if !target.directory && is_pkgconfig(src) => src,
+            _ => return Ok(None),
+        };
+        let contents = fs::read_to_string(src)?;
+        let mut out = String::with_capacity(contents.len());
+        for line in contents.lines() {
+            let var = line.split('=').next().unwrap_or("").trim();
+            let value = match var {
+                _ if !line.contains('=') => None,
+                "prefix" => Some(&dirs.prefix),
+                "exec_prefix" => Some(&dirs.exec_prefix),
+                "libdir" => Some(&dirs.libdir),
+                "includedir" => Some(&dirs.includedir),
+                _ => None,
+            };
+            match value {
+                Some(value) => {
+                    out.push_str(var);
+                    out.push('=');
+                    // Removes the trailing separator of an empty `exec_prefix`
+                    let value = value.components().collect::<PathBuf>();
+                    out.push_str(&value.to_string_lossy());
+                }
+                None => out.push_str(line),
+            }
+            out.push('\n');
+        }
+
+        let dir = TempDir::new("pkgconfig")?;
+        let path = dir.create_file(src.file_name().unwrap_or_default(), out.as_bytes())?;
+        // Keep the modification time of the original, so the copy is not mistaken for a newer file
+        if let Ok(time) = fs::metadata(src).and_then(|md| md.modified()) {
+            fs::File::options()
+                .write(true)
+                .open(&path)?
+                .set_modified(time)?;
+        }
+        Ok(Some(PkgConfig {
+            target: Target {
+                target_file: Some(path),
+                ..target.clone()
+            },
+            _dir: dir,
+        }))
+    }
+}
@@ -0,0 +1,76 @@
+use std::{
+    collections::hash_map::RandomState,
+    ffi::OsStr,
+    fs,
+    hash::{BuildHasher, Hasher},
+    io::{self, ErrorKind, Write},
+    path::{Path, PathBuf},
+};
+
+///
+/// A path in the system temporary directory for `purpose`, which includes a random part, so that another user cannot guess it and create it first
+pub fn unique_path(purpose: &str) -> PathBuf {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u32(std::process::id());
+    std::env::temp_dir().join(format!(
+        "cargo-native-install-{}-{}-{:016x}",
+        std::process::id(),
+        purpose,
+        hasher.finish()
+    ))
+}
+
+///
+/// Creates the directory `path`, which only this user can access. Fails if anything, even a symbolic link, already exists at `path`
+pub fn create_private_dir(path: &Path) -> io::Result<()> {
+    let mut builder = fs::DirBuilder::new();
+    #[cfg(unix)]
+    std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+    builder.create(path)
+}
+
+///
+/// A directory in the system temporary directory which only this user can access, for the files the installation generates,
+/// such as rendered manual pages and rewritten pkg-config files. Files which are installed as root are never written to a path another user could have created.
+/// The directory is removed, with everything in it, when this is dropped
+#[derive(Debug)]
+pub struct TempDir {
+    path: PathBuf,
+}
+
+impl TempDir {
+    ///
+    /// Creates a new directory for `purpose`, which names it
+    pub fn new(purpose: &str) -> io::Result<Self> {
+        loop {
+            let path = unique_path(purpose);
+            match create_private_dir(&path) {
+                Ok(()) => return Ok(TempDir { path }),
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    ///
+    /// Creates the file `name` in the directory with `contents`, returning its path. Fails if the file already exists
+    pub fn create_file(&self, name: &OsStr, contents: &[u8]) -> io::Result<PathBuf> {
+        let path = self.path.join(name);
+        fs::File::options()
+            .write(true)
+            .create_new(true)
+            .open(&path)?
+            .write_all(contents)?;
+        Ok(path)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
@@ -0,0 +1,116 @@
+use std::{fs, path::Path};
+
+use cargo_toml::{FeatureSet, Package};
+use install_dirs::dirs::InstallDirs;
+use minijinja::{Environment, UndefinedBehavior};
+use serde_json::{json, Map, Value};
+
+use crate::{
+    emit,
+    manifest::{NativeInstallMetadata, Target, TemplateEngine, TemplatePackage},
+    tempdir::TempDir,
+    Options, DEFAULT_TARGET,
+};
+
+///
+/// The package `package`, with the features of its manifest, as templates see it
+pub fn package(package: &Package<NativeInstallMetadata>, features: &FeatureSet) -> TemplatePackage {
+    TemplatePackage {
+        name: package.name.clone(),
+        version: package.version.clone(),
+        description: package.description.clone(),
+        authors: package.authors.clone(),
+        license: package.license.clone(),
+        homepage: package.homepage.clone(),
+        repository: package.repository.clone(),
+        features: features.clone(),
+    }
+}
+
+///
+/// The variables the template of `target` is rendered with: each installation directory, both as a variable of its own and in `dirs`,
+/// the package the target belongs to as `package`, and the platform the package is installed for as `platform`
+pub fn context(target: &Target, dirs: &InstallDirs, opts: &Options) -> Value {
+    let dirs = emit::resolved(dirs)
+        .into_iter()
+        .map(|(name, dir)| (name.to_owned(), Value::from(dir.to_string_lossy())))
+        .collect::<Map<_, _>>();
+    let mut context = json!({
+        "package": target.package,
+        "platform": {
+            "triple": opts.multiarch.as_deref().unwrap_or(DEFAULT_TARGET),
+            "os": std::env::consts::OS,
+            "family": std::env::consts::FAMILY,
+            "arch": std::env::consts::ARCH,
+        },
+    });
+    if let Value::Object(context) = &mut context {
+        context.extend(dirs.clone());
+        context.insert("dirs".to_owned(), Value::Object(dirs));
+    }
+    context
+}
+
+///
+/// Renders `source`, the template at `path`, with MiniJinja. A variable which is not defined is an error, so that a misspelled name is not rendered as nothing
+fn minijinja(source: &str, path: &Path, context: &Value) -> Result<String, String> {
+    let name = path.to_string_lossy();
+    let mut env = Environment::new();
+    env.set_undefined_behavior(UndefinedBehavior::Strict);
+    env.set_keep_trailing_newline(true);
+    env.add_template(&name, source)
+        .and_then(|()| env.get_template(&name)?.render(context))
+        .map_err(|e| {
+            let mut message = e.to_string();
+            if let Some(detail) = e.detail() {
+                message = format!("{}, {}", message, detail);
+            }
+            message
+        })
+}
+
+///
+/// A copy of the file of a target with `template-engine`, rendered with the variables of [`context`].
+/// The copy is removed when this is dropped
+pub struct Template {
+    pub target: Target,
+    _dir: TempDir,
+}
+
+impl Template {
+    ///
+    /// Renders the file installed by `target`, if it has a `template-engine`
+    pub fn render(
+        target: &Target,
+        dirs: &InstallDirs,
+        opts: &Options,
+    ) -> Result<Option<Self>, String> {
+        let (engine, src) = match (target.template_engine, &target.target_file) {
+            (Some(engine), Some(src)) if !target.directory => (engine, src),
+            _ => return Ok(None),
+        };
+        let source = fs::read_to_string(src).map_err(|e| format!("{}: {}", src.display(), e))?;
+        let context = context(target, dirs, opts);
+        let out = match engine {
+            TemplateEngine::Minijinja => minijinja(&source, src, &context)?,
+        };
+
+        // Unlike a rewritten pkg-config file, the copy is left newer than the installed file, as what it is rendered with may have changed.
+        // It has the permissions of the template, so that a rendered script is still executable
+        let (dir, path) = TempDir::new("template")
+            .and_then(|dir| {
+                let path = dir.create_file(src.file_name().unwrap_or_default(), out.as_bytes())?;
+                fs::set_permissions(&path, fs::metadata(src)?.permissions())?;
+                Ok((dir, path))
+            })
+            .map_err(|e| format!("{}: {}", src.display(), e))?;
+        Ok(Some(Template {
+            target: Target {
+                target_file: Some(path),
+                template_engine: None,
+                ..target.clone()
+            },
+            _dir: dir,
+        }))
+    }
+}
@@ -0,0 +1,178 @@
+use std::{
+    env,
+    ffi::OsString,
+    path::{Component, Path, PathBuf},
+};
+
+use install_dirs::dirs::InstallDirs;
+
+use crate::{convert_to_path, emit::DIRS, normalize_path, Error};
+
+///
+/// Whether `name` is the placeholder of an installation directory, such as `${bindir}`, which is replaced by [`crate::convert_to_path`] instead.
+/// Names that end in `dir` are reserved for directories, so they are never environment variables
+fn is_directory(name: &str) -> bool {
+    name == "prefix" || name == "exec_prefix" || name.ends_with("dir")
+}
+
+///
+/// Replaces each `${NAME}` in `path` with the value of `NAME`: `CARGO_PKG_NAME` and `CARGO_PKG_VERSION` are the name and version in `package`
+/// (or environment variables, without a package), and any other name is an environment variable.
+/// Directory placeholders, and paths which are not unicode, are left as they are. Returns the first variable which is not set, if any
+pub fn expand(path: &Path, package: Option<(&str, &str)>) -> Result<PathBuf, String> {
+    let mut rest = match path.to_str() {
+        Some(s) if s.contains("${") => s,
+        _ => return Ok(path.to_path_buf()),
+    };
+    let mut expanded = OsString::new();
+    while let Some(start) = rest.find("${") {
+        let end = match rest[start..].find('}') {
+            Some(end) => start + end,
+            None => break,
+        };
+        expanded.push(&rest[..start]);
+        let name = &rest[start + 2..end];
+        match (name, package) {
+            ("CARGO_PKG_NAME", Some((package, _))) => expanded.push(package),
+            ("CARGO_PKG_VERSION", Some((_, version))) => expanded.push(version),
+            (name, _) if is_directory(name) => expanded.push(&rest[start..=end]),
+            (name, _) => expanded.push(env::var_os(name).ok_or_else(|| name.to_owned())?),
+        }
+        rest = &rest[end + 1..];
+    }
+    expanded.push(rest);
+    Ok(PathBuf::from(expanded))
+}
+
+///
+/// The name of the variable at the start of `s`, as a shell or cmd accepts it: letters, digits, and underscores, not starting with a digit
+fn variable_name(s: &str) -> Option<&str> {
+    let len = s
+        .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+        .unwrap_or(s.len());
+    Some(&s[..len]).filter(|name| name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_'))
+}
+
+///
+/// Replaces a leading `~` with `home`, and each `$NAME` and `%NAME%` with the environment variable `NAME`, as a shell (or cmd) would have,
+/// had the path not been written after `=` or in a configuration file. `~` is left as it is without `home`, as is `${NAME}`, which [`expand`] replaces.
+/// Returns the first variable which is not set, if any
+pub fn expand_shell(path: &Path, home: Option<&Path>) -> Result<PathBuf, String> {
+    let mut rest = match path.to_str() {
+        Some(s) if s.starts_with('~') || s.contains(['$', '%']) => s,
+        _ => return Ok(path.to_path_buf()),
+    };
+    let mut expanded = OsString::new();
+    if let (Some(home), Some(after)) = (home, rest.strip_prefix('~')) {
+        if after.is_empty() || after.starts_with(std::path::is_separator) {
+            expanded.push(home);
+            rest = after;
+        }
+    }
+    while let Some(start) = rest.find(['$', '%']) {
+        expanded.push(&rest[..start]);
+        let sigil = &rest[start..=start];
+        let after = &rest[start + 1..];
+        let name = variable_name(after).filter(|name| {
+            // A `%` that does not close a name is only a `%`
+            sigil == "$" || after[name.len()..].starts_with('%')
+        });
+        match name {
+            Some(name) => {
+                expanded.push(env::var_os(name).ok_or_else(|| name.to_owned())?);
+                rest = &after[name.len() + if sigil == "%" { 1 } else { 0 }..];
+            }
+            None => {
+                expanded.push(sigil);
+                rest = after;
+            }
+        }
+    }
+    expanded.push(rest);
+    Ok(PathBuf::from(expanded))
+}
+
+///
+/// The installation directory named `name`, one of [`DIRS`]
+pub fn dir_mut<'a>(dirs: &'a mut InstallDirs, name: &str) -> &'a mut PathBuf {
+    match name {
+        "prefix" => &mut dirs.prefix,
+        "exec_prefix" => &mut dirs.exec_prefix,
+        "bindir" => &mut dirs.bindir,
+        "sbindir" => &mut dirs.sbindir,
+        "libdir" => &mut dirs.libdir,
+        "libexecdir" => &mut dirs.libexecdir,
+        "includedir" => &mut dirs.includedir,
+        "datarootdir" => &mut dirs.datarootdir,
+        "datadir" => &mut dirs.datadir,
+        "mandir" => &mut dirs.mandir,
+        "infodir" => &mut dirs.infodir,
+        "docdir" => &mut dirs.docdir,
+        "localedir" => &mut dirs.localedir,
+        "sysconfdir" => &mut dirs.sysconfdir,
+        "localstatedir" => &mut dirs.localstatedir,
+        "sharedstatedir" => &mut dirs.sharedstatedir,
+        name => unreachable!("{} is not an installation directory", name),
+    }
+}
+
+///
+/// Whether `path` begins with the placeholder of an installation directory, in any of the forms [`convert_to_path`] replaces,
+/// such as `@datarootdir@` or `${datarootdir}`
+pub fn has_placeholder(path: &Path) -> bool {
+    let first = match path.components().next() {
+        Some(Component::Normal(first)) => first.to_str(),
+        _ => None,
+    };
+    first
+        .and_then(|s| {
+            s.strip_prefix('<')
+                .and_then(|s| s.strip_suffix('>'))
+                .or_else(|| s.strip_prefix('@')?.strip_suffix('@'))
+                .or_else(|| s.strip_prefix("${")?.strip_suffix('}'))
+        })
+        .is_some_and(|name| DIRS.contains(&name))
+}
+
+///
+/// Resolves `dirs` with `canonicalize`, replacing the placeholder each directory begins with, if any, by the directory it names once resolved.
+/// As a directory may name another which has a placeholder, the directories are resolved again until none of them change.
+/// Until then, each directory with a placeholder is at its default, and directories which name each other never settle
+pub fn resolve_placeholders(
+    mut dirs: InstallDirs,
+    canonicalize: impl Fn(InstallDirs) -> Result<InstallDirs, Error>,
+) -> Result<InstallDirs, Error> {
+    let mut defaults = InstallDirs::defaults();
+    let mut pending = Vec::new();
+    for name in DIRS {
+        let dir = dir_mut(&mut dirs, name);
+        if has_placeholder(dir) {
+            pending.push((
+                name,
+                std::mem::replace(dir, dir_mut(&mut defaults, name).clone()),
+            ));
+        }
+    }
+    if pending.is_empty() {
+        return canonicalize(dirs);
+    }
+
+    for _ in 0..=DIRS.len() {
+        let resolved = canonicalize(dirs.clone())?;
+        let mut changed = false;
+        for (name, path) in &pending {
+            let dir = normalize_path(&convert_to_path(path, &resolved, Path::new("")));
+            let current = dir_mut(&mut dirs, name);
+            if *current != dir {
+                *current = dir;
+                changed = true;
+            }
+        }
+        if !changed {
+            return Ok(resolved);
+        }
+    }
+    Err(Error::DirectoryCycle(
+        pending.into_iter().map(|(name, _)| name).collect(),
+    ))
+}
@@ -0,0 +1,134 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use cargo_toml::Manifest;
+
+use crate::{
+    dist, manifest::NativeInstallMetadata, output, output::Status, privs, tempdir::TempDir, Error,
+    Options,
+};
+
+///
+/// Directories of the source tree which are not part of the source tarball: version control, and cargo's output
+const SKIPPED: &[&str] = &[".git", ".hg", ".svn", "target"];
+
+///
+/// Copies the source tree `src` to `dest`, leaving out the directories in `SKIPPED`, the target directory given by `--out-dir`,
+/// and `vendor`, which is replaced
+fn copy_tree(src: &Path, dest: &Path, top: bool, opts: &Options) -> io::Result<()> {
+    fs::create_dir_all(dest)?;
+    for entry in fs::read_dir(src)? {
+        let entry = entry?;
+        let name = entry.file_name();
+        let path = entry.path();
+        if top
+            && (SKIPPED.iter().any(|skipped| name == *skipped)
+                || name == "vendor"
+                || opts
+                    .out_dir
+                    .as_deref()
+                    .is_some_and(|dir| src.join(dir) == path))
+        {
+            continue;
+        }
+        let ty = entry.file_type()?;
+        // Elsewhere, links are copied as the files they refer to
+        #[cfg(unix)]
+        if ty.is_symlink() {
+            std::os::unix::fs::symlink(fs::read_link(&path)?, dest.join(&name))?;
+            continue;
+        }
+        if ty.is_dir() {
+            copy_tree(&path, &dest.join(&name), false, opts)?;
+        } else {
+            fs::copy(&path, dest.join(&name))?;
+        }
+    }
+    Ok(())
+}
+
+///
+/// The name of the directory in the tarball, `<package>-<version>`, or the name of the manifest directory for a virtual workspace
+fn source_name(manifest_dir: &Path) -> Result<String, Error> {
+    let manifest = Manifest::<NativeInstallMetadata>::from_path_with_metadata(
+        manifest_dir.join("Cargo.toml"),
+    )?;
+    Ok(match &manifest.package {
+        Some(package) => format!("{}-{}", package.name, package.version),
+        None => std::path::absolute(manifest_dir)?.file_name().map_or_else(
+            || "source".to_owned(),
+            |name| name.to_string_lossy().into_owned(),
+        ),
+    })
+}
+
+///
+/// Packs the source of the package in `manifest_dir` into `archive`, with its dependencies vendored by `cargo vendor` into `vendor`,
+/// and a `.cargo/config.toml` which replaces crates.io (and any git dependencies) with them, so that the package can be built,
+/// and installed with `cargo native-install`, without network access
+pub fn dist(manifest_dir: &Path, archive: &Path, opts: &Options) -> Result<(), Error> {
+    let stage = TempDir::new("vendor")?;
+    pack_vendored(manifest_dir, stage.path(), archive, opts)
+}
+
+fn pack_vendored(
+    manifest_dir: &Path,
+    stage: &Path,
+    archive: &Path,
+    opts: &Options,
+) -> Result<(), Error> {
+    let dir = stage.join(source_name(manifest_dir)?);
+    output::verbose(
+        opts,
+        format_args!("Copying {} to {}", manifest_dir.display(), dir.display()),
+    );
+    copy_tree(manifest_dir, &dir, true, opts)?;
+
+    output::status(
+        opts,
+        Status::Installed,
+        format_args!("Vendoring the dependencies of {}", dir.display()),
+    );
+    let mut cmd = Command::new("cargo");
+    cmd.current_dir(&dir)
+        .arg("vendor")
+        .arg("vendor")
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit());
+    // Dependencies are fetched as the user who ran sudo, into their cargo home, rather than as root
+    let user = privs::invoking_user().filter(|_| !opts.keep_privileges);
+    if let Some(user) = &user {
+        output::verbose(
+            opts,
+            format_args!("Vendoring as {} instead of root", user.name()),
+        );
+        privs::give_to(stage, user)?;
+        privs::drop_privileges(&mut cmd, user);
+    }
+    output::command(opts, &cmd);
+    let out = cmd
+        .output()
+        .map_err(|e| Error::Spawn(PathBuf::from("cargo"), e))?;
+    if !out.status.success() {
+        return Err(Error::Tool(PathBuf::from("cargo"), out.status));
+    }
+
+    // cargo vendor prints the source replacement, which is added to any configuration of the package
+    let config = dir.join(".cargo").join("config.toml");
+    let mut contents = fs::read_to_string(&config).unwrap_or_default();
+    if !contents.is_empty() && !contents.ends_with('\n') {
+        contents.push('\n');
+    }
+    contents.push_str(&String::from_utf8_lossy(&out.stdout));
+    fs::create_dir_all(config.parent().unwrap())?;
+    fs::write(&config, contents)?;
+
+    dist::pack_dir(stage, archive, opts)?;
+    match &user {
+        Some(user) => Ok(privs::give_to(archive, user)?),
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,105 @@
+use std::{collections::BTreeMap, path::Path, thread, time::Duration};
+
+use crate::{
+    install_package, load_targets, output, output::Status, receipt, report, Error, Options,
+};
+
+/// How often the artifacts are checked for changes
+const INTERVAL: Duration = Duration::from_millis(500);
+
+///
+/// The fingerprint of the artifact of each target in the manifest. Targets whose artifact does not exist are omitted
+fn snapshot(manifest_dir: &Path, opts: &Options) -> BTreeMap<String, String> {
+    let targets = match load_targets(manifest_dir, opts) {
+        Ok((_, targets)) => targets,
+        // The manifest may be in the middle of being edited
+        Err(_) => return BTreeMap::new(),
+    };
+    targets
+        .into_iter()
+        .filter(|(name, _)| {
+            opts.install_target
+                .as_ref()
+                .is_none_or(|target| target == name)
+        })
+        .filter_map(|(name, target)| {
+            let fingerprint = receipt::tree_fingerprint(target.target_file.as_deref()?)?;
+            Some((name, fingerprint))
+        })
+        .collect()
+}
+
+///
+/// Installs the package, then installs it again whenever an artifact changes, until the process is interrupted.
+/// Targets whose artifacts have not changed are skipped by the install receipt, as usual
+pub fn watch(opts: &Options, manifest_dir: &Path) -> Result<(), Error> {
+    let conflicts = [
+        ("--dist", opts.dist.is_some()),
+        ("--from-archive", opts.from_archive.is_some()),
+        ("--from-release", opts.from_release.is_some()),
+        ("--emit-script", opts.emit_script.is_some()),
+        ("--emit-ninja", opts.emit_ninja.is_some()),
+        ("--dry-run", opts.dry_run),
+        ("--unstow", opts.unstow),
+        ("switch-version", opts.switch_version.is_some()),
+        ("relocate", opts.relocate.is_some()),
+        ("diff-receipts", opts.diff_receipts.is_some()),
+        ("report-orphans", opts.report_orphans),
+        ("check", opts.check),
+        ("test-install", opts.test_install),
+        ("verify", opts.verify),
+        ("--report", opts.report.is_some()),
+    ];
+    if let Some((option, _)) = conflicts.iter().find(|(_, set)| *set) {
+        return Err(Error::Conflict("--watch", option));
+    }
+
+    if let Err(e) = install_package(opts, manifest_dir) {
+        report(opts, &e);
+    }
+    let mut last = snapshot(manifest_dir, opts);
+    loop {
+        output::status(
+            opts,
+            Status::Installed,
+            format_args!(
+                "Watching {} artifacts for changes (press Ctrl-C to stop)",
+                last.len()
+            ),
+        );
+        let changed = loop {
+            thread::sleep(INTERVAL);
+            let current = snapshot(manifest_dir, opts);
+            if current == last {
+                continue;
+            }
+            // Wait until the artifacts stop changing, so that a build in progress is not installed
+            let mut settled = current;
+            loop {
+                thread::sleep(INTERVAL);
+                let next = snapshot(manifest_dir, opts);
+                if next == settled {
+                    break;
+                }
+                settled = next;
+            }
+            let changed = settled
+                .iter()
+                .filter(|(name, fingerprint)| last.get(*name) != Some(fingerprint))
+                .map(|(name, _)| name.clone())
+                .collect::<Vec<_>>();
+            last = settled;
+            if !changed.is_empty() {
+                break changed;
+            }
+        };
+        output::status(
+            opts,
+            Status::Installed,
+            format_args!("Reinstalling, {} changed", changed.join(", ")),
+        );
+        if let Err(e) = install_package(opts, manifest_dir) {
+            report(opts, &e);
+        }
+    }
+}